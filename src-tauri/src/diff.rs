@@ -0,0 +1,608 @@
+// Diff Module
+// Compares two texts line by line, with Myers' algorithm in linear space,
+// and groups the changes into hunks with a few lines of context around
+// them, the way `diff -u` shows them. A changed line is compared word by
+// word with the line that replaced it. Two edited copies of a text can be
+// merged with a three-way merge, which marks the places both changed in
+// different ways as conflicts, like git does.
+
+use serde::Serialize;
+use std::ops::Range;
+
+/// Unchanged lines shown around a change
+const CONTEXT_LINES: usize = 3;
+
+/// One step of an edit script, by index into the old and new sequences
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Op {
+    Equal(usize, usize),
+    Delete(usize),
+    Insert(usize),
+}
+
+/// Furthest x reached on each diagonal k, for k in -max..=max
+struct Frontier {
+    offset: isize,
+    x: Vec<usize>,
+}
+
+impl Frontier {
+    fn new(max: usize) -> Self {
+        Frontier {
+            offset: max as isize,
+            x: vec![0; 2 * max + 1],
+        }
+    }
+
+    fn get(&self, k: isize) -> usize {
+        self.x[(k + self.offset) as usize]
+    }
+
+    fn set(&mut self, k: isize, x: usize) {
+        self.x[(k + self.offset) as usize] = x;
+    }
+}
+
+/// The state of one comparison of `a` with `b`
+struct Myers<'a, T> {
+    a: &'a [T],
+    b: &'a [T],
+    forward: Frontier,
+    backward: Frontier,
+    ops: Vec<Op>,
+}
+
+impl<T: PartialEq> Myers<'_, T> {
+    /// A point on an optimal path through the two ranges, found by
+    /// searching from both ends until the searches meet
+    fn middle_snake(
+        &mut self,
+        a_range: Range<usize>,
+        b_range: Range<usize>,
+    ) -> Option<(usize, usize)> {
+        let (a, b) = (self.a, self.b);
+        let (forward, backward) = (&mut self.forward, &mut self.backward);
+        let (n, m) = (a_range.len(), b_range.len());
+        let delta = n as isize - m as isize;
+        let odd = delta & 1 == 1;
+        let max_d = ((n + m).div_ceil(2) + 1) as isize;
+        forward.set(1, 0);
+        backward.set(1, 0);
+
+        for d in 0..max_d {
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && forward.get(k - 1) < forward.get(k + 1)) {
+                    forward.get(k + 1)
+                } else {
+                    forward.get(k - 1) + 1
+                };
+                let mut y = (x as isize - k) as usize;
+                let start = (x, y);
+                while x < n && y < m && a[a_range.start + x] == b[b_range.start + y] {
+                    x += 1;
+                    y += 1;
+                }
+                forward.set(k, x);
+                if odd && (k - delta).abs() < d && forward.get(k) + backward.get(delta - k) >= n {
+                    return Some((a_range.start + start.0, b_range.start + start.1));
+                }
+            }
+
+            for k in (-d..=d).rev().step_by(2) {
+                let mut x = if k == -d || (k != d && backward.get(k - 1) < backward.get(k + 1)) {
+                    backward.get(k + 1)
+                } else {
+                    backward.get(k - 1) + 1
+                };
+                let mut y = (x as isize - k) as usize;
+                while x < n && y < m && a[a_range.end - x - 1] == b[b_range.end - y - 1] {
+                    x += 1;
+                    y += 1;
+                }
+                backward.set(k, x);
+                if !odd && (k - delta).abs() <= d && backward.get(k) + forward.get(delta - k) >= n {
+                    return Some((a_range.end - x, b_range.end - y));
+                }
+            }
+        }
+        None
+    }
+
+    fn conquer(&mut self, mut a_range: Range<usize>, mut b_range: Range<usize>) {
+        let (a, b) = (self.a, self.b);
+        while !a_range.is_empty() && !b_range.is_empty() && a[a_range.start] == b[b_range.start] {
+            self.ops.push(Op::Equal(a_range.start, b_range.start));
+            a_range.start += 1;
+            b_range.start += 1;
+        }
+        let mut suffix = 0;
+        while suffix < a_range.len()
+            && suffix < b_range.len()
+            && a[a_range.end - suffix - 1] == b[b_range.end - suffix - 1]
+        {
+            suffix += 1;
+        }
+        a_range.end -= suffix;
+        b_range.end -= suffix;
+
+        if a_range.is_empty() {
+            self.ops.extend(b_range.clone().map(Op::Insert));
+        } else if b_range.is_empty() {
+            self.ops.extend(a_range.clone().map(Op::Delete));
+        } else if let Some((x, y)) = self.middle_snake(a_range.clone(), b_range.clone()) {
+            self.conquer(a_range.start..x, b_range.start..y);
+            self.conquer(x..a_range.end, y..b_range.end);
+        } else {
+            self.ops.extend(a_range.clone().map(Op::Delete));
+            self.ops.extend(b_range.clone().map(Op::Insert));
+        }
+
+        let (a_end, b_end) = (a_range.end, b_range.end);
+        self.ops
+            .extend((0..suffix).map(|i| Op::Equal(a_end + i, b_end + i)));
+    }
+}
+
+/// The shortest edit script turning `a` into `b`
+pub fn diff<T: PartialEq>(a: &[T], b: &[T]) -> Vec<Op> {
+    let max = (a.len() + b.len()).div_ceil(2) + 1;
+    let mut myers = Myers {
+        a,
+        b,
+        forward: Frontier::new(max),
+        backward: Frontier::new(max),
+        ops: Vec::with_capacity(a.len().max(b.len())),
+    };
+    myers.conquer(0..a.len(), 0..b.len());
+    let mut ops = myers.ops;
+
+    // Between two unchanged elements, deletions come first
+    for run in ops.split_mut(|op| matches!(op, Op::Equal(..))) {
+        run.sort_by_key(|op| matches!(op, Op::Insert(_)));
+    }
+    ops
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Context,
+    Added,
+    Removed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DiffLine {
+    kind: LineKind,
+    text: String,
+    /// 1-based line numbers; `None` on the side the line isn't on
+    #[serde(rename = "oldLine")]
+    old_line: Option<usize>,
+    #[serde(rename = "newLine")]
+    new_line: Option<usize>,
+    /// For a changed line, its words and which of them changed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    words: Option<Vec<WordSpan>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WordSpan {
+    text: String,
+    changed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Hunk {
+    #[serde(rename = "oldStart")]
+    old_start: usize,
+    #[serde(rename = "oldLines")]
+    old_lines: usize,
+    #[serde(rename = "newStart")]
+    new_start: usize,
+    #[serde(rename = "newLines")]
+    new_lines: usize,
+    lines: Vec<DiffLine>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TextDiff {
+    hunks: Vec<Hunk>,
+    /// Lines added and removed in all
+    added: usize,
+    removed: usize,
+}
+
+/// Line-by-line differences between `old` and `new`
+pub fn diff_lines(old: &str, new: &str) -> TextDiff {
+    let old_lines: Vec<&str> = old.lines().collect();
+    let new_lines: Vec<&str> = new.lines().collect();
+    let ops = diff(&old_lines, &new_lines);
+
+    let line = |op: Op| match op {
+        Op::Equal(i, j) => DiffLine {
+            kind: LineKind::Context,
+            text: old_lines[i].to_string(),
+            old_line: Some(i + 1),
+            new_line: Some(j + 1),
+            words: None,
+        },
+        Op::Delete(i) => DiffLine {
+            kind: LineKind::Removed,
+            text: old_lines[i].to_string(),
+            old_line: Some(i + 1),
+            new_line: None,
+            words: None,
+        },
+        Op::Insert(j) => DiffLine {
+            kind: LineKind::Added,
+            text: new_lines[j].to_string(),
+            old_line: None,
+            new_line: Some(j + 1),
+            words: None,
+        },
+    };
+
+    // Ranges of `ops` to show: each change with its context, overlapping
+    // ranges merged
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for (i, op) in ops.iter().enumerate() {
+        if matches!(op, Op::Equal(..)) {
+            continue;
+        }
+        let range = i.saturating_sub(CONTEXT_LINES)..(i + CONTEXT_LINES + 1).min(ops.len());
+        match ranges.last_mut() {
+            Some(last) if last.end >= range.start => last.end = range.end,
+            _ => ranges.push(range),
+        }
+    }
+
+    let hunks = ranges
+        .into_iter()
+        .map(|range| {
+            let mut lines: Vec<DiffLine> = ops[range.clone()].iter().map(|&op| line(op)).collect();
+            mark_changed_words(&mut lines);
+            // Where the hunk starts on each side: the first line it has
+            // there, or the line before it when it has none
+            let (mut old_start, mut new_start) = (0, 0);
+            for op in &ops[..range.start] {
+                match op {
+                    Op::Equal(..) => {
+                        old_start += 1;
+                        new_start += 1;
+                    }
+                    Op::Delete(_) => old_start += 1,
+                    Op::Insert(_) => new_start += 1,
+                }
+            }
+            let old_count = lines.iter().filter(|l| l.old_line.is_some()).count();
+            let new_count = lines.iter().filter(|l| l.new_line.is_some()).count();
+            Hunk {
+                old_start: old_start + usize::from(old_count > 0),
+                old_lines: old_count,
+                new_start: new_start + usize::from(new_count > 0),
+                new_lines: new_count,
+                lines,
+            }
+        })
+        .collect();
+
+    TextDiff {
+        hunks,
+        added: ops.iter().filter(|op| matches!(op, Op::Insert(_))).count(),
+        removed: ops.iter().filter(|op| matches!(op, Op::Delete(_))).count(),
+    }
+}
+
+/// Words, runs of whitespace and single other characters
+fn tokenize(line: &str) -> Vec<&str> {
+    #[derive(PartialEq)]
+    enum Class {
+        Word,
+        Space,
+        Other,
+    }
+    let class = |c: char| {
+        if c.is_alphanumeric() || c == '_' {
+            Class::Word
+        } else if c.is_whitespace() {
+            Class::Space
+        } else {
+            Class::Other
+        }
+    };
+
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut chars = line.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let current = class(c);
+        let ends = match chars.peek() {
+            Some(&(_, next)) => current == Class::Other || class(next) != current,
+            None => true,
+        };
+        if ends {
+            let end = i + c.len_utf8();
+            tokens.push(&line[start..end]);
+            start = end;
+        }
+    }
+    tokens
+}
+
+/// Spans of `line`, with the tokens `changed` picks out marked
+fn word_spans(tokens: &[&str], changed: impl Fn(usize) -> bool) -> Vec<WordSpan> {
+    let mut spans: Vec<WordSpan> = Vec::new();
+    for (i, token) in tokens.iter().enumerate() {
+        let changed = changed(i);
+        match spans.last_mut() {
+            Some(last) if last.changed == changed => last.text.push_str(token),
+            _ => spans.push(WordSpan {
+                text: token.to_string(),
+                changed,
+            }),
+        }
+    }
+    spans
+}
+
+/// Compare each removed line word by word with the added line in the same
+/// place of the change, when the two still have words in common
+fn mark_changed_words(lines: &mut [DiffLine]) {
+    let mut i = 0;
+    while i < lines.len() {
+        let removed = lines[i..]
+            .iter()
+            .take_while(|l| l.kind == LineKind::Removed)
+            .count();
+        let added = lines[i + removed..]
+            .iter()
+            .take_while(|l| l.kind == LineKind::Added)
+            .count();
+        for pair in 0..removed.min(added) {
+            let (old, new) = (i + pair, i + removed + pair);
+            let old_tokens = tokenize(&lines[old].text);
+            let new_tokens = tokenize(&lines[new].text);
+            let ops = diff(&old_tokens, &new_tokens);
+            let shares_words = ops.iter().any(|op| match *op {
+                Op::Equal(a, _) => !old_tokens[a].trim().is_empty(),
+                _ => false,
+            });
+            if !shares_words {
+                continue;
+            }
+            let deleted: Vec<usize> = ops
+                .iter()
+                .filter_map(|op| match *op {
+                    Op::Delete(a) => Some(a),
+                    _ => None,
+                })
+                .collect();
+            let inserted: Vec<usize> = ops
+                .iter()
+                .filter_map(|op| match *op {
+                    Op::Insert(b) => Some(b),
+                    _ => None,
+                })
+                .collect();
+            lines[old].words = Some(word_spans(&old_tokens, |a| deleted.contains(&a)));
+            lines[new].words = Some(word_spans(&new_tokens, |b| inserted.contains(&b)));
+        }
+        i += (removed + added).max(1);
+    }
+}
+
+/// The result of a three-way merge
+#[derive(Debug, Clone, Serialize)]
+pub struct Merge {
+    /// The merged text, with conflict markers where both sides changed the
+    /// same lines differently
+    content: String,
+    conflicts: usize,
+}
+
+/// For each element of the old side of `ops`, where it is on the new side
+fn kept_at(old_len: usize, ops: &[Op]) -> Vec<Option<usize>> {
+    let mut kept = vec![None; old_len];
+    for op in ops {
+        if let Op::Equal(i, j) = *op {
+            kept[i] = Some(j);
+        }
+    }
+    kept
+}
+
+/// Merge `ours` and `theirs`, two edited copies of `base`. Changes made on
+/// one side only are taken as they are; where both sides changed the same
+/// lines differently, both versions are kept between conflict markers
+/// labelled with `labels`.
+pub fn merge(base: &str, ours: &str, theirs: &str, labels: (&str, &str)) -> Merge {
+    let base_lines: Vec<&str> = base.lines().collect();
+    let our_lines: Vec<&str> = ours.lines().collect();
+    let their_lines: Vec<&str> = theirs.lines().collect();
+    let in_ours = kept_at(base_lines.len(), &diff(&base_lines, &our_lines));
+    let in_theirs = kept_at(base_lines.len(), &diff(&base_lines, &their_lines));
+
+    let start_marker = format!("<<<<<<< {}", labels.0);
+    let end_marker = format!(">>>>>>> {}", labels.1);
+    let mut merged: Vec<&str> = Vec::new();
+    let mut conflicts = 0;
+    let (mut i, mut j, mut k) = (0, 0, 0);
+    loop {
+        // The next base line both sides kept, and the stretch before it
+        let stable = (i..base_lines.len()).find_map(|b| Some((b, in_ours[b]?, in_theirs[b]?)));
+        let (b_end, o_end, t_end) =
+            stable.unwrap_or((base_lines.len(), our_lines.len(), their_lines.len()));
+        let (old, mine, other) = (
+            &base_lines[i..b_end],
+            &our_lines[j..o_end],
+            &their_lines[k..t_end],
+        );
+        if mine == old {
+            merged.extend(other);
+        } else if other == old || mine == other {
+            merged.extend(mine);
+        } else {
+            conflicts += 1;
+            merged.push(&start_marker);
+            merged.extend(mine);
+            merged.push("=======");
+            merged.extend(other);
+            merged.push(&end_marker);
+        }
+
+        let Some((b, o, t)) = stable else {
+            break;
+        };
+        merged.push(base_lines[b]);
+        (i, j, k) = (b + 1, o + 1, t + 1);
+    }
+
+    let mut content = merged.join("\n");
+    if !content.is_empty() && (ours.ends_with('\n') || theirs.ends_with('\n')) {
+        content.push('\n');
+    }
+    Merge { content, conflicts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Length of the longest common subsequence, the slow way
+    fn lcs_len(a: &[u8], b: &[u8]) -> usize {
+        let mut table = vec![vec![0; b.len() + 1]; a.len() + 1];
+        for i in (0..a.len()).rev() {
+            for j in (0..b.len()).rev() {
+                table[i][j] = if a[i] == b[j] {
+                    table[i + 1][j + 1] + 1
+                } else {
+                    table[i + 1][j].max(table[i][j + 1])
+                };
+            }
+        }
+        table[0][0]
+    }
+
+    #[test]
+    fn test_diff_lines() {
+        // The edit script rebuilds both sides and is as short as possible
+        let pairs: [(&[u8], &[u8]); 6] = [
+            (b"abcabba", b"cbabac"),
+            (b"", b"abc"),
+            (b"abc", b""),
+            (b"xaxbxcx", b"abc"),
+            (b"the quick brown fox", b"a quick brown dog jumps"),
+            (b"aaaaabbbbb", b"bbbbbaaaaa"),
+        ];
+        for (a, b) in pairs {
+            let ops = diff(a, b);
+            let (mut old, mut new, mut equal) = (Vec::new(), Vec::new(), 0);
+            for op in &ops {
+                match *op {
+                    Op::Equal(i, j) => {
+                        assert_eq!(a[i], b[j]);
+                        old.push(a[i]);
+                        new.push(b[j]);
+                        equal += 1;
+                    }
+                    Op::Delete(i) => old.push(a[i]),
+                    Op::Insert(j) => new.push(b[j]),
+                }
+            }
+            assert_eq!((old.as_slice(), new.as_slice()), (a, b));
+            assert_eq!(equal, lcs_len(a, b));
+        }
+
+        let old = "# Title\n\none\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\n";
+        let new = "# New title\n\none\ntwo\nthree\nfour\nfive\nsix\nseven\neight\nnine\nten\n";
+        let result = diff_lines(old, new);
+        assert_eq!((result.added, result.removed), (2, 1));
+        assert_eq!(result.hunks.len(), 2);
+        let first = &result.hunks[0];
+        assert_eq!(
+            (
+                first.old_start,
+                first.old_lines,
+                first.new_start,
+                first.new_lines
+            ),
+            (1, 4, 1, 4)
+        );
+        assert_eq!(first.lines[0].kind, LineKind::Removed);
+        assert_eq!(first.lines[1].text, "# New title");
+        let last = &result.hunks[1];
+        assert_eq!(
+            (
+                last.old_start,
+                last.old_lines,
+                last.new_start,
+                last.new_lines
+            ),
+            (9, 3, 9, 4)
+        );
+        assert_eq!(last.lines[3].new_line, Some(12));
+        assert!(diff_lines(old, old).hunks.is_empty());
+
+        let changed = diff_lines("The quick brown fox.\n", "The quick red fox!\n");
+        let lines = &changed.hunks[0].lines;
+        let spans = |i: usize| -> Vec<(&str, bool)> {
+            let words = lines[i].words.as_ref().unwrap();
+            words.iter().map(|w| (w.text.as_str(), w.changed)).collect()
+        };
+        assert_eq!(
+            spans(0),
+            vec![
+                ("The quick ", false),
+                ("brown", true),
+                (" fox", false),
+                (".", true)
+            ]
+        );
+        assert_eq!(
+            spans(1),
+            vec![
+                ("The quick ", false),
+                ("red", true),
+                (" fox", false),
+                ("!", true)
+            ]
+        );
+        let rewritten = diff_lines("one two\n", "three four\n");
+        assert!(rewritten.hunks[0].lines[0].words.is_none());
+    }
+
+    #[test]
+    fn test_merge() {
+        let base = "# Plan\n\nintro\nsteps\nend\n";
+        let labels = ("Yours", "On disk");
+
+        // Changes to different lines, and the same change on both sides
+        let clean = merge(
+            base,
+            "# Plan\n\nIntro\nsteps\nend\nnotes\n",
+            "# The Plan\n\nIntro\nsteps\nend\n",
+            labels,
+        );
+        assert_eq!(clean.conflicts, 0);
+        assert_eq!(clean.content, "# The Plan\n\nIntro\nsteps\nend\nnotes\n");
+
+        let conflicted = merge(
+            base,
+            "# Plan\n\nintro\nmy steps\nend\n",
+            "# Plan\n\nintro\ntheir steps\nend\n",
+            labels,
+        );
+        assert_eq!(conflicted.conflicts, 1);
+        assert_eq!(
+            conflicted.content,
+            "# Plan\n\nintro\n<<<<<<< Yours\nmy steps\n=======\ntheir steps\n>>>>>>> On disk\nend\n"
+        );
+
+        // One side deleted what the other left alone
+        let deleted = merge(base, "# Plan\n\nend\n", base, labels);
+        assert_eq!(
+            (deleted.content.as_str(), deleted.conflicts),
+            ("# Plan\n\nend\n", 0)
+        );
+    }
+}
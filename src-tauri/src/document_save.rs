@@ -0,0 +1,278 @@
+// Document Save Module
+// Writes files so that a crash or a full disk never leaves half of one
+// behind: the new contents go to a temporary file next to it, are flushed
+// to disk and then renamed over the original, keeping its permissions and
+// owner. A save is refused when the file changed on disk since it was
+// opened, so edits made by another program aren't silently overwritten.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// Numbers the temporary files of this process, so that two saves of the
+/// same file at once (auto-save and a manual save) never share one
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// A version of a file on disk, as last read or written
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiskVersion {
+    /// SHA-256 of the contents, in hex
+    pub hash: String,
+    /// Modification time in milliseconds since the epoch
+    pub modified: i64,
+}
+
+impl DiskVersion {
+    pub fn new(bytes: &[u8], metadata: &fs::Metadata) -> Self {
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        DiskVersion {
+            hash: hash(bytes),
+            modified,
+        }
+    }
+
+    /// The version of the file at `path`, or `None` if there is none
+    pub fn of(path: &Path) -> Result<Option<Self>, String> {
+        let bytes = match fs::read(path) {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(format!("Failed to read file: {}", e)),
+        };
+        let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+        Ok(Some(DiskVersion::new(&bytes, &metadata)))
+    }
+}
+
+pub fn hash(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Why a save didn't happen
+#[derive(Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum SaveError {
+    /// Someone else changed the file since it was opened; `disk` is the
+    /// version there now
+    ChangedOnDisk {
+        disk: DiskVersion,
+    },
+    /// The file was read in an encoding that was only guessed; saving
+    /// edits needs the user's go-ahead
+    EncodingGuessed,
+    Failed {
+        message: String,
+    },
+}
+
+impl From<String> for SaveError {
+    fn from(message: String) -> Self {
+        SaveError::Failed { message }
+    }
+}
+
+/// The file a write to `path` should replace: symlinks are followed, so
+/// that the link stays a link
+fn write_target(path: &Path) -> PathBuf {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.file_type().is_symlink() => {
+            fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Give `temp` the permissions and owner of `original`
+fn copy_attributes(original: &fs::Metadata, temp: &Path) -> Result<(), String> {
+    fs::set_permissions(temp, original.permissions())
+        .map_err(|e| format!("Failed to set file permissions: {}", e))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+        // Only root may give a file away; anyone else keeps their own
+        if let Err(e) = std::os::unix::fs::chown(temp, Some(original.uid()), Some(original.gid())) {
+            log::warn!("Failed to keep the owner of {}: {}", temp.display(), e);
+        }
+    }
+    Ok(())
+}
+
+/// A new temporary file in `dir` for writing the file `name`. It starts out
+/// no more readable than `original`, so a private note is never exposed
+/// while it is being saved.
+fn create_temp(
+    dir: &Path,
+    name: &str,
+    original: Option<&fs::Metadata>,
+) -> Result<(PathBuf, File), String> {
+    let temp = dir.join(format!(
+        ".{}.{}-{}.docura-tmp",
+        name,
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+
+    // Never reuse a file someone else is still writing
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    if let Some(original) = original {
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+        options.mode(original.permissions().mode() & 0o777);
+    }
+    #[cfg(not(unix))]
+    let _ = original;
+    let file = options
+        .open(&temp)
+        .map_err(|e| format!("Failed to create temporary file: {}", e))?;
+    Ok((temp, file))
+}
+
+/// Replace the contents of `path` with `bytes` in one step: readers see
+/// either the old file or the new one, never part of it
+pub fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let target = write_target(path);
+    let dir = target
+        .parent()
+        .filter(|p| !p.as_os_str().is_empty())
+        .unwrap_or(Path::new("."));
+    let name = target
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+    let original = fs::metadata(&target).ok();
+    let (temp, mut file) = create_temp(dir, &name.to_string_lossy(), original.as_ref())?;
+    let mut write = || -> Result<(), String> {
+        file.write_all(bytes)
+            .map_err(|e| format!("Failed to write file: {}", e))?;
+        file.sync_all()
+            .map_err(|e| format!("Failed to flush file to disk: {}", e))?;
+        if let Some(original) = &original {
+            copy_attributes(original, &temp)?;
+        }
+        fs::rename(&temp, &target).map_err(|e| format!("Failed to replace file: {}", e))
+    };
+    if let Err(e) = write() {
+        fs::remove_file(&temp).ok();
+        return Err(e);
+    }
+
+    // Make the rename itself survive a crash
+    #[cfg(unix)]
+    if let Err(e) = File::open(dir).and_then(|d| d.sync_all()) {
+        log::warn!("Failed to flush {}: {}", dir.display(), e);
+    }
+    Ok(())
+}
+
+/// Save `bytes` to `path`, unless the file there is no longer `expected`.
+/// Without `expected` the file is overwritten whatever it holds. Returns
+/// the version written.
+pub fn save(
+    path: &Path,
+    bytes: &[u8],
+    expected: Option<&DiskVersion>,
+) -> Result<DiskVersion, SaveError> {
+    if let Some(expected) = expected {
+        // A file deleted in the meantime is simply written again
+        if let Some(disk) = DiskVersion::of(path)? {
+            if disk.hash != expected.hash {
+                return Err(SaveError::ChangedOnDisk { disk });
+            }
+        }
+    }
+
+    write_atomic(path, bytes)?;
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(DiskVersion::new(bytes, &metadata))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_refuses_external_changes() {
+        let dir = std::env::temp_dir().join(format!("docura-save-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        fs::write(&path, "# Draft\n").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+        }
+
+        // The temporary file is as private as the note from the start
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let original = fs::metadata(&path).unwrap();
+            let (temp, mut file) = create_temp(&dir, "note.md", Some(&original)).unwrap();
+            file.write_all(b"# Half").unwrap();
+            let mode = fs::metadata(&temp).unwrap().permissions().mode();
+            fs::remove_file(&temp).unwrap();
+            assert_eq!(mode & 0o777 & !0o640, 0, "temporary file mode {:o}", mode);
+        }
+
+        let opened = DiskVersion::of(&path).unwrap().unwrap();
+        assert_eq!(opened.hash, hash(b"# Draft\n"));
+        let saved = save(&path, b"# Final\n", Some(&opened)).unwrap();
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Final\n");
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o640);
+        }
+
+        // Another program edits the file; saving over it is refused
+        fs::write(&path, "# Theirs\n").unwrap();
+        match save(&path, b"# Mine\n", Some(&saved)) {
+            Err(SaveError::ChangedOnDisk { disk }) => assert_eq!(disk.hash, hash(b"# Theirs\n")),
+            other => panic!("expected a conflict, got {:?}", other),
+        }
+        assert_eq!(fs::read_to_string(&path).unwrap(), "# Theirs\n");
+
+        // ...unless the user chooses to overwrite it
+        save(&path, b"# Mine\n", None).unwrap();
+        let left: Vec<_> = fs::read_dir(&dir).unwrap().flatten().collect();
+        let content = fs::read_to_string(&path).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(content, "# Mine\n");
+        assert_eq!(left.len(), 1, "temporary files left behind");
+    }
+
+    #[test]
+    fn test_concurrent_writes_never_mix() {
+        let dir = std::env::temp_dir().join(format!("docura-race-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("note.md");
+        let versions: Vec<Vec<u8>> = (0..8u8).map(|i| vec![b'a' + i; 256 * 1024]).collect();
+
+        std::thread::scope(|scope| {
+            for bytes in &versions {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..5 {
+                        write_atomic(path, bytes).unwrap();
+                    }
+                });
+            }
+        });
+
+        // Whichever save came last, the file is all of it
+        let content = fs::read(&path).unwrap();
+        let left = fs::read_dir(&dir).unwrap().count();
+        fs::remove_dir_all(&dir).ok();
+        assert!(versions.contains(&content), "file holds a mix of writes");
+        assert_eq!(left, 1, "temporary files left behind");
+    }
+}
@@ -0,0 +1,798 @@
+// DOCX Export Module
+// Writes the Markdown document tree as an Office Open XML (.docx) package:
+// real heading/list/code styles, numbering definitions, tables, hyperlinks,
+// footnotes and embedded images resolved relative to the document.
+
+use crate::markdown::{self, Alignment, Block, Document, Inline, ListItem};
+use crate::package::{self, xml_escape, Package};
+use std::path::Path;
+
+// Measurements are in twips (1/20 pt) unless noted
+const LIST_INDENT: u32 = 720;
+const LIST_HANGING: u32 = 360;
+const QUOTE_INDENT: u32 = 720;
+/// A4 (11906 twips) minus 1440 twip margins on both sides
+const TEXT_WIDTH: u32 = 9026;
+/// Text width in EMU (914400 per inch), used to scale down large images
+const MAX_IMAGE_WIDTH_EMU: u64 = 5_731_510;
+const EMU_PER_PIXEL: u64 = 9525;
+
+const NS_W: &str = "http://schemas.openxmlformats.org/wordprocessingml/2006/main";
+const NS_R: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships";
+const NS_WP: &str = "http://schemas.openxmlformats.org/drawingml/2006/wordprocessingDrawing";
+const NS_A: &str = "http://schemas.openxmlformats.org/drawingml/2006/main";
+const NS_PIC: &str = "http://schemas.openxmlformats.org/drawingml/2006/picture";
+const REL_HYPERLINK: &str =
+    "http://schemas.openxmlformats.org/officeDocument/2006/relationships/hyperlink";
+const REL_IMAGE: &str = "http://schemas.openxmlformats.org/officeDocument/2006/relationships/image";
+
+/// Word bookmark names: letters, digits and underscores, at most 40 characters.
+/// The leading underscore keeps them hidden in Word's bookmark list.
+fn bookmark_name(anchor: &str) -> String {
+    let name: String = anchor
+        .chars()
+        .map(|c| if c.is_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("_{}", name).chars().take(40).collect()
+}
+
+/// Render a Markdown document to the bytes of a .docx file
+pub fn markdown_to_docx(
+    markdown: &str,
+    title: &str,
+    base_dir: Option<&Path>,
+) -> Result<Vec<u8>, String> {
+    let doc = markdown::parse(markdown);
+    let mut writer = DocxWriter::new(&doc, base_dir);
+    writer.blocks(&doc.blocks, Context::default());
+    writer.package(title)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    left: u32,
+    quote: bool,
+    list_level: usize,
+    footnote: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct RunStyle {
+    bold: bool,
+    italic: bool,
+    strike: bool,
+    code: bool,
+    link: bool,
+}
+
+impl RunStyle {
+    fn properties(&self) -> String {
+        let mut props = String::new();
+        if self.code {
+            props.push_str("<w:rStyle w:val=\"CodeChar\"/>");
+        } else if self.link {
+            props.push_str("<w:rStyle w:val=\"Hyperlink\"/>");
+        }
+        if self.bold {
+            props.push_str("<w:b/>");
+        }
+        if self.italic {
+            props.push_str("<w:i/>");
+        }
+        if self.strike {
+            props.push_str("<w:strike/>");
+        }
+        if props.is_empty() {
+            props
+        } else {
+            format!("<w:rPr>{}</w:rPr>", props)
+        }
+    }
+}
+
+struct Relationship {
+    id: String,
+    kind: &'static str,
+    target: String,
+    external: bool,
+}
+
+struct Media {
+    name: String,
+    data: Vec<u8>,
+}
+
+struct DocxWriter<'a> {
+    out: String,
+    base_dir: Option<&'a Path>,
+    anchors: std::vec::IntoIter<String>,
+    /// `None` for bullet lists, `Some(start)` for ordered ones; numId is index + 1
+    lists: Vec<Option<u64>>,
+    document_rels: Vec<Relationship>,
+    footnote_rels: Vec<Relationship>,
+    media: Vec<Media>,
+    definitions: Vec<(String, Vec<Block>)>,
+    footnotes: Vec<String>,
+    in_footnote: bool,
+    drawing_id: usize,
+    bookmark_id: usize,
+}
+
+impl<'a> DocxWriter<'a> {
+    fn new(doc: &Document, base_dir: Option<&'a Path>) -> Self {
+        let mut definitions = Vec::new();
+        markdown::visit_blocks(&doc.blocks, &mut |block| {
+            if let Block::FootnoteDefinition { label, blocks } = block {
+                definitions.push((label.clone(), blocks.clone()));
+            }
+        });
+
+        Self {
+            out: String::new(),
+            base_dir,
+            anchors: doc.heading_anchors().into_iter(),
+            lists: Vec::new(),
+            document_rels: Vec::new(),
+            footnote_rels: Vec::new(),
+            media: Vec::new(),
+            definitions,
+            footnotes: Vec::new(),
+            in_footnote: false,
+            drawing_id: 0,
+            bookmark_id: 0,
+        }
+    }
+
+    /// Add a relationship to the part currently being written and return its id
+    fn relationship(&mut self, kind: &'static str, target: String, external: bool) -> String {
+        let rels = if self.in_footnote {
+            &mut self.footnote_rels
+        } else {
+            &mut self.document_rels
+        };
+        // rId1-rId3 are reserved for styles, numbering and footnotes
+        let id = format!("rId{}", rels.len() + 10);
+        rels.push(Relationship {
+            id: id.clone(),
+            kind,
+            target,
+            external,
+        });
+        id
+    }
+
+    fn paragraph_properties(&self, ctx: Context, style: &str, extra: &str) -> String {
+        let style = if style == "Normal" && ctx.footnote {
+            "FootnoteText"
+        } else if style == "Normal" && ctx.quote {
+            "Quote"
+        } else {
+            style
+        };
+        let mut props = format!("<w:pPr><w:pStyle w:val=\"{}\"/>", style);
+        props.push_str(extra);
+        if ctx.left > 0 {
+            props.push_str(&format!("<w:ind w:left=\"{}\"/>", ctx.left));
+        }
+        props.push_str("</w:pPr>");
+        props
+    }
+
+    fn blocks(&mut self, blocks: &[Block], ctx: Context) {
+        for block in blocks {
+            self.block(block, ctx);
+        }
+    }
+
+    fn block(&mut self, block: &Block, ctx: Context) {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let level = (*level).clamp(1, 6);
+                let anchor = self.anchors.next().unwrap_or_default();
+                self.bookmark_id += 1;
+                let props = self.paragraph_properties(ctx, &format!("Heading{}", level), "");
+                self.out.push_str(&format!(
+                    "<w:p>{}<w:bookmarkStart w:id=\"{}\" w:name=\"{}\"/>",
+                    props,
+                    self.bookmark_id,
+                    xml_escape(&bookmark_name(&anchor))
+                ));
+                self.inlines(content, RunStyle::default());
+                self.out.push_str(&format!(
+                    "<w:bookmarkEnd w:id=\"{}\"/></w:p>",
+                    self.bookmark_id
+                ));
+            }
+            Block::Paragraph(content) => {
+                let props = self.paragraph_properties(ctx, "Normal", "");
+                self.out.push_str("<w:p>");
+                self.out.push_str(&props);
+                self.inlines(content, RunStyle::default());
+                self.out.push_str("</w:p>");
+            }
+            Block::Quote(children) => {
+                let inner = Context {
+                    left: ctx.left + QUOTE_INDENT,
+                    quote: true,
+                    ..ctx
+                };
+                self.blocks(children, inner);
+            }
+            Block::Code { code, .. } => {
+                let props = self.paragraph_properties(ctx, "CodeBlock", "");
+                self.out.push_str("<w:p>");
+                self.out.push_str(&props);
+                self.run(code.trim_end_matches('\n'), RunStyle::default());
+                self.out.push_str("</w:p>");
+            }
+            Block::List { start, items, .. } => self.list(*start, items, ctx),
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows, ctx),
+            Block::ThematicBreak => {
+                let props = self.paragraph_properties(
+                    ctx,
+                    "Normal",
+                    "<w:pBdr><w:bottom w:val=\"single\" w:sz=\"6\" w:space=\"1\" w:color=\"auto\"/></w:pBdr>",
+                );
+                self.out.push_str(&format!("<w:p>{}</w:p>", props));
+            }
+            // Raw HTML has no Word equivalent; footnote bodies are written at their references
+            Block::Html(_) | Block::FootnoteDefinition { .. } => {}
+        }
+    }
+
+    fn list(&mut self, start: Option<u64>, items: &[ListItem], ctx: Context) {
+        self.lists.push(start);
+        let num_id = self.lists.len();
+        let level = ctx.list_level.min(8);
+
+        for item in items {
+            let (first, rest) = match item.blocks.split_first() {
+                Some((Block::Paragraph(content), rest)) => (Some(content), rest),
+                _ => (None, item.blocks.as_slice()),
+            };
+
+            let numbering = format!(
+                "<w:numPr><w:ilvl w:val=\"{}\"/><w:numId w:val=\"{}\"/></w:numPr>",
+                level, num_id
+            );
+            let item_ctx = Context { left: 0, ..ctx };
+            let props = self.paragraph_properties(item_ctx, "ListParagraph", &numbering);
+            self.out.push_str("<w:p>");
+            self.out.push_str(&props);
+            match item.checked {
+                Some(true) => self.run("☒ ", RunStyle::default()),
+                Some(false) => self.run("☐ ", RunStyle::default()),
+                None => {}
+            }
+            if let Some(content) = first {
+                self.inlines(content, RunStyle::default());
+            }
+            self.out.push_str("</w:p>");
+
+            let inner = Context {
+                left: LIST_INDENT * (level as u32 + 1),
+                list_level: level + 1,
+                ..ctx
+            };
+            self.blocks(rest, inner);
+        }
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+        ctx: Context,
+    ) {
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let width = (TEXT_WIDTH - ctx.left) / columns as u32;
+
+        self.out.push_str(&format!(
+            "<w:tbl><w:tblPr><w:tblStyle w:val=\"TableGrid\"/><w:tblW w:w=\"5000\" w:type=\"pct\"/><w:tblInd w:w=\"{}\" w:type=\"dxa\"/></w:tblPr><w:tblGrid>",
+            ctx.left
+        ));
+        for _ in 0..columns {
+            self.out
+                .push_str(&format!("<w:gridCol w:w=\"{}\"/>", width));
+        }
+        self.out.push_str("</w:tblGrid>");
+
+        let header_row = std::iter::once(header);
+        for (index, row) in header_row.chain(rows.iter().map(Vec::as_slice)).enumerate() {
+            let is_header = index == 0;
+            self.out.push_str("<w:tr>");
+            if is_header {
+                self.out.push_str("<w:trPr><w:tblHeader/></w:trPr>");
+            }
+            for i in 0..columns {
+                let justify = match alignments.get(i) {
+                    Some(Alignment::Center) => "<w:jc w:val=\"center\"/>",
+                    Some(Alignment::Right) => "<w:jc w:val=\"right\"/>",
+                    _ => "",
+                };
+                self.out.push_str(&format!(
+                    "<w:tc><w:tcPr><w:tcW w:w=\"{}\" w:type=\"dxa\"/></w:tcPr><w:p><w:pPr><w:pStyle w:val=\"TableContents\"/>{}</w:pPr>",
+                    width, justify
+                ));
+                if let Some(cell) = row.get(i) {
+                    let style = RunStyle {
+                        bold: is_header,
+                        ..RunStyle::default()
+                    };
+                    self.inlines(cell, style);
+                }
+                self.out.push_str("</w:p></w:tc>");
+            }
+            self.out.push_str("</w:tr>");
+        }
+        self.out.push_str("</w:tbl>");
+    }
+
+    fn inlines(&mut self, content: &[Inline], style: RunStyle) {
+        for inline in content {
+            self.inline(inline, style);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline, style: RunStyle) {
+        match inline {
+            Inline::Text(text) => self.run(text, style),
+            Inline::Code(code) => self.run(
+                code,
+                RunStyle {
+                    code: true,
+                    ..style
+                },
+            ),
+            Inline::Emphasis(children) => self.inlines(
+                children,
+                RunStyle {
+                    italic: true,
+                    ..style
+                },
+            ),
+            Inline::Strong(children) => self.inlines(
+                children,
+                RunStyle {
+                    bold: true,
+                    ..style
+                },
+            ),
+            Inline::Strikethrough(children) => self.inlines(
+                children,
+                RunStyle {
+                    strike: true,
+                    ..style
+                },
+            ),
+            Inline::Link { url, content, .. } => {
+                if let Some(anchor) = url.strip_prefix('#') {
+                    self.out.push_str(&format!(
+                        "<w:hyperlink w:anchor=\"{}\">",
+                        xml_escape(&bookmark_name(anchor))
+                    ));
+                } else {
+                    let id = self.relationship(REL_HYPERLINK, url.clone(), true);
+                    self.out.push_str(&format!("<w:hyperlink r:id=\"{}\">", id));
+                }
+                self.inlines(
+                    content,
+                    RunStyle {
+                        link: true,
+                        ..style
+                    },
+                );
+                self.out.push_str("</w:hyperlink>");
+            }
+            Inline::Image { url, alt, .. } => self.image(url, alt, style),
+            Inline::FootnoteReference(label) => self.footnote(label),
+            Inline::Html(_) => {}
+            Inline::SoftBreak => self.run(" ", style),
+            Inline::HardBreak => self.out.push_str("<w:r><w:br/></w:r>"),
+        }
+    }
+
+    fn run(&mut self, text: &str, style: RunStyle) {
+        self.out.push_str("<w:r>");
+        self.out.push_str(&style.properties());
+        let mut segment = String::new();
+        for c in text.chars() {
+            match c {
+                '\t' | '\n' => {
+                    if !segment.is_empty() {
+                        self.out.push_str(&format!(
+                            "<w:t xml:space=\"preserve\">{}</w:t>",
+                            xml_escape(&segment)
+                        ));
+                        segment.clear();
+                    }
+                    self.out
+                        .push_str(if c == '\t' { "<w:tab/>" } else { "<w:br/>" });
+                }
+                c => segment.push(c),
+            }
+        }
+        if !segment.is_empty() {
+            self.out.push_str(&format!(
+                "<w:t xml:space=\"preserve\">{}</w:t>",
+                xml_escape(&segment)
+            ));
+        }
+        self.out.push_str("</w:r>");
+    }
+
+    fn image(&mut self, url: &str, alt: &str, style: RunStyle) {
+        let Some(path) = markdown::resolve_local_path(url, self.base_dir) else {
+            // Remote or missing images fall back to their alt text
+            let label = if alt.is_empty() { url } else { alt };
+            self.run(&format!("[{}]", label), style);
+            return;
+        };
+
+        let image = match package::load_image(&path) {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!("{}", e);
+                self.run(&format!("[{}]", alt), style);
+                return;
+            }
+        };
+
+        let name = format!("image{}.{}", self.media.len() + 1, image.extension);
+        self.media.push(Media {
+            name: name.clone(),
+            data: image.data,
+        });
+        let id = self.relationship(REL_IMAGE, format!("media/{}", name), false);
+
+        let mut cx = image.width as u64 * EMU_PER_PIXEL;
+        let mut cy = image.height as u64 * EMU_PER_PIXEL;
+        if cx > MAX_IMAGE_WIDTH_EMU {
+            cy = cy * MAX_IMAGE_WIDTH_EMU / cx;
+            cx = MAX_IMAGE_WIDTH_EMU;
+        }
+
+        self.drawing_id += 1;
+        self.out.push_str(&format!(
+            "<w:r><w:drawing><wp:inline distT=\"0\" distB=\"0\" distL=\"0\" distR=\"0\"><wp:extent cx=\"{cx}\" cy=\"{cy}\"/><wp:docPr id=\"{n}\" name=\"Picture {n}\" descr=\"{alt}\"/><wp:cNvGraphicFramePr><a:graphicFrameLocks xmlns:a=\"{ns_a}\" noChangeAspect=\"1\"/></wp:cNvGraphicFramePr><a:graphic xmlns:a=\"{ns_a}\"><a:graphicData uri=\"{ns_pic}\"><pic:pic xmlns:pic=\"{ns_pic}\"><pic:nvPicPr><pic:cNvPr id=\"{n}\" name=\"{name}\"/><pic:cNvPicPr/></pic:nvPicPr><pic:blipFill><a:blip r:embed=\"{id}\"/><a:stretch><a:fillRect/></a:stretch></pic:blipFill><pic:spPr><a:xfrm><a:off x=\"0\" y=\"0\"/><a:ext cx=\"{cx}\" cy=\"{cy}\"/></a:xfrm><a:prstGeom prst=\"rect\"><a:avLst/></a:prstGeom></pic:spPr></pic:pic></a:graphicData></a:graphic></wp:inline></w:drawing></w:r>",
+            cx = cx,
+            cy = cy,
+            n = self.drawing_id,
+            alt = xml_escape(alt),
+            ns_a = NS_A,
+            ns_pic = NS_PIC,
+            name = name,
+            id = id
+        ));
+    }
+
+    fn footnote(&mut self, label: &str) {
+        let Some((_, blocks)) = self.definitions.iter().find(|(l, _)| l == label).cloned() else {
+            self.run(&format!("[^{}]", label), RunStyle::default());
+            return;
+        };
+        // Ids 0 and -1 are the separator footnotes Word expects
+        let id = self.footnotes.len() + 1;
+
+        let saved = std::mem::take(&mut self.out);
+        let was_in_footnote = std::mem::replace(&mut self.in_footnote, true);
+        let ctx = Context {
+            footnote: true,
+            ..Context::default()
+        };
+
+        let reference =
+            "<w:r><w:rPr><w:rStyle w:val=\"FootnoteReference\"/></w:rPr><w:footnoteRef/></w:r><w:r><w:t xml:space=\"preserve\"> </w:t></w:r>";
+        match blocks.split_first() {
+            Some((Block::Paragraph(content), rest)) => {
+                let props = self.paragraph_properties(ctx, "Normal", "");
+                self.out.push_str("<w:p>");
+                self.out.push_str(&props);
+                self.out.push_str(reference);
+                self.inlines(content, RunStyle::default());
+                self.out.push_str("</w:p>");
+                self.blocks(rest, ctx);
+            }
+            _ => {
+                let props = self.paragraph_properties(ctx, "Normal", "");
+                self.out
+                    .push_str(&format!("<w:p>{}{}</w:p>", props, reference));
+                self.blocks(&blocks, ctx);
+            }
+        }
+
+        let body = std::mem::replace(&mut self.out, saved);
+        self.in_footnote = was_in_footnote;
+        self.footnotes
+            .push(format!("<w:footnote w:id=\"{}\">{}</w:footnote>", id, body));
+        self.out.push_str(&format!(
+            "<w:r><w:rPr><w:rStyle w:val=\"FootnoteReference\"/></w:rPr><w:footnoteReference w:id=\"{}\"/></w:r>",
+            id
+        ));
+    }
+
+    fn package(self, title: &str) -> Result<Vec<u8>, String> {
+        let mut zip = Package::new("DOCX");
+        zip.add("[Content_Types].xml", content_types().as_bytes())?;
+        zip.add("_rels/.rels", PACKAGE_RELS.as_bytes())?;
+        zip.add("docProps/core.xml", core_properties(title).as_bytes())?;
+        zip.add("word/document.xml", self.document().as_bytes())?;
+        zip.add("word/styles.xml", STYLES.as_bytes())?;
+        zip.add("word/numbering.xml", self.numbering().as_bytes())?;
+        zip.add("word/footnotes.xml", self.footnotes_part().as_bytes())?;
+        zip.add(
+            "word/_rels/document.xml.rels",
+            relationships(&self.document_rels, true).as_bytes(),
+        )?;
+        zip.add(
+            "word/_rels/footnotes.xml.rels",
+            relationships(&self.footnote_rels, false).as_bytes(),
+        )?;
+        for media in &self.media {
+            zip.add(&format!("word/media/{}", media.name), &media.data)?;
+        }
+
+        zip.finish()
+    }
+
+    fn document(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:document xmlns:w=\"{}\" xmlns:r=\"{}\" xmlns:wp=\"{}\"><w:body>{}<w:sectPr><w:pgSz w:w=\"11906\" w:h=\"16838\"/><w:pgMar w:top=\"1440\" w:right=\"1440\" w:bottom=\"1440\" w:left=\"1440\" w:header=\"708\" w:footer=\"708\" w:gutter=\"0\"/></w:sectPr></w:body></w:document>",
+            NS_W, NS_R, NS_WP, self.out
+        )
+    }
+
+    fn numbering(&self) -> String {
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:numbering xmlns:w=\"{}\">",
+            NS_W
+        );
+        // abstractNum 0 is the bullet list, 1 the decimal list
+        for (abstract_id, ordered) in [(0, false), (1, true)] {
+            xml.push_str(&format!(
+                "<w:abstractNum w:abstractNumId=\"{}\"><w:multiLevelType w:val=\"hybridMultilevel\"/>",
+                abstract_id
+            ));
+            for level in 0..9u32 {
+                let (format, text) = if ordered {
+                    ("decimal", format!("%{}.", level + 1))
+                } else {
+                    let bullets = ["•", "◦", "▪"];
+                    ("bullet", bullets[level as usize % 3].to_string())
+                };
+                xml.push_str(&format!(
+                    "<w:lvl w:ilvl=\"{}\"><w:start w:val=\"1\"/><w:numFmt w:val=\"{}\"/><w:lvlText w:val=\"{}\"/><w:lvlJc w:val=\"left\"/><w:pPr><w:ind w:left=\"{}\" w:hanging=\"{}\"/></w:pPr></w:lvl>",
+                    level,
+                    format,
+                    text,
+                    LIST_INDENT * (level + 1),
+                    LIST_HANGING
+                ));
+            }
+            xml.push_str("</w:abstractNum>");
+        }
+        // One instance per Markdown list so every list restarts its numbering
+        for (i, start) in self.lists.iter().enumerate() {
+            let abstract_id = if start.is_some() { 1 } else { 0 };
+            xml.push_str(&format!(
+                "<w:num w:numId=\"{}\"><w:abstractNumId w:val=\"{}\"/>",
+                i + 1,
+                abstract_id
+            ));
+            if let Some(start) = start {
+                for level in 0..9 {
+                    xml.push_str(&format!(
+                        "<w:lvlOverride w:ilvl=\"{}\"><w:startOverride w:val=\"{}\"/></w:lvlOverride>",
+                        level, start
+                    ));
+                }
+            }
+            xml.push_str("</w:num>");
+        }
+        xml.push_str("</w:numbering>");
+        xml
+    }
+
+    fn footnotes_part(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<w:footnotes xmlns:w=\"{}\" xmlns:r=\"{}\" xmlns:wp=\"{}\"><w:footnote w:type=\"separator\" w:id=\"-1\"><w:p><w:r><w:separator/></w:r></w:p></w:footnote><w:footnote w:type=\"continuationSeparator\" w:id=\"0\"><w:p><w:r><w:continuationSeparator/></w:r></w:p></w:footnote>{}</w:footnotes>",
+            NS_W,
+            NS_R,
+            NS_WP,
+            self.footnotes.concat()
+        )
+    }
+}
+
+fn relationships(rels: &[Relationship], document: bool) -> String {
+    let mut xml = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">",
+    );
+    if document {
+        xml.push_str("<Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>");
+        xml.push_str("<Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/numbering\" Target=\"numbering.xml\"/>");
+        xml.push_str("<Relationship Id=\"rId3\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/footnotes\" Target=\"footnotes.xml\"/>");
+    }
+    for rel in rels {
+        xml.push_str(&format!(
+            "<Relationship Id=\"{}\" Type=\"{}\" Target=\"{}\"{}/>",
+            rel.id,
+            rel.kind,
+            xml_escape(&rel.target),
+            if rel.external {
+                " TargetMode=\"External\""
+            } else {
+                ""
+            }
+        ));
+    }
+    xml.push_str("</Relationships>");
+    xml
+}
+
+fn content_types() -> String {
+    let main = "application/vnd.openxmlformats-officedocument.wordprocessingml";
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\"><Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/><Default Extension=\"xml\" ContentType=\"application/xml\"/><Default Extension=\"png\" ContentType=\"image/png\"/><Default Extension=\"jpeg\" ContentType=\"image/jpeg\"/><Default Extension=\"gif\" ContentType=\"image/gif\"/><Override PartName=\"/word/document.xml\" ContentType=\"{0}.document.main+xml\"/><Override PartName=\"/word/styles.xml\" ContentType=\"{0}.styles+xml\"/><Override PartName=\"/word/numbering.xml\" ContentType=\"{0}.numbering+xml\"/><Override PartName=\"/word/footnotes.xml\" ContentType=\"{0}.footnotes+xml\"/><Override PartName=\"/docProps/core.xml\" ContentType=\"application/vnd.openxmlformats-package.core-properties+xml\"/></Types>",
+        main
+    )
+}
+
+fn core_properties(title: &str) -> String {
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n<cp:coreProperties xmlns:cp=\"http://schemas.openxmlformats.org/package/2006/metadata/core-properties\" xmlns:dc=\"http://purl.org/dc/elements/1.1/\" xmlns:dcterms=\"http://purl.org/dc/terms/\" xmlns:xsi=\"http://www.w3.org/2001/XMLSchema-instance\"><dc:title>{}</dc:title><dcterms:created xsi:type=\"dcterms:W3CDTF\">{}</dcterms:created><dcterms:modified xsi:type=\"dcterms:W3CDTF\">{}</dcterms:modified></cp:coreProperties>",
+        xml_escape(title),
+        now,
+        now
+    )
+}
+
+const PACKAGE_RELS: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>
+<Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\"><Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"word/document.xml\"/><Relationship Id=\"rId2\" Type=\"http://schemas.openxmlformats.org/package/2006/relationships/metadata/core-properties\" Target=\"docProps/core.xml\"/></Relationships>";
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<w:styles xmlns:w="http://schemas.openxmlformats.org/wordprocessingml/2006/main">
+<w:docDefaults><w:rPrDefault><w:rPr><w:rFonts w:ascii="Calibri" w:hAnsi="Calibri" w:eastAsia="Calibri" w:cs="Calibri"/><w:sz w:val="22"/><w:szCs w:val="22"/><w:lang w:val="en-US"/></w:rPr></w:rPrDefault><w:pPrDefault><w:pPr><w:spacing w:after="160" w:line="264" w:lineRule="auto"/></w:pPr></w:pPrDefault></w:docDefaults>
+<w:style w:type="paragraph" w:default="1" w:styleId="Normal"><w:name w:val="Normal"/><w:qFormat/></w:style>
+<w:style w:type="paragraph" w:styleId="Heading1"><w:name w:val="heading 1"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="360" w:after="120"/><w:outlineLvl w:val="0"/></w:pPr><w:rPr><w:b/><w:sz w:val="36"/><w:szCs w:val="36"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading2"><w:name w:val="heading 2"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="320" w:after="120"/><w:outlineLvl w:val="1"/></w:pPr><w:rPr><w:b/><w:sz w:val="30"/><w:szCs w:val="30"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading3"><w:name w:val="heading 3"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="280" w:after="80"/><w:outlineLvl w:val="2"/></w:pPr><w:rPr><w:b/><w:sz w:val="26"/><w:szCs w:val="26"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading4"><w:name w:val="heading 4"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="240" w:after="80"/><w:outlineLvl w:val="3"/></w:pPr><w:rPr><w:b/><w:sz w:val="24"/><w:szCs w:val="24"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading5"><w:name w:val="heading 5"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="220" w:after="40"/><w:outlineLvl w:val="4"/></w:pPr><w:rPr><w:b/><w:sz w:val="22"/><w:szCs w:val="22"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="Heading6"><w:name w:val="heading 6"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:keepNext/><w:spacing w:before="220" w:after="40"/><w:outlineLvl w:val="5"/></w:pPr><w:rPr><w:b/><w:i/><w:sz w:val="22"/><w:szCs w:val="22"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="ListParagraph"><w:name w:val="List Paragraph"/><w:basedOn w:val="Normal"/><w:qFormat/><w:pPr><w:spacing w:after="60"/><w:contextualSpacing/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="Quote"><w:name w:val="Quote"/><w:basedOn w:val="Normal"/><w:next w:val="Normal"/><w:qFormat/><w:pPr><w:pBdr><w:left w:val="single" w:sz="18" w:space="8" w:color="D0D7DE"/></w:pBdr></w:pPr><w:rPr><w:i/><w:color w:val="57606A"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="CodeBlock"><w:name w:val="Code Block"/><w:basedOn w:val="Normal"/><w:qFormat/><w:pPr><w:shd w:val="clear" w:color="auto" w:fill="F3F4F6"/><w:spacing w:after="160" w:line="240" w:lineRule="auto"/></w:pPr><w:rPr><w:rFonts w:ascii="Courier New" w:hAnsi="Courier New" w:cs="Courier New"/><w:sz w:val="19"/><w:szCs w:val="19"/></w:rPr></w:style>
+<w:style w:type="paragraph" w:styleId="TableContents"><w:name w:val="Table Contents"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="0"/></w:pPr></w:style>
+<w:style w:type="paragraph" w:styleId="FootnoteText"><w:name w:val="footnote text"/><w:basedOn w:val="Normal"/><w:pPr><w:spacing w:after="0"/></w:pPr><w:rPr><w:sz w:val="18"/><w:szCs w:val="18"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="CodeChar"><w:name w:val="Code Char"/><w:rPr><w:rFonts w:ascii="Courier New" w:hAnsi="Courier New" w:cs="Courier New"/><w:sz w:val="20"/><w:szCs w:val="20"/><w:shd w:val="clear" w:color="auto" w:fill="F3F4F6"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="Hyperlink"><w:name w:val="Hyperlink"/><w:rPr><w:color w:val="0563C1"/><w:u w:val="single"/></w:rPr></w:style>
+<w:style w:type="character" w:styleId="FootnoteReference"><w:name w:val="footnote reference"/><w:rPr><w:vertAlign w:val="superscript"/></w:rPr></w:style>
+<w:style w:type="table" w:styleId="TableGrid"><w:name w:val="Table Grid"/><w:tblPr><w:tblBorders><w:top w:val="single" w:sz="4" w:space="0" w:color="auto"/><w:left w:val="single" w:sz="4" w:space="0" w:color="auto"/><w:bottom w:val="single" w:sz="4" w:space="0" w:color="auto"/><w:right w:val="single" w:sz="4" w:space="0" w:color="auto"/><w:insideH w:val="single" w:sz="4" w:space="0" w:color="auto"/><w:insideV w:val="single" w:sz="4" w:space="0" w:color="auto"/></w:tblBorders><w:tblCellMar><w:left w:w="108" w:type="dxa"/><w:right w:w="108" w:type="dxa"/></w:tblCellMar></w:tblPr></w:style>
+</w:styles>"#;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn unzip(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).expect("valid zip");
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                (file.name().to_string(), data)
+            })
+            .collect()
+    }
+
+    fn part(parts: &[(String, Vec<u8>)], name: &str) -> String {
+        let (_, data) = parts
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("missing part {}", name));
+        String::from_utf8(data.clone()).unwrap()
+    }
+
+    /// Checks that every element is closed in order (enough to catch broken markup)
+    fn assert_balanced(xml: &str) {
+        let mut stack: Vec<&str> = Vec::new();
+        let mut rest = xml;
+        while let Some(start) = rest.find('<') {
+            let end = rest[start..].find('>').expect("unterminated tag") + start;
+            let tag = &rest[start + 1..end];
+            rest = &rest[end + 1..];
+            if tag.starts_with('?') || tag.ends_with('/') {
+                continue;
+            }
+            if let Some(name) = tag.strip_prefix('/') {
+                assert_eq!(stack.pop(), Some(name), "mismatched </{}>", name);
+            } else {
+                stack.push(tag.split_whitespace().next().unwrap());
+            }
+        }
+        assert!(stack.is_empty(), "unclosed elements: {:?}", stack);
+    }
+
+    #[test]
+    fn test_package_structure() {
+        let markdown = "# Title & Co\n\nSee [docs](https://example.com/?a=1&b=2) and [below](#title--co).\n\n1. One\n2. Two\n   - Nested `code`\n\n| A | B |\n|---|--:|\n| **x** | 1 |\n\n```rust\nfn main() {\n\tprintln!(\"<hi>\");\n}\n```\n\nNote[^1].\n\n[^1]: See [site](https://example.org).\n";
+        let bytes = markdown_to_docx(markdown, "Spec", None).unwrap();
+        let parts = unzip(&bytes);
+
+        let document = part(&parts, "word/document.xml");
+        let rels = part(&parts, "word/_rels/document.xml.rels");
+        let footnotes = part(&parts, "word/footnotes.xml");
+        let numbering = part(&parts, "word/numbering.xml");
+        for (name, data) in &parts {
+            if name.ends_with(".xml") || name.ends_with(".rels") {
+                assert_balanced(std::str::from_utf8(data).unwrap());
+            }
+        }
+
+        assert!(document.contains("<w:pStyle w:val=\"Heading1\"/>"));
+        assert!(document.contains("<w:t xml:space=\"preserve\">Title &amp; Co</w:t>"));
+        assert!(document.contains("<w:hyperlink w:anchor=\"_title__co\">"));
+        assert!(document.contains("<w:bookmarkStart w:id=\"1\" w:name=\"_title__co\"/>"));
+        assert!(document.contains("<w:ilvl w:val=\"1\"/><w:numId w:val=\"2\"/>"));
+        assert!(document.contains("<w:tblHeader/>"));
+        assert!(document.contains("<w:jc w:val=\"right\"/>"));
+        assert!(document.contains(
+            "<w:tab/><w:t xml:space=\"preserve\">println!(&quot;&lt;hi&gt;&quot;);</w:t><w:br/>"
+        ));
+        assert!(document.contains("<w:footnoteReference w:id=\"1\"/>"));
+        assert!(
+            rels.contains("Target=\"https://example.com/?a=1&amp;b=2\" TargetMode=\"External\"")
+        );
+        assert!(footnotes.contains("<w:footnote w:id=\"1\">"));
+        assert!(part(&parts, "word/_rels/footnotes.xml.rels").contains("https://example.org"));
+        assert!(numbering.contains("<w:num w:numId=\"1\"><w:abstractNumId w:val=\"1\"/>"));
+        assert!(numbering.contains("<w:num w:numId=\"2\"><w:abstractNumId w:val=\"0\"/></w:num>"));
+        assert!(part(&parts, "docProps/core.xml").contains("<dc:title>Spec</dc:title>"));
+    }
+
+    #[test]
+    fn test_embeds_relative_images() {
+        let dir = std::env::temp_dir().join(format!("docura-docx-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        image::RgbImage::from_pixel(1200, 300, image::Rgb([200, 10, 10]))
+            .save(dir.join("assets/wide.png"))
+            .unwrap();
+
+        let bytes = markdown_to_docx(
+            "![Wide chart](assets/wide.png) and ![gone](missing.png)",
+            "Images",
+            Some(&dir),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        let parts = unzip(&bytes);
+
+        assert!(parts
+            .iter()
+            .any(|(name, _)| name == "word/media/image1.png"));
+        let rels = part(&parts, "word/_rels/document.xml.rels");
+        assert!(rels.contains("Target=\"media/image1.png\"/>"));
+        let document = part(&parts, "word/document.xml");
+        // 1200px is wider than the text column, so it is scaled to fit
+        assert!(document.contains("<wp:extent cx=\"5731510\" cy=\"1432877\"/>"));
+        assert!(document.contains("descr=\"Wide chart\""));
+        assert!(document.contains("[gone]"));
+    }
+}
@@ -0,0 +1,503 @@
+// EPUB Export Module
+// Packages a folder of Markdown files as an EPUB 3 book: one XHTML chapter
+// per file, a navigation document built from the headings, embedded images
+// and Dublin Core metadata.
+
+use crate::html_export::{self, RenderOptions};
+use crate::markdown::{self, Block, Document, Inline};
+use crate::package::{self, Package};
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+
+/// Headings deeper than this are left out of the table of contents
+const TOC_DEPTH: u8 = 3;
+
+/// A source file that becomes one chapter of the book
+pub struct Chapter {
+    pub path: PathBuf,
+    pub markdown: String,
+}
+
+/// Book-level metadata written to the package document
+pub struct BookInfo {
+    pub title: String,
+    pub author: String,
+    pub language: String,
+    /// Publication date as `YYYY-MM-DD`
+    pub date: String,
+}
+
+struct ParsedChapter {
+    file: String,
+    title: String,
+    /// Table of contents entries: (level, text, href)
+    headings: Vec<(u8, String, String)>,
+    body: String,
+}
+
+struct EpubImage {
+    href: String,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_external(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+/// Read an image into an EPUB core media type; SVG and WebP are kept as-is
+fn load_image(path: &Path) -> Result<(Vec<u8>, &'static str, &'static str), String> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("")
+        .to_lowercase();
+    let raw_type = match extension.as_str() {
+        "svg" => Some(("svg", "image/svg+xml")),
+        "webp" => Some(("webp", "image/webp")),
+        _ => None,
+    };
+    if let Some((extension, media_type)) = raw_type {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+        return Ok((data, extension, media_type));
+    }
+
+    let image = package::load_image(path)?;
+    Ok((image.data, image.extension, image.media_type))
+}
+
+/// Chapter title: the first level 1 heading, falling back to the file name
+fn chapter_title(doc: &Document, path: &Path) -> (String, bool) {
+    match doc.title() {
+        Some(title) => (title, true),
+        None => (
+            path.file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("Chapter")
+                .to_string(),
+            false,
+        ),
+    }
+}
+
+/// Build an EPUB 3 file from chapters given in reading order
+pub fn build_epub(chapters: &[Chapter], info: &BookInfo) -> Result<Vec<u8>, String> {
+    if chapters.is_empty() {
+        return Err("No Markdown files to export".to_string());
+    }
+
+    let files: HashMap<PathBuf, String> = chapters
+        .iter()
+        .enumerate()
+        .map(|(i, chapter)| {
+            (
+                canonical(&chapter.path),
+                format!("chapter-{:03}.xhtml", i + 1),
+            )
+        })
+        .collect();
+
+    let mut images: Vec<EpubImage> = Vec::new();
+    let mut image_hrefs: HashMap<PathBuf, String> = HashMap::new();
+    let mut parsed = Vec::new();
+
+    for (i, chapter) in chapters.iter().enumerate() {
+        let doc = markdown::parse(&chapter.markdown);
+        let base_dir = chapter.path.parent();
+        let file = format!("chapter-{:03}.xhtml", i + 1);
+
+        // Package every local image once, however many chapters use it
+        let mut sources = HashMap::new();
+        markdown::visit_inlines(&doc.blocks, &mut |inline| {
+            if let Inline::Image { url, .. } = inline {
+                if let Some(path) = markdown::resolve_local_path(url, base_dir) {
+                    sources.insert(url.clone(), canonical(&path));
+                }
+            }
+        });
+        let mut chapter_images = HashMap::new();
+        for (url, path) in sources {
+            if let Some(href) = image_hrefs.get(&path) {
+                chapter_images.insert(url, href.clone());
+                continue;
+            }
+            match load_image(&path) {
+                Ok((data, extension, media_type)) => {
+                    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("image");
+                    let safe: String = stem
+                        .chars()
+                        .map(|c| {
+                            if c.is_ascii_alphanumeric() || c == '-' {
+                                c
+                            } else {
+                                '_'
+                            }
+                        })
+                        .collect();
+                    let href = format!("images/{:03}-{}.{}", images.len() + 1, safe, extension);
+                    images.push(EpubImage {
+                        href: href.clone(),
+                        media_type,
+                        data,
+                    });
+                    image_hrefs.insert(path, href.clone());
+                    chapter_images.insert(url, href);
+                }
+                Err(e) => log::warn!("{}", e),
+            }
+        }
+
+        // Links to other chapters point at their XHTML files
+        let rewrite_link = |url: &str| -> Option<String> {
+            if is_external(url) || url.starts_with('#') {
+                return None;
+            }
+            let (path, fragment) = match url.split_once('#') {
+                Some((path, fragment)) => (path, format!("#{}", fragment)),
+                None => (url, String::new()),
+            };
+            let decoded = urlencoding::decode(path).ok()?;
+            let target = canonical(&base_dir?.join(decoded.as_ref()));
+            files
+                .get(&target)
+                .map(|file| format!("{}{}", file, fragment))
+        };
+        let rewrite_image = |url: &str| chapter_images.get(url).cloned();
+        let options = RenderOptions {
+            xhtml: true,
+            rewrite_link: Some(&rewrite_link),
+            rewrite_image: Some(&rewrite_image),
+            ..RenderOptions::default()
+        };
+        let body = html_export::render_body_with(&doc, &options);
+
+        let (title, from_heading) = chapter_title(&doc, &chapter.path);
+        let mut headings = Vec::new();
+        let mut anchors = doc.heading_anchors().into_iter();
+        markdown::visit_blocks(&doc.blocks, &mut |block| {
+            if let Block::Heading { level, content, .. } = block {
+                let anchor = anchors.next().unwrap_or_default();
+                if *level <= TOC_DEPTH {
+                    headings.push((
+                        *level,
+                        markdown::plain_text(content).trim().to_string(),
+                        format!("{}#{}", file, anchor),
+                    ));
+                }
+            }
+        });
+        // The heading used as the chapter title is already the chapter's entry
+        if from_heading {
+            if let Some(i) = headings
+                .iter()
+                .position(|(level, text, _)| *level == 1 && *text == title)
+            {
+                headings.remove(i);
+            }
+        }
+
+        parsed.push(ParsedChapter {
+            file,
+            title,
+            headings,
+            body,
+        });
+    }
+
+    package(&parsed, &images, info)
+}
+
+fn chapter_xhtml(chapter: &ParsedChapter, info: &BookInfo) -> String {
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{0}\" xml:lang=\"{0}\">\n<head>\n<meta charset=\"UTF-8\" />\n<title>{1}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n<body>\n<section epub:type=\"chapter\">\n{2}</section>\n</body>\n</html>\n",
+        html_export::escape(&info.language),
+        html_export::escape(&chapter.title),
+        chapter.body
+    )
+}
+
+/// Nested `<ol>` for headings of varying depth
+fn toc_list(entries: &[(u8, String, String)]) -> String {
+    let Some((first_level, _, _)) = entries.first() else {
+        return String::new();
+    };
+    let mut out = String::from("<ol>");
+    let mut levels = vec![*first_level];
+    let mut item_open = false;
+
+    for (level, text, href) in entries {
+        let current = *levels.last().unwrap_or(first_level);
+        if item_open && *level > current {
+            out.push_str("<ol>");
+            levels.push(*level);
+        } else if item_open {
+            out.push_str("</li>");
+            while levels.len() > 1 && *level < *levels.last().unwrap_or(first_level) {
+                levels.pop();
+                out.push_str("</ol></li>");
+            }
+        }
+        out.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>",
+            html_export::escape(href),
+            html_export::escape(text)
+        ));
+        item_open = true;
+    }
+    out.push_str("</li>");
+    for _ in 1..levels.len() {
+        out.push_str("</ol></li>");
+    }
+    out.push_str("</ol>");
+    out
+}
+
+fn nav_xhtml(chapters: &[ParsedChapter], info: &BookInfo) -> String {
+    let mut toc = String::from("<ol>\n");
+    for chapter in chapters {
+        toc.push_str(&format!(
+            "<li><a href=\"{}\">{}</a>{}</li>\n",
+            chapter.file,
+            html_export::escape(&chapter.title),
+            toc_list(&chapter.headings)
+        ));
+    }
+    toc.push_str("</ol>");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<!DOCTYPE html>\n<html xmlns=\"http://www.w3.org/1999/xhtml\" xmlns:epub=\"http://www.idpf.org/2007/ops\" lang=\"{0}\" xml:lang=\"{0}\">\n<head>\n<meta charset=\"UTF-8\" />\n<title>{1}</title>\n<link rel=\"stylesheet\" type=\"text/css\" href=\"style.css\" />\n</head>\n<body>\n<nav epub:type=\"toc\" id=\"toc\">\n<h1>Contents</h1>\n{2}\n</nav>\n</body>\n</html>\n",
+        html_export::escape(&info.language),
+        html_export::escape(&info.title),
+        toc
+    )
+}
+
+/// EPUB 2 table of contents, still read by older devices
+fn toc_ncx(chapters: &[ParsedChapter], info: &BookInfo, identifier: &str) -> String {
+    let mut points = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        points.push_str(&format!(
+            "<navPoint id=\"nav-{0}\" playOrder=\"{0}\"><navLabel><text>{1}</text></navLabel><content src=\"{2}\"/></navPoint>\n",
+            i + 1,
+            html_export::escape(&chapter.title),
+            chapter.file
+        ));
+    }
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ncx xmlns=\"http://www.daisy.org/z3986/2005/ncx/\" version=\"2005-1\">\n<head><meta name=\"dtb:uid\" content=\"{}\"/></head>\n<docTitle><text>{}</text></docTitle>\n<navMap>\n{}</navMap>\n</ncx>\n",
+        html_export::escape(identifier),
+        html_export::escape(&info.title),
+        points
+    )
+}
+
+fn package_opf(
+    chapters: &[ParsedChapter],
+    images: &[EpubImage],
+    info: &BookInfo,
+    identifier: &str,
+) -> String {
+    let modified = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%SZ");
+    let mut manifest = String::from(
+        "<item id=\"nav\" href=\"nav.xhtml\" media-type=\"application/xhtml+xml\" properties=\"nav\"/>\n<item id=\"ncx\" href=\"toc.ncx\" media-type=\"application/x-dtbncx+xml\"/>\n<item id=\"css\" href=\"style.css\" media-type=\"text/css\"/>\n",
+    );
+    let mut spine = String::new();
+    for (i, chapter) in chapters.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"chapter-{0}\" href=\"{1}\" media-type=\"application/xhtml+xml\"/>\n",
+            i + 1,
+            chapter.file
+        ));
+        spine.push_str(&format!("<itemref idref=\"chapter-{}\"/>\n", i + 1));
+    }
+    for (i, image) in images.iter().enumerate() {
+        manifest.push_str(&format!(
+            "<item id=\"image-{}\" href=\"{}\" media-type=\"{}\"/>\n",
+            i + 1,
+            image.href,
+            image.media_type
+        ));
+    }
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<package xmlns=\"http://www.idpf.org/2007/opf\" version=\"3.0\" unique-identifier=\"book-id\" xml:lang=\"{lang}\">\n<metadata xmlns:dc=\"http://purl.org/dc/elements/1.1/\">\n<dc:identifier id=\"book-id\">{id}</dc:identifier>\n<dc:title>{title}</dc:title>\n<dc:creator>{author}</dc:creator>\n<dc:language>{lang}</dc:language>\n<dc:date>{date}</dc:date>\n<meta property=\"dcterms:modified\">{modified}</meta>\n</metadata>\n<manifest>\n{manifest}</manifest>\n<spine toc=\"ncx\">\n{spine}</spine>\n</package>\n",
+        lang = html_export::escape(&info.language),
+        id = html_export::escape(identifier),
+        title = html_export::escape(&info.title),
+        author = html_export::escape(&info.author),
+        date = html_export::escape(&info.date),
+        modified = modified,
+        manifest = manifest,
+        spine = spine
+    )
+}
+
+fn package(
+    chapters: &[ParsedChapter],
+    images: &[EpubImage],
+    info: &BookInfo,
+) -> Result<Vec<u8>, String> {
+    // Stable identifier so re-exporting the same book does not look like a new title
+    let mut hasher = DefaultHasher::new();
+    info.title.hash(&mut hasher);
+    info.author.hash(&mut hasher);
+    for chapter in chapters {
+        chapter.title.hash(&mut hasher);
+    }
+    let identifier = format!("urn:docura:{:016x}", hasher.finish());
+
+    let mut zip = Package::new("EPUB");
+
+    // The mimetype entry must come first and be stored uncompressed
+    zip.add_stored("mimetype", b"application/epub+zip")?;
+    zip.add("META-INF/container.xml", CONTAINER_XML.as_bytes())?;
+    zip.add(
+        "OEBPS/content.opf",
+        package_opf(chapters, images, info, &identifier).as_bytes(),
+    )?;
+    zip.add("OEBPS/nav.xhtml", nav_xhtml(chapters, info).as_bytes())?;
+    zip.add(
+        "OEBPS/toc.ncx",
+        toc_ncx(chapters, info, &identifier).as_bytes(),
+    )?;
+    zip.add("OEBPS/style.css", STYLESHEET.as_bytes())?;
+    for chapter in chapters {
+        zip.add(
+            &format!("OEBPS/{}", chapter.file),
+            chapter_xhtml(chapter, info).as_bytes(),
+        )?;
+    }
+    for image in images {
+        zip.add(&format!("OEBPS/{}", image.href), &image.data)?;
+    }
+
+    zip.finish()
+}
+
+const CONTAINER_XML: &str = "<?xml version=\"1.0\" encoding=\"UTF-8\"?>
+<container version=\"1.0\" xmlns=\"urn:oasis:names:tc:opendocument:xmlns:container\">
+<rootfiles><rootfile full-path=\"OEBPS/content.opf\" media-type=\"application/oebps-package+xml\"/></rootfiles>
+</container>
+";
+
+const STYLESHEET: &str = "body { font-family: serif; line-height: 1.5; margin: 0 5%; }
+h1, h2, h3, h4, h5, h6 { font-family: sans-serif; line-height: 1.25; page-break-after: avoid; }
+pre { background: #f3f4f6; padding: 0.75em; white-space: pre-wrap; font-size: 0.85em; }
+code { font-family: monospace; }
+blockquote { margin: 1em 0; padding-left: 1em; border-left: 3px solid #d0d7de; color: #57606a; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #d0d7de; padding: 0.3em 0.6em; }
+img { max-width: 100%; }
+li.task-list-item { list-style: none; }
+.footnotes { font-size: 0.85em; border-top: 1px solid #d0d7de; margin-top: 2em; }
+nav ol { list-style: none; padding-left: 1em; }
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn info() -> BookInfo {
+        BookInfo {
+            title: "Handbook".to_string(),
+            author: "alice".to_string(),
+            language: "en".to_string(),
+            date: "2026-01-02".to_string(),
+        }
+    }
+
+    fn read_entries(bytes: &[u8]) -> Vec<(String, String)> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).expect("valid zip");
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                (
+                    file.name().to_string(),
+                    String::from_utf8_lossy(&data).into_owned(),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_toc_nesting() {
+        let entries = vec![
+            (2, "A".to_string(), "c.xhtml#a".to_string()),
+            (3, "B".to_string(), "c.xhtml#b".to_string()),
+            (2, "C".to_string(), "c.xhtml#c".to_string()),
+        ];
+        assert_eq!(
+            toc_list(&entries),
+            "<ol><li><a href=\"c.xhtml#a\">A</a><ol><li><a href=\"c.xhtml#b\">B</a></li></ol></li><li><a href=\"c.xhtml#c\">C</a></li></ol>"
+        );
+    }
+
+    #[test]
+    fn test_builds_book_from_folder() {
+        let dir = std::env::temp_dir().join(format!("docura-epub-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        image::RgbImage::from_pixel(4, 4, image::Rgb([0, 128, 255]))
+            .save(dir.join("assets/logo.png"))
+            .unwrap();
+        let intro = dir.join("01-intro.md");
+        let usage = dir.join("02-usage.md");
+        std::fs::write(&intro, "").unwrap();
+        std::fs::write(&usage, "").unwrap();
+
+        let chapters = vec![
+            Chapter {
+                path: intro,
+                markdown: "# Introduction\n\n![Logo](assets/logo.png)\n\nRead [usage](02-usage.md#setup).<br>\n\n## Goals\n".to_string(),
+            },
+            Chapter {
+                path: usage,
+                markdown: "# Usage\n\n## Setup\n\n- [x] Installed\n\n---\n\n![Logo again](assets/logo.png)\n".to_string(),
+            },
+        ];
+        let bytes = build_epub(&chapters, &info()).unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        let entries = read_entries(&bytes);
+        let entry = |name: &str| {
+            entries
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|(_, data)| data.clone())
+                .unwrap_or_else(|| panic!("missing {}", name))
+        };
+
+        assert_eq!(
+            entries[0],
+            ("mimetype".to_string(), "application/epub+zip".to_string())
+        );
+        let opf = entry("OEBPS/content.opf");
+        assert!(opf.contains("<dc:creator>alice</dc:creator>"));
+        assert!(opf.contains("<dc:date>2026-01-02</dc:date>"));
+        assert!(opf.contains("href=\"images/001-logo.png\" media-type=\"image/png\""));
+        assert_eq!(opf.matches("images/").count(), 1);
+        assert!(opf.contains("<itemref idref=\"chapter-1\"/>\n<itemref idref=\"chapter-2\"/>"));
+
+        let nav = entry("OEBPS/nav.xhtml");
+        assert!(nav.contains("<li><a href=\"chapter-001.xhtml\">Introduction</a><ol><li><a href=\"chapter-001.xhtml#goals\">Goals</a></li></ol></li>"));
+        assert!(nav.contains("<li><a href=\"chapter-002.xhtml\">Usage</a><ol><li><a href=\"chapter-002.xhtml#setup\">Setup</a></li></ol></li>"));
+
+        let first = entry("OEBPS/chapter-001.xhtml");
+        assert!(first.contains("<img src=\"images/001-logo.png\" alt=\"Logo\" />"));
+        assert!(first.contains("<a href=\"chapter-002.xhtml#setup\">usage</a>"));
+        assert!(!first.contains("<br>"));
+        let second = entry("OEBPS/chapter-002.xhtml");
+        assert!(second
+            .contains("<input type=\"checkbox\" disabled=\"disabled\" checked=\"checked\" />"));
+        assert!(second.contains("<hr />"));
+        assert!(second.contains("<img src=\"images/001-logo.png\" alt=\"Logo again\" />"));
+    }
+}
@@ -0,0 +1,199 @@
+// File Filter Module
+// Decides which entries the folder tree shows. Besides dot-files it honors
+// `.gitignore`, `.ignore` and `.docuraignore` files in the opened folder and
+// its subfolders (deeper files take precedence, like in git), plus the
+// glob patterns configured in the settings.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::path::Path;
+
+/// Per-workspace ignore file for entries that should only be hidden in Docura
+pub const DOCURA_IGNORE_FILE: &str = ".docuraignore";
+
+/// Read in this order, so later files override earlier ones
+const IGNORE_FILES: [&str; 3] = [".gitignore", ".ignore", DOCURA_IGNORE_FILE];
+
+#[derive(Clone)]
+pub struct FileFilter {
+    /// Show hidden and ignored entries anyway
+    show_ignored: bool,
+    /// Patterns from the settings, relative to the opened folder
+    patterns: Gitignore,
+    /// Ignore files of each folder from the repository root down to the
+    /// folder being read
+    layers: Vec<Option<Gitignore>>,
+}
+
+/// Whether `path` is one of the ignore files read for each folder
+pub fn is_ignore_file(path: &Path) -> bool {
+    path.file_name()
+        .is_some_and(|name| IGNORE_FILES.iter().any(|file| name == *file))
+}
+
+fn load_ignore_files(dir: &Path) -> Option<Gitignore> {
+    let mut builder = GitignoreBuilder::new(dir);
+    let mut found = false;
+    for name in IGNORE_FILES {
+        let path = dir.join(name);
+        if path.is_file() {
+            found = true;
+            // Invalid lines are reported; the valid ones still apply
+            if let Some(e) = builder.add(&path) {
+                log::warn!("Invalid pattern in {}: {}", path.display(), e);
+            }
+        }
+    }
+    if !found {
+        return None;
+    }
+
+    builder
+        .build()
+        .map_err(|e| log::warn!("Failed to read ignore files in {}: {}", dir.display(), e))
+        .ok()
+}
+
+impl FileFilter {
+    /// Filter for the folder `root` opened in the sidebar
+    pub fn new(root: &Path, patterns: &[String], show_ignored: bool) -> Self {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in patterns.iter().map(|p| p.trim()).filter(|p| !p.is_empty()) {
+            if let Err(e) = builder.add_line(None, pattern) {
+                log::warn!("Invalid ignore pattern {:?}: {}", pattern, e);
+            }
+        }
+        let patterns = builder.build().unwrap_or_else(|e| {
+            log::warn!("Failed to build ignore patterns: {}", e);
+            Gitignore::empty()
+        });
+
+        let mut filter = FileFilter {
+            show_ignored,
+            patterns,
+            layers: Vec::new(),
+        };
+
+        // When the opened folder is inside a repository, the ignore files
+        // between it and the repository root apply as well
+        let repository = root.ancestors().find(|dir| dir.join(".git").exists());
+        if let Some(repository) = repository {
+            let parents: Vec<&Path> = root
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| dir.starts_with(repository))
+                .collect();
+            for parent in parents.into_iter().rev() {
+                filter.enter(parent);
+            }
+        }
+        filter.enter(root);
+        filter
+    }
+
+    /// Filter for `dir`, a folder somewhere below `root`
+    pub fn for_directory(root: &Path, dir: &Path, patterns: &[String], show_ignored: bool) -> Self {
+        let mut filter = FileFilter::new(root, patterns, show_ignored);
+        if let Ok(relative) = dir.strip_prefix(root) {
+            let mut current = root.to_path_buf();
+            for component in relative.components() {
+                current.push(component);
+                filter.enter(&current);
+            }
+        }
+        filter
+    }
+
+    /// Apply the ignore files of `dir` to the entries read from it
+    pub fn enter(&mut self, dir: &Path) {
+        let layer = if self.show_ignored {
+            None
+        } else {
+            load_ignore_files(dir)
+        };
+        self.layers.push(layer);
+    }
+
+    /// Undo the last `enter`
+    pub fn leave(&mut self) {
+        self.layers.pop();
+    }
+
+    pub fn is_visible(&self, path: &Path, is_dir: bool) -> bool {
+        let name = path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        // git's own database is never useful in the tree
+        if name == ".git" {
+            return false;
+        }
+        if self.show_ignored {
+            return true;
+        }
+        if name.starts_with('.') || self.patterns.matched(path, is_dir).is_ignore() {
+            return false;
+        }
+
+        // The closest ignore file with a matching rule decides
+        for layer in self.layers.iter().rev().flatten() {
+            match layer.matched(path, is_dir) {
+                Match::Ignore(_) => return false,
+                Match::Whitelist(_) => return true,
+                Match::None => {}
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_ignore_rules() {
+        let root = std::env::temp_dir().join(format!("docura-filter-test-{}", std::process::id()));
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::create_dir_all(root.join("docs/build")).unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n!keep.log\n").unwrap();
+        fs::write(root.join(".docuraignore"), "assets/\n").unwrap();
+        fs::write(root.join("docs/.ignore"), "build/\n!debug.log\n").unwrap();
+
+        let patterns = vec!["/drafts".to_string(), "*.tmp".to_string(), String::new()];
+        let visible = |filter: &FileFilter, path: &str, is_dir: bool| {
+            filter.is_visible(&root.join(path), is_dir)
+        };
+
+        let filter = FileFilter::new(&root, &patterns, false);
+        assert!(visible(&filter, "notes.md", false));
+        assert!(visible(&filter, "keep.log", false));
+        assert!(visible(&filter, "target", false));
+        assert!(!visible(&filter, "target", true));
+        assert!(!visible(&filter, "error.log", false));
+        assert!(!visible(&filter, "assets", true));
+        assert!(!visible(&filter, "drafts", true));
+        assert!(!visible(&filter, "scratch.tmp", false));
+        assert!(!visible(&filter, ".env", false));
+        assert!(!visible(&filter, ".git", true));
+
+        let docs = FileFilter::for_directory(&root, &root.join("docs"), &patterns, false);
+        assert!(!visible(&docs, "docs/build", true));
+        assert!(!visible(&docs, "docs/error.log", false));
+        assert!(visible(&docs, "docs/debug.log", false));
+        // Settings patterns starting with a slash only match at the top
+        assert!(visible(&docs, "docs/drafts", true));
+
+        // A subfolder opened on its own still honors the repository's rules
+        let opened = FileFilter::new(&root.join("docs"), &[], false);
+        assert!(!visible(&opened, "docs/error.log", false));
+
+        let everything = FileFilter::new(&root, &patterns, true);
+        assert!(visible(&everything, "error.log", false));
+        assert!(visible(&everything, ".env", false));
+        assert!(!visible(&everything, ".git", true));
+
+        fs::remove_dir_all(&root).ok();
+    }
+}
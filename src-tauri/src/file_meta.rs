@@ -0,0 +1,230 @@
+// File Metadata Module
+// Optional details about folder tree entries (size, times, word count and
+// front matter title) and the orders the tree can be sorted in.
+
+use crate::markdown;
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::fs;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct FileMetadata {
+    /// Size in bytes, for files
+    #[serde(default)]
+    size: Option<u64>,
+    /// Unix timestamps in seconds
+    #[serde(default)]
+    modified: Option<i64>,
+    #[serde(default)]
+    created: Option<i64>,
+    /// Words in a Markdown file, not counting its front matter
+    #[serde(rename = "wordCount", default)]
+    word_count: Option<usize>,
+    /// `title` from a Markdown file's front matter
+    #[serde(default)]
+    title: Option<String>,
+}
+
+/// Orders for the folder tree; folders always come before files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortMode {
+    /// Alphabetically, ignoring case
+    #[default]
+    Name,
+    /// Most recently modified first
+    Modified,
+    /// Most recently created first
+    Created,
+    /// By front matter title, falling back to the name
+    Title,
+    /// Alphabetically, with numbers by value (`2` before `10`)
+    Natural,
+}
+
+impl SortMode {
+    /// Whether sorting needs the entries' metadata
+    pub fn needs_metadata(self) -> bool {
+        !matches!(self, SortMode::Name | SortMode::Natural)
+    }
+}
+
+fn unix_seconds(time: std::io::Result<SystemTime>) -> Option<i64> {
+    time.ok()?
+        .duration_since(UNIX_EPOCH)
+        .ok()
+        .map(|d| d.as_secs() as i64)
+}
+
+/// Read the metadata of `path`. Markdown files are read for their word
+/// count and title only when `with_content` is set.
+pub fn read_metadata(path: &Path, with_content: bool) -> Option<FileMetadata> {
+    let stat = fs::metadata(path)
+        .map_err(|e| log::warn!("Failed to read metadata of {}: {}", path.display(), e))
+        .ok()?;
+    let mut metadata = FileMetadata {
+        size: stat.is_file().then_some(stat.len()),
+        modified: unix_seconds(stat.modified()),
+        created: unix_seconds(stat.created()),
+        ..Default::default()
+    };
+
+    if with_content && stat.is_file() && markdown::is_markdown_path(path) {
+        match fs::read_to_string(path) {
+            Ok(content) => {
+                let (front_matter, body) = markdown::split_front_matter(&content);
+                // Counted like the status bar does
+                metadata.word_count = Some(body.split_whitespace().count());
+                metadata.title =
+                    front_matter.and_then(|f| markdown::front_matter_value(f, "title"));
+            }
+            Err(e) => log::warn!("Failed to read {}: {}", path.display(), e),
+        }
+    }
+    Some(metadata)
+}
+
+/// Metadata to attach to a tree entry: all of it with `with_metadata`,
+/// otherwise only what sorting in `sort` order needs
+pub fn for_entry(path: &Path, sort: SortMode, with_metadata: bool) -> Option<FileMetadata> {
+    if with_metadata || sort.needs_metadata() {
+        read_metadata(path, with_metadata || sort == SortMode::Title)
+    } else {
+        None
+    }
+}
+
+/// Compare names with runs of digits ordered by their value
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let (mut a, mut b) = (a.chars().peekable(), b.chars().peekable());
+    loop {
+        match (a.peek().copied(), b.peek().copied()) {
+            (None, None) => return Ordering::Equal,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                let take_number = |chars: &mut std::iter::Peekable<std::str::Chars>| {
+                    let mut digits = String::new();
+                    while let Some(c) = chars.next_if(|c| c.is_ascii_digit()) {
+                        digits.push(c);
+                    }
+                    digits
+                };
+                let (x, y) = (take_number(&mut a), take_number(&mut b));
+                let (x_value, y_value) = (x.trim_start_matches('0'), y.trim_start_matches('0'));
+                let ordering = x_value
+                    .len()
+                    .cmp(&y_value.len())
+                    .then_with(|| x_value.cmp(y_value))
+                    .then_with(|| x.len().cmp(&y.len()));
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+            }
+            (Some(x), Some(y)) => {
+                let ordering = x.to_lowercase().cmp(y.to_lowercase());
+                if ordering != Ordering::Equal {
+                    return ordering;
+                }
+                a.next();
+                b.next();
+            }
+        }
+    }
+}
+
+/// What an entry is sorted by
+pub struct SortKey<'a> {
+    pub name: &'a str,
+    pub is_dir: bool,
+    pub metadata: Option<&'a FileMetadata>,
+}
+
+impl SortKey<'_> {
+    fn title(&self) -> &str {
+        self.metadata
+            .and_then(|m| m.title.as_deref())
+            .unwrap_or(self.name)
+    }
+}
+
+pub fn compare(a: &SortKey, b: &SortKey, mode: SortMode) -> Ordering {
+    let by_name = || a.name.to_lowercase().cmp(&b.name.to_lowercase());
+    let newest = |time: fn(&FileMetadata) -> Option<i64>| {
+        let a = a.metadata.and_then(time);
+        let b = b.metadata.and_then(time);
+        b.cmp(&a)
+    };
+
+    b.is_dir.cmp(&a.is_dir).then_with(|| match mode {
+        SortMode::Name => by_name(),
+        SortMode::Natural => natural_cmp(a.name, b.name).then_with(by_name),
+        SortMode::Modified => newest(|m| m.modified).then_with(by_name),
+        SortMode::Created => newest(|m| m.created).then_with(by_name),
+        SortMode::Title => natural_cmp(a.title(), b.title()).then_with(by_name),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sort_modes() {
+        let meta = |modified, title: Option<&str>| FileMetadata {
+            modified: Some(modified),
+            title: title.map(str::to_string),
+            ..Default::default()
+        };
+        let entries = [
+            ("Chapter 10.md", false, meta(300, Some("Appendix"))),
+            ("chapter 2.md", false, meta(100, None)),
+            ("Chapter 1.md", false, meta(200, Some("Zebra"))),
+            ("archive", true, meta(50, None)),
+        ];
+        let sorted = |mode| {
+            let mut keys: Vec<SortKey> = entries
+                .iter()
+                .map(|(name, is_dir, metadata)| SortKey {
+                    name,
+                    is_dir: *is_dir,
+                    metadata: Some(metadata),
+                })
+                .collect();
+            keys.sort_by(|a, b| compare(a, b, mode));
+            keys.iter().map(|k| k.name).collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            sorted(SortMode::Name),
+            ["archive", "Chapter 1.md", "Chapter 10.md", "chapter 2.md"]
+        );
+        assert_eq!(
+            sorted(SortMode::Natural),
+            ["archive", "Chapter 1.md", "chapter 2.md", "Chapter 10.md"]
+        );
+        assert_eq!(
+            sorted(SortMode::Modified),
+            ["archive", "Chapter 10.md", "Chapter 1.md", "chapter 2.md"]
+        );
+        assert_eq!(
+            sorted(SortMode::Title),
+            ["archive", "Chapter 10.md", "chapter 2.md", "Chapter 1.md"]
+        );
+        assert_eq!(natural_cmp("v007", "v7"), Ordering::Greater);
+
+        let path = std::env::temp_dir().join(format!("docura-meta-test-{}.md", std::process::id()));
+        fs::write(
+            &path,
+            "---\ntitle: 'Real Title'\n---\n# Heading\n\nThree more words\n",
+        )
+        .unwrap();
+        let metadata = read_metadata(&path, true).unwrap();
+        fs::remove_file(&path).ok();
+        assert_eq!(metadata.title.as_deref(), Some("Real Title"));
+        assert_eq!(metadata.word_count, Some(5));
+        assert_eq!(metadata.size, Some(56));
+    }
+}
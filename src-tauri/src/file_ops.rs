@@ -0,0 +1,374 @@
+// File Operations Module
+// Creating, renaming, moving, duplicating and trashing entries of the
+// folder tree. None of them ever replaces an existing entry, and trashed
+// entries go to the freedesktop.org Trash so they can be restored from the
+// file manager.
+
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Component, Path, PathBuf};
+
+/// A new name for an entry inside its folder: not empty and no path
+fn validate_name(name: &str) -> Result<&str, String> {
+    let name = name.trim();
+    let mut components = Path::new(name).components();
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) if !name.contains(['/', '\0']) => Ok(name),
+        _ => Err(format!("Invalid name: {:?}", name)),
+    }
+}
+
+fn file_name(path: &Path) -> Result<String, String> {
+    path.file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .ok_or_else(|| format!("Invalid path: {}", path.display()))
+}
+
+fn absolute(path: &Path) -> Result<PathBuf, String> {
+    if path.is_absolute() {
+        return Ok(path.to_path_buf());
+    }
+    std::env::current_dir()
+        .map(|dir| dir.join(path))
+        .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))
+}
+
+/// Whether a rename failed because source and target are on different
+/// file systems (EXDEV)
+fn crosses_devices(e: &std::io::Error) -> bool {
+    cfg!(unix) && e.raw_os_error() == Some(18)
+}
+
+fn ensure_free(path: &Path) -> Result<(), String> {
+    // symlink_metadata, so dangling symlinks count as existing too
+    if fs::symlink_metadata(path).is_ok() {
+        return Err(format!("{} already exists", path.display()));
+    }
+    Ok(())
+}
+
+/// Create a new file named `name` in `parent`
+pub fn create_file(parent: &Path, name: &str, content: &str) -> Result<PathBuf, String> {
+    let path = parent.join(validate_name(name)?);
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&path)
+        .map_err(|e| match e.kind() {
+            ErrorKind::AlreadyExists => format!("{} already exists", path.display()),
+            _ => format!("Failed to create file: {}", e),
+        })?;
+    file.write_all(content.as_bytes())
+        .map_err(|e| format!("Failed to write file: {}", e))?;
+    Ok(path)
+}
+
+/// Create a new folder named `name` in `parent`
+pub fn create_folder(parent: &Path, name: &str) -> Result<PathBuf, String> {
+    let path = parent.join(validate_name(name)?);
+    fs::create_dir(&path).map_err(|e| match e.kind() {
+        ErrorKind::AlreadyExists => format!("{} already exists", path.display()),
+        _ => format!("Failed to create folder: {}", e),
+    })?;
+    Ok(path)
+}
+
+/// Rename `from` to `to`, copying across file systems. Fails if `to` exists.
+fn move_entry(from: &Path, to: &Path) -> Result<(), String> {
+    // Checked right before renaming; rename itself would replace the target
+    ensure_free(to)?;
+    match fs::rename(from, to) {
+        Ok(()) => Ok(()),
+        Err(e) if crosses_devices(&e) => {
+            copy_entry(from, to)?;
+            remove_entry(from)
+        }
+        Err(e) => Err(format!("Failed to move {}: {}", from.display(), e)),
+    }
+}
+
+fn remove_entry(path: &Path) -> Result<(), String> {
+    let result = if fs::symlink_metadata(path).is_ok_and(|m| m.is_dir()) {
+        fs::remove_dir_all(path)
+    } else {
+        fs::remove_file(path)
+    };
+    result.map_err(|e| format!("Failed to remove {}: {}", path.display(), e))
+}
+
+/// Copy a file, or a folder with everything in it, to `to`, which must not exist
+fn copy_entry(from: &Path, to: &Path) -> Result<(), String> {
+    let metadata = fs::symlink_metadata(from)
+        .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+
+    if metadata.is_dir() {
+        fs::create_dir(to).map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        let entries =
+            fs::read_dir(from).map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+            copy_entry(&entry.path(), &to.join(entry.file_name()))?;
+        }
+        fs::set_permissions(to, metadata.permissions())
+            .map_err(|e| format!("Failed to copy permissions: {}", e))?;
+    } else if metadata.is_symlink() {
+        let target = fs::read_link(from)
+            .map_err(|e| format!("Failed to read link {}: {}", from.display(), e))?;
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, to)
+            .map_err(|e| format!("Failed to create link {}: {}", to.display(), e))?;
+        #[cfg(not(unix))]
+        return Err(format!("Cannot copy link to {}", target.display()));
+    } else {
+        let mut source = fs::File::open(from)
+            .map_err(|e| format!("Failed to read {}: {}", from.display(), e))?;
+        let mut target = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(to)
+            .map_err(|e| format!("Failed to create {}: {}", to.display(), e))?;
+        std::io::copy(&mut source, &mut target)
+            .map_err(|e| format!("Failed to copy {}: {}", from.display(), e))?;
+        target
+            .set_permissions(metadata.permissions())
+            .map_err(|e| format!("Failed to copy permissions: {}", e))?;
+    }
+    Ok(())
+}
+
+/// Give `path` a new name in the same folder
+pub fn rename(path: &Path, new_name: &str) -> Result<PathBuf, String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Cannot rename {}", path.display()))?;
+    let target = parent.join(validate_name(new_name)?);
+    if target == path {
+        return Ok(target);
+    }
+
+    // Only the case changes: on case-insensitive file systems the "other"
+    // file is this one
+    let same_entry = file_name(path)?.to_lowercase() == file_name(&target)?.to_lowercase()
+        && fs::metadata(&target).is_ok()
+        && fs::canonicalize(path).ok() == fs::canonicalize(&target).ok();
+    if same_entry {
+        fs::rename(path, &target).map_err(|e| format!("Failed to rename: {}", e))?;
+    } else {
+        move_entry(path, &target)?;
+    }
+    Ok(target)
+}
+
+/// Move `path` into the folder `target_dir`, keeping its name
+pub fn move_into(path: &Path, target_dir: &Path) -> Result<PathBuf, String> {
+    if !target_dir.is_dir() {
+        return Err(format!("{} is not a folder", target_dir.display()));
+    }
+    if let (Ok(source), Ok(target)) = (path.canonicalize(), target_dir.canonicalize()) {
+        if target.starts_with(&source) {
+            return Err("Cannot move a folder into itself".to_string());
+        }
+    }
+
+    let target = target_dir.join(file_name(path)?);
+    if target == path {
+        return Ok(target);
+    }
+    move_entry(path, &target)?;
+    Ok(target)
+}
+
+/// `notes.md` becomes `notes copy.md`, then `notes copy 2.md`, ...
+fn copy_name(name: &str, is_dir: bool, attempt: usize) -> String {
+    let (stem, extension) = match name.rfind('.') {
+        Some(dot) if dot > 0 && !is_dir => name.split_at(dot),
+        _ => (name, ""),
+    };
+    match attempt {
+        1 => format!("{} copy{}", stem, extension),
+        n => format!("{} copy {}{}", stem, n, extension),
+    }
+}
+
+/// Copy `path` next to itself under the first free "copy" name
+pub fn duplicate(path: &Path) -> Result<PathBuf, String> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| format!("Cannot duplicate {}", path.display()))?;
+    let name = file_name(path)?;
+    let is_dir = path.is_dir();
+
+    for attempt in 1.. {
+        let target = parent.join(copy_name(&name, is_dir, attempt));
+        if fs::symlink_metadata(&target).is_ok() {
+            continue;
+        }
+        copy_entry(path, &target)?;
+        return Ok(target);
+    }
+    unreachable!()
+}
+
+/// `$XDG_DATA_HOME/Trash`, the trash for files on the home file system
+fn home_trash() -> Result<PathBuf, String> {
+    let data_home = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .filter(|p| p.is_absolute())
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))
+        .ok_or_else(|| "Could not find HOME directory".to_string())?;
+    Ok(data_home.join("Trash"))
+}
+
+/// Percent-encode a path for the `Path=` key of a `.trashinfo` file
+fn encode_trash_path(path: &Path) -> String {
+    path.to_string_lossy()
+        .split('/')
+        .map(|part| urlencoding::encode(part).into_owned())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Move `path` into the trash directory `trash`. `topdir` is the top of the
+/// file system for a per-volume trash, which records relative paths.
+fn trash_into(path: &Path, trash: &Path, topdir: Option<&Path>) -> Result<PathBuf, String> {
+    let files = trash.join("files");
+    let info = trash.join("info");
+    for dir in [&files, &info] {
+        fs::create_dir_all(dir).map_err(|e| format!("Failed to create trash: {}", e))?;
+    }
+    // Other users on a shared volume must not see what was trashed
+    #[cfg(unix)]
+    if topdir.is_some() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(trash, fs::Permissions::from_mode(0o700))
+            .map_err(|e| format!("Failed to create trash: {}", e))?;
+    }
+
+    let original = absolute(path)?;
+    let recorded = topdir
+        .and_then(|topdir| original.strip_prefix(topdir).ok())
+        .unwrap_or(&original);
+    let contents = format!(
+        "[Trash Info]\nPath={}\nDeletionDate={}\n",
+        encode_trash_path(recorded),
+        chrono::Local::now().format("%Y-%m-%dT%H:%M:%S")
+    );
+
+    // The info file is created first; its name reserves the one in files/
+    let name = file_name(path)?;
+    for attempt in 1.. {
+        let trashed_name = match attempt {
+            1 => name.clone(),
+            n => format!("{}.{}", name, n),
+        };
+        let info_path = info.join(format!("{}.trashinfo", trashed_name));
+        let target = files.join(&trashed_name);
+        let info_file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&info_path);
+        let mut info_file = match info_file {
+            Ok(file) => file,
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(format!("Failed to write trash info: {}", e)),
+        };
+        if fs::symlink_metadata(&target).is_ok() {
+            fs::remove_file(&info_path).ok();
+            continue;
+        }
+
+        let result = info_file
+            .write_all(contents.as_bytes())
+            .map_err(|e| format!("Failed to write trash info: {}", e))
+            .and_then(|_| {
+                fs::rename(path, &target)
+                    .map_err(|e| format!("Failed to move {} to the trash: {}", path.display(), e))
+            });
+        if let Err(e) = result {
+            fs::remove_file(&info_path).ok();
+            return Err(e);
+        }
+        return Ok(target);
+    }
+    unreachable!()
+}
+
+/// Move `path` to the trash: the home trash when it is on the same file
+/// system, otherwise `.Trash-<uid>` at the top of the path's file system
+pub fn trash(path: &Path) -> Result<PathBuf, String> {
+    fs::symlink_metadata(path).map_err(|e| format!("Failed to trash {}: {}", path.display(), e))?;
+    let home_trash = home_trash()?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let device = |p: &Path| fs::symlink_metadata(p).map(|m| m.dev()).ok();
+        let home = home_trash
+            .ancestors()
+            .find(|dir| dir.exists())
+            .ok_or_else(|| "Could not find the trash".to_string())?;
+        let path_device = device(path);
+        if path_device != device(home) {
+            // The mount point is the topmost folder still on the same device
+            let absolute = absolute(path)?;
+            let topdir = absolute
+                .ancestors()
+                .skip(1)
+                .take_while(|dir| device(dir) == path_device)
+                .last()
+                .ok_or_else(|| format!("Cannot trash {}", path.display()))?;
+            let uid = fs::metadata(home)
+                .map_err(|e| format!("Failed to trash: {}", e))?
+                .uid();
+            return trash_into(path, &topdir.join(format!(".Trash-{}", uid)), Some(topdir));
+        }
+    }
+
+    trash_into(path, &home_trash, None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_operations() {
+        let root = std::env::temp_dir().join(format!("docura-ops-test-{}", std::process::id()));
+        let trash = root.join("Trash");
+        fs::create_dir_all(root.join("notes")).unwrap();
+
+        let file = create_file(&root, "draft.md", "# Draft").unwrap();
+        assert!(create_file(&root, "draft.md", "").is_err());
+        assert!(create_file(&root, "../escape.md", "").is_err());
+        assert!(create_folder(&root, "notes").is_err());
+
+        fs::write(root.join("taken.md"), "keep").unwrap();
+        assert!(rename(&file, "taken.md").is_err());
+        assert_eq!(fs::read_to_string(root.join("taken.md")).unwrap(), "keep");
+        let file = rename(&file, "plan.md").unwrap();
+
+        let copy = duplicate(&file).unwrap();
+        let second_copy = duplicate(&file).unwrap();
+        let folder_copy = duplicate(&root.join("notes")).unwrap();
+        assert_eq!(copy, root.join("plan copy.md"));
+        assert_eq!(second_copy, root.join("plan copy 2.md"));
+        assert_eq!(folder_copy, root.join("notes copy"));
+        assert_eq!(fs::read_to_string(&copy).unwrap(), "# Draft");
+
+        let moved = move_into(&file, &root.join("notes")).unwrap();
+        assert_eq!(moved, root.join("notes/plan.md"));
+        assert!(move_into(&root.join("notes"), &root.join("notes")).is_err());
+
+        let trashed = trash_into(&copy, &trash, None).unwrap();
+        fs::write(root.join("plan copy.md"), "again").unwrap();
+        let trashed_again = trash_into(&root.join("plan copy.md"), &trash, None).unwrap();
+        let info = fs::read_to_string(trash.join("info/plan copy.md.trashinfo")).unwrap();
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(trashed, trash.join("files/plan copy.md"));
+        assert_eq!(trashed_again, trash.join("files/plan copy.md.2"));
+        assert!(info.starts_with("[Trash Info]\nPath=/"));
+        assert!(info.contains("/plan%20copy.md\nDeletionDate="));
+    }
+}
@@ -0,0 +1,605 @@
+// Git Module
+// Version control for opened folders that live in a git repository, done
+// in-process so that no git binary is needed: which files changed, the
+// contents of a file as of a commit, staging, committing and the commits
+// that changed a file. Paths given to and returned from here are absolute;
+// git's own paths are relative to the work tree, with `/` separators.
+
+use gix::bstr::{BStr, BString, ByteSlice};
+use gix::status::index_worktree::iter::Summary;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// How a file differs between two of the commit, the index and the work tree
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Change {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+    TypeChanged,
+    Untracked,
+    Conflicted,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FileStatus {
+    pub path: String,
+    /// Between the last commit and the index: what committing would record
+    pub staged: Option<Change>,
+    /// Between the index and the file on disk: what staging would add
+    pub unstaged: Option<Change>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RepoStatus {
+    /// The work tree the files belong to
+    pub root: String,
+    /// The checked-out branch, `None` when HEAD is detached
+    pub branch: Option<String>,
+    /// Changed files only, sorted by path
+    pub files: Vec<FileStatus>,
+}
+
+/// A commit in the log of a file
+#[derive(Debug, Clone, Serialize)]
+pub struct FileCommit {
+    pub id: String,
+    #[serde(rename = "shortId")]
+    pub short_id: String,
+    /// The first line of the message
+    pub summary: String,
+    pub author: String,
+    /// Commit time in milliseconds since the epoch
+    pub time: i64,
+}
+
+pub struct Repo {
+    repo: gix::Repository,
+    workdir: PathBuf,
+}
+
+impl Repo {
+    /// The repository `path` is in, or `None` if it isn't in one with a
+    /// work tree
+    pub fn discover(path: &Path) -> Result<Option<Self>, String> {
+        let repo = match gix::discover(path) {
+            Ok(repo) => repo,
+            Err(gix::discover::Error::Discover(_)) => return Ok(None),
+            Err(e) => return Err(format!("Failed to open git repository: {}", e)),
+        };
+        let Some(workdir) = repo.workdir() else {
+            return Ok(None);
+        };
+        let workdir = fs::canonicalize(workdir)
+            .map_err(|e| format!("Failed to open git repository: {}", e))?;
+        Ok(Some(Repo { repo, workdir }))
+    }
+
+    /// Like `discover`, for operations that need a repository
+    pub fn open(path: &Path) -> Result<Self, String> {
+        Repo::discover(path)?
+            .ok_or_else(|| format!("{} is not in a git repository", path.display()))
+    }
+
+    /// The path of `path` in the repository
+    fn relative(&self, path: &Path) -> Result<BString, String> {
+        // The file may be gone (deleted, to be staged as such); its folder
+        // is resolved instead
+        let resolved = match fs::canonicalize(path) {
+            Ok(resolved) => resolved,
+            Err(_) => {
+                let parent = path.parent().unwrap_or(Path::new("."));
+                let name = path
+                    .file_name()
+                    .ok_or_else(|| format!("Invalid path: {}", path.display()))?;
+                fs::canonicalize(parent)
+                    .map_err(|e| format!("Failed to resolve {}: {}", path.display(), e))?
+                    .join(name)
+            }
+        };
+        let relative = resolved
+            .strip_prefix(&self.workdir)
+            .map_err(|_| format!("{} is outside the repository", path.display()))?;
+        let relative = gix::path::into_bstr(relative);
+        Ok(gix::path::to_unix_separators_on_windows(relative).into_owned())
+    }
+
+    fn absolute(&self, relative: &BStr) -> String {
+        self.workdir
+            .join(gix::path::from_bstr(relative))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    /// The commit HEAD points at, `None` on a branch without commits yet
+    fn head_id(&self) -> Result<Option<gix::ObjectId>, String> {
+        let mut head = self
+            .repo
+            .head()
+            .map_err(|e| format!("Failed to read HEAD: {}", e))?;
+        let id = head
+            .try_peel_to_id()
+            .map_err(|e| format!("Failed to read HEAD: {}", e))?;
+        Ok(id.map(|id| id.detach()))
+    }
+
+    /// Staged and unstaged changes of every changed file, untracked ones
+    /// included and ignored ones left out
+    pub fn status(&self) -> Result<RepoStatus, String> {
+        let items = self
+            .repo
+            .status(gix::progress::Discard)
+            .map_err(|e| format!("Failed to read git status: {}", e))?
+            .untracked_files(gix::status::UntrackedFiles::Files)
+            .into_iter(None)
+            .map_err(|e| format!("Failed to read git status: {}", e))?;
+
+        let mut files: BTreeMap<BString, FileStatus> = BTreeMap::new();
+        for item in items {
+            let item = item.map_err(|e| format!("Failed to read git status: {}", e))?;
+            let location = item.location().to_owned();
+            let file = files.entry(location.clone()).or_insert_with(|| FileStatus {
+                path: self.absolute(location.as_ref()),
+                staged: None,
+                unstaged: None,
+            });
+            match item {
+                gix::status::Item::TreeIndex(change) => {
+                    use gix::diff::index::ChangeRef;
+                    file.staged = Some(match change {
+                        ChangeRef::Addition { .. } => Change::Added,
+                        ChangeRef::Deletion { .. } => Change::Deleted,
+                        ChangeRef::Modification { .. } => Change::Modified,
+                        ChangeRef::Rewrite { .. } => Change::Renamed,
+                    });
+                }
+                gix::status::Item::IndexWorktree(change) => {
+                    file.unstaged = change.summary().map(|summary| match summary {
+                        Summary::Added => Change::Untracked,
+                        Summary::IntentToAdd => Change::Added,
+                        Summary::Removed => Change::Deleted,
+                        Summary::Modified => Change::Modified,
+                        Summary::TypeChange => Change::TypeChanged,
+                        Summary::Renamed | Summary::Copied => Change::Renamed,
+                        Summary::Conflict => Change::Conflicted,
+                    });
+                }
+            }
+        }
+
+        let branch = self
+            .repo
+            .head_name()
+            .map_err(|e| format!("Failed to read HEAD: {}", e))?
+            .map(|name| name.shorten().to_string());
+        Ok(RepoStatus {
+            root: self.workdir.to_string_lossy().to_string(),
+            branch,
+            files: files
+                .into_values()
+                .filter(|f| f.staged.is_some() || f.unstaged.is_some())
+                .collect(),
+        })
+    }
+
+    fn find_commit(&self, commit: Option<&str>) -> Result<Option<gix::Commit<'_>>, String> {
+        let id = match commit {
+            Some(hex) => Some(
+                gix::ObjectId::from_hex(hex.as_bytes())
+                    .map_err(|e| format!("Invalid commit id {}: {}", hex, e))?,
+            ),
+            None => self.head_id()?,
+        };
+        id.map(|id| {
+            self.repo
+                .find_commit(id)
+                .map_err(|e| format!("Failed to find commit {}: {}", id, e))
+        })
+        .transpose()
+    }
+
+    /// The id of the blob at `relative` in `commit`
+    fn blob_id(
+        &self,
+        commit: &gix::Commit<'_>,
+        relative: &BStr,
+    ) -> Result<Option<gix::ObjectId>, String> {
+        let tree = commit
+            .tree()
+            .map_err(|e| format!("Failed to read commit {}: {}", commit.id, e))?;
+        let entry = tree
+            .lookup_entry_by_path(gix::path::from_bstr(relative))
+            .map_err(|e| format!("Failed to read commit {}: {}", commit.id, e))?;
+        Ok(entry
+            .filter(|entry| entry.mode().is_blob_or_symlink())
+            .map(|entry| entry.object_id()))
+    }
+
+    /// The contents of `path` as of `commit`, HEAD when `None`, or `None`
+    /// if the file isn't in it
+    pub fn file_at(&self, path: &Path, commit: Option<&str>) -> Result<Option<Vec<u8>>, String> {
+        let relative = self.relative(path)?;
+        let Some(commit) = self.find_commit(commit)? else {
+            return Ok(None);
+        };
+        let Some(id) = self.blob_id(&commit, relative.as_ref())? else {
+            return Ok(None);
+        };
+        let blob = self
+            .repo
+            .find_blob(id)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        Ok(Some(blob.data.clone()))
+    }
+
+    fn open_index(&self) -> Result<gix::index::File, String> {
+        if self.repo.index_path().exists() {
+            self.repo
+                .open_index()
+                .map_err(|e| format!("Failed to read git index: {}", e))
+        } else {
+            // No index until the first file is staged
+            let state = gix::index::State::new(self.repo.object_hash());
+            Ok(gix::index::File::from_state(state, self.repo.index_path()))
+        }
+    }
+
+    fn write_index(&self, mut index: gix::index::File) -> Result<(), String> {
+        // The cached trees describe the old entries
+        index.remove_tree();
+        index
+            .write(Default::default())
+            .map_err(|e| format!("Failed to write git index: {}", e))
+    }
+
+    /// Record the files at `paths` in the index as they are on disk; a
+    /// deleted file is staged as deleted
+    pub fn stage(&self, paths: &[PathBuf]) -> Result<(), String> {
+        use gix::index::entry::{Flags, Mode, Stage, Stat};
+
+        let mut index = self.open_index()?;
+        // Line endings, LFS and other filters from the attributes apply as with `git add`
+        let (mut pipeline, _) = self
+            .repo
+            .filter_pipeline(None)
+            .map_err(|e| format!("Failed to read git attributes: {}", e))?;
+        let mut added = false;
+        for path in paths {
+            let relative = self.relative(path)?;
+            let relative: &BStr = relative.as_ref();
+            // Staging a conflicted file resolves it
+            index.remove_entries(|_, p, entry| {
+                p == relative && entry.stage() != Stage::Unconflicted
+            });
+
+            let metadata = match fs::symlink_metadata(path) {
+                Ok(metadata) => metadata,
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                    index.remove_entries(|_, p, _| p == relative);
+                    continue;
+                }
+                Err(e) => return Err(format!("Failed to read {}: {}", path.display(), e)),
+            };
+            if !metadata.file_type().is_symlink() && !metadata.is_file() {
+                return Err(format!("{} is not a file", path.display()));
+            }
+            let (id, kind, _) = pipeline
+                .worktree_file_to_object(relative, &index)
+                .map_err(|e| format!("Failed to stage {}: {}", path.display(), e))?
+                .ok_or_else(|| format!("{} is not a file", path.display()))?;
+            let mode = match kind {
+                gix::object::tree::EntryKind::Link => Mode::SYMLINK,
+                gix::object::tree::EntryKind::BlobExecutable => Mode::FILE_EXECUTABLE,
+                _ => Mode::FILE,
+            };
+            let stat = gix::index::fs::Metadata::from_path_no_follow(path)
+                .ok()
+                .and_then(|metadata| Stat::from_fs(&metadata).ok())
+                .unwrap_or_default();
+            match index.entry_mut_by_path_and_stage(relative, Stage::Unconflicted) {
+                Some(entry) => {
+                    entry.id = id;
+                    entry.mode = mode;
+                    entry.stat = stat;
+                }
+                None => {
+                    index.dangerously_push_entry(stat, id, Flags::empty(), mode, relative);
+                    added = true;
+                }
+            }
+        }
+        if added {
+            index.sort_entries();
+        }
+        self.write_index(index)
+    }
+
+    /// Take the files at `paths` out of the next commit: their index
+    /// entries go back to what HEAD has, the files on disk stay as they are
+    pub fn unstage(&self, paths: &[PathBuf]) -> Result<(), String> {
+        use gix::index::entry::{Flags, Mode, Stage, Stat};
+
+        let head = self.find_commit(None)?;
+        let mut index = self.open_index()?;
+        let mut added = false;
+        for path in paths {
+            let relative = self.relative(path)?;
+            let relative: &BStr = relative.as_ref();
+            let committed = match &head {
+                Some(commit) => self.blob_id(commit, relative)?,
+                None => None,
+            };
+            let Some(id) = committed else {
+                index.remove_entries(|_, p, _| p == relative);
+                continue;
+            };
+            let mode = match fs::symlink_metadata(path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => Mode::SYMLINK,
+                Ok(metadata) => file_mode(&metadata),
+                Err(_) => Mode::FILE,
+            };
+            // A zeroed stat makes the next status compare the file's contents
+            match index.entry_mut_by_path_and_stage(relative, Stage::Unconflicted) {
+                Some(entry) => {
+                    entry.id = id;
+                    entry.stat = Stat::default();
+                }
+                None => {
+                    index.dangerously_push_entry(
+                        Stat::default(),
+                        id,
+                        Flags::empty(),
+                        mode,
+                        relative,
+                    );
+                    added = true;
+                }
+            }
+        }
+        if added {
+            index.sort_entries();
+        }
+        self.write_index(index)
+    }
+
+    /// Commit what is staged on the current branch. Returns the short id of
+    /// the new commit.
+    pub fn commit(&self, message: &str) -> Result<String, String> {
+        use gix::index::entry::Stage;
+
+        let message = message.trim();
+        if message.is_empty() {
+            return Err("A commit needs a message".to_string());
+        }
+        let index = self.open_index()?;
+        let empty = gix::ObjectId::empty_tree(self.repo.object_hash());
+        let mut tree = self
+            .repo
+            .edit_tree(empty)
+            .map_err(|e| format!("Failed to build commit tree: {}", e))?;
+        for entry in index.entries() {
+            let path = entry.path(&index);
+            if entry.stage() != Stage::Unconflicted {
+                return Err(format!("Resolve the conflict in {} first", path));
+            }
+            let kind = entry
+                .mode
+                .to_tree_entry_mode()
+                .ok_or_else(|| format!("Unsupported index entry {}", path))?
+                .kind();
+            tree.upsert(path, kind, entry.id)
+                .map_err(|e| format!("Failed to build commit tree: {}", e))?;
+        }
+        let tree = tree
+            .write()
+            .map_err(|e| format!("Failed to write commit tree: {}", e))?
+            .detach();
+
+        let parent = self.find_commit(None)?;
+        if let Some(parent) = &parent {
+            let parent_tree = parent
+                .tree_id()
+                .map_err(|e| format!("Failed to read HEAD: {}", e))?;
+            if parent_tree == tree {
+                return Err("Nothing is staged to commit".to_string());
+            }
+        }
+        let id = self
+            .repo
+            .commit("HEAD", message, tree, parent.map(|p| p.id))
+            .map_err(|e| match e {
+                gix::commit::Error::AuthorMissing | gix::commit::Error::CommitterMissing => {
+                    "Set user.name and user.email in your git configuration to commit".to_string()
+                }
+                e => format!("Failed to commit: {}", e),
+            })?;
+        Ok(short_id(&id))
+    }
+
+    /// The commits reachable from HEAD that changed `path`, newest first
+    pub fn file_log(&self, path: &Path, limit: usize) -> Result<Vec<FileCommit>, String> {
+        let relative = self.relative(path)?;
+        let relative: &BStr = relative.as_ref();
+        let Some(head) = self.head_id()? else {
+            return Ok(Vec::new());
+        };
+        let walk = self
+            .repo
+            .rev_walk([head])
+            .all()
+            .map_err(|e| format!("Failed to read git log: {}", e))?;
+
+        let mut log = Vec::new();
+        for info in walk {
+            let info = info.map_err(|e| format!("Failed to read git log: {}", e))?;
+            let commit = info
+                .object()
+                .map_err(|e| format!("Failed to read commit {}: {}", info.id, e))?;
+            let blob = self.blob_id(&commit, relative)?;
+            // Changed compared to every parent, so merges that only took
+            // one side's version don't count
+            let mut changed = blob.is_some();
+            for parent in info.parent_ids() {
+                let parent = self
+                    .repo
+                    .find_commit(parent)
+                    .map_err(|e| format!("Failed to read commit {}: {}", parent, e))?;
+                if self.blob_id(&parent, relative)? == blob {
+                    changed = false;
+                    break;
+                }
+                changed = true;
+            }
+            if !changed {
+                continue;
+            }
+
+            let message = commit
+                .message()
+                .map_err(|e| format!("Failed to read commit {}: {}", info.id, e))?;
+            let author = commit
+                .author()
+                .map_err(|e| format!("Failed to read commit {}: {}", info.id, e))?;
+            let time = commit
+                .time()
+                .map_err(|e| format!("Failed to read commit {}: {}", info.id, e))?;
+            log.push(FileCommit {
+                id: info.id.to_string(),
+                short_id: short_id(&info.id),
+                summary: message.summary().to_string(),
+                author: author.name.to_str_lossy().to_string(),
+                time: time.seconds * 1000,
+            });
+            if log.len() == limit {
+                break;
+            }
+        }
+        Ok(log)
+    }
+}
+
+fn short_id(id: &gix::oid) -> String {
+    id.to_hex_with_len(7).to_string()
+}
+
+fn file_mode(metadata: &fs::Metadata) -> gix::index::entry::Mode {
+    use gix::index::entry::Mode;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if metadata.permissions().mode() & 0o111 != 0 {
+            return Mode::FILE_EXECUTABLE;
+        }
+    }
+    let _ = metadata;
+    Mode::FILE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stage_commit_and_log() {
+        let dir = std::env::temp_dir().join(format!("docura-git-test-{}", std::process::id()));
+        fs::create_dir_all(dir.join("notes")).unwrap();
+        gix::init(&dir).unwrap();
+        let config = dir.join(".git").join("config");
+        let mut settings = fs::read_to_string(&config).unwrap();
+        settings.push_str("[user]\n\tname = Docura\n\temail = docura@example.com\n");
+        fs::write(&config, settings).unwrap();
+        let note = dir.join("notes").join("plan.md");
+        let readme = dir.join("README.md");
+        fs::write(&note, "# Plan\n").unwrap();
+        fs::write(&readme, "Docs\n").unwrap();
+
+        let repo = Repo::open(&dir.join("notes")).unwrap();
+        let changes = |status: &RepoStatus| {
+            status
+                .files
+                .iter()
+                .map(|f| (f.path.clone(), f.staged, f.unstaged))
+                .collect::<Vec<_>>()
+        };
+        let canonical = |path: &Path| {
+            fs::canonicalize(path)
+                .unwrap()
+                .to_string_lossy()
+                .to_string()
+        };
+        let status = repo.status().unwrap();
+        assert_eq!(
+            changes(&status),
+            vec![
+                (canonical(&readme), None, Some(Change::Untracked)),
+                (canonical(&note), None, Some(Change::Untracked)),
+            ]
+        );
+
+        repo.stage(&[note.clone(), readme.clone()]).unwrap();
+        let first = repo.commit("Add plan").unwrap();
+        assert!(repo.status().unwrap().files.is_empty());
+        assert!(repo.commit("Again").is_err());
+
+        fs::write(&note, "# Plan\n\nFinal\n").unwrap();
+        let status = repo.status().unwrap();
+        assert_eq!(status.files[0].unstaged, Some(Change::Modified));
+        repo.stage(std::slice::from_ref(&note)).unwrap();
+        assert_eq!(
+            repo.status().unwrap().files[0].staged,
+            Some(Change::Modified)
+        );
+        repo.unstage(std::slice::from_ref(&note)).unwrap();
+        let status = repo.status().unwrap();
+        assert_eq!(status.files[0].staged, None);
+        assert_eq!(status.files[0].unstaged, Some(Change::Modified));
+        repo.stage(std::slice::from_ref(&note)).unwrap();
+        let second = repo.commit("Finish plan").unwrap();
+
+        let log = repo.file_log(&note, 10).unwrap();
+        let readme_log = repo.file_log(&readme, 10).unwrap();
+        let old = repo.file_at(&note, Some(&log[1].id)).unwrap();
+        let head = repo.file_at(&note, None).unwrap();
+        let missing = repo.file_at(&dir.join("gone.md"), None).unwrap();
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(
+            log.iter().map(|c| c.short_id.as_str()).collect::<Vec<_>>(),
+            vec![second.as_str(), first.as_str()]
+        );
+        assert_eq!(log[0].summary, "Finish plan");
+        assert_eq!(readme_log.len(), 1);
+        assert_eq!(old.unwrap(), b"# Plan\n");
+        assert_eq!(head.unwrap(), b"# Plan\n\nFinal\n");
+        assert!(missing.is_none());
+    }
+
+    #[test]
+    fn test_stage_applies_attributes() {
+        let dir = std::env::temp_dir().join(format!("docura-git-eol-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        gix::init(&dir).unwrap();
+        let attributes = dir.join(".gitattributes");
+        let note = dir.join("windows.md");
+        fs::write(&attributes, "* text eol=lf\n").unwrap();
+        fs::write(&note, "# Notes\r\n\r\nWritten on Windows\r\n").unwrap();
+
+        let repo = Repo::open(&dir).unwrap();
+        repo.stage(&[attributes.clone(), note.clone()]).unwrap();
+        let index = repo.open_index().unwrap();
+        let entry = index.entry_by_path(b"windows.md".as_bstr()).unwrap();
+        let staged = repo.repo.find_object(entry.id).unwrap().data.clone();
+        let status = repo.status().unwrap();
+        fs::remove_dir_all(&dir).ok();
+
+        assert_eq!(staged, b"# Notes\n\nWritten on Windows\n");
+        // Staged as git add would, so the file doesn't show up as modified
+        assert!(status
+            .files
+            .iter()
+            .all(|f| f.unstaged != Some(Change::Modified)));
+    }
+}
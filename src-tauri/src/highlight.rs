@@ -0,0 +1,492 @@
+// Syntax Highlighting Module
+// A small lexer that wraps keywords, strings, comments, numbers, types and
+// function names of common languages in highlight.js class names, so
+// exported code blocks are coloured without any script.
+
+use crate::html_export::escape;
+
+struct Language {
+    names: &'static [&'static str],
+    keywords: &'static [&'static str],
+    literals: &'static [&'static str],
+    types: &'static [&'static str],
+    line_comments: &'static [&'static str],
+    block_comment: Option<(&'static str, &'static str)>,
+    quotes: &'static [char],
+    case_insensitive: bool,
+}
+
+const LANGUAGES: &[Language] = &[
+    Language {
+        names: &["rust", "rs"],
+        keywords: &[
+            "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+            "extern", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+            "mut", "pub", "ref", "return", "self", "Self", "static", "struct", "super", "trait",
+            "type", "unsafe", "use", "where", "while",
+        ],
+        literals: &["true", "false", "None", "Some", "Ok", "Err"],
+        types: &[
+            "bool", "char", "str", "String", "Vec", "Option", "Result", "Box", "i8", "i16", "i32",
+            "i64", "i128", "isize", "u8", "u16", "u32", "u64", "u128", "usize", "f32", "f64",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"'],
+        case_insensitive: false,
+    },
+    Language {
+        names: &[
+            "javascript",
+            "js",
+            "jsx",
+            "typescript",
+            "ts",
+            "tsx",
+            "mjs",
+            "cjs",
+        ],
+        keywords: &[
+            "async",
+            "await",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "delete",
+            "do",
+            "else",
+            "export",
+            "extends",
+            "finally",
+            "for",
+            "from",
+            "function",
+            "if",
+            "import",
+            "in",
+            "instanceof",
+            "interface",
+            "let",
+            "new",
+            "of",
+            "return",
+            "static",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "type",
+            "typeof",
+            "var",
+            "void",
+            "while",
+            "yield",
+        ],
+        literals: &["true", "false", "null", "undefined", "NaN", "Infinity"],
+        types: &[
+            "string", "number", "boolean", "any", "unknown", "never", "object", "Array", "Promise",
+            "Map", "Set",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["python", "py"],
+        keywords: &[
+            "and", "as", "assert", "async", "await", "break", "class", "continue", "def", "del",
+            "elif", "else", "except", "finally", "for", "from", "global", "if", "import", "in",
+            "is", "lambda", "nonlocal", "not", "or", "pass", "raise", "return", "try", "while",
+            "with", "yield",
+        ],
+        literals: &["True", "False", "None"],
+        types: &[
+            "int", "float", "str", "bool", "list", "dict", "set", "tuple", "bytes",
+        ],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["bash", "sh", "shell", "zsh", "console"],
+        keywords: &[
+            "if", "then", "else", "elif", "fi", "for", "while", "until", "do", "done", "case",
+            "esac", "in", "function", "return", "export", "local", "echo", "cd", "exit",
+        ],
+        literals: &["true", "false"],
+        types: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        case_insensitive: false,
+    },
+    Language {
+        names: &[
+            "c", "h", "cpp", "c++", "cc", "hpp", "java", "cs", "csharp", "kotlin", "kt", "swift",
+        ],
+        keywords: &[
+            "abstract",
+            "break",
+            "case",
+            "catch",
+            "class",
+            "const",
+            "continue",
+            "default",
+            "do",
+            "else",
+            "enum",
+            "extends",
+            "final",
+            "for",
+            "fun",
+            "func",
+            "goto",
+            "if",
+            "implements",
+            "import",
+            "include",
+            "let",
+            "namespace",
+            "new",
+            "package",
+            "private",
+            "protected",
+            "public",
+            "return",
+            "sizeof",
+            "static",
+            "struct",
+            "switch",
+            "this",
+            "throw",
+            "try",
+            "typedef",
+            "using",
+            "val",
+            "var",
+            "virtual",
+            "void",
+            "while",
+        ],
+        literals: &["true", "false", "null", "nullptr", "NULL", "nil"],
+        types: &[
+            "int", "long", "short", "char", "float", "double", "bool", "boolean", "byte",
+            "unsigned", "signed", "auto", "string", "String",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["go", "golang"],
+        keywords: &[
+            "break",
+            "case",
+            "chan",
+            "const",
+            "continue",
+            "default",
+            "defer",
+            "else",
+            "fallthrough",
+            "for",
+            "func",
+            "go",
+            "goto",
+            "if",
+            "import",
+            "interface",
+            "map",
+            "package",
+            "range",
+            "return",
+            "select",
+            "struct",
+            "switch",
+            "type",
+            "var",
+        ],
+        literals: &["true", "false", "nil", "iota"],
+        types: &[
+            "bool", "byte", "error", "float32", "float64", "int", "int8", "int16", "int32",
+            "int64", "rune", "string", "uint", "uint8", "uint16", "uint32", "uint64",
+        ],
+        line_comments: &["//"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\'', '`'],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["json", "jsonc"],
+        keywords: &[],
+        literals: &["true", "false", "null"],
+        types: &[],
+        line_comments: &["//"],
+        block_comment: None,
+        quotes: &['"'],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["toml", "ini", "yaml", "yml"],
+        keywords: &[],
+        literals: &["true", "false", "null", "yes", "no"],
+        types: &[],
+        line_comments: &["#"],
+        block_comment: None,
+        quotes: &['"', '\''],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["css", "scss", "less"],
+        keywords: &["important", "media", "import", "keyframes", "from", "to"],
+        literals: &[],
+        types: &[],
+        line_comments: &[],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['"', '\''],
+        case_insensitive: false,
+    },
+    Language {
+        names: &["sql", "mysql", "postgresql", "sqlite"],
+        keywords: &[
+            "select",
+            "from",
+            "where",
+            "insert",
+            "into",
+            "values",
+            "update",
+            "set",
+            "delete",
+            "create",
+            "table",
+            "drop",
+            "alter",
+            "index",
+            "join",
+            "left",
+            "right",
+            "inner",
+            "outer",
+            "on",
+            "group",
+            "by",
+            "order",
+            "having",
+            "limit",
+            "as",
+            "and",
+            "or",
+            "not",
+            "in",
+            "is",
+            "distinct",
+            "union",
+            "primary",
+            "key",
+            "references",
+        ],
+        literals: &["true", "false", "null"],
+        types: &[
+            "int",
+            "integer",
+            "text",
+            "varchar",
+            "boolean",
+            "date",
+            "timestamp",
+            "real",
+        ],
+        line_comments: &["--"],
+        block_comment: Some(("/*", "*/")),
+        quotes: &['\''],
+        case_insensitive: true,
+    },
+];
+
+fn language(name: &str) -> Option<&'static Language> {
+    let name = name.trim().to_lowercase();
+    LANGUAGES
+        .iter()
+        .find(|lang| lang.names.contains(&name.as_str()))
+}
+
+fn push_token(out: &mut String, class: &str, text: &str) {
+    out.push_str(&format!(
+        "<span class=\"hljs-{}\">{}</span>",
+        class,
+        escape(text)
+    ));
+}
+
+/// Highlight `code` as `language`, returning HTML for the inside of
+/// `<code>`. Returns `None` for languages without a definition.
+pub fn highlight(code: &str, language_name: &str) -> Option<String> {
+    let lang = language(language_name)?;
+    let chars: Vec<char> = code.chars().collect();
+    let mut out = String::with_capacity(code.len() * 2);
+    let mut plain = String::new();
+    let mut i = 0;
+
+    let starts_with = |i: usize, pattern: &str| -> bool {
+        pattern
+            .chars()
+            .enumerate()
+            .all(|(offset, c)| chars.get(i + offset) == Some(&c))
+    };
+    let flush = |out: &mut String, plain: &mut String| {
+        out.push_str(&escape(plain));
+        plain.clear();
+    };
+
+    while i < chars.len() {
+        let c = chars[i];
+
+        // Comments run to the end of the line or the block terminator
+        if let Some(marker) = lang.line_comments.iter().find(|m| starts_with(i, m)) {
+            // `#` only starts a comment at a word boundary (not in `a#b` or `$#`)
+            let boundary = i == 0 || chars[i - 1].is_whitespace();
+            if *marker != "#" || boundary {
+                let end = chars[i..]
+                    .iter()
+                    .position(|&c| c == '\n')
+                    .map_or(chars.len(), |p| i + p);
+                flush(&mut out, &mut plain);
+                push_token(
+                    &mut out,
+                    "comment",
+                    &chars[i..end].iter().collect::<String>(),
+                );
+                i = end;
+                continue;
+            }
+        }
+        if let Some((open, close)) = lang.block_comment {
+            if starts_with(i, open) {
+                let body_start = i + open.chars().count();
+                let end = (body_start..chars.len())
+                    .find(|&j| starts_with(j, close))
+                    .map_or(chars.len(), |j| j + close.chars().count());
+                flush(&mut out, &mut plain);
+                push_token(
+                    &mut out,
+                    "comment",
+                    &chars[i..end].iter().collect::<String>(),
+                );
+                i = end;
+                continue;
+            }
+        }
+
+        if lang.quotes.contains(&c) {
+            let mut end = i + 1;
+            while end < chars.len() && chars[end] != c {
+                if chars[end] == '\\' && end + 1 < chars.len() {
+                    end += 1;
+                }
+                // Only template literals and Go raw strings span lines
+                if chars[end] == '\n' && c != '`' {
+                    break;
+                }
+                end += 1;
+            }
+            let end = (end + 1).min(chars.len());
+            flush(&mut out, &mut plain);
+            push_token(
+                &mut out,
+                "string",
+                &chars[i..end].iter().collect::<String>(),
+            );
+            i = end;
+            continue;
+        }
+
+        let after_word = i > 0 && (chars[i - 1].is_alphanumeric() || chars[i - 1] == '_');
+        if c.is_ascii_digit() && !after_word {
+            let end = (i..chars.len())
+                .find(|&j| {
+                    !(chars[j].is_ascii_alphanumeric() || chars[j] == '.' || chars[j] == '_')
+                })
+                .unwrap_or(chars.len());
+            flush(&mut out, &mut plain);
+            push_token(
+                &mut out,
+                "number",
+                &chars[i..end].iter().collect::<String>(),
+            );
+            i = end;
+            continue;
+        }
+
+        if (c.is_alphabetic() || c == '_') && !after_word {
+            let end = (i..chars.len())
+                .find(|&j| !(chars[j].is_alphanumeric() || chars[j] == '_'))
+                .unwrap_or(chars.len());
+            let word: String = chars[i..end].iter().collect();
+            let matches = |list: &[&str]| {
+                if lang.case_insensitive {
+                    list.iter().any(|w| w.eq_ignore_ascii_case(&word))
+                } else {
+                    list.contains(&word.as_str())
+                }
+            };
+            let class = if matches(lang.keywords) {
+                Some("keyword")
+            } else if matches(lang.literals) {
+                Some("literal")
+            } else if matches(lang.types) {
+                Some("type")
+            } else if chars.get(end) == Some(&'(') {
+                Some("title")
+            } else {
+                None
+            };
+            match class {
+                Some(class) => {
+                    flush(&mut out, &mut plain);
+                    push_token(&mut out, class, &word);
+                }
+                None => plain.push_str(&word),
+            }
+            i = end;
+            continue;
+        }
+
+        plain.push(c);
+        i += 1;
+    }
+    flush(&mut out, &mut plain);
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlight_tokens() {
+        let html = highlight(
+            "fn main() {\n    // say \"hi\"\n    let s = \"a<b\";\n    foo(42);\n}\n",
+            "rust",
+        )
+        .unwrap();
+        assert_eq!(
+            html,
+            "<span class=\"hljs-keyword\">fn</span> <span class=\"hljs-title\">main</span>() {\n    <span class=\"hljs-comment\">// say &quot;hi&quot;</span>\n    <span class=\"hljs-keyword\">let</span> s = <span class=\"hljs-string\">&quot;a&lt;b&quot;</span>;\n    <span class=\"hljs-title\">foo</span>(<span class=\"hljs-number\">42</span>);\n}\n"
+        );
+
+        let sql = highlight("SELECT id FROM t -- all\n", "SQL").unwrap();
+        assert!(sql.starts_with("<span class=\"hljs-keyword\">SELECT</span> id <span class=\"hljs-keyword\">FROM</span> t <span class=\"hljs-comment\">-- all</span>"));
+        let shell = highlight("echo $# x2 # note", "bash").unwrap();
+        assert!(shell.contains("$# x2 <span class=\"hljs-comment\"># note</span>"));
+        assert!(highlight("x", "brainfuck").is_none());
+    }
+}
@@ -0,0 +1,246 @@
+// History Module
+// Keeps earlier versions of every saved document, so that a save (or an
+// auto-save) that overwrote good work can be undone. Each file gets a
+// folder in the history store, named after a hash of its path, holding
+// its revisions gzip-compressed and a list of them. Old revisions are
+// thinned out by the retention policy: the last few are kept, then one an
+// hour and one a day.
+
+use crate::document_save;
+use chrono::TimeZone;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+const LOG_FILE: &str = "revisions.json";
+
+/// Saves can race (an auto-save and a manual one); revision lists are
+/// updated one at a time
+static LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Revision {
+    /// When it was saved, in milliseconds since the epoch; unique per file
+    pub id: i64,
+    /// Size on disk, before compression
+    pub size: u64,
+    pub hash: String,
+}
+
+/// The revisions of one file, oldest first
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct RevisionLog {
+    path: String,
+    revisions: Vec<Revision>,
+}
+
+/// Which revisions to keep. The newest is always kept.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Retention {
+    /// The most recent revisions
+    pub keep_last: usize,
+    /// The newest revision of each of the last hours that have any
+    pub keep_hourly: usize,
+    /// The newest revision of each of the last days that have any
+    pub keep_daily: usize,
+}
+
+impl Default for Retention {
+    fn default() -> Self {
+        Retention {
+            keep_last: 20,
+            keep_hourly: 24,
+            keep_daily: 30,
+        }
+    }
+}
+
+fn file_dir(root: &Path, path: &Path) -> PathBuf {
+    let hash = document_save::hash(path.to_string_lossy().as_bytes());
+    root.join(&hash[..32])
+}
+
+fn revision_file(dir: &Path, id: i64) -> PathBuf {
+    dir.join(format!("{}.gz", id))
+}
+
+fn load_log(dir: &Path) -> Result<RevisionLog, String> {
+    match fs::read_to_string(dir.join(LOG_FILE)) {
+        Ok(json) => {
+            serde_json::from_str(&json).map_err(|e| format!("Failed to parse revision list: {}", e))
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(RevisionLog::default()),
+        Err(e) => Err(format!("Failed to read revision list: {}", e)),
+    }
+}
+
+fn save_log(dir: &Path, log: &RevisionLog) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(log)
+        .map_err(|e| format!("Failed to serialize revision list: {}", e))?;
+    document_save::write_atomic(&dir.join(LOG_FILE), json.as_bytes())
+}
+
+/// The ids of the `revisions` that `retention` keeps
+pub fn retained(revisions: &[Revision], retention: Retention) -> HashSet<i64> {
+    let mut newest_first: Vec<i64> = revisions.iter().map(|r| r.id).collect();
+    newest_first.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut keep: HashSet<i64> = newest_first
+        .iter()
+        .take(retention.keep_last.max(1))
+        .copied()
+        .collect();
+    for (format, count) in [
+        ("%Y-%m-%d %H", retention.keep_hourly),
+        ("%Y-%m-%d", retention.keep_daily),
+    ] {
+        let mut periods = HashSet::new();
+        for &id in &newest_first {
+            let Some(time) = chrono::Local.timestamp_millis_opt(id).single() else {
+                continue;
+            };
+            if periods.len() == count {
+                break;
+            }
+            // The first revision seen in a period is its newest
+            if periods.insert(time.format(format).to_string()) {
+                keep.insert(id);
+            }
+        }
+    }
+    keep
+}
+
+/// Add `bytes` as the newest revision of `path`, unless it already is, and
+/// drop the revisions `retention` no longer keeps
+pub fn record(
+    root: &Path,
+    path: &Path,
+    bytes: &[u8],
+    retention: Retention,
+) -> Result<Option<Revision>, String> {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let dir = file_dir(root, path);
+    fs::create_dir_all(&dir).map_err(|e| format!("Failed to create history directory: {}", e))?;
+    let mut log = load_log(&dir)?;
+    log.path = path.to_string_lossy().to_string();
+
+    let hash = document_save::hash(bytes);
+    let last = log.revisions.last();
+    if last.is_some_and(|r| r.hash == hash) {
+        return Ok(None);
+    }
+    let now = chrono::Utc::now().timestamp_millis();
+    let id = last.map_or(now, |r| now.max(r.id + 1));
+
+    let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(bytes)
+        .map_err(|e| format!("Failed to compress revision: {}", e))?;
+    let compressed = encoder
+        .finish()
+        .map_err(|e| format!("Failed to compress revision: {}", e))?;
+    document_save::write_atomic(&revision_file(&dir, id), &compressed)?;
+
+    let revision = Revision {
+        id,
+        size: bytes.len() as u64,
+        hash,
+    };
+    log.revisions.push(revision.clone());
+
+    let keep = retained(&log.revisions, retention);
+    for dropped in log.revisions.iter().filter(|r| !keep.contains(&r.id)) {
+        if let Err(e) = fs::remove_file(revision_file(&dir, dropped.id)) {
+            log::warn!("Failed to remove revision {}: {}", dropped.id, e);
+        }
+    }
+    log.revisions.retain(|r| keep.contains(&r.id));
+    save_log(&dir, &log)?;
+    Ok(Some(revision))
+}
+
+/// The revisions of `path`, newest first
+pub fn list(root: &Path, path: &Path) -> Result<Vec<Revision>, String> {
+    let mut revisions = load_log(&file_dir(root, path))?.revisions;
+    revisions.reverse();
+    Ok(revisions)
+}
+
+/// The contents of revision `id` of `path`, as they were on disk
+pub fn read(root: &Path, path: &Path, id: i64) -> Result<Vec<u8>, String> {
+    let file = fs::File::open(revision_file(&file_dir(root, path), id))
+        .map_err(|e| format!("Failed to open revision {}: {}", id, e))?;
+    let mut bytes = Vec::new();
+    GzDecoder::new(file)
+        .read_to_end(&mut bytes)
+        .map_err(|e| format!("Failed to read revision {}: {}", id, e))?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_and_prune() {
+        let root = std::env::temp_dir().join(format!("docura-history-test-{}", std::process::id()));
+        let path = Path::new("/notes/plan.md");
+        let keep_two = Retention {
+            keep_last: 2,
+            keep_hourly: 0,
+            keep_daily: 0,
+        };
+
+        let first = record(&root, path, b"# Plan\n", keep_two).unwrap().unwrap();
+        assert!(record(&root, path, b"# Plan\n", keep_two)
+            .unwrap()
+            .is_none());
+        let second = record(&root, path, b"# Plan\n\nDraft\n", keep_two)
+            .unwrap()
+            .unwrap();
+        let third = record(&root, path, b"# Plan\n\nFinal\n", keep_two)
+            .unwrap()
+            .unwrap();
+        assert!(second.id > first.id && third.id > second.id);
+
+        let revisions = list(&root, path).unwrap();
+        let old = read(&root, path, second.id).unwrap();
+        let pruned = read(&root, path, first.id);
+        fs::remove_dir_all(&root).ok();
+        assert_eq!(revisions, vec![third, second]);
+        assert_eq!(old, b"# Plan\n\nDraft\n");
+        assert!(pruned.is_err());
+
+        // One every ten minutes for five hours: the newest, and the newest
+        // of each of the last three hours (the newest among them)
+        let start = 1_700_000_000_000;
+        let revisions: Vec<Revision> = (0..30)
+            .map(|i| Revision {
+                id: start + i * 600_000,
+                size: 0,
+                hash: i.to_string(),
+            })
+            .collect();
+        let hourly = Retention {
+            keep_last: 1,
+            keep_hourly: 3,
+            keep_daily: 0,
+        };
+        let kept = retained(&revisions, hourly);
+        assert_eq!(kept.len(), 3);
+        assert!(kept.contains(&revisions[29].id));
+        // The newest of the last day is the newest revision, kept already
+        let daily = Retention {
+            keep_daily: 1,
+            ..hourly
+        };
+        assert_eq!(retained(&revisions, daily), kept);
+    }
+}
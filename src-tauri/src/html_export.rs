@@ -0,0 +1,799 @@
+// HTML Export Module
+// Renders the Markdown document tree to HTML so exports no longer depend
+// on the webview's preview, and builds self-contained documents with the
+// stylesheet, images and optionally fonts embedded.
+
+use crate::highlight;
+use crate::markdown::{self, Alignment, Block, Document, Inline};
+use crate::themes::Theme;
+use base64::{engine::general_purpose, Engine as _};
+use std::path::{Path, PathBuf};
+
+/// Stylesheet of the default HTML export
+pub const STYLESHEET: &str = "body {
+    font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
+    max-width: 800px;
+    margin: 0 auto;
+    padding: 20px;
+    line-height: 1.6;
+}
+h1, h2, h3, h4, h5, h6 { color: #333; }
+pre { background: #f4f4f4; padding: 10px; border-radius: 4px; overflow-x: auto; }
+code { background: #f4f4f4; padding: 2px 4px; border-radius: 2px; }
+blockquote { border-left: 4px solid #ddd; margin-left: 0; padding-left: 20px; color: #666; }
+";
+
+/// Rules a standalone document needs on top of `STYLESHEET`, since it may be
+/// viewed without any other styling
+pub const STANDALONE_STYLESHEET: &str = "img { max-width: 100%; height: auto; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { border: 1px solid #ddd; padding: 6px 12px; }
+th { background: #f4f4f4; }
+pre code { background: none; padding: 0; }
+.task-list-item { list-style: none; }
+.footnotes { border-top: 1px solid #ddd; margin-top: 2em; font-size: 0.9em; }
+";
+
+/// Escape text for use in HTML element content and attribute values
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// Reduce raw HTML to its text for XHTML output, which cannot trust raw markup.
+/// Line breaks survive; common entities are decoded so they are not double-escaped.
+fn strip_tags(html: &str) -> String {
+    let mut out = String::new();
+    let mut text = String::new();
+    let mut tag = String::new();
+    let mut in_tag = false;
+
+    let flush = |text: &mut String, out: &mut String| {
+        let decoded = text
+            .replace("&nbsp;", "\u{a0}")
+            .replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+        out.push_str(&escape(&decoded));
+        text.clear();
+    };
+
+    for c in html.chars() {
+        match c {
+            '<' => {
+                in_tag = true;
+                tag.clear();
+            }
+            '>' if in_tag => {
+                in_tag = false;
+                let name = tag
+                    .trim_start_matches('/')
+                    .split([' ', '/'])
+                    .next()
+                    .unwrap_or("");
+                if name.eq_ignore_ascii_case("br") {
+                    flush(&mut text, &mut out);
+                    out.push_str("<br />");
+                }
+            }
+            c if in_tag => tag.push(c),
+            c => text.push(c),
+        }
+    }
+    flush(&mut text, &mut out);
+    out
+}
+
+/// Apply `rewrite` to the `src` of every `<img>` tag in a raw HTML fragment
+fn rewrite_img_sources(html: &str, rewrite: UrlRewriter) -> String {
+    // ASCII lowercasing keeps byte offsets, so positions carry over to `html`
+    let lower = html.to_ascii_lowercase();
+    let mut out = String::with_capacity(html.len());
+    let mut copied = 0;
+    let mut search = 0;
+
+    while let Some(found) = lower[search..].find("<img") {
+        let tag_start = search + found;
+        let tag_end = lower[tag_start..]
+            .find('>')
+            .map_or(lower.len(), |i| tag_start + i);
+        search = tag_end;
+
+        let Some((value_start, value_end)) = src_attribute(&lower[tag_start..tag_end]) else {
+            continue;
+        };
+        let (value_start, value_end) = (tag_start + value_start, tag_start + value_end);
+        let url = html[value_start..value_end]
+            .replace("&quot;", "\"")
+            .replace("&#39;", "'")
+            .replace("&amp;", "&");
+        if let Some(replacement) = rewrite(&url) {
+            out.push_str(&html[copied..value_start]);
+            out.push_str(&escape(&replacement));
+            copied = value_end;
+        }
+    }
+    out.push_str(&html[copied..]);
+    out
+}
+
+/// Byte range of the `src` attribute value within a (lowercased) tag
+fn src_attribute(tag: &str) -> Option<(usize, usize)> {
+    let mut from = 0;
+    let name = loop {
+        let index = from + tag[from..].find("src")?;
+        from = index + 3;
+        let preceded = tag[..index].ends_with(|c: char| c.is_ascii_whitespace());
+        let rest = tag[from..].trim_start();
+        if preceded && rest.starts_with('=') {
+            break tag.len() - rest.len();
+        }
+    };
+    let value = tag[name + 1..].trim_start();
+    let start = tag.len() - value.len();
+    match value.chars().next()? {
+        quote @ ('"' | '\'') => {
+            let end = value[1..].find(quote)?;
+            Some((start + 1, start + 1 + end))
+        }
+        _ => {
+            let end = value
+                .find(|c: char| c.is_ascii_whitespace())
+                .unwrap_or(value.len());
+            Some((start, start + end))
+        }
+    }
+}
+
+/// Maps a URL to its replacement; `None` keeps the original
+pub type UrlRewriter<'a> = &'a dyn Fn(&str) -> Option<String>;
+
+/// Options for exporters that embed the rendered body in another container
+#[derive(Default)]
+pub struct RenderOptions<'a> {
+    /// Self-close void elements and give boolean attributes values (EPUB needs XHTML)
+    pub xhtml: bool,
+    /// Replace a link target, e.g. `.md` to `.html`
+    pub rewrite_link: Option<UrlRewriter<'a>>,
+    /// Replace an image source, e.g. with a packaged or embedded copy
+    pub rewrite_image: Option<UrlRewriter<'a>>,
+    /// Mark up code block tokens with highlight.js classes
+    pub highlight: bool,
+}
+
+/// Render a document to an HTML fragment (the contents of `<body>`)
+pub fn render_body(doc: &Document) -> String {
+    render_body_with(doc, &RenderOptions::default())
+}
+
+/// Render a document to an HTML fragment, applying `options`
+pub fn render_body_with(doc: &Document, options: &RenderOptions) -> String {
+    let mut renderer = HtmlRenderer {
+        out: String::new(),
+        anchors: doc.heading_anchors().into_iter(),
+        footnotes: Vec::new(),
+        options,
+    };
+    renderer.blocks(&doc.blocks, false);
+    renderer.footnote_section(doc);
+    renderer.out
+}
+
+struct HtmlRenderer<'a> {
+    out: String,
+    anchors: std::vec::IntoIter<String>,
+    /// Footnote labels in order of first reference
+    footnotes: Vec<String>,
+    options: &'a RenderOptions<'a>,
+}
+
+impl HtmlRenderer<'_> {
+    /// End of a void element: `>` in HTML, ` />` in XHTML
+    fn void_end(&self) -> &'static str {
+        if self.options.xhtml {
+            " />"
+        } else {
+            ">"
+        }
+    }
+
+    fn footnote_number(&mut self, label: &str) -> usize {
+        match self.footnotes.iter().position(|l| l == label) {
+            Some(index) => index + 1,
+            None => {
+                self.footnotes.push(label.to_string());
+                self.footnotes.len()
+            }
+        }
+    }
+
+    fn blocks(&mut self, blocks: &[Block], tight: bool) {
+        for block in blocks {
+            self.block(block, tight);
+        }
+    }
+
+    fn block(&mut self, block: &Block, tight: bool) {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let anchor = self.anchors.next().unwrap_or_default();
+                if anchor.is_empty() {
+                    self.out.push_str(&format!("<h{}>", level));
+                } else {
+                    self.out
+                        .push_str(&format!("<h{} id=\"{}\">", level, escape(&anchor)));
+                }
+                self.inlines(content);
+                self.out.push_str(&format!("</h{}>\n", level));
+            }
+            Block::Paragraph(content) => {
+                if tight {
+                    self.inlines(content);
+                    self.out.push('\n');
+                } else {
+                    self.out.push_str("<p>");
+                    self.inlines(content);
+                    self.out.push_str("</p>\n");
+                }
+            }
+            Block::Quote(children) => {
+                self.out.push_str("<blockquote>\n");
+                self.blocks(children, false);
+                self.out.push_str("</blockquote>\n");
+            }
+            Block::Code { language, code } => {
+                let highlighted = language
+                    .as_deref()
+                    .filter(|_| self.options.highlight)
+                    .and_then(|lang| highlight::highlight(code, lang));
+                match (language, &highlighted) {
+                    (Some(lang), Some(_)) => self.out.push_str(&format!(
+                        "<pre><code class=\"hljs language-{}\">",
+                        escape(lang)
+                    )),
+                    (Some(lang), None) => self
+                        .out
+                        .push_str(&format!("<pre><code class=\"language-{}\">", escape(lang))),
+                    (None, _) => self.out.push_str("<pre><code>"),
+                }
+                match highlighted {
+                    Some(html) => self.out.push_str(&html),
+                    None => self.out.push_str(&escape(code)),
+                }
+                self.out.push_str("</code></pre>\n");
+            }
+            Block::List {
+                start,
+                tight,
+                items,
+            } => {
+                let tag = match start {
+                    Some(1) => {
+                        self.out.push_str("<ol>\n");
+                        "ol"
+                    }
+                    Some(n) => {
+                        self.out.push_str(&format!("<ol start=\"{}\">\n", n));
+                        "ol"
+                    }
+                    None => {
+                        self.out.push_str("<ul>\n");
+                        "ul"
+                    }
+                };
+                for item in items {
+                    match item.checked {
+                        Some(checked) if self.options.xhtml => self.out.push_str(&format!(
+                            "<li class=\"task-list-item\"><input type=\"checkbox\" disabled=\"disabled\"{} /> ",
+                            if checked { " checked=\"checked\"" } else { "" }
+                        )),
+                        Some(checked) => self.out.push_str(&format!(
+                            "<li class=\"task-list-item\"><input type=\"checkbox\" disabled{}> ",
+                            if checked { " checked" } else { "" }
+                        )),
+                        None => self.out.push_str("<li>"),
+                    }
+                    self.blocks(&item.blocks, *tight);
+                    self.out.push_str("</li>\n");
+                }
+                self.out.push_str(&format!("</{}>\n", tag));
+            }
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => {
+                self.out.push_str("<table>\n<thead>\n<tr>");
+                for (i, cell) in header.iter().enumerate() {
+                    self.cell("th", alignments.get(i), cell);
+                }
+                self.out.push_str("</tr>\n</thead>\n<tbody>\n");
+                for row in rows {
+                    self.out.push_str("<tr>");
+                    for (i, cell) in row.iter().enumerate() {
+                        self.cell("td", alignments.get(i), cell);
+                    }
+                    self.out.push_str("</tr>\n");
+                }
+                self.out.push_str("</tbody>\n</table>\n");
+            }
+            Block::ThematicBreak => {
+                self.out.push_str("<hr");
+                self.out.push_str(self.void_end());
+                self.out.push('\n');
+            }
+            Block::Html(html) if self.options.xhtml => {
+                let text = strip_tags(html);
+                if !text.trim().is_empty() {
+                    self.out.push_str(&format!("<p>{}</p>\n", text.trim()));
+                }
+            }
+            Block::Html(html) => self.raw_html(html),
+            // Definitions are collected into the footnote section at the end
+            Block::FootnoteDefinition { .. } => {}
+        }
+    }
+
+    fn cell(&mut self, tag: &str, alignment: Option<&Alignment>, content: &[Inline]) {
+        let style = match alignment {
+            Some(Alignment::Left) => " style=\"text-align: left\"",
+            Some(Alignment::Center) => " style=\"text-align: center\"",
+            Some(Alignment::Right) => " style=\"text-align: right\"",
+            _ => "",
+        };
+        self.out.push_str(&format!("<{}{}>", tag, style));
+        self.inlines(content);
+        self.out.push_str(&format!("</{}>", tag));
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            self.inline(inline);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => self.out.push_str(&escape(text)),
+            Inline::Code(code) => {
+                self.out.push_str("<code>");
+                self.out.push_str(&escape(code));
+                self.out.push_str("</code>");
+            }
+            Inline::Emphasis(children) => self.wrap("em", children),
+            Inline::Strong(children) => self.wrap("strong", children),
+            Inline::Strikethrough(children) => self.wrap("del", children),
+            Inline::Link {
+                url,
+                title,
+                content,
+            } => {
+                let url = self
+                    .options
+                    .rewrite_link
+                    .and_then(|rewrite| rewrite(url))
+                    .unwrap_or_else(|| url.clone());
+                self.out.push_str(&format!("<a href=\"{}\"", escape(&url)));
+                if !title.is_empty() {
+                    self.out.push_str(&format!(" title=\"{}\"", escape(title)));
+                }
+                self.out.push('>');
+                self.inlines(content);
+                self.out.push_str("</a>");
+            }
+            Inline::Image { url, title, alt } => {
+                let src = self
+                    .options
+                    .rewrite_image
+                    .and_then(|rewrite| rewrite(url))
+                    .unwrap_or_else(|| url.clone());
+                self.out.push_str(&format!(
+                    "<img src=\"{}\" alt=\"{}\"",
+                    escape(&src),
+                    escape(alt)
+                ));
+                if !title.is_empty() {
+                    self.out.push_str(&format!(" title=\"{}\"", escape(title)));
+                }
+                self.out.push_str(self.void_end());
+            }
+            Inline::FootnoteReference(label) => {
+                let number = self.footnote_number(label);
+                self.out.push_str(&format!(
+                    "<sup class=\"footnote-ref\"><a href=\"#fn-{0}\" id=\"fnref-{0}\">{1}</a></sup>",
+                    escape(label),
+                    number
+                ));
+            }
+            Inline::Html(html) if self.options.xhtml => self.out.push_str(&strip_tags(html)),
+            Inline::Html(html) => self.raw_html(html),
+            Inline::SoftBreak => self.out.push('\n'),
+            Inline::HardBreak => {
+                self.out.push_str("<br");
+                self.out.push_str(self.void_end());
+                self.out.push('\n');
+            }
+        }
+    }
+
+    /// Raw HTML passes through, with `<img>` sources rewritten like Markdown images
+    fn raw_html(&mut self, html: &str) {
+        match self.options.rewrite_image {
+            Some(rewrite) => self.out.push_str(&rewrite_img_sources(html, rewrite)),
+            None => self.out.push_str(html),
+        }
+    }
+
+    fn wrap(&mut self, tag: &str, children: &[Inline]) {
+        self.out.push_str(&format!("<{}>", tag));
+        self.inlines(children);
+        self.out.push_str(&format!("</{}>", tag));
+    }
+
+    fn footnote_section(&mut self, doc: &Document) {
+        let mut definitions = Vec::new();
+        markdown::visit_blocks(&doc.blocks, &mut |block| {
+            if let Block::FootnoteDefinition { label, blocks } = block {
+                definitions.push((label.clone(), blocks.clone()));
+            }
+        });
+        if definitions.is_empty() {
+            return;
+        }
+
+        // Referenced footnotes first, in reference order; unreferenced ones after
+        for (label, _) in &definitions {
+            self.footnote_number(label);
+        }
+        let order = self.footnotes.clone();
+
+        self.out.push_str("<section class=\"footnotes\">\n<ol>\n");
+        for label in order {
+            if let Some((_, blocks)) = definitions.iter().find(|(l, _)| *l == label) {
+                self.out
+                    .push_str(&format!("<li id=\"fn-{}\">\n", escape(&label)));
+                self.blocks(blocks, false);
+                self.out.push_str(&format!(
+                    "<a href=\"#fnref-{}\" class=\"footnote-backref\">↩</a>\n</li>\n",
+                    escape(&label)
+                ));
+            }
+        }
+        self.out.push_str("</ol>\n</section>\n");
+    }
+}
+
+/// Stylesheet reproducing an editor theme: its palette as CSS variables, the
+/// document styles of the preview and colours for highlighted code
+pub fn theme_stylesheet(theme: &Theme) -> String {
+    let syntax = &theme.syntax;
+    format!(
+        ":root {{
+    color-scheme: {scheme};
+    --bg-primary: {bg_primary};
+    --bg-secondary: {bg_secondary};
+    --bg-tertiary: {bg_tertiary};
+    --text-primary: {text_primary};
+    --text-secondary: {text_secondary};
+    --border-color: {border_color};
+    --accent-color: {accent_color};
+    --accent-hover: {accent_hover};
+    --heading-color: {heading_color};
+    --code-bg: {code_bg};
+    --code-color: {code_color};
+    --blockquote-border: {blockquote_border};
+    --link-color: {link_color};
+    --link-hover: {link_hover};
+    --text-strong: {text_strong};
+    --text-emphasis: {text_emphasis};
+}}
+body {{
+    font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
+    max-width: 860px;
+    margin: 0 auto;
+    padding: 40px 20px;
+    line-height: 1.7;
+    color: var(--text-primary);
+    background: var(--bg-primary);
+}}
+h1, h2, h3, h4, h5, h6 {{ color: var(--heading-color); line-height: 1.3; }}
+h1, h2 {{ border-bottom: 1px solid var(--border-color); padding-bottom: 0.3em; }}
+a {{ color: var(--link-color); }}
+a:hover {{ color: var(--link-hover); }}
+strong {{ color: var(--text-strong); }}
+em {{ color: var(--text-emphasis); }}
+::selection {{ background: var(--accent-color); color: var(--bg-primary); }}
+code {{ background: var(--code-bg); color: var(--code-color); padding: 2px 4px; border-radius: 4px; }}
+pre {{ background: var(--code-bg); padding: 12px 16px; border-radius: 8px; overflow-x: auto; }}
+pre code {{ background: none; color: var(--text-primary); padding: 0; }}
+blockquote {{ border-left: 4px solid var(--blockquote-border); background: var(--bg-secondary); margin-left: 0; padding: 0.5em 1em; color: var(--text-secondary); }}
+table {{ border-collapse: collapse; margin: 1em 0; }}
+th, td {{ border: 1px solid var(--border-color); padding: 6px 12px; }}
+th {{ background: var(--bg-secondary); }}
+tr:nth-child(even) td {{ background: var(--bg-secondary); }}
+hr {{ border: 0; border-top: 1px solid var(--border-color); }}
+img {{ max-width: 100%; height: auto; }}
+.task-list-item {{ list-style: none; }}
+.task-list-item input {{ accent-color: var(--accent-color); }}
+.footnotes {{ border-top: 1px solid var(--border-color); margin-top: 2em; font-size: 0.9em; color: var(--text-secondary); }}
+kbd {{ background: var(--bg-tertiary); border-radius: 3px; padding: 1px 4px; }}
+.hljs-keyword {{ color: {keyword}; }}
+.hljs-string {{ color: {string}; }}
+.hljs-comment {{ color: {comment}; font-style: italic; }}
+.hljs-number, .hljs-literal {{ color: {number}; }}
+.hljs-title {{ color: {function}; }}
+.hljs-type {{ color: {type_name}; }}
+",
+        scheme = if theme.dark { "dark" } else { "light" },
+        bg_primary = theme.bg_primary,
+        bg_secondary = theme.bg_secondary,
+        bg_tertiary = theme.bg_tertiary,
+        text_primary = theme.text_primary,
+        text_secondary = theme.text_secondary,
+        border_color = theme.border_color,
+        accent_color = theme.accent_color,
+        accent_hover = theme.accent_hover,
+        heading_color = theme.heading_color,
+        code_bg = theme.code_bg,
+        code_color = theme.code_color,
+        blockquote_border = theme.blockquote_border,
+        link_color = theme.link_color,
+        link_hover = theme.link_hover,
+        text_strong = theme.text_strong,
+        text_emphasis = theme.text_emphasis,
+        keyword = syntax.keyword,
+        string = syntax.string,
+        comment = syntax.comment,
+        number = syntax.number,
+        function = syntax.function,
+        type_name = syntax.type_name,
+    )
+}
+
+/// Media type of an embeddable asset, by file extension
+fn media_type(path: &Path) -> Option<&'static str> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let media_type = match extension.as_str() {
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "webp" => "image/webp",
+        "svg" => "image/svg+xml",
+        "bmp" => "image/bmp",
+        "ico" => "image/x-icon",
+        "avif" => "image/avif",
+        "woff2" => "font/woff2",
+        "woff" => "font/woff",
+        "ttf" => "font/ttf",
+        "otf" => "font/otf",
+        _ => return None,
+    };
+    Some(media_type)
+}
+
+/// Read a file into a base64 `data:` URI
+pub fn data_uri(path: &Path) -> Result<String, String> {
+    let media_type =
+        media_type(path).ok_or_else(|| format!("Unsupported asset type: {}", path.display()))?;
+    let data =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    Ok(format!(
+        "data:{};base64,{}",
+        media_type,
+        general_purpose::STANDARD.encode(data)
+    ))
+}
+
+/// Options for a self-contained HTML document
+#[derive(Default)]
+pub struct StandaloneOptions<'a> {
+    /// Directory relative image paths are resolved against
+    pub base_dir: Option<&'a Path>,
+    /// Font family for the body text, e.g. the editor font
+    pub font_family: Option<&'a str>,
+    /// Embed the font files of `font_family` so it renders without the font installed
+    pub embed_fonts: bool,
+    /// Editor theme to style the document with; the default stylesheet otherwise
+    pub theme: Option<&'static Theme>,
+}
+
+/// Render a complete HTML document that needs no other files: the stylesheet
+/// is inlined and local images become `data:` URIs. Images that cannot be
+/// read keep their original path.
+pub fn standalone_document(doc: &Document, title: &str, options: &StandaloneOptions) -> String {
+    let embed_image = |url: &str| -> Option<String> {
+        let path = markdown::resolve_local_path(url, options.base_dir)?;
+        data_uri(&path)
+            .map_err(|e| log::warn!("Image not embedded: {}", e))
+            .ok()
+    };
+    let render_options = RenderOptions {
+        rewrite_image: Some(&embed_image),
+        highlight: options.theme.is_some(),
+        ..RenderOptions::default()
+    };
+    let body = render_body_with(doc, &render_options);
+
+    let mut css = match options.theme {
+        Some(theme) => theme_stylesheet(theme),
+        None => format!("{}{}", STYLESHEET, STANDALONE_STYLESHEET),
+    };
+    if let Some(family) = options.font_family.filter(|f| !f.trim().is_empty()) {
+        if options.embed_fonts {
+            css.push_str(&font_faces(family));
+        }
+        css.push_str(&format!(
+            "body {{ font-family: \"{}\", -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif; }}\n",
+            family.replace(['"', '\\', '<'], "")
+        ));
+    }
+
+    format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+    <meta charset=\"UTF-8\">
+    <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">
+    <title>{}</title>
+    <style>
+{}    </style>
+</head>
+<body>
+{}
+</body>
+</html>",
+        escape(title),
+        css,
+        body
+    )
+}
+
+/// Find the regular, bold and italic files of an installed font family with fontconfig
+fn font_files(family: &str) -> Vec<(PathBuf, u16, &'static str)> {
+    let faces = [
+        ("", 400, "normal"),
+        (":bold", 700, "normal"),
+        (":italic", 400, "italic"),
+        (":bold:italic", 700, "italic"),
+    ];
+    let mut files: Vec<(PathBuf, u16, &'static str)> = Vec::new();
+    for (suffix, weight, style) in faces {
+        let output = std::process::Command::new("fc-match")
+            .arg("--format=%{family}\n%{file}")
+            .arg(format!("{}{}", family, suffix))
+            .output();
+        let output = match output {
+            Ok(output) if output.status.success() => output,
+            Ok(_) => continue,
+            Err(e) => {
+                log::warn!("Failed to run fc-match: {}", e);
+                break;
+            }
+        };
+        let text = String::from_utf8_lossy(&output.stdout);
+        let mut lines = text.lines();
+        let (Some(matched), Some(file)) = (lines.next(), lines.next()) else {
+            continue;
+        };
+        // fc-match always answers; skip substitutes from another family
+        let same_family = matched
+            .split(',')
+            .any(|name| name.trim().eq_ignore_ascii_case(family.trim()));
+        let path = PathBuf::from(file);
+        if same_family && !files.iter().any(|(p, _, _)| *p == path) {
+            files.push((path, weight, style));
+        }
+    }
+    files
+}
+
+fn font_faces(family: &str) -> String {
+    let mut css = String::new();
+    for (path, weight, style) in font_files(family) {
+        let format = match path.extension().and_then(|e| e.to_str()) {
+            Some("woff2") => "woff2",
+            Some("woff") => "woff",
+            Some("otf") => "opentype",
+            _ => "truetype",
+        };
+        match data_uri(&path) {
+            Ok(uri) => css.push_str(&format!(
+                "@font-face {{ font-family: \"{}\"; src: url({}) format(\"{}\"); font-weight: {}; font-style: {}; }}\n",
+                family.replace(['"', '\\', '<'], ""),
+                uri,
+                format,
+                weight,
+                style
+            )),
+            Err(e) => log::warn!("Font not embedded: {}", e),
+        }
+    }
+    css
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_body() {
+        let doc = markdown::parse(
+            "# Title & more\n\n- [x] done\n- item with `<b>`\n\n| L | R |\n|---|--:|\n| a | b |\n\nSee[^n].\n\n[^n]: The note.\n",
+        );
+        let html = render_body(&doc);
+        assert!(html.contains("<h1 id=\"title--more\">Title &amp; more</h1>"));
+        assert!(html.contains(
+            "<li class=\"task-list-item\"><input type=\"checkbox\" disabled checked> done\n</li>"
+        ));
+        assert!(html.contains("<li>item with <code>&lt;b&gt;</code>\n</li>"));
+        assert!(html.contains("<td style=\"text-align: right\">b</td>"));
+        assert!(html.contains("<a href=\"#fn-n\" id=\"fnref-n\">1</a>"));
+        assert!(html.contains("<li id=\"fn-n\">\n<p>The note.</p>"));
+    }
+
+    #[test]
+    fn test_standalone_document_embeds_images() {
+        let dir = std::env::temp_dir().join(format!("docura-html-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        std::fs::write(
+            dir.join("assets/dot.svg"),
+            "<svg xmlns=\"http://www.w3.org/2000/svg\"/>",
+        )
+        .unwrap();
+
+        let doc = markdown::parse(
+            "![Dot](assets/dot.svg)\n\n<p><img class=\"x\" src=\"assets/dot.svg\" data-src=\"keep\"></p>\n\n![Gone](missing.png)\n",
+        );
+        let options = StandaloneOptions {
+            base_dir: Some(&dir),
+            font_family: Some("Fira Sans"),
+            embed_fonts: false,
+            theme: None,
+        };
+        let html = standalone_document(&doc, "Notes <1>", &options);
+        std::fs::remove_dir_all(&dir).ok();
+
+        let uri =
+            "data:image/svg+xml;base64,PHN2ZyB4bWxucz0iaHR0cDovL3d3dy53My5vcmcvMjAwMC9zdmciLz4=";
+        assert!(html.contains(&format!("<img src=\"{}\" alt=\"Dot\">", uri)));
+        assert!(html.contains(&format!(
+            "<img class=\"x\" src=\"{}\" data-src=\"keep\">",
+            uri
+        )));
+        assert!(html.contains("<img src=\"missing.png\" alt=\"Gone\">"));
+        assert!(html.contains("<title>Notes &lt;1&gt;</title>"));
+        assert!(html.contains("body { font-family: \"Fira Sans\", "));
+        assert!(!html.contains("<link") && !html.contains("<script"));
+    }
+
+    #[test]
+    fn test_theme_stylesheet_and_highlighting() {
+        let theme = crate::themes::find("gruvbox-dark").unwrap();
+        let css = theme_stylesheet(theme);
+        assert!(css.contains("--bg-primary: #282828;"));
+        assert!(css.contains("color-scheme: dark;"));
+        assert!(css.contains(".hljs-keyword { color: #fb4934; }"));
+
+        let doc = markdown::parse("```python\nreturn None\n```\n\n```\nplain\n```\n");
+        let options = RenderOptions {
+            highlight: true,
+            ..RenderOptions::default()
+        };
+        let html = render_body_with(&doc, &options);
+        assert!(html.contains("<pre><code class=\"hljs language-python\"><span class=\"hljs-keyword\">return</span> <span class=\"hljs-literal\">None</span>"));
+        assert!(html.contains("<pre><code>plain\n</code></pre>"));
+        assert!(crate::themes::find("no-such-theme").is_none());
+    }
+}
@@ -12,6 +12,73 @@ mod plasma_sync;
 // Dropbox sync module
 mod dropbox_sync;
 
+// Markdown document model shared by the exporters
+mod markdown;
+
+// Zip packaging shared by the DOCX, ODT and EPUB exporters
+mod package;
+
+// Editor theme palettes and code highlighting for themed HTML exports
+mod highlight;
+mod themes;
+
+// Full-text search over opened folders
+mod search;
+
+// Live updates of opened folders
+mod watcher;
+
+// Lazy, paginated folder tree for the sidebar
+mod tree;
+
+// Hidden and ignored files in the folder tree
+mod file_filter;
+
+// Sizes, dates, word counts and titles of folder tree entries
+mod file_meta;
+
+// Creating, renaming, moving and trashing files from the sidebar
+mod file_ops;
+
+// Links between the files of a folder, kept working across renames
+mod link_updates;
+mod links;
+
+// Backlinks, broken links and orphan notes of an opened folder
+mod link_index;
+
+// Checking a folder for broken links, missing images and clashing headings
+mod lint;
+
+// Which files open as text, and their encodings and line endings
+mod text_files;
+
+// Crash-safe saving that won't overwrite changes made by other programs
+mod document_save;
+
+// Earlier versions of saved documents, and comparing them
+mod diff;
+mod history;
+
+// Journal of open buffers, for recovering unsaved work after a crash
+mod session;
+
+// Status, staging, commits and file history of folders kept in git
+mod git;
+
+// Installed fonts embedded in PDF exports, for text beyond Latin-1
+mod truetype;
+
+// Exporters
+mod docx_export;
+mod epub_export;
+mod html_export;
+mod mediawiki_export;
+mod odt_export;
+mod pdf_export;
+mod rtf_export;
+mod site_export;
+
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct FileItem {
     name: String,
@@ -19,10 +86,33 @@ pub struct FileItem {
     #[serde(rename = "type")]
     file_type: String,
     children: Option<Vec<FileItem>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    metadata: Option<file_meta::FileMetadata>,
+}
+
+impl FileItem {
+    fn sort_key(&self) -> file_meta::SortKey<'_> {
+        file_meta::SortKey {
+            name: &self.name,
+            is_dir: self.file_type == "folder",
+            metadata: self.metadata.as_ref(),
+        }
+    }
+}
+
+/// Sort tree entries: folders first, then in `sort` order
+fn sort_file_items(items: &mut [FileItem], sort: file_meta::SortMode) {
+    items.sort_by(|a, b| file_meta::compare(&a.sort_key(), &b.sort_key(), sort));
 }
 
 #[command]
-async fn get_folder_files(folder_path: String) -> Result<Vec<FileItem>, String> {
+async fn get_folder_files(
+    folder_path: String,
+    sort: Option<file_meta::SortMode>,
+    with_metadata: Option<bool>,
+) -> Result<Vec<FileItem>, String> {
+    let sort = sort.unwrap_or_default();
+    let with_metadata = with_metadata.unwrap_or(false);
     let path = Path::new(&folder_path);
 
     if !path.exists() {
@@ -34,6 +124,9 @@ async fn get_folder_files(folder_path: String) -> Result<Vec<FileItem>, String>
     }
 
     let mut files = Vec::new();
+    // Canonical folders above the one being read, to stop at symlink loops
+    let mut ancestors = tree::canonical_ancestors(path);
+    let mut filter = folder_filter(path, path).await;
 
     // Read directory entries
     match std::fs::read_dir(path) {
@@ -48,21 +141,29 @@ async fn get_folder_files(folder_path: String) -> Result<Vec<FileItem>, String>
                             .unwrap_or("Unknown")
                             .to_string();
 
-                        // Skip hidden files
-                        if file_name.starts_with('.') {
+                        // Skip hidden and ignored files
+                        let is_dir = file_path.is_dir();
+                        if !filter.is_visible(&file_path, is_dir) {
                             continue;
                         }
 
                         let file_path_str = file_path.to_string_lossy().to_string();
 
-                        if file_path.is_dir() {
+                        if is_dir {
                             // For directories, recursively get children
-                            let children = get_directory_contents(&file_path)?;
+                            let children = get_directory_contents(
+                                &file_path,
+                                &mut ancestors,
+                                &mut filter,
+                                sort,
+                                with_metadata,
+                            )?;
                             files.push(FileItem {
                                 name: file_name,
                                 path: file_path_str,
                                 file_type: "folder".to_string(),
                                 children: Some(children),
+                                metadata: file_meta::for_entry(&file_path, sort, with_metadata),
                             });
                         } else {
                             files.push(FileItem {
@@ -70,6 +171,7 @@ async fn get_folder_files(folder_path: String) -> Result<Vec<FileItem>, String>
                                 path: file_path_str,
                                 file_type: "file".to_string(),
                                 children: None,
+                                metadata: file_meta::for_entry(&file_path, sort, with_metadata),
                             });
                         }
                     }
@@ -84,19 +186,32 @@ async fn get_folder_files(folder_path: String) -> Result<Vec<FileItem>, String>
         }
     }
 
-    // Sort files: directories first, then files, both alphabetically
-    files.sort_by(|a, b| match (a.file_type.as_str(), b.file_type.as_str()) {
-        ("folder", "file") => std::cmp::Ordering::Less,
-        ("file", "folder") => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
+    // Sort files: directories first, then files
+    sort_file_items(&mut files, sort);
 
     Ok(files)
 }
 
-fn get_directory_contents(dir_path: &Path) -> Result<Vec<FileItem>, String> {
+fn get_directory_contents(
+    dir_path: &Path,
+    ancestors: &mut Vec<PathBuf>,
+    filter: &mut file_filter::FileFilter,
+    sort: file_meta::SortMode,
+    with_metadata: bool,
+) -> Result<Vec<FileItem>, String> {
     let mut contents = Vec::new();
 
+    // A symlink back to a folder we are already inside would never end
+    let Ok(canonical) = dir_path.canonicalize() else {
+        return Ok(contents);
+    };
+    if ancestors.contains(&canonical) {
+        log::warn!("Skipping symlink loop at {}", dir_path.display());
+        return Ok(contents);
+    }
+    ancestors.push(canonical);
+    filter.enter(dir_path);
+
     match std::fs::read_dir(dir_path) {
         Ok(entries) => {
             for entry in entries {
@@ -109,20 +224,28 @@ fn get_directory_contents(dir_path: &Path) -> Result<Vec<FileItem>, String> {
                             .unwrap_or("Unknown")
                             .to_string();
 
-                        // Skip hidden files
-                        if file_name.starts_with('.') {
+                        // Skip hidden and ignored files
+                        let is_dir = file_path.is_dir();
+                        if !filter.is_visible(&file_path, is_dir) {
                             continue;
                         }
 
                         let file_path_str = file_path.to_string_lossy().to_string();
 
-                        if file_path.is_dir() {
-                            let children = get_directory_contents(&file_path)?;
+                        if is_dir {
+                            let children = get_directory_contents(
+                                &file_path,
+                                ancestors,
+                                filter,
+                                sort,
+                                with_metadata,
+                            )?;
                             contents.push(FileItem {
                                 name: file_name,
                                 path: file_path_str,
                                 file_type: "folder".to_string(),
                                 children: Some(children),
+                                metadata: file_meta::for_entry(&file_path, sort, with_metadata),
                             });
                         } else {
                             contents.push(FileItem {
@@ -130,6 +253,7 @@ fn get_directory_contents(dir_path: &Path) -> Result<Vec<FileItem>, String> {
                                 path: file_path_str,
                                 file_type: "file".to_string(),
                                 children: None,
+                                metadata: file_meta::for_entry(&file_path, sort, with_metadata),
                             });
                         }
                     }
@@ -143,20 +267,216 @@ fn get_directory_contents(dir_path: &Path) -> Result<Vec<FileItem>, String> {
             // Ignore errors for subdirectories we can't read
         }
     }
+    filter.leave();
+    ancestors.pop();
 
     // Sort contents
-    contents.sort_by(|a, b| match (a.file_type.as_str(), b.file_type.as_str()) {
-        ("folder", "file") => std::cmp::Ordering::Less,
-        ("file", "folder") => std::cmp::Ordering::Greater,
-        _ => a.name.to_lowercase().cmp(&b.name.to_lowercase()),
-    });
+    sort_file_items(&mut contents, sort);
 
     Ok(contents)
 }
 
+/// Which entries of `dir` to show, following the ignore files from the
+/// opened folder `root` down and the patterns in the settings
+async fn folder_filter(root: &Path, dir: &Path) -> file_filter::FileFilter {
+    let config = load_config().await.unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        AppConfig::default()
+    });
+    file_filter::FileFilter::for_directory(
+        root,
+        dir,
+        &config.ignore_patterns,
+        config.show_ignored_files,
+    )
+}
+
 #[command]
-async fn read_file_content(file_path: String) -> Result<String, String> {
-    let path = Path::new(&file_path);
+async fn list_directory(
+    listings: tauri::State<'_, tree::DirectoryListings>,
+    dir_path: String,
+    root_path: Option<String>,
+    offset: Option<usize>,
+    limit: Option<usize>,
+    sort: Option<file_meta::SortMode>,
+    with_metadata: Option<bool>,
+) -> Result<tree::DirectoryPage, String> {
+    let dir = Path::new(&dir_path);
+    let root = root_path.as_deref().map(Path::new).unwrap_or(dir);
+    let config = load_config().await.unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        AppConfig::default()
+    });
+    let mut filter = file_filter::FileFilter::for_directory(
+        root,
+        dir,
+        &config.ignore_patterns,
+        config.show_ignored_files,
+    );
+    let key = tree::ListingKey::new(
+        dir,
+        sort.unwrap_or_default(),
+        root,
+        &config.ignore_patterns,
+        config.show_ignored_files,
+    );
+
+    // One level at a time; the sidebar asks for subfolders as they are expanded
+    tree::list_directory(
+        &key,
+        offset.unwrap_or(0),
+        limit.unwrap_or(500),
+        &mut filter,
+        with_metadata.unwrap_or(false),
+        &listings,
+    )
+}
+
+/// The tree entry for `path` after a file operation
+async fn file_item_at(path: &Path) -> Result<FileItem, String> {
+    let is_dir = path.is_dir();
+    let children = if is_dir {
+        Some(get_folder_files(path.to_string_lossy().to_string(), None, None).await?)
+    } else {
+        None
+    };
+    Ok(FileItem {
+        name: path
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: path.to_string_lossy().to_string(),
+        file_type: if is_dir { "folder" } else { "file" }.to_string(),
+        children,
+        metadata: file_meta::read_metadata(path, true),
+    })
+}
+
+#[command]
+async fn create_file(
+    parent_path: String,
+    name: String,
+    content: Option<String>,
+) -> Result<FileItem, String> {
+    let path = file_ops::create_file(
+        Path::new(&parent_path),
+        &name,
+        content.as_deref().unwrap_or(""),
+    )?;
+    log::info!("Created {}", path.display());
+    file_item_at(&path).await
+}
+
+#[command]
+async fn create_folder(parent_path: String, name: String) -> Result<FileItem, String> {
+    let path = file_ops::create_folder(Path::new(&parent_path), &name)?;
+    log::info!("Created {}", path.display());
+    file_item_at(&path).await
+}
+
+#[command]
+async fn rename_path(path: String, new_name: String) -> Result<FileItem, String> {
+    let renamed = file_ops::rename(Path::new(&path), &new_name)?;
+    log::info!("Renamed {} to {}", path, renamed.display());
+    file_item_at(&renamed).await
+}
+
+#[command]
+async fn move_path(path: String, target_folder: String) -> Result<FileItem, String> {
+    let moved = file_ops::move_into(Path::new(&path), Path::new(&target_folder))?;
+    log::info!("Moved {} to {}", path, moved.display());
+    file_item_at(&moved).await
+}
+
+#[command]
+async fn duplicate_path(path: String) -> Result<FileItem, String> {
+    let copy = file_ops::duplicate(Path::new(&path))?;
+    log::info!("Duplicated {} as {}", path, copy.display());
+    file_item_at(&copy).await
+}
+
+/// Returns where the entry is stored in the trash. A trashed folder's
+/// contents aren't listed; nothing shows them any more.
+#[command]
+async fn trash_path(path: String) -> Result<FileItem, String> {
+    let trashed = file_ops::trash(Path::new(&path))?;
+    log::info!("Moved {} to the trash", path);
+    Ok(FileItem {
+        name: trashed
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        path: trashed.to_string_lossy().to_string(),
+        file_type: if trashed.is_dir() { "folder" } else { "file" }.to_string(),
+        children: None,
+        metadata: None,
+    })
+}
+
+/// Every file of a folder tree, for resolving links between them
+fn collect_file_paths(items: &[FileItem], out: &mut Vec<PathBuf>) {
+    for item in items {
+        match &item.children {
+            Some(children) => collect_file_paths(children, out),
+            None => out.push(PathBuf::from(&item.path)),
+        }
+    }
+}
+
+async fn folder_file_paths(folder_path: &str) -> Result<Vec<PathBuf>, String> {
+    let items = get_folder_files(folder_path.to_string(), None, None).await?;
+    let mut files = Vec::new();
+    collect_file_paths(&items, &mut files);
+    Ok(files)
+}
+
+async fn link_update_plan(
+    folder_path: &str,
+    path: &str,
+    new_path: &str,
+) -> Result<link_updates::LinkUpdatePlan, String> {
+    let files = folder_file_paths(folder_path).await?;
+    let index = links::FileIndex::new(Path::new(folder_path), files);
+    link_updates::plan(&index, Path::new(path), Path::new(new_path))
+}
+
+/// The links in `folder_path` that renaming or moving `path` to `new_path`
+/// would rewrite, without changing anything
+#[command]
+async fn preview_link_updates(
+    folder_path: String,
+    path: String,
+    new_path: String,
+) -> Result<link_updates::LinkUpdatePlan, String> {
+    link_update_plan(&folder_path, &path, &new_path).await
+}
+
+/// Rename or move `path` to `new_path` and rewrite the links to it
+#[command]
+async fn rename_with_links(
+    folder_path: String,
+    path: String,
+    new_path: String,
+) -> Result<FileItem, String> {
+    let plan = link_update_plan(&folder_path, &path, &new_path).await?;
+    let retention = load_config().await.unwrap_or_default().history_retention;
+    let moved = link_updates::apply(&plan, &|path, bytes| record_revision(path, bytes, retention))?;
+    log::info!("Moved {} to {} and updated its links", path, moved.display());
+    file_item_at(&moved).await
+}
+
+/// A file as opened in the editor: its text and format, and the version on
+/// disk that saving it is checked against
+#[derive(Debug, Serialize)]
+pub struct OpenedDocument {
+    #[serde(flatten)]
+    document: text_files::TextDocument,
+    version: document_save::DiskVersion,
+}
+
+/// Read and decode a file, if it is one Docura opens as text
+async fn read_text_document(file_path: &str) -> Result<OpenedDocument, String> {
+    let path = Path::new(file_path);
 
     if !path.exists() {
         return Err("File does not exist".to_string());
@@ -166,34 +486,227 @@ async fn read_file_content(file_path: String) -> Result<String, String> {
         return Err("Path is not a file".to_string());
     }
 
-    // Only read text files (markdown, txt, etc.)
-    if let Some(extension) = path.extension() {
-        let ext = extension.to_string_lossy().to_lowercase();
-        let allowed_extensions = ["md", "markdown", "txt", "mdown", "mkdn", "mdx"];
+    let config = load_config().await.unwrap_or_default();
+    if !text_files::is_text_path(path, &config.text_extensions) {
+        return Err("File type not supported".to_string());
+    }
 
-        if !allowed_extensions.contains(&ext.as_str()) {
-            return Err("File type not supported for content search".to_string());
-        }
+    let bytes = fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    let metadata = fs::metadata(path).map_err(|e| format!("Failed to read file: {}", e))?;
+    Ok(OpenedDocument {
+        document: text_files::decode(&bytes)?,
+        version: document_save::DiskVersion::new(&bytes, &metadata),
+    })
+}
+
+#[command]
+async fn read_file_content(file_path: String) -> Result<String, String> {
+    Ok(read_text_document(&file_path).await?.document.content)
+}
+
+/// The text of a file together with its encoding, line endings and version,
+/// to be passed back when saving
+#[command]
+async fn read_document(file_path: String) -> Result<OpenedDocument, String> {
+    read_text_document(&file_path).await
+}
+
+/// Save `content` in the encoding and with the line endings the file was
+/// read with (UTF-8 and `\n` for new files). Fails with `changedOnDisk` if
+/// the file is no longer the `expected` version; saving again without one
+/// overwrites it. Fails with `encodingGuessed` while the file's encoding is
+/// only a guess, until the user confirms it. Returns the version written.
+#[command]
+async fn save_document(
+    file_path: String,
+    content: String,
+    format: Option<text_files::TextFormat>,
+    expected: Option<document_save::DiskVersion>,
+) -> Result<document_save::DiskVersion, document_save::SaveError> {
+    let path = Path::new(&file_path);
+    let format = format.unwrap_or_default();
+    if format.encoding_guessed {
+        return Err(document_save::SaveError::EncodingGuessed);
+    }
+    let bytes = text_files::encode(&content, format)?;
+    let retention = load_config().await.unwrap_or_default().history_retention;
+    // What is on disk may never have been saved from here
+    if let Ok(current) = fs::read(path) {
+        record_revision(path, &current, retention);
     }
+    let version = document_save::save(path, &bytes, expected.as_ref())?;
+    record_revision(path, &bytes, retention);
+    log::info!("Saved {}", file_path);
+    Ok(version)
+}
+
+fn get_history_dir() -> Result<PathBuf, String> {
+    Ok(get_config_dir()?.join("history"))
+}
 
-    match fs::read_to_string(path) {
-        Ok(content) => Ok(content),
-        Err(e) => Err(format!("Failed to read file: {}", e)),
+/// Add a revision to the history of `path`. A failure is only logged, so
+/// that it never stops a save.
+fn record_revision(path: &Path, bytes: &[u8], retention: history::Retention) {
+    let recorded =
+        get_history_dir().and_then(|root| history::record(&root, path, bytes, retention));
+    if let Err(e) = recorded {
+        log::warn!("Failed to record a revision of {}: {}", path.display(), e);
     }
 }
 
+/// Replace the file at `path` with an older version of it. Both the
+/// version on disk and `bytes` end up in its history, so this can be undone.
+async fn replace_with_revision(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let retention = load_config().await.unwrap_or_default().history_retention;
+    if let Ok(current) = fs::read(path) {
+        record_revision(path, &current, retention);
+    }
+    document_save::write_atomic(path, bytes)?;
+    record_revision(path, bytes, retention);
+    Ok(())
+}
+
+/// Saved versions of a file, newest first
 #[command]
-async fn export_to_pdf(_content: String, filename: String) -> Result<String, String> {
-    // For now, return a placeholder message
-    // In a real implementation, you would use a library like wkhtmltopdf or headless Chrome
-    log::info!("Export to PDF requested for: {}", filename);
-    Ok("PDF export feature coming soon!".to_string())
+async fn list_revisions(file_path: String) -> Result<Vec<history::Revision>, String> {
+    history::list(&get_history_dir()?, Path::new(&file_path))
+}
+
+#[command]
+async fn get_revision(
+    file_path: String,
+    revision_id: i64,
+) -> Result<text_files::TextDocument, String> {
+    let bytes = history::read(&get_history_dir()?, Path::new(&file_path), revision_id)?;
+    text_files::decode(&bytes)
+}
+
+/// What changed from one revision of a file to another, or to the file as
+/// it is on disk when `to_revision` is left out
+#[command]
+async fn diff_revisions(
+    file_path: String,
+    from_revision: i64,
+    to_revision: Option<i64>,
+) -> Result<diff::TextDiff, String> {
+    let root = get_history_dir()?;
+    let path = Path::new(&file_path);
+    let old = text_files::decode(&history::read(&root, path, from_revision)?)?;
+    let new = match to_revision {
+        Some(id) => history::read(&root, path, id)?,
+        None => fs::read(path).map_err(|e| format!("Failed to read file: {}", e))?,
+    };
+    let new = text_files::decode(&new)?;
+    Ok(diff::diff_lines(&old.content, &new.content))
+}
+
+/// Line and word differences between two texts
+#[command]
+async fn diff_texts(old: String, new: String) -> Result<diff::TextDiff, String> {
+    Ok(diff::diff_lines(&old, &new))
+}
+
+/// What `content` changes in the file on disk, e.g. an editor buffer or a
+/// recovered temp file compared with the saved file
+#[command]
+async fn diff_with_file(file_path: String, content: String) -> Result<diff::TextDiff, String> {
+    let disk = read_text_document(&file_path).await?;
+    Ok(diff::diff_lines(&disk.document.content, &content))
+}
+
+/// Combine two edited copies of `base`. Where both changed the same lines,
+/// the result holds both versions between conflict markers.
+#[command]
+async fn merge_texts(
+    base: String,
+    ours: String,
+    theirs: String,
+    our_label: Option<String>,
+    their_label: Option<String>,
+) -> Result<diff::Merge, String> {
+    let labels = (
+        our_label.as_deref().unwrap_or("Yours"),
+        their_label.as_deref().unwrap_or("Theirs"),
+    );
+    Ok(diff::merge(&base, &ours, &theirs, labels))
+}
+
+/// Put a revision back on disk. The version it replaces becomes a revision
+/// itself, so restoring can be undone.
+#[command]
+async fn restore_revision(file_path: String, revision_id: i64) -> Result<(), String> {
+    let path = Path::new(&file_path);
+    let bytes = history::read(&get_history_dir()?, path, revision_id)?;
+    replace_with_revision(path, &bytes).await?;
+    log::info!("Restored revision {} of {}", revision_id, file_path);
+    Ok(())
+}
+
+#[command]
+async fn export_to_pdf(
+    content: String,
+    filename: String,
+    document_path: Option<String>,
+) -> Result<String, String> {
+    // Relative images (e.g. assets/ from save_clipboard_image) live next to the document
+    let base_dir = document_path
+        .as_deref()
+        .and_then(|p| Path::new(p).parent())
+        .map(Path::to_path_buf);
+    let title = Path::new(&filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let pdf = pdf_export::markdown_to_pdf(&content, &title, base_dir.as_deref());
+
+    match fs::write(&filename, pdf.bytes) {
+        Ok(_) if !pdf.missing.is_empty() => {
+            let missing: String = pdf.missing.iter().collect();
+            log::warn!("No installed font has these characters: {}", missing);
+            Ok(format!(
+                "PDF exported to {}, but no installed font has these characters, shown as ?: {}",
+                filename, missing
+            ))
+        }
+        Ok(_) => {
+            log::info!("PDF exported successfully to: {}", filename);
+            Ok(format!("PDF exported to {}", filename))
+        }
+        Err(e) => {
+            log::error!("Failed to export PDF: {}", e);
+            Err(format!("Failed to export PDF: {}", e))
+        }
+    }
 }
 
 #[tauri::command]
-async fn export_to_html(content: String, filename: String) -> Result<String, String> {
+async fn export_to_html(
+    content: String,
+    filename: String,
+    theme: Option<String>,
+) -> Result<String, String> {
     use std::fs;
 
+    // Unknown theme ids fall back to the default stylesheet
+    let theme = theme.as_deref().and_then(|id| {
+        let found = themes::find(id);
+        if found.is_none() {
+            log::warn!("Unknown theme for HTML export: {}", id);
+        }
+        found
+    });
+    let options = html_export::RenderOptions {
+        highlight: theme.is_some(),
+        ..html_export::RenderOptions::default()
+    };
+    let body = html_export::render_body_with(&markdown::parse(&content), &options);
+    let stylesheet = match theme {
+        Some(theme) => html_export::theme_stylesheet(theme),
+        None => html_export::STYLESHEET.to_string(),
+    };
+
     let html_content = format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -202,25 +715,15 @@ async fn export_to_html(content: String, filename: String) -> Result<String, Str
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
     <title>{}</title>
     <style>
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, sans-serif;
-            max-width: 800px;
-            margin: 0 auto;
-            padding: 20px;
-            line-height: 1.6;
-        }}
-        h1, h2, h3, h4, h5, h6 {{ color: #333; }}
-        pre {{ background: #f4f4f4; padding: 10px; border-radius: 4px; overflow-x: auto; }}
-        code {{ background: #f4f4f4; padding: 2px 4px; border-radius: 2px; }}
-        blockquote {{ border-left: 4px solid #ddd; margin-left: 0; padding-left: 20px; color: #666; }}
-    </style>
+{}    </style>
 </head>
 <body>
 {}
 </body>
 </html>"#,
         filename.replace(".html", ""),
-        content
+        stylesheet,
+        body
     );
 
     match fs::write(&filename, html_content) {
@@ -235,10 +738,52 @@ async fn export_to_html(content: String, filename: String) -> Result<String, Str
     }
 }
 
+#[tauri::command]
+async fn export_to_html_standalone(
+    content: String,
+    filename: String,
+    document_path: Option<String>,
+    font_family: Option<String>,
+    embed_fonts: Option<bool>,
+    theme: Option<String>,
+) -> Result<String, String> {
+    let base_dir = document_path
+        .as_deref()
+        .and_then(|p| Path::new(p).parent())
+        .map(Path::to_path_buf);
+    let title = Path::new(&filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let options = html_export::StandaloneOptions {
+        base_dir: base_dir.as_deref(),
+        font_family: font_family.as_deref(),
+        embed_fonts: embed_fonts.unwrap_or(false),
+        theme: theme.as_deref().and_then(themes::find),
+    };
+    let html_content =
+        html_export::standalone_document(&markdown::parse(&content), &title, &options);
+
+    match fs::write(&filename, html_content) {
+        Ok(_) => {
+            log::info!("Self-contained HTML exported successfully to: {}", filename);
+            Ok(format!("Self-contained HTML exported to {}", filename))
+        }
+        Err(e) => {
+            log::error!("Failed to export self-contained HTML: {}", e);
+            Err(format!("Failed to export self-contained HTML: {}", e))
+        }
+    }
+}
+
 #[tauri::command]
 async fn export_to_html_plain(content: String, filename: String) -> Result<String, String> {
     use std::fs;
 
+    let body = html_export::render_body(&markdown::parse(&content));
+
     let html_content = format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -252,7 +797,7 @@ async fn export_to_html_plain(content: String, filename: String) -> Result<Strin
 </body>
 </html>"#,
         filename.replace(".html", ""),
-        content
+        body
     );
 
     match fs::write(&filename, html_content) {
@@ -271,6 +816,8 @@ async fn export_to_html_plain(content: String, filename: String) -> Result<Strin
 async fn export_to_html_tailwind(content: String, filename: String) -> Result<String, String> {
     use std::fs;
 
+    let body = html_export::render_body(&markdown::parse(&content));
+
     let html_content = format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -289,7 +836,7 @@ async fn export_to_html_tailwind(content: String, filename: String) -> Result<St
 </body>
 </html>"#,
         filename.replace(".html", ""),
-        content
+        body
     );
 
     match fs::write(&filename, html_content) {
@@ -308,6 +855,8 @@ async fn export_to_html_tailwind(content: String, filename: String) -> Result<St
 async fn export_to_html_bootstrap(content: String, filename: String) -> Result<String, String> {
     use std::fs;
 
+    let body = html_export::render_body(&markdown::parse(&content));
+
     let html_content = format!(
         r#"<!DOCTYPE html>
 <html lang="en">
@@ -329,7 +878,7 @@ async fn export_to_html_bootstrap(content: String, filename: String) -> Result<S
 </body>
 </html>"#,
         filename.replace(".html", ""),
-        content
+        body
     );
 
     match fs::write(&filename, html_content) {
@@ -372,19 +921,7 @@ async fn export_to_json(content: String, filename: String) -> Result<String, Str
 async fn export_to_rtf(content: String, filename: String) -> Result<String, String> {
     use std::fs;
 
-    // Convert basic Markdown to RTF
-    let rtf_content = content
-        .replace("**", "\\b ")
-        .replace("*", "\\i ")
-        .replace("#", "\\fs28\\b ")
-        .replace("\n", "\\par\n");
-
-    let rtf_document = format!(
-        r#"{{\rtf1\ansi\deff0 {{\fonttbl {{\f0 Times New Roman;}}}}
-\f0\fs24 {}
-}}"#,
-        rtf_content
-    );
+    let rtf_document = rtf_export::render(&markdown::parse(&content));
 
     match fs::write(&filename, rtf_document) {
         Ok(_) => {
@@ -402,19 +939,7 @@ async fn export_to_rtf(content: String, filename: String) -> Result<String, Stri
 async fn export_to_mediawiki(content: String, filename: String) -> Result<String, String> {
     use std::fs;
 
-    // Convert Markdown to MediaWiki format
-    let mediawiki_content = content
-        .replace("# ", "= ")
-        .replace("## ", "== ")
-        .replace("### ", "=== ")
-        .replace("#### ", "==== ")
-        .replace("##### ", "===== ")
-        .replace("###### ", "====== ")
-        .replace("**", "'''")
-        .replace("*", "''")
-        .replace("[", "[[")
-        .replace("](", "|")
-        .replace(")", "]]");
+    let mediawiki_content = mediawiki_export::render(&markdown::parse(&content));
 
     match fs::write(&filename, mediawiki_content) {
         Ok(_) => {
@@ -428,6 +953,535 @@ async fn export_to_mediawiki(content: String, filename: String) -> Result<String
     }
 }
 
+#[tauri::command]
+async fn export_to_docx(
+    content: String,
+    filename: String,
+    document_path: Option<String>,
+) -> Result<String, String> {
+    let base_dir = document_path
+        .as_deref()
+        .and_then(|p| Path::new(p).parent())
+        .map(Path::to_path_buf);
+    let title = Path::new(&filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let docx = docx_export::markdown_to_docx(&content, &title, base_dir.as_deref())?;
+
+    match fs::write(&filename, docx) {
+        Ok(_) => {
+            log::info!("DOCX exported successfully to: {}", filename);
+            Ok(format!("DOCX exported to {}", filename))
+        }
+        Err(e) => {
+            log::error!("Failed to export DOCX: {}", e);
+            Err(format!("Failed to export DOCX: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn export_to_odt(
+    content: String,
+    filename: String,
+    document_path: Option<String>,
+) -> Result<String, String> {
+    let base_dir = document_path
+        .as_deref()
+        .and_then(|p| Path::new(p).parent())
+        .map(Path::to_path_buf);
+    let title = Path::new(&filename)
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .unwrap_or("Document")
+        .to_string();
+
+    let odt = odt_export::markdown_to_odt(&content, &title, base_dir.as_deref())?;
+
+    match fs::write(&filename, odt) {
+        Ok(_) => {
+            log::info!("ODT exported successfully to: {}", filename);
+            Ok(format!("ODT exported to {}", filename))
+        }
+        Err(e) => {
+            log::error!("Failed to export ODT: {}", e);
+            Err(format!("Failed to export ODT: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn export_folder_to_epub(
+    folder_path: String,
+    filename: String,
+    title: Option<String>,
+) -> Result<String, String> {
+    let files = get_folder_files(folder_path.clone(), None, None).await?;
+    let mut paths = Vec::new();
+    collect_markdown_files(&files, &mut paths);
+
+    let mut chapters = Vec::new();
+    for path in paths {
+        // Notes in any encoding the editor opens
+        let markdown = text_files::read(&path)?.content;
+        chapters.push(epub_export::Chapter { path, markdown });
+    }
+
+    let folder_name = Path::new(&folder_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Book")
+        .to_string();
+    let info = epub_export::BookInfo {
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or(folder_name),
+        author: get_username().await.unwrap_or_default(),
+        language: "en".to_string(),
+        date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+    };
+
+    let epub = epub_export::build_epub(&chapters, &info)?;
+
+    match fs::write(&filename, epub) {
+        Ok(_) => {
+            log::info!("EPUB exported successfully to: {}", filename);
+            Ok(format!("EPUB exported to {}", filename))
+        }
+        Err(e) => {
+            log::error!("Failed to export EPUB: {}", e);
+            Err(format!("Failed to export EPUB: {}", e))
+        }
+    }
+}
+
+#[tauri::command]
+async fn export_folder_to_site(
+    folder_path: String,
+    output_path: String,
+    title: Option<String>,
+    theme: Option<String>,
+) -> Result<String, String> {
+    match build_folder_site(&folder_path, &output_path, title, theme).await {
+        Ok(report) => {
+            log::info!(
+                "Site exported successfully to: {} ({} pages, {} files copied)",
+                output_path,
+                report.pages,
+                report.assets
+            );
+            if report.skipped.is_empty() {
+                return Ok(format!(
+                    "Website with {} pages exported to {}",
+                    report.pages, output_path
+                ));
+            }
+            let skipped: Vec<String> = report
+                .skipped
+                .iter()
+                .map(|path| path.to_string_lossy().to_string())
+                .collect();
+            Ok(format!(
+                "Website with {} pages exported to {}, but these files weren't copied because a page or the stylesheet has the same name: {}",
+                report.pages,
+                output_path,
+                skipped.join(", ")
+            ))
+        }
+        Err(e) => {
+            log::error!("Failed to export site: {}", e);
+            Err(format!("Failed to export site: {}", e))
+        }
+    }
+}
+
+/// Render a folder to a static site; shared by the command and `--export-site`
+async fn build_folder_site(
+    folder_path: &str,
+    output_path: &str,
+    title: Option<String>,
+    theme: Option<String>,
+) -> Result<site_export::SiteReport, String> {
+    let files = get_folder_files(folder_path.to_string(), None, None).await?;
+
+    let folder_name = Path::new(folder_path)
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("Documents")
+        .to_string();
+    // Unknown theme ids fall back to the default stylesheet
+    let theme = theme.as_deref().and_then(|id| {
+        let found = themes::find(id);
+        if found.is_none() {
+            log::warn!("Unknown theme for site export: {}", id);
+        }
+        found
+    });
+    let options = site_export::SiteOptions {
+        title: title
+            .filter(|t| !t.trim().is_empty())
+            .unwrap_or(folder_name),
+        theme,
+    };
+
+    site_export::build_site(
+        Path::new(folder_path),
+        &files,
+        Path::new(output_path),
+        &options,
+    )
+}
+
+/// Handle `docura --export-site <folder> <output> [--title <title>] [--theme <id>]`
+/// without opening a window. Returns the exit code, or `None` for a normal start.
+fn run_cli_export(args: &[String]) -> Option<i32> {
+    if args.get(1).map(String::as_str) != Some("--export-site") {
+        return None;
+    }
+
+    let usage = "Usage: docura --export-site <folder> <output> [--title <title>] [--theme <id>]";
+    let mut positional = Vec::new();
+    let mut title = None;
+    let mut theme = None;
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.as_str() {
+            "--title" => title = rest.next().cloned(),
+            "--theme" => theme = rest.next().cloned(),
+            _ => positional.push(arg.clone()),
+        }
+    }
+    let [folder, output] = positional.as_slice() else {
+        eprintln!("{}", usage);
+        return Some(2);
+    };
+
+    match tauri::async_runtime::block_on(build_folder_site(folder, output, title, theme)) {
+        Ok(report) => {
+            println!(
+                "Exported {} pages and {} files to {}",
+                report.pages, report.assets, output
+            );
+            for path in &report.skipped {
+                eprintln!(
+                    "Not copied, a page or the stylesheet has the same name: {}",
+                    path.display()
+                );
+            }
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("Failed to export site: {}", e);
+            Some(1)
+        }
+    }
+}
+
+/// Markdown files of a folder tree, in the order the sidebar lists them
+fn collect_markdown_files(items: &[FileItem], out: &mut Vec<PathBuf>) {
+    for item in items {
+        match &item.children {
+            Some(children) => collect_markdown_files(children, out),
+            None if markdown::is_markdown_path(Path::new(&item.path)) => {
+                out.push(PathBuf::from(&item.path))
+            }
+            None => {}
+        }
+    }
+}
+
+#[command]
+async fn watch_folder(
+    app: tauri::AppHandle,
+    window: tauri::Window,
+    watchers: tauri::State<'_, watcher::FolderWatchers>,
+    folder_path: String,
+) -> Result<(), String> {
+    let path = Path::new(&folder_path);
+    if !path.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+
+    // Each window watches the one folder it shows; a new folder replaces the old watcher
+    let label = window.label().to_string();
+    let config = load_config().await.unwrap_or_else(|e| {
+        log::warn!("{}", e);
+        AppConfig::default()
+    });
+    let filter = watcher::WatchFilter::new(path, config.ignore_patterns, config.show_ignored_files);
+    let folder_watcher = watcher::watch(app, label.clone(), path, filter)?;
+    watchers
+        .lock()
+        .map_err(|e| format!("Failed to lock folder watchers: {}", e))?
+        .insert(label.clone(), folder_watcher);
+    log::info!("Watching {} for window {}", folder_path, label);
+    Ok(())
+}
+
+#[command]
+async fn unwatch_folder(
+    window: tauri::Window,
+    watchers: tauri::State<'_, watcher::FolderWatchers>,
+) -> Result<(), String> {
+    watchers
+        .lock()
+        .map_err(|e| format!("Failed to lock folder watchers: {}", e))?
+        .remove(window.label());
+    Ok(())
+}
+
+#[command]
+async fn search_folder(
+    indexes: tauri::State<'_, search::SearchIndexes>,
+    folder_path: String,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<search::SearchHit>, String> {
+    let files = get_folder_files(folder_path.clone(), None, None).await?;
+    let mut paths = Vec::new();
+    collect_markdown_files(&files, &mut paths);
+
+    // The first search indexes the whole folder, later ones only changed
+    // files. They are read without holding the index, which every other
+    // window's searches and saves wait on.
+    let root = PathBuf::from(&folder_path);
+    let indexed = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock search index: {}", e))?
+        .get(&root)
+        .map(search::SearchIndex::stamps)
+        .unwrap_or_default();
+    let reads = tokio::task::spawn_blocking(move || search::read_changed(paths, &indexed))
+        .await
+        .map_err(|e| format!("Failed to index {}: {}", folder_path, e))?;
+
+    let mut indexes = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock search index: {}", e))?;
+    let index = indexes.entry(root).or_default();
+    let read = index.apply(reads);
+    if read > 0 {
+        log::info!("Search index for {}: {} files indexed", folder_path, read);
+    }
+
+    Ok(index.search(&query, limit.unwrap_or(50)))
+}
+
+#[command]
+async fn search_update_file(
+    indexes: tauri::State<'_, search::SearchIndexes>,
+    file_path: String,
+) -> Result<(), String> {
+    let path = Path::new(&file_path);
+    if !markdown::is_markdown_path(path) {
+        return Ok(());
+    }
+
+    let mut indexes = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock search index: {}", e))?;
+    for (folder, index) in indexes.iter_mut() {
+        if path.starts_with(folder) {
+            index.update_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Run `query` on the link index of `folder_path`, after re-reading the
+/// files that changed since the last query
+async fn query_link_index<T>(
+    indexes: &link_index::LinkIndexes,
+    folder_path: &str,
+    query: impl FnOnce(&link_index::LinkIndex) -> T,
+) -> Result<T, String> {
+    let files = folder_file_paths(folder_path).await?;
+
+    // Notes are read without holding the index, as for search_folder
+    let root = PathBuf::from(folder_path);
+    let indexed = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock link index: {}", e))?
+        .get(&root)
+        .map(link_index::LinkIndex::stamps)
+        .unwrap_or_default();
+    let reads = tokio::task::spawn_blocking(move || link_index::read_changed(files, &indexed))
+        .await
+        .map_err(|e| format!("Failed to index links of {}: {}", folder_path, e))?;
+
+    let mut indexes = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock link index: {}", e))?;
+    let index = indexes
+        .entry(root.clone())
+        .or_insert_with(|| link_index::LinkIndex::new(&root));
+    let read = index.apply(reads);
+    if read > 0 {
+        log::info!("Link index for {}: {} files indexed", folder_path, read);
+    }
+    Ok(query(index))
+}
+
+#[command]
+async fn get_file_links(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    folder_path: String,
+    file_path: String,
+) -> Result<link_index::FileLinks, String> {
+    query_link_index(&indexes, &folder_path, |index| {
+        index.file_links(Path::new(&file_path))
+    })
+    .await
+}
+
+#[command]
+async fn get_broken_links(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    folder_path: String,
+) -> Result<Vec<link_index::BrokenLink>, String> {
+    query_link_index(&indexes, &folder_path, |index| index.broken_links()).await
+}
+
+#[command]
+async fn get_orphan_notes(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    folder_path: String,
+) -> Result<Vec<String>, String> {
+    query_link_index(&indexes, &folder_path, |index| index.orphans()).await
+}
+
+#[command]
+async fn get_link_graph(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    folder_path: String,
+) -> Result<link_index::LinkGraph, String> {
+    query_link_index(&indexes, &folder_path, |index| index.graph()).await
+}
+
+/// Problems with the links and headings of every note in `folder_path`.
+/// External URLs are only requested with `check_external`.
+#[command]
+async fn lint_folder(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    folder_path: String,
+    check_external: Option<bool>,
+) -> Result<Vec<lint::Diagnostic>, String> {
+    let (mut diagnostics, external) =
+        query_link_index(&indexes, &folder_path, lint::check_notes).await?;
+    if check_external.unwrap_or(false) {
+        let client = lint::url_client()?;
+        diagnostics.extend(lint::check_urls(&client, &external).await);
+    }
+    lint::sort_diagnostics(&mut diagnostics);
+    log::info!("Checked {}: {} problems", folder_path, diagnostics.len());
+    Ok(diagnostics)
+}
+
+#[command]
+async fn links_update_file(
+    indexes: tauri::State<'_, link_index::LinkIndexes>,
+    file_path: String,
+) -> Result<(), String> {
+    let path = Path::new(&file_path);
+    if !markdown::is_markdown_path(path) {
+        return Ok(());
+    }
+
+    let mut indexes = indexes
+        .lock()
+        .map_err(|e| format!("Failed to lock link index: {}", e))?;
+    for (folder, index) in indexes.iter_mut() {
+        if path.starts_with(folder) {
+            index.update_file(path)?;
+        }
+    }
+    Ok(())
+}
+
+/// Changed files of the git repository `folder_path` is in, or `None` if
+/// it isn't in one
+#[command]
+async fn git_status(folder_path: String) -> Result<Option<git::RepoStatus>, String> {
+    match git::Repo::discover(Path::new(&folder_path))? {
+        Some(repo) => repo.status().map(Some),
+        None => Ok(None),
+    }
+}
+
+/// What `content` (the file on disk when left out) changes in the file as
+/// of `commit`, HEAD when left out
+#[command]
+async fn git_diff_file(
+    file_path: String,
+    content: Option<String>,
+    commit: Option<String>,
+) -> Result<diff::TextDiff, String> {
+    let path = Path::new(&file_path);
+    let committed = git::Repo::open(path)?.file_at(path, commit.as_deref())?;
+    let old = match committed {
+        Some(bytes) => text_files::decode(&bytes)?.content,
+        None => String::new(),
+    };
+    let new = match content {
+        Some(content) => content,
+        None => read_text_document(&file_path).await?.document.content,
+    };
+    Ok(diff::diff_lines(&old, &new))
+}
+
+#[command]
+async fn git_stage(file_paths: Vec<String>) -> Result<(), String> {
+    let Some(first) = file_paths.first() else {
+        return Ok(());
+    };
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    git::Repo::open(Path::new(first))?.stage(&paths)
+}
+
+#[command]
+async fn git_unstage(file_paths: Vec<String>) -> Result<(), String> {
+    let Some(first) = file_paths.first() else {
+        return Ok(());
+    };
+    let paths: Vec<PathBuf> = file_paths.iter().map(PathBuf::from).collect();
+    git::Repo::open(Path::new(first))?.unstage(&paths)
+}
+
+/// Commit what is staged in the repository `folder_path` is in. Returns the
+/// short id of the commit.
+#[command]
+async fn git_commit(folder_path: String, message: String) -> Result<String, String> {
+    let id = git::Repo::open(Path::new(&folder_path))?.commit(&message)?;
+    log::info!("Committed {} in {}", id, folder_path);
+    Ok(id)
+}
+
+/// The commits that changed a file, newest first
+#[command]
+async fn git_file_log(
+    file_path: String,
+    limit: Option<usize>,
+) -> Result<Vec<git::FileCommit>, String> {
+    let path = Path::new(&file_path);
+    git::Repo::open(path)?.file_log(path, limit.unwrap_or(100))
+}
+
+/// Put the version of a file from `commit` on disk, without staging it.
+/// The version it replaces is kept in the file's history.
+#[command]
+async fn git_checkout_file(file_path: String, commit: String) -> Result<(), String> {
+    let path = Path::new(&file_path);
+    let bytes = git::Repo::open(path)?
+        .file_at(path, Some(&commit))?
+        .ok_or_else(|| format!("{} is not in commit {}", file_path, commit))?;
+    replace_with_revision(path, &bytes).await?;
+    log::info!("Checked out {} from {}", file_path, commit);
+    Ok(())
+}
+
 #[command]
 async fn print_document(_content: String) -> Result<String, String> {
     // For now, return a placeholder message
@@ -445,13 +1499,6 @@ pub struct RecentItem {
     timestamp: i64,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-pub struct TempFile {
-    id: String,
-    content: String,
-    timestamp: i64,
-}
-
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct SyncFolder {
     #[serde(alias = "local_path")]
@@ -506,6 +1553,20 @@ pub struct AppConfig {
     dropbox: DropboxConfig,
     #[serde(default)]
     dropbox_sync_enabled: bool,
+    /// Extra gitignore-style patterns hidden from the folder tree
+    #[serde(default)]
+    ignore_patterns: Vec<String>,
+    /// Show hidden and ignored files in the folder tree
+    #[serde(default)]
+    show_ignored_files: bool,
+    #[serde(default)]
+    tree_sort: file_meta::SortMode,
+    /// Extensions opened as text besides the built-in ones
+    #[serde(default)]
+    text_extensions: Vec<String>,
+    /// How many earlier versions of saved documents to keep
+    #[serde(default)]
+    history_retention: history::Retention,
 }
 
 fn default_live_editor_type() -> String {
@@ -523,6 +1584,11 @@ impl Default for AppConfig {
             live_editor_type: "modern".to_string(),
             dropbox: DropboxConfig::default(),
             dropbox_sync_enabled: false,
+            ignore_patterns: Vec::new(),
+            show_ignored_files: false,
+            tree_sort: file_meta::SortMode::Name,
+            text_extensions: Vec::new(),
+            history_retention: history::Retention::default(),
         }
     }
 }
@@ -545,17 +1611,8 @@ fn get_config_dir() -> Result<PathBuf, String> {
     Ok(config_dir)
 }
 
-fn get_temp_dir() -> Result<PathBuf, String> {
-    let config_dir = get_config_dir()?;
-    let temp_dir = config_dir.join("temp");
-
-    // Create temp directory if it doesn't exist
-    if !temp_dir.exists() {
-        fs::create_dir_all(&temp_dir)
-            .map_err(|e| format!("Failed to create temp directory: {}", e))?;
-    }
-
-    Ok(temp_dir)
+fn get_session_dir() -> Result<PathBuf, String> {
+    Ok(get_config_dir()?.join("session"))
 }
 
 #[command]
@@ -585,7 +1642,7 @@ async fn save_config(config: AppConfig) -> Result<(), String> {
     let json = serde_json::to_string_pretty(&config)
         .map_err(|e| format!("Failed to serialize config: {}", e))?;
 
-    fs::write(&config_file, json).map_err(|e| format!("Failed to write config file: {}", e))?;
+    document_save::write_atomic(&config_file, json.as_bytes())?;
 
     log::info!("Config saved successfully");
     Ok(())
@@ -633,6 +1690,49 @@ async fn add_recent_item(path: String, item_type: String) -> Result<(), String>
     Ok(())
 }
 
+#[command]
+async fn set_show_ignored_files(show: bool) -> Result<(), String> {
+    let mut config = load_config().await?;
+    config.show_ignored_files = show;
+    save_config(config).await
+}
+
+#[command]
+async fn set_tree_sort(sort: file_meta::SortMode) -> Result<(), String> {
+    let mut config = load_config().await?;
+    config.tree_sort = sort;
+    save_config(config).await
+}
+
+#[command]
+async fn set_ignore_patterns(patterns: Vec<String>) -> Result<(), String> {
+    let mut config = load_config().await?;
+    config.ignore_patterns = patterns
+        .into_iter()
+        .map(|p| p.trim().to_string())
+        .filter(|p| !p.is_empty())
+        .collect();
+    save_config(config).await
+}
+
+#[command]
+async fn set_text_extensions(extensions: Vec<String>) -> Result<(), String> {
+    let mut config = load_config().await?;
+    config.text_extensions = extensions
+        .into_iter()
+        .map(|e| e.trim().trim_start_matches('.').to_lowercase())
+        .filter(|e| !e.is_empty())
+        .collect();
+    save_config(config).await
+}
+
+#[command]
+async fn set_history_retention(retention: history::Retention) -> Result<(), String> {
+    let mut config = load_config().await?;
+    config.history_retention = retention;
+    save_config(config).await
+}
+
 #[command]
 async fn get_recent_items() -> Result<Vec<RecentItem>, String> {
     let config = load_config().await?;
@@ -872,7 +1972,11 @@ async fn grant_file_scope(app: tauri::AppHandle, file_path: String) -> Result<()
 }
 
 #[command]
-async fn open_new_window(app: tauri::AppHandle, folder_path: Option<String>) -> Result<(), String> {
+async fn open_new_window(
+    app: tauri::AppHandle,
+    folder_path: Option<String>,
+    recover_buffer: Option<String>,
+) -> Result<(), String> {
     // Generate unique window label
     let window_label = format!(
         "docura-{}",
@@ -919,105 +2023,52 @@ async fn open_new_window(app: tauri::AppHandle, folder_path: Option<String>) ->
         log::info!("Event emitted successfully to window: {}", window_label);
     }
 
+    // Likewise for an unsaved buffer from a crashed session to restore there
+    if let Some(buffer_id) = recover_buffer {
+        tokio::time::sleep(tokio::time::Duration::from_millis(2000)).await;
+        app.emit_to(&window_label, "recover-buffer", buffer_id)
+            .map_err(|e| e.to_string())?;
+    }
+
     Ok(())
 }
 
-// Temp file management commands
-#[command]
-async fn save_temp_file(content: String, temp_id: Option<String>) -> Result<String, String> {
-    let temp_dir = get_temp_dir()?;
-
-    // Use existing ID or generate new one
-    let id = temp_id.unwrap_or_else(|| {
-        std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_millis()
-            .to_string()
-    });
+// Session journal commands, for recovering unsaved work after a crash
 
-    let temp_file_path = temp_dir.join(format!("temp-{}.md", id));
-
-    fs::write(&temp_file_path, content).map_err(|e| format!("Failed to save temp file: {}", e))?;
-
-    log::info!("Saved temp file: {:?}", temp_file_path);
-    Ok(id)
+/// Record the state of the calling window's buffer, with its text while it
+/// has unsaved changes. Returns the buffer's id, assigned on first use.
+#[command]
+async fn update_session_buffer(
+    buffer: session::Buffer,
+    content: Option<String>,
+) -> Result<String, String> {
+    session::update(&get_session_dir()?, buffer, content.as_deref())
 }
 
+/// Forget a buffer that was closed, saved or deliberately discarded
 #[command]
-async fn load_temp_files() -> Result<Vec<TempFile>, String> {
-    let temp_dir = get_temp_dir()?;
-    let mut temp_files = Vec::new();
-
-    if !temp_dir.exists() {
-        return Ok(temp_files);
-    }
-
-    let entries =
-        fs::read_dir(&temp_dir).map_err(|e| format!("Failed to read temp directory: {}", e))?;
-
-    for entry in entries {
-        if let Ok(entry) = entry {
-            let path = entry.path();
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("md") {
-                if let Some(file_name) = path.file_stem().and_then(|s| s.to_str()) {
-                    // Extract ID from filename (temp-{id}.md)
-                    if let Some(id) = file_name.strip_prefix("temp-") {
-                        let content = fs::read_to_string(&path).unwrap_or_default();
-
-                        let metadata = fs::metadata(&path).ok();
-                        let timestamp = metadata
-                            .and_then(|m| m.modified().ok())
-                            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-                            .map(|d| d.as_secs() as i64)
-                            .unwrap_or(0);
-
-                        temp_files.push(TempFile {
-                            id: id.to_string(),
-                            content,
-                            timestamp,
-                        });
-                    }
-                }
-            }
-        }
-    }
-
-    // Sort by timestamp (newest first)
-    temp_files.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-
-    log::info!("Loaded {} temp files", temp_files.len());
-    Ok(temp_files)
+async fn close_session_buffer(buffer_id: String) -> Result<(), String> {
+    session::close(&get_session_dir()?, &buffer_id)
 }
 
+/// Unsaved buffers left behind by an earlier run that crashed or was killed
 #[command]
-async fn delete_temp_file(temp_id: String) -> Result<(), String> {
-    let temp_dir = get_temp_dir()?;
-    let temp_file_path = temp_dir.join(format!("temp-{}.md", temp_id));
-
-    if temp_file_path.exists() {
-        fs::remove_file(&temp_file_path)
-            .map_err(|e| format!("Failed to delete temp file: {}", e))?;
-        log::info!("Deleted temp file: {:?}", temp_file_path);
-    }
-
-    Ok(())
+async fn list_recoverable_buffers() -> Result<Vec<session::RecoveredBuffer>, String> {
+    let root = get_session_dir()?;
+    // Untitled buffers saved as temp files by earlier versions
+    session::adopt_temp_files(&root, &get_config_dir()?.join("temp"))?;
+    let buffers = session::recoverable(&root)?;
+    log::info!("Found {} unsaved buffers to recover", buffers.len());
+    Ok(buffers)
 }
 
+/// Take over an unsaved buffer from an earlier run in the calling window
 #[command]
-async fn clear_all_temp_files() -> Result<(), String> {
-    let temp_dir = get_temp_dir()?;
-
-    if temp_dir.exists() {
-        fs::remove_dir_all(&temp_dir)
-            .map_err(|e| format!("Failed to clear temp directory: {}", e))?;
-        // Recreate the directory
-        fs::create_dir(&temp_dir)
-            .map_err(|e| format!("Failed to recreate temp directory: {}", e))?;
-        log::info!("Cleared all temp files");
-    }
-
-    Ok(())
+async fn restore_session_buffer(
+    window: tauri::Window,
+    buffer_id: String,
+) -> Result<session::RecoveredBuffer, String> {
+    session::claim(&get_session_dir()?, &buffer_id, window.label())
 }
 
 #[command]
@@ -1414,9 +2465,18 @@ async fn get_cli_args() -> Result<Option<String>, String> {
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
+    let args: Vec<String> = env::args().collect();
+    if let Some(code) = run_cli_export(&args) {
+        std::process::exit(code);
+    }
+
     tauri::Builder::default()
         .plugin(tauri_plugin_dialog::init())
         .plugin(tauri_plugin_fs::init())
+        .manage(search::SearchIndexes::default())
+        .manage(watcher::FolderWatchers::default())
+        .manage(tree::DirectoryListings::default())
+        .manage(link_index::LinkIndexes::default())
         .setup(move |app| {
             if cfg!(debug_assertions) {
                 app.handle().plugin(
@@ -1440,23 +2500,76 @@ pub fn run() {
 
             Ok(())
         })
+        .on_window_event(|window, event| {
+            // A closed window no longer needs its folder watched
+            if let tauri::WindowEvent::Destroyed = event {
+                if let Ok(mut watchers) = window.state::<watcher::FolderWatchers>().lock() {
+                    watchers.remove(window.label());
+                }
+            }
+        })
         .invoke_handler(tauri::generate_handler![
             get_folder_files,
+            list_directory,
+            create_file,
+            create_folder,
+            rename_path,
+            move_path,
+            duplicate_path,
+            trash_path,
+            preview_link_updates,
+            rename_with_links,
             read_file_content,
+            read_document,
+            save_document,
+            list_revisions,
+            get_revision,
+            diff_revisions,
+            restore_revision,
+            diff_texts,
+            diff_with_file,
+            merge_texts,
             export_to_pdf,
             export_to_html,
+            export_to_html_standalone,
             export_to_html_plain,
             export_to_html_tailwind,
             export_to_html_bootstrap,
             export_to_json,
             export_to_rtf,
             export_to_mediawiki,
+            export_to_docx,
+            export_to_odt,
+            export_folder_to_epub,
+            export_folder_to_site,
+            search_folder,
+            search_update_file,
+            get_file_links,
+            get_broken_links,
+            get_orphan_notes,
+            get_link_graph,
+            lint_folder,
+            links_update_file,
+            git_status,
+            git_diff_file,
+            git_stage,
+            git_unstage,
+            git_commit,
+            git_file_log,
+            git_checkout_file,
+            watch_folder,
+            unwatch_folder,
             print_document,
             load_config,
             save_config,
             add_recent_item,
             get_recent_items,
             clear_recent_items,
+            set_show_ignored_files,
+            set_ignore_patterns,
+            set_text_extensions,
+            set_history_retention,
+            set_tree_sort,
             is_tiling_wm,
             grant_file_scope,
             check_omakase_command,
@@ -1464,10 +2577,10 @@ pub fn run() {
             get_omakase_font,
             open_new_window,
             save_clipboard_image,
-            save_temp_file,
-            load_temp_files,
-            delete_temp_file,
-            clear_all_temp_files,
+            update_session_buffer,
+            close_session_buffer,
+            list_recoverable_buffers,
+            restore_session_buffer,
             quit_app,
             get_username,
             // Plasma theme sync
@@ -0,0 +1,452 @@
+// Link Index Module
+// Records the outgoing links and heading anchors of every Markdown file in
+// an opened folder, to answer "what links here", list broken links and find
+// notes nothing links to. Like the search index, files are only re-read
+// when their size or modification time changes, and a saved file is
+// updated right away.
+
+use crate::links::{self, FileIndex, Link, LinkKind};
+use crate::markdown;
+use crate::search::{file_stamp, Stamp};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Longer lines are cut short when shown around a link
+const CONTEXT_CHARS: usize = 200;
+
+/// One index per opened folder, shared between windows
+pub type LinkIndexes = Mutex<HashMap<PathBuf, LinkIndex>>;
+
+struct WrittenLink {
+    link: Link,
+    line: usize,
+    column: usize,
+    /// The line the link is on
+    context: String,
+}
+
+struct IndexedFile {
+    stamp: Stamp,
+    links: Vec<WrittenLink>,
+    anchors: Vec<String>,
+}
+
+/// A link from one file to another (or to a place in the same file)
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkRef {
+    /// The file the link is written in
+    pub source: String,
+    pub line: usize,
+    pub column: usize,
+    pub kind: LinkKind,
+    /// The destination as written
+    pub dest: String,
+    /// The file it points at, if it is a local path
+    pub target: Option<String>,
+    pub context: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FileLinks {
+    path: String,
+    outgoing: Vec<LinkRef>,
+    backlinks: Vec<LinkRef>,
+    anchors: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum BrokenReason {
+    MissingFile,
+    MissingAnchor,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BrokenLink {
+    #[serde(flatten)]
+    pub link: LinkRef,
+    pub reason: BrokenReason,
+}
+
+#[derive(Debug, Serialize)]
+pub struct GraphEdge {
+    from: String,
+    to: String,
+    /// Links from `from` to `to`
+    count: usize,
+}
+
+/// Notes and the links between them
+#[derive(Debug, Serialize)]
+pub struct LinkGraph {
+    nodes: Vec<String>,
+    edges: Vec<GraphEdge>,
+}
+
+fn read_links(content: &str) -> (Vec<WrittenLink>, Vec<String>) {
+    let links = links::extract(content)
+        .into_iter()
+        .map(|link| {
+            let (line, column) = links::line_col(content, link.range.start);
+            let context = content
+                .lines()
+                .nth(line - 1)
+                .unwrap_or_default()
+                .trim()
+                .chars()
+                .take(CONTEXT_CHARS)
+                .collect();
+            WrittenLink {
+                link,
+                line,
+                column,
+                context,
+            }
+        })
+        .collect();
+    (links, markdown::parse(content).heading_anchors())
+}
+
+/// Whether `fragment` names one of `anchors`, either as the anchor itself
+/// or as heading text (`[[Note#Some Heading]]`)
+pub fn has_anchor(anchors: &[String], fragment: &str) -> bool {
+    let fragment = urlencoding::decode(fragment).unwrap_or_else(|_| fragment.into());
+    anchors
+        .iter()
+        .any(|a| *a == fragment || *a == markdown::slugify(&fragment))
+}
+
+pub struct LinkIndex {
+    files: HashMap<PathBuf, IndexedFile>,
+    /// Every file of the folder, Markdown or not, for resolving links
+    targets: FileIndex,
+}
+
+/// Notes read to bring an index in line with a folder
+pub struct FileReads {
+    /// Every file of the folder, Markdown or not
+    files: Vec<PathBuf>,
+    read: Vec<(PathBuf, IndexedFile)>,
+    /// Notes that are gone or could not be read
+    failed: Vec<PathBuf>,
+}
+
+/// Read the notes among `files` whose stamp differs from `indexed`, the
+/// [`LinkIndex::stamps`] of the index they are for. Only the disk is
+/// touched, so a query doesn't hold the index while notes are read.
+pub fn read_changed(files: Vec<PathBuf>, indexed: &HashMap<PathBuf, Stamp>) -> FileReads {
+    let mut read = Vec::new();
+    let mut failed = Vec::new();
+    for path in files.iter().filter(|f| markdown::is_markdown_path(f)) {
+        let Some(stamp) = file_stamp(path) else {
+            failed.push(path.clone());
+            continue;
+        };
+        if indexed.get(path) == Some(&stamp) {
+            continue;
+        }
+        match read_file(path, stamp) {
+            Ok(file) => read.push((path.clone(), file)),
+            Err(e) => {
+                log::warn!("{}", e);
+                failed.push(path.clone());
+            }
+        }
+    }
+    FileReads {
+        files,
+        read,
+        failed,
+    }
+}
+
+fn read_file(path: &Path, stamp: Stamp) -> Result<IndexedFile, String> {
+    let bytes = fs::read(path)
+        .map_err(|e| format!("Failed to index links of {}: {}", path.display(), e))?;
+    let (links, anchors) = read_links(&String::from_utf8_lossy(&bytes));
+    Ok(IndexedFile {
+        stamp,
+        links,
+        anchors,
+    })
+}
+
+impl LinkIndex {
+    pub fn new(root: &Path) -> Self {
+        LinkIndex {
+            files: HashMap::new(),
+            targets: FileIndex::new(root, Vec::new()),
+        }
+    }
+
+    /// Stamps of the indexed notes, for [`read_changed`]
+    pub fn stamps(&self) -> HashMap<PathBuf, Stamp> {
+        self.files
+            .iter()
+            .map(|(path, file)| (path.clone(), file.stamp))
+            .collect()
+    }
+
+    /// Bring the index in line with the files of `reads`: changed notes are
+    /// replaced and notes no longer listed or readable are dropped. Returns
+    /// how many notes were read.
+    pub fn apply(&mut self, reads: FileReads) -> usize {
+        let notes: HashSet<&PathBuf> = reads
+            .files
+            .iter()
+            .filter(|f| markdown::is_markdown_path(f))
+            .collect();
+        self.files.retain(|path, _| notes.contains(path));
+        for path in &reads.failed {
+            self.files.remove(path);
+        }
+
+        let count = reads.read.len();
+        self.files.extend(reads.read);
+        self.targets = FileIndex::new(&self.targets.root, reads.files);
+        count
+    }
+
+    /// Re-read a single file, e.g. right after it was saved
+    pub fn update_file(&mut self, path: &Path) -> Result<(), String> {
+        let Some(stamp) = file_stamp(path) else {
+            self.files.remove(path);
+            return Ok(());
+        };
+        if !self.targets.files.iter().any(|f| f == path) {
+            let mut files = std::mem::take(&mut self.targets.files);
+            files.push(path.to_path_buf());
+            self.targets = FileIndex::new(&self.targets.root, files);
+        }
+        match read_file(path, stamp) {
+            Ok(file) => {
+                self.files.insert(path.to_path_buf(), file);
+                Ok(())
+            }
+            Err(e) => {
+                self.files.remove(path);
+                Err(e)
+            }
+        }
+    }
+
+    /// The indexed notes, sorted by path
+    pub fn notes(&self) -> Vec<&PathBuf> {
+        let mut notes: Vec<&PathBuf> = self.files.keys().collect();
+        notes.sort();
+        notes
+    }
+
+    /// The file `link` in `source` points at; a bare `#anchor` points at
+    /// `source` itself
+    fn resolve(&self, link: &Link, source: &Path) -> Option<PathBuf> {
+        match link.split_fragment() {
+            ("", Some(_)) => Some(source.to_path_buf()),
+            _ => self.targets.resolve(link, source),
+        }
+    }
+
+    fn link_ref(&self, source: &Path, written: &WrittenLink, target: Option<&Path>) -> LinkRef {
+        LinkRef {
+            source: source.to_string_lossy().to_string(),
+            line: written.line,
+            column: written.column,
+            kind: written.link.kind,
+            dest: written.link.dest.clone(),
+            target: target.map(|t| t.to_string_lossy().to_string()),
+            context: written.context.clone(),
+        }
+    }
+
+    /// All links with the file they point at, sorted by source file
+    fn resolved_links(&self) -> Vec<(&PathBuf, &WrittenLink, Option<PathBuf>)> {
+        self.notes()
+            .into_iter()
+            .flat_map(|source| {
+                self.files[source]
+                    .links
+                    .iter()
+                    .map(move |written| (source, written, self.resolve(&written.link, source)))
+            })
+            .collect()
+    }
+
+    /// Outgoing links, backlinks and anchors of `path`
+    pub fn file_links(&self, path: &Path) -> FileLinks {
+        let file = self.files.get(path);
+        let outgoing = file
+            .map(|f| {
+                f.links
+                    .iter()
+                    .map(|written| {
+                        let target = self.resolve(&written.link, path);
+                        self.link_ref(path, written, target.as_deref())
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        let backlinks = self
+            .resolved_links()
+            .into_iter()
+            .filter(|(source, _, target)| {
+                source.as_path() != path && target.as_deref() == Some(path)
+            })
+            .map(|(source, written, target)| self.link_ref(source, written, target.as_deref()))
+            .collect();
+
+        FileLinks {
+            path: path.to_string_lossy().to_string(),
+            outgoing,
+            backlinks,
+            anchors: file.map(|f| f.anchors.clone()).unwrap_or_default(),
+        }
+    }
+
+    /// Links to files that don't exist, or to headings a note doesn't have
+    pub fn broken_links(&self) -> Vec<BrokenLink> {
+        self.resolved_links()
+            .into_iter()
+            .filter_map(|(source, written, target)| {
+                let target = target?;
+                let reason = match self.files.get(&target) {
+                    Some(note) => {
+                        let fragment = written.link.split_fragment().1?;
+                        if fragment.is_empty() || has_anchor(&note.anchors, fragment) {
+                            return None;
+                        }
+                        BrokenReason::MissingAnchor
+                    }
+                    None if target.exists() => return None,
+                    None => BrokenReason::MissingFile,
+                };
+                Some(BrokenLink {
+                    link: self.link_ref(source, written, Some(&target)),
+                    reason,
+                })
+            })
+            .collect()
+    }
+
+    /// Notes no other note links to, by path
+    pub fn orphans(&self) -> Vec<String> {
+        let linked: HashSet<PathBuf> = self
+            .resolved_links()
+            .into_iter()
+            .filter_map(|(source, _, target)| target.filter(|t| t != source))
+            .collect();
+        let mut orphans: Vec<String> = self
+            .files
+            .keys()
+            .filter(|path| !linked.contains(*path))
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        orphans.sort();
+        orphans
+    }
+
+    /// Every note, and the links between notes
+    pub fn graph(&self) -> LinkGraph {
+        let mut edges: BTreeMap<(&PathBuf, PathBuf), usize> = BTreeMap::new();
+        for (source, _, target) in self.resolved_links() {
+            if let Some(target) = target.filter(|t| t != source && self.files.contains_key(t)) {
+                *edges.entry((source, target)).or_default() += 1;
+            }
+        }
+
+        let mut nodes: Vec<String> = self
+            .files
+            .keys()
+            .map(|path| path.to_string_lossy().to_string())
+            .collect();
+        nodes.sort();
+        LinkGraph {
+            nodes,
+            edges: edges
+                .into_iter()
+                .map(|((from, to), count)| GraphEdge {
+                    from: from.to_string_lossy().to_string(),
+                    to: to.to_string_lossy().to_string(),
+                    count,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backlinks_broken_links_and_orphans() {
+        let root =
+            std::env::temp_dir().join(format!("docura-link-index-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("notes")).unwrap();
+        let write = |name: &str, content: &str| {
+            let path = root.join(name);
+            fs::write(&path, content).unwrap();
+            path
+        };
+        let index_md = write(
+            "index.md",
+            "# Home\n\nSee [ideas](notes/ideas.md#big-plans) and [[ideas#Small Things]].\n\
+             [Gone](old.md), [bad anchor](notes/ideas.md#nope) and [top](#home).\n",
+        );
+        let ideas = write(
+            "notes/ideas.md",
+            "# Big plans\n\n## Small things\n\n![](../pic.png)\n",
+        );
+        let lonely = write("notes/lonely.md", "Nobody links here. [[index]]\n");
+        let pic = write("pic.png", "");
+
+        let mut index = LinkIndex::new(&root);
+        let files = vec![index_md.clone(), ideas.clone(), lonely.clone(), pic.clone()];
+        assert_eq!(index.apply(read_changed(files.clone(), &index.stamps())), 3);
+        assert_eq!(index.apply(read_changed(files, &index.stamps())), 0);
+
+        let links = index.file_links(&ideas);
+        let backlinks: Vec<(&str, usize)> = links
+            .backlinks
+            .iter()
+            .map(|l| (l.dest.as_str(), l.line))
+            .collect();
+        assert_eq!(
+            backlinks,
+            vec![
+                ("notes/ideas.md#big-plans", 3),
+                ("ideas#Small Things", 3),
+                ("notes/ideas.md#nope", 4)
+            ]
+        );
+        assert_eq!(links.anchors, vec!["big-plans", "small-things"]);
+        assert_eq!(
+            links.outgoing[0].target.as_deref(),
+            Some(pic.to_str().unwrap())
+        );
+        assert!(links.backlinks[0].context.starts_with("See [ideas]"));
+
+        let broken = index.broken_links();
+        let broken: Vec<(&str, BrokenReason)> = broken
+            .iter()
+            .map(|b| (b.link.dest.as_str(), b.reason))
+            .collect();
+        assert_eq!(
+            broken,
+            vec![
+                ("old.md", BrokenReason::MissingFile),
+                ("notes/ideas.md#nope", BrokenReason::MissingAnchor)
+            ]
+        );
+        assert_eq!(index.orphans(), vec![lonely.to_string_lossy().to_string()]);
+
+        // Saving a file updates its links right away
+        fs::write(&ideas, "# Big plans\n\n[[lonely]]\n").unwrap();
+        index.update_file(&ideas).unwrap();
+        assert!(index.orphans().is_empty());
+        assert_eq!(index.graph().edges.len(), 3);
+        fs::remove_dir_all(&root).ok();
+    }
+}
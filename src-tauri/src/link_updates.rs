@@ -0,0 +1,422 @@
+// Link Updates Module
+// Keeps links working when a file or folder is renamed or moved: finds the
+// relative links, images, reference definitions and wiki links in the
+// folder's Markdown files that point at the moved path, and rewrites them.
+// Links inside moved Markdown files are rewritten for their new location.
+// The plan can be shown to the user before anything is touched. Files keep
+// their encoding and line endings, and their history gets both versions.
+
+use crate::document_save;
+use crate::file_ops;
+use crate::links::{self, FileIndex, Link};
+use crate::markdown;
+use crate::text_files;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LinkChange {
+    /// 1-based position of the link destination
+    line: usize,
+    column: usize,
+    old: String,
+    new: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileLinkChanges {
+    /// Where the file is now, before the move
+    path: String,
+    changes: Vec<LinkChange>,
+}
+
+/// A file whose links couldn't be checked or can't be written back
+#[derive(Debug, Clone, Serialize)]
+pub struct SkippedFile {
+    path: String,
+    reason: String,
+}
+
+/// A file's contents before and after its links are updated
+#[derive(Debug)]
+struct Edit {
+    /// Where the file is after the move
+    path: PathBuf,
+    old: Vec<u8>,
+    new: Vec<u8>,
+}
+
+/// The links a rename or move would update
+#[derive(Debug, Serialize)]
+pub struct LinkUpdatePlan {
+    from: String,
+    to: String,
+    files: Vec<FileLinkChanges>,
+    skipped: Vec<SkippedFile>,
+    #[serde(skip)]
+    edits: Vec<Edit>,
+}
+
+/// Where `path` ends up when `from` is moved to `to`
+fn moved_path(path: &Path, from: &Path, to: &Path) -> Option<PathBuf> {
+    path.strip_prefix(from).ok().map(|rest| {
+        if rest.as_os_str().is_empty() {
+            to.to_path_buf()
+        } else {
+            to.join(rest)
+        }
+    })
+}
+
+/// Wiki links leave out `.md`; keep it that way
+fn wiki_target(path: &str, written: &str) -> String {
+    if Path::new(written).extension().is_none() && markdown::is_markdown_path(Path::new(path)) {
+        path.rsplit_once('.')
+            .map_or(path, |(stem, _)| stem)
+            .to_string()
+    } else {
+        path.to_string()
+    }
+}
+
+/// The destination for `link`, written in `source_dir`, now that its
+/// target lives at `target`. Keeps the style of the original: `./`,
+/// percent-encoding, absolute paths and the `#fragment`.
+fn rewrite(link: &Link, bracketed: bool, target: &Path, source_dir: &Path, root: &Path) -> String {
+    let (path, fragment) = link.split_fragment();
+    let fragment = fragment.map(|f| format!("#{}", f)).unwrap_or_default();
+
+    if link.is_wiki() {
+        let name = target
+            .file_name()
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let new_path = if path.contains('/') {
+            links::relative_path(root, target)
+        } else {
+            name
+        };
+        return wiki_target(&new_path, path) + &fragment;
+    }
+
+    let scheme = if path.starts_with("file://") {
+        "file://"
+    } else {
+        ""
+    };
+    let mut new_path = if Path::new(path.trim_start_matches("file://")).is_absolute() {
+        target.to_string_lossy().replace('\\', "/")
+    } else {
+        links::relative_path(source_dir, target)
+    };
+    if path.starts_with("./") && !new_path.starts_with("../") {
+        new_path = format!("./{}", new_path);
+    }
+
+    // Without angle brackets a space would end the destination
+    let encode = path.contains('%') || (!bracketed && new_path.contains(char::is_whitespace));
+    if encode {
+        new_path = new_path
+            .split('/')
+            .map(|part| match part {
+                "." | ".." => part.to_string(),
+                _ => urlencoding::encode(part).into_owned(),
+            })
+            .collect::<Vec<_>>()
+            .join("/");
+    }
+    format!("{}{}{}", scheme, new_path, fragment)
+}
+
+/// Work out which links change when `from` is renamed or moved to `to`.
+/// Nothing is written.
+pub fn plan(index: &FileIndex, from: &Path, to: &Path) -> Result<LinkUpdatePlan, String> {
+    let from = links::normalize(from);
+    let to = links::normalize(to);
+    let new_location =
+        |path: &Path| moved_path(path, &from, &to).unwrap_or_else(|| path.to_path_buf());
+
+    let mut files = Vec::new();
+    let mut skipped = Vec::new();
+    let mut edits = Vec::new();
+    for file in index.files.iter().filter(|f| markdown::is_markdown_path(f)) {
+        let mut skip = |reason: String| {
+            log::warn!("Not updating links in {}: {}", file.display(), reason);
+            skipped.push(SkippedFile {
+                path: file.to_string_lossy().to_string(),
+                reason,
+            });
+        };
+        let read = fs::read(file)
+            .map_err(|e| format!("Failed to read file: {}", e))
+            .and_then(|bytes| text_files::decode(&bytes).map(|document| (bytes, document)));
+        let (bytes, document) = match read {
+            Ok(read) => read,
+            Err(e) => {
+                skip(e);
+                continue;
+            }
+        };
+        let content = document.content;
+        let new_file = new_location(file);
+        let file_moves = new_file != *file;
+        let new_dir = new_file.parent().unwrap_or(&index.root);
+
+        let mut changes = Vec::new();
+        let mut updated = content.clone();
+        // Back to front, so earlier ranges stay valid
+        for link in links::extract(&content).iter().rev() {
+            let Some(target) = index.resolve(link, file) else {
+                continue;
+            };
+            let new_target = new_location(&target);
+            if new_target == target && !file_moves {
+                continue;
+            }
+            // Bare wiki links find their file wherever it is
+            if new_target == target && link.is_wiki() && !link.split_fragment().0.contains('/') {
+                continue;
+            }
+
+            let bracketed = content[..link.range.start].ends_with('<');
+            let new_dest = rewrite(link, bracketed, &new_target, new_dir, &index.root);
+            if new_dest == link.dest {
+                continue;
+            }
+            let (line, column) = links::line_col(&content, link.range.start);
+            updated.replace_range(link.range.clone(), &new_dest);
+            changes.push(LinkChange {
+                line,
+                column,
+                old: link.dest.clone(),
+                new: new_dest,
+            });
+        }
+
+        if changes.is_empty() {
+            continue;
+        }
+        // e.g. a new name a Latin-1 file can't hold
+        let new = match text_files::encode(&updated, document.format) {
+            Ok(new) => new,
+            Err(e) => {
+                skip(e);
+                continue;
+            }
+        };
+        changes.reverse();
+        files.push(FileLinkChanges {
+            path: file.to_string_lossy().to_string(),
+            changes,
+        });
+        edits.push(Edit {
+            path: new_file,
+            old: bytes,
+            new,
+        });
+    }
+
+    Ok(LinkUpdatePlan {
+        from: from.to_string_lossy().to_string(),
+        to: to.to_string_lossy().to_string(),
+        files,
+        skipped,
+        edits,
+    })
+}
+
+/// Rename `from` to `to` (same folder) or move it into another folder
+/// (same name), like the sidebar does
+pub fn move_path(from: &Path, to: &Path) -> Result<PathBuf, String> {
+    let name = to
+        .file_name()
+        .ok_or_else(|| format!("Invalid path: {}", to.display()))?;
+    match to.parent() {
+        Some(parent) if Some(parent) == from.parent() => {
+            file_ops::rename(from, &name.to_string_lossy())
+        }
+        Some(parent) if Some(name) == from.file_name() => file_ops::move_into(from, parent),
+        _ => Err("Can only rename or move, not both at once".to_string()),
+    }
+}
+
+/// Move the file, then update the links. `record` adds each file's
+/// versions before and after to its history. A file whose links can't be
+/// written is reported, but the move stays.
+pub fn apply(plan: &LinkUpdatePlan, record: &dyn Fn(&Path, &[u8])) -> Result<PathBuf, String> {
+    let moved = move_path(Path::new(&plan.from), Path::new(&plan.to))?;
+
+    let failed: Vec<String> = plan
+        .edits
+        .iter()
+        .filter_map(|edit| {
+            record(&edit.path, &edit.old);
+            document_save::write_atomic(&edit.path, &edit.new)
+                .map(|_| record(&edit.path, &edit.new))
+                .map_err(|e| {
+                    log::error!("Failed to update links in {}: {}", edit.path.display(), e)
+                })
+                .err()
+                .map(|_| edit.path.to_string_lossy().to_string())
+        })
+        .collect();
+    if !failed.is_empty() {
+        return Err(format!(
+            "Moved, but failed to update links in: {}",
+            failed.join(", ")
+        ));
+    }
+    Ok(moved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_and_apply() {
+        let root = std::env::temp_dir().join(format!("docura-links-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("assets")).unwrap();
+        fs::create_dir_all(root.join("notes")).unwrap();
+        fs::write(
+            root.join("index.md"),
+            "[Design](design.md#goals) ![](assets/x.png) [[design]] [[notes/todo]]\n\
+             [web](https://example.com/design.md) [self](#top)\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("design.md"),
+            "![chart](./assets/x.png) [up](index.md)\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("notes/todo.md"),
+            "See [design](<../design.md>) and [[design|the design]].\n",
+        )
+        .unwrap();
+        fs::write(root.join("assets/x.png"), "").unwrap();
+
+        let files = ["index.md", "design.md", "notes/todo.md", "assets/x.png"]
+            .iter()
+            .map(|f| root.join(f))
+            .collect();
+        let index = FileIndex::new(&root, files);
+
+        let rename = plan(&index, &root.join("design.md"), &root.join("Design Doc.md")).unwrap();
+        let changed: Vec<(String, Vec<(&str, &str)>)> = rename
+            .files
+            .iter()
+            .map(|f| {
+                let name = Path::new(&f.path)
+                    .strip_prefix(&root)
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string();
+                (
+                    name,
+                    f.changes
+                        .iter()
+                        .map(|c| (c.old.as_str(), c.new.as_str()))
+                        .collect(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            changed,
+            vec![
+                (
+                    "index.md".to_string(),
+                    vec![
+                        ("design.md#goals", "Design%20Doc.md#goals"),
+                        ("design", "Design Doc")
+                    ]
+                ),
+                (
+                    "notes/todo.md".to_string(),
+                    vec![
+                        ("../design.md", "../Design Doc.md"),
+                        ("design", "Design Doc")
+                    ]
+                ),
+            ]
+        );
+        assert_eq!(
+            (
+                rename.files[0].changes[0].line,
+                rename.files[0].changes[0].column
+            ),
+            (1, 10)
+        );
+
+        // Moving a file rewrites its own relative links as well
+        let moved = plan(
+            &index,
+            &root.join("design.md"),
+            &root.join("notes/design.md"),
+        )
+        .unwrap();
+        assert_eq!(moved.files.len(), 3);
+        assert!(moved.skipped.is_empty());
+        let recorded = std::cell::RefCell::new(Vec::new());
+        apply(&moved, &|path, bytes| {
+            recorded
+                .borrow_mut()
+                .push((path.to_path_buf(), bytes.len()))
+        })
+        .unwrap();
+        // Every updated file gets its version before and after
+        assert_eq!(recorded.borrow().len(), 6);
+        let design = fs::read_to_string(root.join("notes/design.md")).unwrap();
+        let todo = fs::read_to_string(root.join("notes/todo.md")).unwrap();
+        let index_md = fs::read_to_string(root.join("index.md")).unwrap();
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(design, "![chart](../assets/x.png) [up](../index.md)\n");
+        assert_eq!(
+            todo,
+            "See [design](<design.md>) and [[design|the design]].\n"
+        );
+        assert!(
+            index_md.starts_with("[Design](notes/design.md#goals) ![](assets/x.png) [[design]]")
+        );
+        assert!(index_md.contains("(https://example.com/design.md) [self](#top)"));
+    }
+
+    #[test]
+    fn test_keeps_encoding_and_reports_skipped_files() {
+        let root =
+            std::env::temp_dir().join(format!("docura-links-encoding-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("design.md"), "").unwrap();
+        fs::write(root.join("latin.md"), b"Caf\xe9: [d](design.md)\r\n").unwrap();
+        fs::write(root.join("binary.md"), b"\x00\x01\x02\x00[d](design.md)").unwrap();
+        let index =
+            |names: &[&str]| FileIndex::new(&root, names.iter().map(|f| root.join(f)).collect());
+
+        let rename = plan(
+            &index(&["design.md", "latin.md", "binary.md"]),
+            &root.join("design.md"),
+            &root.join("Café.md"),
+        )
+        .unwrap();
+        assert_eq!(rename.files.len(), 1);
+        let skipped: Vec<&str> = rename.skipped.iter().map(|f| f.path.as_str()).collect();
+        assert_eq!(skipped, vec![root.join("binary.md").to_string_lossy()]);
+        apply(&rename, &|_, _| {}).unwrap();
+        let latin = fs::read(root.join("latin.md")).unwrap();
+
+        // A name Latin-1 can't hold leaves the file as it is
+        let rename = plan(
+            &index(&["Café.md", "latin.md"]),
+            &root.join("Café.md"),
+            &root.join("Дизайн.md"),
+        )
+        .unwrap();
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!(latin, b"Caf\xe9: [d](Caf\xe9.md)\r\n");
+        assert!(rename.files.is_empty());
+        assert_eq!(rename.skipped.len(), 1);
+    }
+}
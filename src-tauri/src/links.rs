@@ -0,0 +1,358 @@
+// Links Module
+// Finds the links in Markdown source together with the exact byte range of
+// their destinations, so they can be resolved to files in the workspace and
+// rewritten in place. Covers inline links and images, reference
+// definitions, and `[[wiki links]]` / `![[embeds]]`.
+
+use crate::markdown;
+use pulldown_cmark::{Event, LinkType, Options, Parser, Tag};
+use serde::Serialize;
+use std::ops::Range;
+use std::path::{Component, Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LinkKind {
+    /// `[text](dest)`
+    Link,
+    /// `![alt](dest)`
+    Image,
+    /// `[label]: dest`
+    Definition,
+    /// `[[dest]]` or `[[dest|text]]`
+    WikiLink,
+    /// `![[dest]]`
+    WikiEmbed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    pub kind: LinkKind,
+    /// The destination as written, without angle brackets
+    pub dest: String,
+    /// Byte range of `dest` in the source
+    pub range: Range<usize>,
+}
+
+impl Link {
+    /// The part before `#`, and the fragment after it
+    pub fn split_fragment(&self) -> (&str, Option<&str>) {
+        match self.dest.split_once('#') {
+            Some((path, fragment)) => (path, Some(fragment)),
+            None => (&self.dest, None),
+        }
+    }
+
+    /// Links to websites, mail addresses and other URL schemes
+    pub fn is_external(&self) -> bool {
+        let scheme = self.dest.split_once(':').map(|(scheme, _)| scheme);
+        scheme.is_some_and(|s| {
+            s.len() > 1
+                && s.chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+        }) && !self.dest.starts_with("file:")
+    }
+
+    pub fn is_wiki(&self) -> bool {
+        matches!(self.kind, LinkKind::WikiLink | LinkKind::WikiEmbed)
+    }
+}
+
+/// Skip a `[...]` label starting at `start`, returning the index after `]`
+fn skip_label(source: &str, start: usize) -> Option<usize> {
+    let bytes = source.as_bytes();
+    let mut depth = 0;
+    let mut i = start;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'`' => {
+                // Brackets inside code spans don't count
+                let ticks = bytes[i..].iter().take_while(|&&b| b == b'`').count();
+                let fence = &source[i..i + ticks];
+                match source[i + ticks..].find(fence) {
+                    Some(end) => i += ticks + end + ticks - 1,
+                    None => i += ticks - 1,
+                }
+            }
+            b'[' => depth += 1,
+            b']' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i + 1);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Range of the destination starting at (or after whitespace from) `start`
+fn dest_range(source: &str, start: usize) -> Option<Range<usize>> {
+    let bytes = source.as_bytes();
+    let mut i = start;
+    while i < bytes.len() && bytes[i].is_ascii_whitespace() {
+        i += 1;
+    }
+    if bytes.get(i) == Some(&b'<') {
+        let end = source[i + 1..].find('>')? + i + 1;
+        return Some(i + 1..end);
+    }
+
+    let begin = i;
+    let mut depth = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => i += 1,
+            b'(' => depth += 1,
+            b')' if depth == 0 => break,
+            b')' => depth -= 1,
+            b if b.is_ascii_whitespace() => break,
+            _ => {}
+        }
+        i += 1;
+    }
+    let end = i.min(bytes.len());
+    (end > begin).then_some(begin..end)
+}
+
+fn link(source: &str, kind: LinkKind, range: Range<usize>) -> Link {
+    Link {
+        kind,
+        dest: source[range.clone()].to_string(),
+        range,
+    }
+}
+
+/// Every link in `source` whose destination is written out, in order
+pub fn extract(source: &str) -> Vec<Link> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS
+        | Options::ENABLE_WIKILINKS;
+    let parser = Parser::new_ext(source, options);
+
+    let mut links: Vec<Link> = parser
+        .reference_definitions()
+        .iter()
+        .filter_map(|(_, definition)| {
+            let span = definition.span.clone();
+            let label_start = source[span.clone()].find('[')? + span.start;
+            let label_end = skip_label(source, label_start)?;
+            let colon = source[label_end..]
+                .starts_with(':')
+                .then_some(label_end + 1)?;
+            Some(link(
+                source,
+                LinkKind::Definition,
+                dest_range(source, colon)?,
+            ))
+        })
+        .collect();
+
+    for (event, range) in parser.into_offset_iter() {
+        let (tag_kind, link_type) = match event {
+            Event::Start(Tag::Link { link_type, .. }) => (LinkKind::Link, link_type),
+            Event::Start(Tag::Image { link_type, .. }) => (LinkKind::Image, link_type),
+            _ => continue,
+        };
+        let label_start = range.start + source[range.clone()].find('[').unwrap_or(0);
+
+        match link_type {
+            LinkType::Inline => {
+                let Some(label_end) = skip_label(source, label_start) else {
+                    continue;
+                };
+                if !source[label_end..].starts_with('(') {
+                    continue;
+                }
+                if let Some(dest) = dest_range(source, label_end + 1) {
+                    links.push(link(source, tag_kind, dest));
+                }
+            }
+            LinkType::WikiLink { .. } => {
+                let start = label_start + 2;
+                let end = source[start..range.end]
+                    .find(['|', ']'])
+                    .map_or(range.end, |i| start + i);
+                let kind = match tag_kind {
+                    LinkKind::Image => LinkKind::WikiEmbed,
+                    _ => LinkKind::WikiLink,
+                };
+                if end > start {
+                    links.push(link(source, kind, start..end));
+                }
+            }
+            LinkType::Autolink if range.len() > 2 => {
+                links.push(link(source, tag_kind, range.start + 1..range.end - 1));
+            }
+            // Reference links point at definitions, which are listed already;
+            // email autolinks have no scheme to tell them from paths
+            _ => {}
+        }
+    }
+
+    links.sort_by_key(|l| l.range.start);
+    links
+}
+
+/// The files of an opened folder, for resolving links between them
+pub struct FileIndex {
+    pub root: PathBuf,
+    pub files: Vec<PathBuf>,
+}
+
+impl FileIndex {
+    pub fn new(root: &Path, mut files: Vec<PathBuf>) -> Self {
+        // Shorter paths first: a bare wiki link means the closest match
+        files.sort_by_key(|f| (f.components().count(), f.clone()));
+        FileIndex {
+            root: root.to_path_buf(),
+            files,
+        }
+    }
+
+    /// The file a bare `[[name]]` refers to: by file name, or by name
+    /// without the `.md` extension
+    fn find_by_name(&self, name: &str) -> Option<&PathBuf> {
+        let has_extension = Path::new(name).extension().is_some();
+        self.files.iter().find(|file| {
+            file.file_name().is_some_and(|n| n == name)
+                || (!has_extension
+                    && markdown::is_markdown_path(file)
+                    && file.file_stem().is_some_and(|stem| stem == name))
+        })
+    }
+
+    /// The path `link` in the file `source` points at, whether it exists or
+    /// not. `None` for external links and links within the same file.
+    pub fn resolve(&self, link: &Link, source: &Path) -> Option<PathBuf> {
+        if link.is_external() {
+            return None;
+        }
+        let (path, _) = link.split_fragment();
+        if path.is_empty() {
+            return None;
+        }
+        let base = source.parent()?;
+
+        if link.is_wiki() {
+            if !path.contains('/') {
+                if let Some(file) = self.find_by_name(path) {
+                    return Some(file.clone());
+                }
+            }
+            // Paths in wiki links start at the top of the workspace
+            let target = normalize(&self.root.join(path));
+            let with_extension =
+                target.with_file_name(format!("{}.md", target.file_name()?.to_string_lossy()));
+            return Some(
+                if target.extension().is_none() && self.files.contains(&with_extension) {
+                    with_extension
+                } else {
+                    target
+                },
+            );
+        }
+
+        let decoded = urlencoding::decode(path).map(|p| p.into_owned()).ok()?;
+        let decoded = decoded.strip_prefix("file://").unwrap_or(&decoded);
+        let target = Path::new(decoded);
+        Some(if target.is_absolute() {
+            normalize(target)
+        } else {
+            normalize(&base.join(target))
+        })
+    }
+}
+
+/// 1-based line and column (in characters) of a byte offset
+pub fn line_col(source: &str, offset: usize) -> (usize, usize) {
+    let before = &source[..offset];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    (line, before[line_start..].chars().count() + 1)
+}
+
+/// Resolve `..` and `.` without touching the file system
+pub fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    normalized.push("..");
+                }
+            }
+            other => normalized.push(other),
+        }
+    }
+    normalized
+}
+
+/// Path of `target` relative to the folder `from`, with `/` separators
+pub fn relative_path(from: &Path, target: &Path) -> String {
+    let from: Vec<_> = from.components().collect();
+    let target: Vec<_> = target.components().collect();
+    let common = from.iter().zip(&target).take_while(|(a, b)| a == b).count();
+
+    let mut parts: Vec<String> = vec!["..".to_string(); from.len() - common];
+    parts.extend(
+        target[common..]
+            .iter()
+            .map(|c| c.as_os_str().to_string_lossy().to_string()),
+    );
+    parts.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_links() {
+        let source = "# Notes\n\n\
+            See [the *design*](design.md#goals \"Design\") and ![chart](<assets/my chart.png>).\n\
+            Also [[Roadmap]], [[plans/2025|next year]] and ![[diagram.png]].\n\
+            Read [this][ref], [mail](mailto:me@example.com) or <https://example.com>.\n\
+            `[not](a-link.md)` and [a (b)](path/(x).md).\n\n\
+            [ref]: ../shared/ref.md \"Title\"\n";
+        let links = extract(source);
+
+        let found: Vec<(LinkKind, &str)> =
+            links.iter().map(|l| (l.kind, l.dest.as_str())).collect();
+        assert_eq!(
+            found,
+            vec![
+                (LinkKind::Link, "design.md#goals"),
+                (LinkKind::Image, "assets/my chart.png"),
+                (LinkKind::WikiLink, "Roadmap"),
+                (LinkKind::WikiLink, "plans/2025"),
+                (LinkKind::WikiEmbed, "diagram.png"),
+                (LinkKind::Link, "mailto:me@example.com"),
+                (LinkKind::Link, "https://example.com"),
+                (LinkKind::Link, "path/(x).md"),
+                (LinkKind::Definition, "../shared/ref.md"),
+            ]
+        );
+        assert!(links.iter().all(|l| source[l.range.clone()] == l.dest));
+        assert!(links[5].is_external() && !links[0].is_external());
+        assert_eq!(links[0].split_fragment(), ("design.md", Some("goals")));
+        assert_eq!(line_col(source, links[2].range.start), (4, 8));
+        assert!(links[6].is_external());
+
+        assert_eq!(
+            normalize(Path::new("/a/b/../c/./d.md")),
+            Path::new("/a/c/d.md")
+        );
+        assert_eq!(
+            relative_path(Path::new("/w/notes"), Path::new("/w/assets/x.png")),
+            "../assets/x.png"
+        );
+    }
+}
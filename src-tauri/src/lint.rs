@@ -0,0 +1,340 @@
+// Lint Module
+// Checks the Markdown files of an opened folder for links that lead
+// nowhere: relative links and images whose file is gone, links to headings
+// that don't exist, headings sharing an anchor, and (when asked for, since
+// it goes over the network) external URLs that don't answer. Every problem
+// is reported with its file, line and column.
+
+use crate::link_index::{BrokenReason, LinkIndex};
+use crate::links::{self, LinkKind};
+use crate::markdown;
+use pulldown_cmark::{Event, Options, Parser, Tag, TagEnd};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// External links checked at the same time
+const URL_CHECKS_AT_ONCE: usize = 8;
+const URL_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Rule {
+    /// A relative link to a file that doesn't exist
+    BrokenLink,
+    /// An image that doesn't exist
+    MissingImage,
+    /// A link to a heading the target doesn't have
+    MissingAnchor,
+    /// A heading whose anchor an earlier heading already has
+    DuplicateAnchor,
+    /// An external URL that fails or answers with an error
+    UnreachableUrl,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Diagnostic {
+    file: String,
+    /// 1-based
+    line: usize,
+    column: usize,
+    rule: Rule,
+    severity: Severity,
+    message: String,
+}
+
+/// An `http(s)` link, to be checked separately
+#[derive(Debug, Clone)]
+pub struct ExternalLink {
+    file: PathBuf,
+    line: usize,
+    column: usize,
+    url: String,
+}
+
+/// Headings of `content` whose anchor was taken by an earlier heading:
+/// (anchor, byte offset, line of the first one)
+fn duplicate_anchors(content: &str) -> Vec<(String, usize, usize)> {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+
+    let mut first_seen: HashMap<String, usize> = HashMap::new();
+    let mut duplicates = Vec::new();
+    // The heading being read: its explicit id, start and text so far
+    let mut heading: Option<(Option<String>, usize, String)> = None;
+    for (event, range) in Parser::new_ext(content, options).into_offset_iter() {
+        match event {
+            Event::Start(Tag::Heading { id, .. }) => {
+                heading = Some((id.map(|id| id.to_string()), range.start, String::new()));
+            }
+            Event::Text(text) | Event::Code(text) => {
+                if let Some((_, _, heading_text)) = heading.as_mut() {
+                    heading_text.push_str(&text);
+                }
+            }
+            Event::End(TagEnd::Heading(_)) => {
+                let Some((id, start, text)) = heading.take() else {
+                    continue;
+                };
+                let anchor = id.unwrap_or_else(|| markdown::slugify(&text));
+                let line = links::line_col(content, start).0;
+                match first_seen.get(&anchor) {
+                    Some(&first_line) => duplicates.push((anchor, start, first_line)),
+                    None => {
+                        first_seen.insert(anchor, line);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    duplicates
+}
+
+/// Check the notes of `index` without going over the network. Returns the
+/// problems found and the external links, for `check_urls`.
+pub fn check_notes(index: &LinkIndex) -> (Vec<Diagnostic>, Vec<ExternalLink>) {
+    let mut diagnostics: Vec<Diagnostic> = index
+        .broken_links()
+        .into_iter()
+        .map(|broken| {
+            let link = broken.link;
+            let is_image = matches!(link.kind, LinkKind::Image | LinkKind::WikiEmbed);
+            let (rule, severity, message) = match broken.reason {
+                BrokenReason::MissingFile if is_image => (
+                    Rule::MissingImage,
+                    Severity::Error,
+                    format!("Image not found: {}", link.dest),
+                ),
+                BrokenReason::MissingFile => (
+                    Rule::BrokenLink,
+                    Severity::Error,
+                    format!("File not found: {}", link.dest),
+                ),
+                BrokenReason::MissingAnchor => (
+                    Rule::MissingAnchor,
+                    Severity::Warning,
+                    format!("No heading for {}", link.dest),
+                ),
+            };
+            Diagnostic {
+                file: link.source,
+                line: link.line,
+                column: link.column,
+                rule,
+                severity,
+                message,
+            }
+        })
+        .collect();
+
+    let mut external = Vec::new();
+    for note in index.notes() {
+        let content = match fs::read(note) {
+            Ok(bytes) => String::from_utf8_lossy(&bytes).into_owned(),
+            Err(e) => {
+                log::warn!("Failed to read {}: {}", note.display(), e);
+                continue;
+            }
+        };
+
+        for (anchor, start, first_line) in duplicate_anchors(&content) {
+            let (line, column) = links::line_col(&content, start);
+            diagnostics.push(Diagnostic {
+                file: note.to_string_lossy().to_string(),
+                line,
+                column,
+                rule: Rule::DuplicateAnchor,
+                severity: Severity::Warning,
+                message: format!(
+                    "Heading anchor #{} is already used on line {}; links to it go there",
+                    anchor, first_line
+                ),
+            });
+        }
+
+        for link in links::extract(&content) {
+            if link.dest.starts_with("http://") || link.dest.starts_with("https://") {
+                let (line, column) = links::line_col(&content, link.range.start);
+                external.push(ExternalLink {
+                    file: note.clone(),
+                    line,
+                    column,
+                    url: link.dest,
+                });
+            }
+        }
+    }
+
+    (diagnostics, external)
+}
+
+/// The client `check_urls` is meant to be used with
+pub fn url_client() -> Result<reqwest::Client, String> {
+    reqwest::Client::builder()
+        .timeout(URL_TIMEOUT)
+        .user_agent(concat!("Docura/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|e| format!("Failed to create HTTP client: {}", e))
+}
+
+async fn check_url(client: &reqwest::Client, url: &str) -> Result<(), String> {
+    // Some servers don't answer HEAD requests; ask again with GET
+    if let Ok(response) = client.head(url).send().await {
+        if response.status().is_success() {
+            return Ok(());
+        }
+    }
+    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
+    match response.status() {
+        status if status.is_success() => Ok(()),
+        status => Err(format!("HTTP {}", status)),
+    }
+}
+
+/// Request every distinct URL of `links` and report the ones that fail
+pub async fn check_urls(client: &reqwest::Client, links: &[ExternalLink]) -> Vec<Diagnostic> {
+    let mut urls: Vec<&str> = links.iter().map(|l| l.url.as_str()).collect();
+    urls.sort_unstable();
+    urls.dedup();
+
+    let mut failures: HashMap<String, String> = HashMap::new();
+    for batch in urls.chunks(URL_CHECKS_AT_ONCE) {
+        let mut checks = tokio::task::JoinSet::new();
+        for url in batch {
+            let (client, url) = (client.clone(), url.to_string());
+            checks.spawn(async move {
+                let result = check_url(&client, &url).await;
+                (url, result)
+            });
+        }
+        while let Some(checked) = checks.join_next().await {
+            match checked {
+                Ok((url, Err(e))) => {
+                    failures.insert(url, e);
+                }
+                Ok(_) => {}
+                Err(e) => log::warn!("Failed to check a link: {}", e),
+            }
+        }
+    }
+
+    links
+        .iter()
+        .filter_map(|link| {
+            let error = failures.get(&link.url)?;
+            Some(Diagnostic {
+                file: link.file.to_string_lossy().to_string(),
+                line: link.line,
+                column: link.column,
+                rule: Rule::UnreachableUrl,
+                severity: Severity::Warning,
+                message: format!("{} is unreachable: {}", link.url, error),
+            })
+        })
+        .collect()
+}
+
+/// Sort by file and position
+pub fn sort_diagnostics(diagnostics: &mut [Diagnostic]) {
+    diagnostics.sort_by(|a, b| {
+        Path::new(&a.file)
+            .cmp(Path::new(&b.file))
+            .then(a.line.cmp(&b.line))
+            .then(a.column.cmp(&b.column))
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::link_index::read_changed;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+
+    /// A local web server answering 200 for `/ok` and 404 for anything else
+    fn stub_server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0; 1024];
+                let read = stream.read(&mut request).unwrap_or(0);
+                let request = String::from_utf8_lossy(&request[..read]);
+                let status = match request.split_whitespace().nth(1) {
+                    Some("/ok") => "200 OK",
+                    _ => "404 Not Found",
+                };
+                let response = format!(
+                    "HTTP/1.1 {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+                    status
+                );
+                stream.write_all(response.as_bytes()).ok();
+            }
+        });
+        format!("http://{}", address)
+    }
+
+    #[tokio::test]
+    async fn test_lint_folder() {
+        let root = std::env::temp_dir().join(format!("docura-lint-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("img")).unwrap();
+        let server = stub_server();
+        let note = root.join("note.md");
+        fs::write(
+            &note,
+            format!(
+                "# Setup\n\n![shot](img/gone.png) ![ok](img/here.png) [next](next.md)\n\n\
+                 ## Setup\n\n[up](#setup) [nowhere](#install)\n\n\
+                 [fine]({server}/ok) and <{server}/missing>\n"
+            ),
+        )
+        .unwrap();
+        fs::write(root.join("img/here.png"), "").unwrap();
+
+        let mut index = LinkIndex::new(&root);
+        let files = vec![note.clone(), root.join("img/here.png")];
+        index.apply(read_changed(files, &index.stamps()));
+        let (mut diagnostics, external) = check_notes(&index);
+        assert_eq!(external.len(), 2);
+        diagnostics.extend(check_urls(&url_client().unwrap(), &external).await);
+        sort_diagnostics(&mut diagnostics);
+        fs::remove_dir_all(&root).ok();
+
+        let url_column = format!("[fine]({server}/ok) and <").chars().count() + 1;
+        let found: Vec<(usize, usize, Rule)> = diagnostics
+            .iter()
+            .map(|d| (d.line, d.column, d.rule))
+            .collect();
+        assert_eq!(
+            found,
+            vec![
+                (3, 9, Rule::MissingImage),
+                (3, 50, Rule::BrokenLink),
+                (5, 1, Rule::DuplicateAnchor),
+                (7, 24, Rule::MissingAnchor),
+                (9, url_column, Rule::UnreachableUrl),
+            ]
+        );
+        assert_eq!(
+            diagnostics[2].message,
+            "Heading anchor #setup is already used on line 1; links to it go there"
+        );
+        assert!(diagnostics[4]
+            .message
+            .ends_with("is unreachable: HTTP 404 Not Found"));
+    }
+}
@@ -0,0 +1,661 @@
+// Markdown Document Model
+// Parses Markdown (CommonMark plus GFM tables, task lists, footnotes and
+// strikethrough) into a tree that the exporters walk.
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag, TagEnd};
+use std::iter::Peekable;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Document {
+    /// Raw YAML front matter (without the `---` fences), if present
+    pub front_matter: Option<String>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Block {
+    Heading {
+        level: u8,
+        id: Option<String>,
+        content: Vec<Inline>,
+    },
+    Paragraph(Vec<Inline>),
+    Quote(Vec<Block>),
+    Code {
+        language: Option<String>,
+        code: String,
+    },
+    /// `start` is `Some` for ordered lists
+    List {
+        start: Option<u64>,
+        tight: bool,
+        items: Vec<ListItem>,
+    },
+    Table {
+        alignments: Vec<Alignment>,
+        header: Vec<Vec<Inline>>,
+        rows: Vec<Vec<Vec<Inline>>>,
+    },
+    ThematicBreak,
+    Html(String),
+    FootnoteDefinition {
+        label: String,
+        blocks: Vec<Block>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ListItem {
+    /// `Some` for GFM task list items
+    pub checked: Option<bool>,
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Alignment {
+    None,
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Inline {
+    Text(String),
+    Code(String),
+    Emphasis(Vec<Inline>),
+    Strong(Vec<Inline>),
+    Strikethrough(Vec<Inline>),
+    Link {
+        url: String,
+        title: String,
+        content: Vec<Inline>,
+    },
+    Image {
+        url: String,
+        title: String,
+        alt: String,
+    },
+    FootnoteReference(String),
+    Html(String),
+    SoftBreak,
+    HardBreak,
+}
+
+/// Parse Markdown source into a document tree
+pub fn parse(markdown: &str) -> Document {
+    let options = Options::ENABLE_TABLES
+        | Options::ENABLE_FOOTNOTES
+        | Options::ENABLE_STRIKETHROUGH
+        | Options::ENABLE_TASKLISTS
+        | Options::ENABLE_HEADING_ATTRIBUTES
+        | Options::ENABLE_YAML_STYLE_METADATA_BLOCKS;
+
+    let mut builder = Builder {
+        events: Parser::new_ext(markdown, options).peekable(),
+        front_matter: None,
+        loose: false,
+        task_markers: Vec::new(),
+    };
+    let blocks = builder.blocks(None);
+
+    Document {
+        front_matter: builder.front_matter,
+        blocks,
+    }
+}
+
+/// Concatenate the visible text of inline content
+pub fn plain_text(inlines: &[Inline]) -> String {
+    let mut text = String::new();
+    for inline in inlines {
+        match inline {
+            Inline::Text(t) | Inline::Code(t) => text.push_str(t),
+            Inline::Emphasis(children)
+            | Inline::Strong(children)
+            | Inline::Strikethrough(children)
+            | Inline::Link {
+                content: children, ..
+            } => text.push_str(&plain_text(children)),
+            Inline::Image { alt, .. } => text.push_str(alt),
+            Inline::SoftBreak | Inline::HardBreak => text.push(' '),
+            Inline::FootnoteReference(_) | Inline::Html(_) => {}
+        }
+    }
+    text
+}
+
+/// GitHub-style heading anchor: lowercase, spaces to dashes, punctuation dropped
+pub fn slugify(text: &str) -> String {
+    text.trim()
+        .to_lowercase()
+        .chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '-' || c == '_' {
+                Some(c)
+            } else if c.is_whitespace() {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+impl Document {
+    /// Anchors for every heading in document order, de-duplicated like GitHub (`a`, `a-1`, ...)
+    pub fn heading_anchors(&self) -> Vec<String> {
+        let mut seen = std::collections::HashMap::new();
+        let mut anchors = Vec::new();
+        visit_blocks(&self.blocks, &mut |block| {
+            if let Block::Heading { id, content, .. } = block {
+                let base = id.clone().unwrap_or_else(|| slugify(&plain_text(content)));
+                let count = seen.entry(base.clone()).or_insert(0);
+                anchors.push(if *count == 0 {
+                    base
+                } else {
+                    format!("{}-{}", base, count)
+                });
+                *count += 1;
+            }
+        });
+        anchors
+    }
+
+    /// Text of the first level 1 heading outside lists and quotes, if it
+    /// is not empty
+    pub fn title(&self) -> Option<String> {
+        let content = self.blocks.iter().find_map(|block| match block {
+            Block::Heading {
+                level: 1, content, ..
+            } => Some(content),
+            _ => None,
+        })?;
+        let title = plain_text(content).trim().to_string();
+        (!title.is_empty()).then_some(title)
+    }
+}
+
+/// Split leading YAML front matter (without its fences) from the body,
+/// without parsing the whole document
+pub fn split_front_matter(markdown: &str) -> (Option<&str>, &str) {
+    let Some(rest) = markdown
+        .strip_prefix("---\n")
+        .or_else(|| markdown.strip_prefix("---\r\n"))
+    else {
+        return (None, markdown);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, markdown)
+}
+
+/// Value of a top-level `key: value` line in YAML front matter, unquoted
+pub fn front_matter_value(front_matter: &str, key: &str) -> Option<String> {
+    let value = front_matter
+        .lines()
+        .find_map(|line| line.strip_prefix(key)?.strip_prefix(':'))?;
+    let value = value.trim();
+    let value = [('"', '"'), ('\'', '\'')]
+        .iter()
+        .find_map(|&(open, close)| value.strip_prefix(open).and_then(|v| v.strip_suffix(close)))
+        .unwrap_or(value)
+        .trim();
+    (!value.is_empty()).then(|| value.to_string())
+}
+
+/// File extensions treated as Markdown documents
+pub const MARKDOWN_EXTENSIONS: [&str; 4] = ["md", "markdown", "mdown", "mkdn"];
+
+/// Whether `path` has one of the Markdown extensions
+pub fn is_markdown_path(path: &Path) -> bool {
+    path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| MARKDOWN_EXTENSIONS.contains(&e.to_lowercase().as_str()))
+        .unwrap_or(false)
+}
+
+/// Resolve a Markdown image reference (e.g. `assets/foo.png`) against the document folder
+pub fn resolve_local_path(url: &str, base_dir: Option<&Path>) -> Option<PathBuf> {
+    if url.contains("://") && !url.starts_with("file://") {
+        return None;
+    }
+    let url = url.strip_prefix("file://").unwrap_or(url);
+    let decoded = urlencoding::decode(url)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| url.to_string());
+    let path = Path::new(&decoded);
+
+    let resolved = if path.is_absolute() {
+        path.to_path_buf()
+    } else {
+        base_dir?.join(path)
+    };
+    resolved.is_file().then_some(resolved)
+}
+
+/// Call `f` for every block in the tree, depth first
+pub fn visit_blocks(blocks: &[Block], f: &mut dyn FnMut(&Block)) {
+    for block in blocks {
+        f(block);
+        match block {
+            Block::Quote(children)
+            | Block::FootnoteDefinition {
+                blocks: children, ..
+            } => visit_blocks(children, f),
+            Block::List { items, .. } => {
+                for item in items {
+                    visit_blocks(&item.blocks, f);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Call `f` for every inline in the tree (including nested emphasis and link text)
+pub fn visit_inlines(blocks: &[Block], f: &mut dyn FnMut(&Inline)) {
+    fn walk(inlines: &[Inline], f: &mut dyn FnMut(&Inline)) {
+        for inline in inlines {
+            f(inline);
+            match inline {
+                Inline::Emphasis(children)
+                | Inline::Strong(children)
+                | Inline::Strikethrough(children)
+                | Inline::Link {
+                    content: children, ..
+                } => walk(children, f),
+                _ => {}
+            }
+        }
+    }
+
+    visit_blocks(blocks, &mut |block| match block {
+        Block::Heading { content, .. } | Block::Paragraph(content) => walk(content, f),
+        Block::Table { header, rows, .. } => {
+            for cell in header.iter().chain(rows.iter().flatten()) {
+                walk(cell, f);
+            }
+        }
+        _ => {}
+    });
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+/// Events that belong inside a paragraph (they also appear bare in tight list items)
+fn is_inline_event(event: &Event) -> bool {
+    match event {
+        Event::Start(tag) => matches!(
+            tag,
+            Tag::Emphasis | Tag::Strong | Tag::Strikethrough | Tag::Link { .. } | Tag::Image { .. }
+        ),
+        Event::Text(_)
+        | Event::Code(_)
+        | Event::InlineHtml(_)
+        | Event::FootnoteReference(_)
+        | Event::SoftBreak
+        | Event::HardBreak
+        | Event::TaskListMarker(_) => true,
+        _ => false,
+    }
+}
+
+struct Builder<'a> {
+    events: Peekable<Parser<'a>>,
+    front_matter: Option<String>,
+    /// Set when an explicit paragraph is seen inside the current list item
+    loose: bool,
+    /// One slot per open list item, filled by its task list marker
+    task_markers: Vec<Option<bool>>,
+}
+
+impl Builder<'_> {
+    /// Collect blocks until `end` (or the end of input)
+    fn blocks(&mut self, end: Option<TagEnd>) -> Vec<Block> {
+        let mut blocks = Vec::new();
+
+        while let Some(event) = self.events.peek() {
+            if is_inline_event(event) {
+                // Tight list items carry their text without a paragraph
+                let content = self.inlines_while_inline();
+                if !content.is_empty() {
+                    blocks.push(Block::Paragraph(content));
+                }
+                continue;
+            }
+
+            let Some(event) = self.events.next() else {
+                break;
+            };
+            match event {
+                Event::End(tag) if Some(tag) == end => break,
+                Event::Start(tag) => {
+                    if let Some(block) = self.block(tag) {
+                        blocks.push(block);
+                    }
+                }
+                Event::Rule => blocks.push(Block::ThematicBreak),
+                Event::Html(html) => blocks.push(Block::Html(html.to_string())),
+                _ => {}
+            }
+        }
+
+        blocks
+    }
+
+    fn block(&mut self, tag: Tag) -> Option<Block> {
+        let block = match tag {
+            Tag::Paragraph => {
+                self.loose = true;
+                Block::Paragraph(self.inlines(TagEnd::Paragraph))
+            }
+            Tag::Heading { level, id, .. } => Block::Heading {
+                level: heading_level(level),
+                id: id.map(|id| id.to_string()),
+                content: self.inlines(TagEnd::Heading(level)),
+            },
+            Tag::BlockQuote(kind) => Block::Quote(self.blocks(Some(TagEnd::BlockQuote(kind)))),
+            Tag::CodeBlock(kind) => {
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(|lang| lang.to_string())
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                Block::Code {
+                    language,
+                    code: self.text_until(TagEnd::CodeBlock),
+                }
+            }
+            Tag::HtmlBlock => Block::Html(self.text_until(TagEnd::HtmlBlock)),
+            Tag::List(start) => self.list(start),
+            Tag::Table(alignments) => self.table(alignments),
+            Tag::FootnoteDefinition(label) => Block::FootnoteDefinition {
+                label: label.to_string(),
+                blocks: self.blocks(Some(TagEnd::FootnoteDefinition)),
+            },
+            Tag::MetadataBlock(kind) => {
+                self.front_matter = Some(self.text_until(TagEnd::MetadataBlock(kind)));
+                return None;
+            }
+            _ => return None,
+        };
+        Some(block)
+    }
+
+    fn list(&mut self, start: Option<u64>) -> Block {
+        let mut items = Vec::new();
+        let mut tight = true;
+
+        while let Some(event) = self.events.next() {
+            match event {
+                Event::Start(Tag::Item) => {
+                    let outer_loose = std::mem::replace(&mut self.loose, false);
+                    self.task_markers.push(None);
+                    let blocks = self.blocks(Some(TagEnd::Item));
+                    let checked = self.task_markers.pop().flatten();
+                    tight &= !self.loose;
+                    self.loose = outer_loose;
+                    items.push(ListItem { checked, blocks });
+                }
+                Event::End(TagEnd::List(_)) => break,
+                _ => {}
+            }
+        }
+
+        Block::List {
+            start,
+            tight,
+            items,
+        }
+    }
+
+    fn table(&mut self, alignments: Vec<pulldown_cmark::Alignment>) -> Block {
+        let alignments = alignments
+            .into_iter()
+            .map(|a| match a {
+                pulldown_cmark::Alignment::None => Alignment::None,
+                pulldown_cmark::Alignment::Left => Alignment::Left,
+                pulldown_cmark::Alignment::Center => Alignment::Center,
+                pulldown_cmark::Alignment::Right => Alignment::Right,
+            })
+            .collect();
+        let mut header = Vec::new();
+        let mut rows = Vec::new();
+        let mut in_head = false;
+
+        while let Some(event) = self.events.next() {
+            match event {
+                Event::Start(Tag::TableHead) => in_head = true,
+                Event::End(TagEnd::TableHead) => in_head = false,
+                Event::Start(Tag::TableRow) => rows.push(Vec::new()),
+                Event::Start(Tag::TableCell) => {
+                    let cell = self.inlines(TagEnd::TableCell);
+                    if in_head {
+                        header.push(cell);
+                    } else if let Some(row) = rows.last_mut() {
+                        row.push(cell);
+                    }
+                }
+                Event::End(TagEnd::Table) => break,
+                _ => {}
+            }
+        }
+
+        Block::Table {
+            alignments,
+            header,
+            rows,
+        }
+    }
+
+    fn text_until(&mut self, end: TagEnd) -> String {
+        let mut text = String::new();
+        for event in self.events.by_ref() {
+            match event {
+                Event::End(tag) if tag == end => break,
+                Event::Text(t) | Event::Html(t) | Event::Code(t) => text.push_str(&t),
+                _ => {}
+            }
+        }
+        text
+    }
+
+    fn inlines_while_inline(&mut self) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        while self.events.peek().is_some_and(is_inline_event) {
+            if let Some(event) = self.events.next() {
+                self.inline(event, &mut inlines);
+            }
+        }
+        inlines
+    }
+
+    fn inlines(&mut self, end: TagEnd) -> Vec<Inline> {
+        let mut inlines = Vec::new();
+        while let Some(event) = self.events.next() {
+            match event {
+                Event::End(tag) if tag == end => break,
+                event => self.inline(event, &mut inlines),
+            }
+        }
+        inlines
+    }
+
+    fn inline(&mut self, event: Event, inlines: &mut Vec<Inline>) {
+        let inline = match event {
+            Event::Text(text) => {
+                // Adjacent text events (e.g. around escapes) are merged
+                if let Some(Inline::Text(previous)) = inlines.last_mut() {
+                    previous.push_str(&text);
+                    return;
+                }
+                Inline::Text(text.to_string())
+            }
+            Event::Code(code) => Inline::Code(code.to_string()),
+            Event::InlineHtml(html) | Event::Html(html) => Inline::Html(html.to_string()),
+            Event::FootnoteReference(label) => Inline::FootnoteReference(label.to_string()),
+            Event::SoftBreak => Inline::SoftBreak,
+            Event::HardBreak => Inline::HardBreak,
+            Event::TaskListMarker(checked) => {
+                if let Some(slot @ None) = self.task_markers.last_mut() {
+                    *slot = Some(checked);
+                }
+                return;
+            }
+            Event::Start(Tag::Emphasis) => Inline::Emphasis(self.inlines(TagEnd::Emphasis)),
+            Event::Start(Tag::Strong) => Inline::Strong(self.inlines(TagEnd::Strong)),
+            Event::Start(Tag::Strikethrough) => {
+                Inline::Strikethrough(self.inlines(TagEnd::Strikethrough))
+            }
+            Event::Start(Tag::Link {
+                dest_url, title, ..
+            }) => Inline::Link {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+                content: self.inlines(TagEnd::Link),
+            },
+            Event::Start(Tag::Image {
+                dest_url, title, ..
+            }) => Inline::Image {
+                url: dest_url.to_string(),
+                title: title.to_string(),
+                alt: plain_text(&self.inlines(TagEnd::Image)),
+            },
+            _ => return,
+        };
+        inlines.push(inline);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(s: &str) -> Inline {
+        Inline::Text(s.to_string())
+    }
+
+    #[test]
+    fn test_code_is_not_emphasis() {
+        let doc = parse("```\na * b * c\n```\n\nuse `*x*` here (really)");
+        assert_eq!(
+            doc.blocks[0],
+            Block::Code {
+                language: None,
+                code: "a * b * c\n".to_string()
+            }
+        );
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph(vec![
+                text("use "),
+                Inline::Code("*x*".to_string()),
+                text(" here (really)"),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_task_lists_and_tightness() {
+        let doc = parse("- [x] done\n- [ ] todo\n  - plain\n");
+        let Block::List {
+            start,
+            tight,
+            items,
+        } = &doc.blocks[0]
+        else {
+            panic!("expected a list");
+        };
+        assert_eq!(*start, None);
+        assert!(*tight);
+        assert_eq!(items[0].checked, Some(true));
+        assert_eq!(items[1].checked, Some(false));
+        assert_eq!(items[0].blocks, vec![Block::Paragraph(vec![text("done")])]);
+        let Block::List { items: nested, .. } = &items[1].blocks[1] else {
+            panic!("expected a nested list");
+        };
+        assert_eq!(nested[0].checked, None);
+
+        let loose = parse("1. one\n\n2. two\n");
+        assert!(matches!(
+            loose.blocks[0],
+            Block::List {
+                start: Some(1),
+                tight: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_tables_footnotes_and_front_matter() {
+        let doc = parse(
+            "---\ntitle: Spec\n---\n\n| A | B |\n|:--|--:|\n| ~~x~~ | [y](y.md) |\n\nNote[^1]\n\n[^1]: Footnote.\n",
+        );
+        assert_eq!(doc.front_matter.as_deref(), Some("title: Spec\n"));
+        let source = "---\ntags: [a]\ntitle: \"Spec: v2\"\n---\nBody\n";
+        let (front_matter, body) = split_front_matter(source);
+        assert_eq!(body, "Body\n");
+        assert_eq!(
+            front_matter
+                .and_then(|f| front_matter_value(f, "title"))
+                .as_deref(),
+            Some("Spec: v2")
+        );
+        let Block::Table {
+            alignments,
+            header,
+            rows,
+        } = &doc.blocks[0]
+        else {
+            panic!("expected a table");
+        };
+        assert_eq!(alignments, &vec![Alignment::Left, Alignment::Right]);
+        assert_eq!(header[1], vec![text("B")]);
+        assert_eq!(rows[0][0], vec![Inline::Strikethrough(vec![text("x")])]);
+        assert!(matches!(&rows[0][1][0], Inline::Link { url, .. } if url == "y.md"));
+        assert_eq!(
+            doc.blocks[1],
+            Block::Paragraph(vec![
+                text("Note"),
+                Inline::FootnoteReference("1".to_string())
+            ])
+        );
+        assert!(matches!(&doc.blocks[2], Block::FootnoteDefinition { label, .. } if label == "1"));
+    }
+
+    #[test]
+    fn test_heading_anchors() {
+        let doc = parse("# Hello, World!\n\n## Hello, World!\n\n### Custom {#custom-id}\n");
+        assert_eq!(
+            doc.heading_anchors(),
+            vec!["hello-world", "hello-world-1", "custom-id"]
+        );
+    }
+
+    #[test]
+    fn test_title() {
+        let doc = parse("> # Quoted\n\n## Background\n\n# Real *title*\n\n# Second\n");
+        assert_eq!(doc.title().as_deref(), Some("Real title"));
+        assert_eq!(parse("## Background\n").title(), None);
+        assert_eq!(parse("#\n\n# Later\n").title(), None);
+    }
+}
@@ -0,0 +1,366 @@
+// MediaWiki Export Module
+// Converts the Markdown document tree to MediaWiki markup: closed headings,
+// `*`/`#` lists, wikitables, syntaxhighlight blocks, file embeds and
+// `<ref>` footnotes. Text that would otherwise be read as wiki markup is
+// wrapped in `<nowiki>`.
+
+use crate::markdown::{self, Alignment, Block, Document, Inline};
+use std::collections::HashMap;
+
+/// Render a document as MediaWiki markup
+pub fn render(doc: &Document) -> String {
+    let mut footnotes = HashMap::new();
+    markdown::visit_blocks(&doc.blocks, &mut |block| {
+        if let Block::FootnoteDefinition { label, blocks } = block {
+            footnotes.insert(label.clone(), blocks.clone());
+        }
+    });
+
+    let mut writer = WikiWriter {
+        out: String::new(),
+        footnotes,
+        has_references: false,
+    };
+    writer.blocks(&doc.blocks, "");
+    if writer.has_references {
+        writer.out.push_str("== References ==\n<references />\n");
+    }
+    writer.out.trim_end().to_string() + "\n"
+}
+
+/// Escape characters that are significant to the HTML layer of the parser
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Escape running text; wiki markup sequences are neutralised with `<nowiki>`
+fn escape_text(text: &str) -> String {
+    let escaped = escape_html(text);
+    let needs_nowiki = ["[", "]", "{{", "}}", "''", "~~~", "__", "|", "://"]
+        .iter()
+        .any(|pattern| escaped.contains(pattern));
+    if needs_nowiki {
+        format!("<nowiki>{}</nowiki>", escaped)
+    } else {
+        escaped
+    }
+}
+
+/// Characters that start a block construct when they begin a line
+fn guard_line_start(line: String) -> String {
+    match line.chars().next() {
+        Some('*' | '#' | ':' | ';' | '=' | ' ' | '{' | '|' | '!') => format!("<nowiki/>{}", line),
+        Some('-') if line.starts_with("----") => format!("<nowiki/>{}", line),
+        _ => line,
+    }
+}
+
+/// External URLs may not contain spaces or the closing bracket
+fn encode_url(url: &str) -> String {
+    url.replace(' ', "%20")
+        .replace('[', "%5B")
+        .replace(']', "%5D")
+}
+
+fn decode_path(path: &str) -> String {
+    urlencoding::decode(path)
+        .map(|s| s.into_owned())
+        .unwrap_or_else(|_| path.to_string())
+}
+
+fn is_external(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+struct WikiWriter {
+    out: String,
+    footnotes: HashMap<String, Vec<Block>>,
+    has_references: bool,
+}
+
+impl WikiWriter {
+    /// `prefix` is the list marker stack (e.g. `*#`) of the enclosing list items
+    fn blocks(&mut self, blocks: &[Block], prefix: &str) {
+        for block in blocks {
+            self.block(block, prefix);
+        }
+    }
+
+    fn block(&mut self, block: &Block, prefix: &str) {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let marker = "=".repeat((*level).clamp(1, 6) as usize);
+                let text = self.inlines(content);
+                self.out
+                    .push_str(&format!("{} {} {}\n\n", marker, text.trim(), marker));
+            }
+            Block::Paragraph(content) => {
+                let text = self.inlines(content);
+                if prefix.is_empty() {
+                    self.out.push_str(&guard_line_start(text));
+                    self.out.push_str("\n\n");
+                } else {
+                    // Continuation paragraphs inside a list item use the `:` indent marker
+                    self.out.push_str(&format!("{}: {}\n", prefix, text));
+                }
+            }
+            Block::Quote(children) => {
+                self.out.push_str("<blockquote>\n");
+                self.blocks(children, "");
+                self.out.truncate(self.out.trim_end().len());
+                self.out.push_str("\n</blockquote>\n\n");
+            }
+            Block::Code { language, code } => {
+                if !prefix.is_empty() {
+                    self.out.push_str(&format!("{}: ", prefix));
+                }
+                let lang = language.as_deref().unwrap_or("text");
+                self.out.push_str(&format!(
+                    "<syntaxhighlight lang=\"{}\">\n{}\n</syntaxhighlight>\n",
+                    escape_html(lang),
+                    code.trim_end_matches('\n')
+                ));
+                if prefix.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            Block::List { start, items, .. } => {
+                let marker = if start.is_some() { "#" } else { "*" };
+                let nested = format!("{}{}", prefix, marker);
+                for item in items {
+                    self.list_item(item, &nested);
+                }
+                if prefix.is_empty() {
+                    self.out.push('\n');
+                }
+            }
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows),
+            Block::ThematicBreak => self.out.push_str("----\n\n"),
+            Block::Html(html) => {
+                self.out.push_str(html.trim_end());
+                self.out.push_str("\n\n");
+            }
+            // Definitions are emitted inline as <ref> at their references
+            Block::FootnoteDefinition { .. } => {}
+        }
+    }
+
+    fn list_item(&mut self, item: &markdown::ListItem, prefix: &str) {
+        let task = match item.checked {
+            Some(true) => "☑ ",
+            Some(false) => "☐ ",
+            None => "",
+        };
+        let (first, rest) = match item.blocks.split_first() {
+            Some((Block::Paragraph(content), rest)) => (Some(content), rest),
+            _ => (None, item.blocks.as_slice()),
+        };
+
+        let text = match first {
+            Some(content) => self.inlines(content),
+            None => String::new(),
+        };
+        self.out.push_str(&format!("{} {}{}\n", prefix, task, text));
+        self.blocks(rest, prefix);
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) {
+        self.out.push_str("{| class=\"wikitable\"\n");
+        for (i, cell) in header.iter().enumerate() {
+            let text = self.cell_text(cell);
+            self.out
+                .push_str(&format!("!{} {}\n", cell_style(alignments.get(i)), text));
+        }
+        for row in rows {
+            self.out.push_str("|-\n");
+            for (i, cell) in row.iter().enumerate() {
+                let text = self.cell_text(cell);
+                self.out
+                    .push_str(&format!("|{} {}\n", cell_style(alignments.get(i)), text));
+            }
+        }
+        self.out.push_str("|}\n\n");
+    }
+
+    /// Cell content must not start a new cell with a stray `|` or `!`
+    fn cell_text(&mut self, content: &[Inline]) -> String {
+        let text = self.inlines(content);
+        if text.is_empty() {
+            text
+        } else {
+            guard_line_start(text).replace("||", "<nowiki>||</nowiki>")
+        }
+    }
+
+    fn inlines(&mut self, content: &[Inline]) -> String {
+        let mut out = String::new();
+        for inline in content {
+            self.inline(&mut out, inline);
+        }
+        out
+    }
+
+    fn inline(&mut self, out: &mut String, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => out.push_str(&escape_text(text)),
+            Inline::Code(code) => out.push_str(&format!(
+                "<code><nowiki>{}</nowiki></code>",
+                escape_html(code)
+            )),
+            Inline::Emphasis(children) => {
+                let text = self.inlines(children);
+                out.push_str(&format!("''{}''", text));
+            }
+            Inline::Strong(children) => {
+                let text = self.inlines(children);
+                out.push_str(&format!("'''{}'''", text));
+            }
+            Inline::Strikethrough(children) => {
+                let text = self.inlines(children);
+                out.push_str(&format!("<s>{}</s>", text));
+            }
+            Inline::Link { url, content, .. } => {
+                let text = self.inlines(content);
+                out.push_str(&link(url, &text));
+            }
+            Inline::Image { url, alt, .. } => {
+                if is_external(url) {
+                    out.push_str(&format!("[{} {}]", encode_url(url), escape_text(alt)));
+                } else {
+                    // Uploaded files are addressed by name, without their local directory
+                    let name = url.rsplit('/').next().unwrap_or(url);
+                    let name = decode_path(name);
+                    if alt.is_empty() {
+                        out.push_str(&format!("[[File:{}]]", name));
+                    } else {
+                        out.push_str(&format!(
+                            "[[File:{}|alt={}]]",
+                            name,
+                            escape_text(alt).replace('|', "&#124;")
+                        ));
+                    }
+                }
+            }
+            Inline::FootnoteReference(label) => {
+                self.has_references = true;
+                let Some(blocks) = self.footnotes.get(label).cloned() else {
+                    out.push_str(&format!("<ref name=\"{}\" />", escape_html(label)));
+                    return;
+                };
+                let mut parts = Vec::new();
+                for block in &blocks {
+                    if let Block::Paragraph(content) = block {
+                        parts.push(self.inlines(content));
+                    }
+                }
+                out.push_str(&format!(
+                    "<ref name=\"{}\">{}</ref>",
+                    escape_html(label),
+                    parts.join(" ")
+                ));
+            }
+            Inline::Html(html) => out.push_str(html),
+            Inline::SoftBreak => out.push(' '),
+            Inline::HardBreak => out.push_str("<br />"),
+        }
+    }
+}
+
+fn cell_style(alignment: Option<&Alignment>) -> &'static str {
+    match alignment {
+        Some(Alignment::Center) => " style=\"text-align: center;\" |",
+        Some(Alignment::Right) => " style=\"text-align: right;\" |",
+        _ => "",
+    }
+}
+
+/// External URLs become `[url text]`; relative Markdown links become wiki page links
+fn link(url: &str, text: &str) -> String {
+    if is_external(url) {
+        return if text.is_empty() {
+            format!("[{}]", encode_url(url))
+        } else {
+            format!("[{} {}]", encode_url(url), text)
+        };
+    }
+
+    let (path, fragment) = match url.split_once('#') {
+        Some((path, fragment)) => (path, Some(fragment)),
+        None => (url, None),
+    };
+    let page = path.rsplit('/').next().unwrap_or(path);
+    let page = page
+        .strip_suffix(".md")
+        .or_else(|| page.strip_suffix(".markdown"))
+        .unwrap_or(page);
+    let mut target = decode_path(page);
+    if let Some(fragment) = fragment {
+        target.push('#');
+        target.push_str(fragment);
+    }
+    if text.is_empty() {
+        format!("[[{}]]", target)
+    } else {
+        format!("[[{}|{}]]", target, text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(markdown: &str) -> String {
+        render(&markdown::parse(markdown))
+    }
+
+    #[test]
+    fn test_headings_lists_and_code() {
+        let wiki = export(
+            "# Title\n\n## Setup #1\n\n1. Install\n2. Run\n   - with `--flag`\n\n- [x] Done\n\n```bash\n# comment, not a heading\nls [a]\n```\n",
+        );
+        assert_eq!(
+            wiki,
+            "= Title =\n\n== Setup #1 ==\n\n# Install\n# Run\n#* with <code><nowiki>--flag</nowiki></code>\n\n* ☑ Done\n\n<syntaxhighlight lang=\"bash\">\n# comment, not a heading\nls [a]\n</syntaxhighlight>\n"
+        );
+    }
+
+    #[test]
+    fn test_tables() {
+        let wiki = export("| Name | Count |\n|------|:-----:|\n| a | 1 |\n| b | 2 |\n");
+        assert_eq!(
+            wiki,
+            "{| class=\"wikitable\"\n! Name\n! style=\"text-align: center;\" | Count\n|-\n| a\n| style=\"text-align: center;\" | 1\n|-\n| b\n| style=\"text-align: center;\" | 2\n|}\n"
+        );
+    }
+
+    #[test]
+    fn test_links_images_and_escaping() {
+        let wiki = export(
+            "See [the docs](<https://example.com/a b>) and [setup](guide/Setup.md#install).\n\n![Diagram](assets/flow%20chart.png)\n\nBrackets [x] and {{template}} stay literal; <b>tags</b> too.\n",
+        );
+        assert!(wiki.contains("[https://example.com/a%20b the docs]"));
+        assert!(wiki.contains("[[Setup#install|setup]]"));
+        assert!(wiki.contains("[[File:flow chart.png|alt=Diagram]]"));
+        assert!(wiki.contains("<nowiki>Brackets [x] and {{template}} stay literal; </nowiki>"));
+    }
+
+    #[test]
+    fn test_footnotes_become_refs() {
+        let wiki = export("Claim[^src].\n\n[^src]: A *source*.\n");
+        assert_eq!(
+            wiki,
+            "Claim<ref name=\"src\">A ''source''.</ref>.\n\n== References ==\n<references />\n"
+        );
+    }
+}
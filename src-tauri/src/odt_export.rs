@@ -0,0 +1,642 @@
+// ODT Export Module
+// Writes the Markdown document tree as an OpenDocument Text (.odt) package
+// using the standard LibreOffice style names (Heading 1-6, Text body,
+// Quotations, Preformatted Text, List 1, Numbering 123), so the result picks
+// up native styles and outline levels when opened.
+
+use crate::markdown::{self, Alignment, Block, Document, Inline, ListItem};
+use crate::package::{self, xml_escape, Package};
+use std::path::Path;
+
+/// A4 width minus 2cm margins on both sides
+const TEXT_WIDTH_CM: f64 = 17.0;
+const CM_PER_PIXEL: f64 = 2.54 / 96.0;
+
+const NAMESPACES: &str = concat!(
+    "xmlns:office=\"urn:oasis:names:tc:opendocument:xmlns:office:1.0\" ",
+    "xmlns:style=\"urn:oasis:names:tc:opendocument:xmlns:style:1.0\" ",
+    "xmlns:text=\"urn:oasis:names:tc:opendocument:xmlns:text:1.0\" ",
+    "xmlns:table=\"urn:oasis:names:tc:opendocument:xmlns:table:1.0\" ",
+    "xmlns:draw=\"urn:oasis:names:tc:opendocument:xmlns:drawing:1.0\" ",
+    "xmlns:fo=\"urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0\" ",
+    "xmlns:svg=\"urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0\" ",
+    "xmlns:xlink=\"http://www.w3.org/1999/xlink\" ",
+    "xmlns:dc=\"http://purl.org/dc/elements/1.1/\" ",
+    "xmlns:meta=\"urn:oasis:names:tc:opendocument:xmlns:meta:1.0\""
+);
+
+/// Render a Markdown document to the bytes of an .odt file
+pub fn markdown_to_odt(
+    markdown: &str,
+    title: &str,
+    base_dir: Option<&Path>,
+) -> Result<Vec<u8>, String> {
+    let doc = markdown::parse(markdown);
+    let mut writer = OdtWriter::new(&doc, base_dir);
+    writer.blocks(&doc.blocks, Context::default());
+    writer.package(title)
+}
+
+/// Escape text for a paragraph. ODF collapses white space, so runs of spaces,
+/// tabs and newlines are written as `text:s`, `text:tab` and `text:line-break`.
+fn text_content(text: &str) -> String {
+    // A single space between words stays literal; leading and repeated spaces do not
+    fn flush(out: &mut String, spaces: &mut usize, line_start: bool) {
+        let mut count = std::mem::take(spaces);
+        if count > 0 && !line_start {
+            out.push(' ');
+            count -= 1;
+        }
+        match count {
+            0 => {}
+            1 => out.push_str("<text:s/>"),
+            n => out.push_str(&format!("<text:s text:c=\"{}\"/>", n)),
+        }
+    }
+
+    let mut out = String::with_capacity(text.len());
+    let mut line_start = true;
+    let mut spaces = 0;
+
+    for c in text.chars() {
+        match c {
+            ' ' => spaces += 1,
+            '\t' => {
+                flush(&mut out, &mut spaces, line_start);
+                out.push_str("<text:tab/>");
+                line_start = false;
+            }
+            '\n' => {
+                spaces = 0;
+                out.push_str("<text:line-break/>");
+                line_start = true;
+            }
+            c => {
+                flush(&mut out, &mut spaces, line_start);
+                out.push_str(&xml_escape(&c.to_string()));
+                line_start = false;
+            }
+        }
+    }
+    flush(&mut out, &mut spaces, line_start);
+    out
+}
+
+/// Length in centimetres as ODF writes it
+fn cm(value: f64) -> String {
+    format!("{:.3}cm", value)
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    quote: bool,
+    /// Inside a list item, where ODF does not allow tables
+    list: bool,
+    footnote: bool,
+}
+
+impl Context {
+    fn paragraph_style(&self) -> &'static str {
+        if self.footnote {
+            "Footnote"
+        } else if self.quote {
+            "Quotations"
+        } else if self.list {
+            "List"
+        } else {
+            "Text_20_body"
+        }
+    }
+}
+
+struct Picture {
+    path: String,
+    media_type: &'static str,
+    data: Vec<u8>,
+}
+
+struct OdtWriter<'a> {
+    out: String,
+    /// Per-table column and cell styles for content.xml
+    automatic_styles: String,
+    base_dir: Option<&'a Path>,
+    anchors: std::vec::IntoIter<String>,
+    definitions: Vec<(String, Vec<Block>)>,
+    pictures: Vec<Picture>,
+    tables: usize,
+    notes: usize,
+    in_footnote: bool,
+}
+
+impl<'a> OdtWriter<'a> {
+    fn new(doc: &Document, base_dir: Option<&'a Path>) -> Self {
+        let mut definitions = Vec::new();
+        markdown::visit_blocks(&doc.blocks, &mut |block| {
+            if let Block::FootnoteDefinition { label, blocks } = block {
+                definitions.push((label.clone(), blocks.clone()));
+            }
+        });
+
+        Self {
+            out: String::new(),
+            automatic_styles: String::new(),
+            base_dir,
+            anchors: doc.heading_anchors().into_iter(),
+            definitions,
+            pictures: Vec::new(),
+            tables: 0,
+            notes: 0,
+            in_footnote: false,
+        }
+    }
+
+    fn blocks(&mut self, blocks: &[Block], ctx: Context) {
+        for block in blocks {
+            self.block(block, ctx);
+        }
+    }
+
+    fn block(&mut self, block: &Block, ctx: Context) {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let level = (*level).clamp(1, 6);
+                let anchor = self.anchors.next().unwrap_or_default();
+                self.out.push_str(&format!(
+                    "<text:h text:style-name=\"Heading_20_{0}\" text:outline-level=\"{0}\">",
+                    level
+                ));
+                if !anchor.is_empty() {
+                    self.out.push_str(&format!(
+                        "<text:bookmark text:name=\"{}\"/>",
+                        xml_escape(&anchor)
+                    ));
+                }
+                self.inlines(content);
+                self.out.push_str("</text:h>");
+            }
+            Block::Paragraph(content) => {
+                self.out.push_str(&format!(
+                    "<text:p text:style-name=\"{}\">",
+                    ctx.paragraph_style()
+                ));
+                self.inlines(content);
+                self.out.push_str("</text:p>");
+            }
+            Block::Quote(children) => {
+                let inner = Context { quote: true, ..ctx };
+                self.blocks(children, inner);
+            }
+            Block::Code { code, .. } => {
+                self.out.push_str(&format!(
+                    "<text:p text:style-name=\"Preformatted_20_Text\">{}</text:p>",
+                    text_content(code.trim_end_matches('\n'))
+                ));
+            }
+            Block::List { start, items, .. } => self.list(*start, items, ctx),
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => {
+                if ctx.list {
+                    self.table_as_text(header, rows, ctx);
+                } else {
+                    self.table(alignments, header, rows);
+                }
+            }
+            Block::ThematicBreak => self
+                .out
+                .push_str("<text:p text:style-name=\"Horizontal_20_Line\"/>"),
+            // Raw HTML has no ODF equivalent; footnote bodies are written at their references
+            Block::Html(_) | Block::FootnoteDefinition { .. } => {}
+        }
+    }
+
+    fn list(&mut self, start: Option<u64>, items: &[ListItem], ctx: Context) {
+        let style = if start.is_some() {
+            "Numbering_20_123"
+        } else {
+            "List_20_1"
+        };
+        self.out
+            .push_str(&format!("<text:list text:style-name=\"{}\">", style));
+
+        let inner = Context { list: true, ..ctx };
+        for (i, item) in items.iter().enumerate() {
+            match start {
+                Some(start) if i == 0 && start != 1 => self
+                    .out
+                    .push_str(&format!("<text:list-item text:start-value=\"{}\">", start)),
+                _ => self.out.push_str("<text:list-item>"),
+            }
+
+            let (first, rest) = match item.blocks.split_first() {
+                Some((Block::Paragraph(content), rest)) => (Some(content), rest),
+                _ => (None, item.blocks.as_slice()),
+            };
+            // Every list item needs a leading paragraph to carry its label
+            if first.is_some() || item.checked.is_some() || rest.is_empty() {
+                self.out.push_str(&format!(
+                    "<text:p text:style-name=\"{}\">",
+                    inner.paragraph_style()
+                ));
+                match item.checked {
+                    Some(true) => self.out.push_str("☒ "),
+                    Some(false) => self.out.push_str("☐ "),
+                    None => {}
+                }
+                if let Some(content) = first {
+                    self.inlines(content);
+                }
+                self.out.push_str("</text:p>");
+            }
+            self.blocks(rest, inner);
+            self.out.push_str("</text:list-item>");
+        }
+        self.out.push_str("</text:list>");
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) {
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        self.tables += 1;
+        let name = format!("Table{}", self.tables);
+        self.automatic_styles.push_str(&format!(
+            "<style:style style:name=\"{name}\" style:family=\"table\"><style:table-properties style:width=\"{width}\" table:align=\"margins\" fo:margin-top=\"0cm\" fo:margin-bottom=\"0.212cm\"/></style:style><style:style style:name=\"{name}.A\" style:family=\"table-column\"><style:table-column-properties style:column-width=\"{column}\"/></style:style>",
+            name = name,
+            width = cm(TEXT_WIDTH_CM),
+            column = cm(TEXT_WIDTH_CM / columns as f64)
+        ));
+
+        self.out.push_str(&format!(
+            "<table:table table:name=\"{0}\" table:style-name=\"{0}\"><table:table-column table:style-name=\"{0}.A\" table:number-columns-repeated=\"{1}\"/>",
+            name, columns
+        ));
+        self.out.push_str("<table:table-header-rows>");
+        self.table_row(header, alignments, columns, true);
+        self.out.push_str("</table:table-header-rows>");
+        for row in rows {
+            self.table_row(row, alignments, columns, false);
+        }
+        self.out.push_str("</table:table>");
+    }
+
+    fn table_row(
+        &mut self,
+        cells: &[Vec<Inline>],
+        alignments: &[Alignment],
+        columns: usize,
+        is_header: bool,
+    ) {
+        self.out.push_str("<table:table-row>");
+        for i in 0..columns {
+            let base = if is_header {
+                "Table_20_Heading"
+            } else {
+                "Table_20_Contents"
+            };
+            let style = match alignments.get(i) {
+                Some(Alignment::Center) => format!("{}_Center", base),
+                Some(Alignment::Right) => format!("{}_Right", base),
+                _ => base.to_string(),
+            };
+            self.out.push_str(&format!(
+                "<table:table-cell table:style-name=\"TableCell\" office:value-type=\"string\"><text:p text:style-name=\"{}\">",
+                style
+            ));
+            if let Some(cell) = cells.get(i) {
+                self.inlines(cell);
+            }
+            self.out.push_str("</text:p></table:table-cell>");
+        }
+        self.out.push_str("</table:table-row>");
+    }
+
+    /// Tables are not allowed inside list items; write their rows as tab-separated lines
+    fn table_as_text(&mut self, header: &[Vec<Inline>], rows: &[Vec<Vec<Inline>>], ctx: Context) {
+        let header_row = std::iter::once(header);
+        for row in header_row.chain(rows.iter().map(Vec::as_slice)) {
+            self.out.push_str(&format!(
+                "<text:p text:style-name=\"{}\">",
+                ctx.paragraph_style()
+            ));
+            for (i, cell) in row.iter().enumerate() {
+                if i > 0 {
+                    self.out.push_str("<text:tab/>");
+                }
+                self.inlines(cell);
+            }
+            self.out.push_str("</text:p>");
+        }
+    }
+
+    fn inlines(&mut self, content: &[Inline]) {
+        for inline in content {
+            self.inline(inline);
+        }
+    }
+
+    fn span(&mut self, style: &str, children: &[Inline]) {
+        self.out
+            .push_str(&format!("<text:span text:style-name=\"{}\">", style));
+        self.inlines(children);
+        self.out.push_str("</text:span>");
+    }
+
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => self.out.push_str(&text_content(text)),
+            Inline::Code(code) => self.out.push_str(&format!(
+                "<text:span text:style-name=\"Source_20_Text\">{}</text:span>",
+                text_content(code)
+            )),
+            Inline::Emphasis(children) => self.span("Emphasis", children),
+            Inline::Strong(children) => self.span("Strong_20_Emphasis", children),
+            Inline::Strikethrough(children) => self.span("Strikethrough", children),
+            Inline::Link { url, content, .. } => {
+                self.out.push_str(&format!(
+                    "<text:a xlink:type=\"simple\" xlink:href=\"{}\" text:style-name=\"Internet_20_link\" text:visited-style-name=\"Visited_20_Internet_20_Link\">",
+                    xml_escape(url)
+                ));
+                self.inlines(content);
+                self.out.push_str("</text:a>");
+            }
+            Inline::Image { url, alt, .. } => self.image(url, alt),
+            Inline::FootnoteReference(label) => self.footnote(label),
+            Inline::Html(_) => {}
+            Inline::SoftBreak => self.out.push(' '),
+            Inline::HardBreak => self.out.push_str("<text:line-break/>"),
+        }
+    }
+
+    fn image(&mut self, url: &str, alt: &str) {
+        let Some(path) = markdown::resolve_local_path(url, self.base_dir) else {
+            // Remote or missing images fall back to their alt text
+            let label = if alt.is_empty() { url } else { alt };
+            self.out.push_str(&text_content(&format!("[{}]", label)));
+            return;
+        };
+
+        let image = match package::load_image(&path) {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!("{}", e);
+                self.out.push_str(&text_content(&format!("[{}]", alt)));
+                return;
+            }
+        };
+
+        let index = self.pictures.len() + 1;
+        let href = format!("Pictures/image{}.{}", index, image.extension);
+        self.pictures.push(Picture {
+            path: href.clone(),
+            media_type: image.media_type,
+            data: image.data,
+        });
+
+        let mut width = image.width as f64 * CM_PER_PIXEL;
+        let mut height = image.height as f64 * CM_PER_PIXEL;
+        if width > TEXT_WIDTH_CM {
+            height = height * TEXT_WIDTH_CM / width;
+            width = TEXT_WIDTH_CM;
+        }
+
+        self.out.push_str(&format!(
+            "<draw:frame draw:style-name=\"InlineImage\" draw:name=\"Image{}\" text:anchor-type=\"as-char\" svg:width=\"{}\" svg:height=\"{}\" draw:z-index=\"0\"><draw:image xlink:href=\"{}\" xlink:type=\"simple\" xlink:show=\"embed\" xlink:actuate=\"onLoad\" draw:mime-type=\"{}\"/>",
+            index,
+            cm(width),
+            cm(height),
+            href,
+            image.media_type
+        ));
+        if !alt.is_empty() {
+            self.out
+                .push_str(&format!("<svg:desc>{}</svg:desc>", xml_escape(alt)));
+        }
+        self.out.push_str("</draw:frame>");
+    }
+
+    fn footnote(&mut self, label: &str) {
+        let definition = self.definitions.iter().find(|(l, _)| l == label).cloned();
+        // Notes cannot nest, so references inside a footnote stay as text
+        let Some((_, blocks)) = definition.filter(|_| !self.in_footnote) else {
+            self.out.push_str(&text_content(&format!("[^{}]", label)));
+            return;
+        };
+        self.notes += 1;
+        let id = self.notes;
+
+        let saved = std::mem::take(&mut self.out);
+        self.in_footnote = true;
+        let ctx = Context {
+            footnote: true,
+            ..Context::default()
+        };
+        self.blocks(&blocks, ctx);
+        if self.out.is_empty() {
+            self.out.push_str("<text:p text:style-name=\"Footnote\"/>");
+        }
+        let body = std::mem::replace(&mut self.out, saved);
+        self.in_footnote = false;
+
+        self.out.push_str(&format!(
+            "<text:note text:id=\"ftn{0}\" text:note-class=\"footnote\"><text:note-citation>{0}</text:note-citation><text:note-body>{1}</text:note-body></text:note>",
+            id, body
+        ));
+    }
+
+    fn package(self, title: &str) -> Result<Vec<u8>, String> {
+        let mut zip = Package::new("ODT");
+        // The mimetype entry must come first and be stored uncompressed
+        zip.add_stored("mimetype", b"application/vnd.oasis.opendocument.text")?;
+        zip.add("META-INF/manifest.xml", self.manifest().as_bytes())?;
+        zip.add("content.xml", self.content().as_bytes())?;
+        zip.add("styles.xml", STYLES.as_bytes())?;
+        zip.add("meta.xml", meta(title).as_bytes())?;
+        for picture in &self.pictures {
+            zip.add(&picture.path, &picture.data)?;
+        }
+
+        zip.finish()
+    }
+
+    fn content(&self) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-content {} office:version=\"1.3\">{}<office:automatic-styles>{}{}</office:automatic-styles><office:body><office:text>{}</office:text></office:body></office:document-content>",
+            NAMESPACES, FONT_FACES, CONTENT_STYLES, self.automatic_styles, self.out
+        )
+    }
+
+    fn manifest(&self) -> String {
+        let mut xml = String::from(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<manifest:manifest xmlns:manifest=\"urn:oasis:names:tc:opendocument:xmlns:manifest:1.0\" manifest:version=\"1.3\"><manifest:file-entry manifest:full-path=\"/\" manifest:version=\"1.3\" manifest:media-type=\"application/vnd.oasis.opendocument.text\"/>",
+        );
+        for part in ["content.xml", "styles.xml", "meta.xml"] {
+            xml.push_str(&format!(
+                "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"text/xml\"/>",
+                part
+            ));
+        }
+        for picture in &self.pictures {
+            xml.push_str(&format!(
+                "<manifest:file-entry manifest:full-path=\"{}\" manifest:media-type=\"{}\"/>",
+                picture.path, picture.media_type
+            ));
+        }
+        xml.push_str("</manifest:manifest>");
+        xml
+    }
+}
+
+fn meta(title: &str) -> String {
+    let now = chrono::Utc::now().format("%Y-%m-%dT%H:%M:%S");
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<office:document-meta {} office:version=\"1.3\"><office:meta><meta:generator>Docura</meta:generator><dc:title>{}</dc:title><meta:creation-date>{}</meta:creation-date><dc:date>{}</dc:date></office:meta></office:document-meta>",
+        NAMESPACES,
+        xml_escape(title),
+        now,
+        now
+    )
+}
+
+const FONT_FACES: &str = "<office:font-face-decls><style:font-face style:name=\"Liberation Serif\" svg:font-family=\"&apos;Liberation Serif&apos;\" style:font-family-generic=\"roman\" style:font-pitch=\"variable\"/><style:font-face style:name=\"Liberation Sans\" svg:font-family=\"&apos;Liberation Sans&apos;\" style:font-family-generic=\"swiss\" style:font-pitch=\"variable\"/><style:font-face style:name=\"Liberation Mono\" svg:font-family=\"&apos;Liberation Mono&apos;\" style:font-family-generic=\"modern\" style:font-pitch=\"fixed\"/></office:font-face-decls>";
+
+/// Automatic styles shared by every document: table cells, aligned cell
+/// paragraphs and the inline image frame
+const CONTENT_STYLES: &str = concat!(
+    "<style:style style:name=\"TableCell\" style:family=\"table-cell\"><style:table-cell-properties fo:padding=\"0.097cm\" fo:border=\"0.5pt solid #000000\"/></style:style>",
+    "<style:style style:name=\"Table_20_Contents_Center\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Contents\"><style:paragraph-properties fo:text-align=\"center\"/></style:style>",
+    "<style:style style:name=\"Table_20_Contents_Right\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Contents\"><style:paragraph-properties fo:text-align=\"end\"/></style:style>",
+    "<style:style style:name=\"Table_20_Heading_Center\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Heading\"><style:paragraph-properties fo:text-align=\"center\"/></style:style>",
+    "<style:style style:name=\"Table_20_Heading_Right\" style:family=\"paragraph\" style:parent-style-name=\"Table_20_Heading\"><style:paragraph-properties fo:text-align=\"end\"/></style:style>",
+    "<style:style style:name=\"InlineImage\" style:family=\"graphic\" style:parent-style-name=\"Graphics\"><style:graphic-properties style:vertical-pos=\"top\" style:vertical-rel=\"baseline\"/></style:style>"
+);
+
+const STYLES: &str = r##"<?xml version="1.0" encoding="UTF-8"?>
+<office:document-styles xmlns:office="urn:oasis:names:tc:opendocument:xmlns:office:1.0" xmlns:style="urn:oasis:names:tc:opendocument:xmlns:style:1.0" xmlns:text="urn:oasis:names:tc:opendocument:xmlns:text:1.0" xmlns:table="urn:oasis:names:tc:opendocument:xmlns:table:1.0" xmlns:draw="urn:oasis:names:tc:opendocument:xmlns:drawing:1.0" xmlns:fo="urn:oasis:names:tc:opendocument:xmlns:xsl-fo-compatible:1.0" xmlns:svg="urn:oasis:names:tc:opendocument:xmlns:svg-compatible:1.0" xmlns:xlink="http://www.w3.org/1999/xlink" office:version="1.3">
+<office:font-face-decls><style:font-face style:name="Liberation Serif" svg:font-family="&apos;Liberation Serif&apos;" style:font-family-generic="roman" style:font-pitch="variable"/><style:font-face style:name="Liberation Sans" svg:font-family="&apos;Liberation Sans&apos;" style:font-family-generic="swiss" style:font-pitch="variable"/><style:font-face style:name="Liberation Mono" svg:font-family="&apos;Liberation Mono&apos;" style:font-family-generic="modern" style:font-pitch="fixed"/></office:font-face-decls>
+<office:styles>
+<style:default-style style:family="paragraph"><style:paragraph-properties style:tab-stop-distance="1.251cm"/><style:text-properties style:font-name="Liberation Serif" fo:font-size="12pt" fo:language="en" fo:country="US"/></style:default-style>
+<style:style style:name="Standard" style:family="paragraph" style:class="text"/>
+<style:style style:name="Heading" style:family="paragraph" style:parent-style-name="Standard" style:next-style-name="Text_20_body" style:class="text"><style:paragraph-properties fo:margin-top="0.423cm" fo:margin-bottom="0.212cm" fo:keep-with-next="always"/><style:text-properties style:font-name="Liberation Sans" fo:font-size="14pt"/></style:style>
+<style:style style:name="Text_20_body" style:display-name="Text body" style:family="paragraph" style:parent-style-name="Standard" style:class="text"><style:paragraph-properties fo:margin-top="0cm" fo:margin-bottom="0.247cm" fo:line-height="115%"/></style:style>
+<style:style style:name="List" style:family="paragraph" style:parent-style-name="Text_20_body" style:class="list"><style:paragraph-properties fo:margin-bottom="0.106cm"/></style:style>
+<style:style style:name="Heading_20_1" style:display-name="Heading 1" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="1" style:class="text"><style:text-properties fo:font-size="130%" fo:font-weight="bold"/></style:style>
+<style:style style:name="Heading_20_2" style:display-name="Heading 2" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="2" style:class="text"><style:text-properties fo:font-size="115%" fo:font-weight="bold"/></style:style>
+<style:style style:name="Heading_20_3" style:display-name="Heading 3" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="3" style:class="text"><style:text-properties fo:font-size="101%" fo:font-weight="bold"/></style:style>
+<style:style style:name="Heading_20_4" style:display-name="Heading 4" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="4" style:class="text"><style:text-properties fo:font-size="95%" fo:font-style="italic" fo:font-weight="bold"/></style:style>
+<style:style style:name="Heading_20_5" style:display-name="Heading 5" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="5" style:class="text"><style:text-properties fo:font-size="85%" fo:font-weight="bold"/></style:style>
+<style:style style:name="Heading_20_6" style:display-name="Heading 6" style:family="paragraph" style:parent-style-name="Heading" style:next-style-name="Text_20_body" style:default-outline-level="6" style:class="text"><style:text-properties fo:font-size="85%" fo:font-style="italic" fo:font-weight="bold"/></style:style>
+<style:style style:name="Quotations" style:family="paragraph" style:parent-style-name="Standard" style:class="html"><style:paragraph-properties fo:margin-left="1cm" fo:margin-right="1cm" fo:margin-top="0cm" fo:margin-bottom="0.282cm" style:border-line-width-left="0.018cm 0.018cm 0.018cm" fo:padding-left="0.2cm" fo:border-left="1.1pt solid #d0d7de"/><style:text-properties fo:font-style="italic" fo:color="#57606a"/></style:style>
+<style:style style:name="Preformatted_20_Text" style:display-name="Preformatted Text" style:family="paragraph" style:parent-style-name="Standard" style:class="html"><style:paragraph-properties fo:margin-top="0cm" fo:margin-bottom="0.247cm" fo:background-color="#f3f4f6" fo:padding="0.15cm"/><style:text-properties style:font-name="Liberation Mono" fo:font-size="10pt"/></style:style>
+<style:style style:name="Table_20_Contents" style:display-name="Table Contents" style:family="paragraph" style:parent-style-name="Standard" style:class="extra"/>
+<style:style style:name="Table_20_Heading" style:display-name="Table Heading" style:family="paragraph" style:parent-style-name="Table_20_Contents" style:class="extra"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Footnote" style:family="paragraph" style:parent-style-name="Standard" style:class="extra"><style:paragraph-properties fo:margin-left="0.6cm" fo:text-indent="-0.6cm"/><style:text-properties fo:font-size="10pt"/></style:style>
+<style:style style:name="Horizontal_20_Line" style:display-name="Horizontal Line" style:family="paragraph" style:parent-style-name="Standard" style:next-style-name="Text_20_body" style:class="html"><style:paragraph-properties fo:margin-top="0cm" fo:margin-bottom="0.5cm" fo:border-bottom="0.5pt solid #808080" fo:padding="0cm"/><style:text-properties fo:font-size="6pt"/></style:style>
+<style:style style:name="Graphics" style:family="graphic"><style:graphic-properties text:anchor-type="as-char" svg:y="0cm" style:wrap="none" style:vertical-pos="top" style:vertical-rel="baseline"/></style:style>
+<style:style style:name="Emphasis" style:family="text"><style:text-properties fo:font-style="italic"/></style:style>
+<style:style style:name="Strong_20_Emphasis" style:display-name="Strong Emphasis" style:family="text"><style:text-properties fo:font-weight="bold"/></style:style>
+<style:style style:name="Source_20_Text" style:display-name="Source Text" style:family="text"><style:text-properties style:font-name="Liberation Mono" fo:font-size="95%" fo:background-color="#f3f4f6"/></style:style>
+<style:style style:name="Strikethrough" style:family="text"><style:text-properties style:text-line-through-style="solid" style:text-line-through-type="single"/></style:style>
+<style:style style:name="Internet_20_link" style:display-name="Internet link" style:family="text"><style:text-properties fo:color="#0563c1" style:text-underline-style="solid" style:text-underline-width="auto" style:text-underline-color="font-color"/></style:style>
+<style:style style:name="Visited_20_Internet_20_Link" style:display-name="Visited Internet Link" style:family="text"><style:text-properties fo:color="#800000" style:text-underline-style="solid" style:text-underline-width="auto" style:text-underline-color="font-color"/></style:style>
+<style:style style:name="Bullet_20_Symbols" style:display-name="Bullet Symbols" style:family="text"/>
+<style:style style:name="Numbering_20_Symbols" style:display-name="Numbering Symbols" style:family="text"/>
+<text:outline-style style:name="Outline"><text:outline-level-style text:level="1" style:num-format=""/><text:outline-level-style text:level="2" style:num-format=""/><text:outline-level-style text:level="3" style:num-format=""/><text:outline-level-style text:level="4" style:num-format=""/><text:outline-level-style text:level="5" style:num-format=""/><text:outline-level-style text:level="6" style:num-format=""/></text:outline-style>
+<text:list-style style:name="List_20_1" style:display-name="List 1"><text:list-level-style-bullet text:level="1" text:style-name="Bullet_20_Symbols" text:bullet-char="•"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="0.635cm" fo:text-indent="-0.635cm" fo:margin-left="0.635cm"/></style:list-level-properties></text:list-level-style-bullet><text:list-level-style-bullet text:level="2" text:style-name="Bullet_20_Symbols" text:bullet-char="◦"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="1.27cm" fo:text-indent="-0.635cm" fo:margin-left="1.27cm"/></style:list-level-properties></text:list-level-style-bullet><text:list-level-style-bullet text:level="3" text:style-name="Bullet_20_Symbols" text:bullet-char="▪"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="1.905cm" fo:text-indent="-0.635cm" fo:margin-left="1.905cm"/></style:list-level-properties></text:list-level-style-bullet><text:list-level-style-bullet text:level="4" text:style-name="Bullet_20_Symbols" text:bullet-char="•"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="2.54cm" fo:text-indent="-0.635cm" fo:margin-left="2.54cm"/></style:list-level-properties></text:list-level-style-bullet><text:list-level-style-bullet text:level="5" text:style-name="Bullet_20_Symbols" text:bullet-char="◦"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="3.175cm" fo:text-indent="-0.635cm" fo:margin-left="3.175cm"/></style:list-level-properties></text:list-level-style-bullet><text:list-level-style-bullet text:level="6" text:style-name="Bullet_20_Symbols" text:bullet-char="▪"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="3.81cm" fo:text-indent="-0.635cm" fo:margin-left="3.81cm"/></style:list-level-properties></text:list-level-style-bullet></text:list-style>
+<text:list-style style:name="Numbering_20_123" style:display-name="Numbering 123"><text:list-level-style-number text:level="1" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="0.635cm" fo:text-indent="-0.635cm" fo:margin-left="0.635cm"/></style:list-level-properties></text:list-level-style-number><text:list-level-style-number text:level="2" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="1.27cm" fo:text-indent="-0.635cm" fo:margin-left="1.27cm"/></style:list-level-properties></text:list-level-style-number><text:list-level-style-number text:level="3" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="1.905cm" fo:text-indent="-0.635cm" fo:margin-left="1.905cm"/></style:list-level-properties></text:list-level-style-number><text:list-level-style-number text:level="4" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="2.54cm" fo:text-indent="-0.635cm" fo:margin-left="2.54cm"/></style:list-level-properties></text:list-level-style-number><text:list-level-style-number text:level="5" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="3.175cm" fo:text-indent="-0.635cm" fo:margin-left="3.175cm"/></style:list-level-properties></text:list-level-style-number><text:list-level-style-number text:level="6" text:style-name="Numbering_20_Symbols" style:num-suffix="." style:num-format="1"><style:list-level-properties text:list-level-position-and-space-mode="label-alignment"><style:list-level-label-alignment text:label-followed-by="listtab" text:list-tab-stop-position="3.81cm" fo:text-indent="-0.635cm" fo:margin-left="3.81cm"/></style:list-level-properties></text:list-level-style-number></text:list-style>
+<text:notes-configuration text:note-class="footnote" style:num-format="1" text:start-value="0" text:footnotes-position="page" text:start-numbering-at="document"/>
+</office:styles>
+<office:automatic-styles><style:page-layout style:name="A4"><style:page-layout-properties fo:page-width="21cm" fo:page-height="29.7cm" style:print-orientation="portrait" fo:margin-top="2cm" fo:margin-bottom="2cm" fo:margin-left="2cm" fo:margin-right="2cm"/></style:page-layout></office:automatic-styles>
+<office:master-styles><style:master-page style:name="Standard" style:page-layout-name="A4"/></office:master-styles>
+</office:document-styles>"##;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Cursor, Read};
+
+    fn unzip(bytes: &[u8]) -> Vec<(String, Vec<u8>)> {
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).expect("valid zip");
+        (0..archive.len())
+            .map(|i| {
+                let mut file = archive.by_index(i).unwrap();
+                let mut data = Vec::new();
+                file.read_to_end(&mut data).unwrap();
+                (file.name().to_string(), data)
+            })
+            .collect()
+    }
+
+    fn part(parts: &[(String, Vec<u8>)], name: &str) -> String {
+        let (_, data) = parts
+            .iter()
+            .find(|(n, _)| n == name)
+            .unwrap_or_else(|| panic!("missing part {}", name));
+        String::from_utf8(data.clone()).unwrap()
+    }
+
+    #[test]
+    fn test_package_structure() {
+        let markdown = "# Title & Co\n\nSee [docs](https://example.com/?a=1&b=2).\n\n3. Three\n4. Four\n   - Nested `a  b`\n\n| A | B |\n|---|--:|\n| **x** | 1 |\n\n```\nfn main() {\n\tlet  x = 1;\n}\n```\n\nNote[^1].\n\n[^1]: A *source*.\n";
+        let bytes = markdown_to_odt(markdown, "Spec", None).unwrap();
+        let parts = unzip(&bytes);
+
+        assert_eq!(parts[0].0, "mimetype");
+        assert_eq!(parts[0].1, b"application/vnd.oasis.opendocument.text");
+        let content = part(&parts, "content.xml");
+        assert!(content.contains("<text:h text:style-name=\"Heading_20_1\" text:outline-level=\"1\"><text:bookmark text:name=\"title--co\"/>Title &amp; Co</text:h>"));
+        assert!(content.contains("xlink:href=\"https://example.com/?a=1&amp;b=2\""));
+        assert!(content.contains("<text:list text:style-name=\"Numbering_20_123\"><text:list-item text:start-value=\"3\">"));
+        assert!(content.contains("<text:list text:style-name=\"List_20_1\"><text:list-item><text:p text:style-name=\"List\">Nested <text:span text:style-name=\"Source_20_Text\">a <text:s/>b</text:span>"));
+        assert!(content.contains("<table:table-column table:style-name=\"Table1.A\" table:number-columns-repeated=\"2\"/><table:table-header-rows>"));
+        assert!(content.contains("<text:p text:style-name=\"Table_20_Heading_Right\">B</text:p>"));
+        assert!(content.contains(
+            "fn main() {<text:line-break/><text:tab/>let <text:s/>x = 1;<text:line-break/>}"
+        ));
+        assert!(content.contains("<text:note-citation>1</text:note-citation><text:note-body><text:p text:style-name=\"Footnote\">A <text:span text:style-name=\"Emphasis\">source</text:span>.</text:p>"));
+        assert!(part(&parts, "styles.xml").contains("style:default-outline-level=\"1\""));
+        assert!(part(&parts, "meta.xml").contains("<dc:title>Spec</dc:title>"));
+    }
+
+    #[test]
+    fn test_embeds_relative_images() {
+        let dir = std::env::temp_dir().join(format!("docura-odt-test-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("assets")).unwrap();
+        image::RgbImage::from_pixel(960, 240, image::Rgb([10, 10, 200]))
+            .save(dir.join("assets/wide.png"))
+            .unwrap();
+
+        let bytes = markdown_to_odt(
+            "![Wide chart](assets/wide.png) and ![gone](missing.png)",
+            "Images",
+            Some(&dir),
+        )
+        .unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        let parts = unzip(&bytes);
+
+        assert!(parts.iter().any(|(name, _)| name == "Pictures/image1.png"));
+        assert!(part(&parts, "META-INF/manifest.xml").contains(
+            "manifest:full-path=\"Pictures/image1.png\" manifest:media-type=\"image/png\""
+        ));
+        let content = part(&parts, "content.xml");
+        // 960px is 25.4cm, scaled down to the 17cm text width
+        assert!(content.contains("svg:width=\"17.000cm\" svg:height=\"4.250cm\""));
+        assert!(content.contains("<svg:desc>Wide chart</svg:desc>"));
+        assert!(content.contains("[gone]"));
+    }
+}
@@ -0,0 +1,111 @@
+// Package Module
+// Zip container, XML escaping and image embedding shared by the exporters
+// that write zipped XML packages (DOCX, ODT, EPUB).
+
+use std::io::{Cursor, Write};
+use std::path::Path;
+use zip::write::SimpleFileOptions;
+use zip::ZipWriter;
+
+/// Escape text for XML content and attribute values, dropping characters XML 1.0 forbids
+pub fn xml_escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            '\t' | '\n' | '\r' => out.push(c),
+            c if (c as u32) < 0x20 => {}
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// An in-memory zip archive; `kind` names the format in error messages
+pub struct Package {
+    zip: ZipWriter<Cursor<Vec<u8>>>,
+    kind: &'static str,
+}
+
+impl Package {
+    pub fn new(kind: &'static str) -> Self {
+        Self {
+            zip: ZipWriter::new(Cursor::new(Vec::new())),
+            kind,
+        }
+    }
+
+    /// Add a deflated entry
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+        self.write(name, data, options)
+    }
+
+    /// Add an uncompressed entry (the `mimetype` of EPUB and ODF must be stored)
+    pub fn add_stored(&mut self, name: &str, data: &[u8]) -> Result<(), String> {
+        let options =
+            SimpleFileOptions::default().compression_method(zip::CompressionMethod::Stored);
+        self.write(name, data, options)
+    }
+
+    fn write(&mut self, name: &str, data: &[u8], options: SimpleFileOptions) -> Result<(), String> {
+        self.zip
+            .start_file(name, options)
+            .and_then(|_| self.zip.write_all(data).map_err(Into::into))
+            .map_err(|e| format!("Failed to write {} entry {}: {}", self.kind, name, e))
+    }
+
+    pub fn finish(self) -> Result<Vec<u8>, String> {
+        self.zip
+            .finish()
+            .map(|cursor| cursor.into_inner())
+            .map_err(|e| format!("Failed to write {}: {}", self.kind, e))
+    }
+}
+
+/// Image bytes ready to embed, with the pixel size used for layout
+pub struct EmbeddedImage {
+    pub data: Vec<u8>,
+    pub extension: &'static str,
+    pub media_type: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Read an image for embedding: PNG, JPEG and GIF are kept as-is, anything
+/// else the image crate can decode is converted to PNG
+pub fn load_image(path: &Path) -> Result<EmbeddedImage, String> {
+    let data = std::fs::read(path)
+        .map_err(|e| format!("Failed to read image {}: {}", path.display(), e))?;
+    let format = image::guess_format(&data)
+        .map_err(|e| format!("Failed to decode image {}: {}", path.display(), e))?;
+    let decoded = image::load_from_memory_with_format(&data, format)
+        .map_err(|e| format!("Failed to decode image {}: {}", path.display(), e))?;
+    let (width, height) = (decoded.width(), decoded.height());
+
+    let (data, extension, media_type) = match format {
+        image::ImageFormat::Png => (data, "png", "image/png"),
+        image::ImageFormat::Jpeg => (data, "jpeg", "image/jpeg"),
+        image::ImageFormat::Gif => (data, "gif", "image/gif"),
+        _ => {
+            let mut png = Vec::new();
+            decoded
+                .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+                .map_err(|e| format!("Failed to convert image {}: {}", path.display(), e))?;
+            (png, "png", "image/png")
+        }
+    };
+
+    Ok(EmbeddedImage {
+        data,
+        extension,
+        media_type,
+        width,
+        height,
+    })
+}
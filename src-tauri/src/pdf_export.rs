@@ -0,0 +1,1658 @@
+// PDF Export Module
+// Lays Markdown out into a paginated PDF using the PDF base-14 fonts,
+// so export works offline without a browser or bundled font files.
+// Characters those fonts can't encode (Cyrillic, Greek, CJK, ...) are drawn
+// with a subset of an installed TrueType font that has them.
+
+use crate::markdown::{self, Alignment, Block, Document, Inline};
+use crate::truetype;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+// A4 in points
+const PAGE_WIDTH: f32 = 595.28;
+const PAGE_HEIGHT: f32 = 841.89;
+const MARGIN: f32 = 56.0;
+
+const BODY_SIZE: f32 = 11.0;
+const CODE_SIZE: f32 = 9.5;
+const FOOTER_SIZE: f32 = 9.0;
+const LINE_SPACING: f32 = 1.4;
+const LIST_INDENT: f32 = 18.0;
+const QUOTE_INDENT: f32 = 14.0;
+const CELL_PADDING: f32 = 4.0;
+const CODE_PADDING: f32 = 6.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Color(f32, f32, f32);
+
+const BLACK: Color = Color(0.13, 0.13, 0.13);
+const GREY: Color = Color(0.4, 0.4, 0.4);
+const LINK_BLUE: Color = Color(0.1, 0.35, 0.75);
+const RULE_GREY: Color = Color(0.8, 0.8, 0.8);
+const CODE_BACKGROUND: Color = Color(0.95, 0.95, 0.95);
+const HEADER_BACKGROUND: Color = Color(0.92, 0.92, 0.92);
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Font {
+    Regular,
+    Bold,
+    Italic,
+    BoldItalic,
+    Mono,
+}
+
+impl Font {
+    const ALL: [Font; 5] = [
+        Font::Regular,
+        Font::Bold,
+        Font::Italic,
+        Font::BoldItalic,
+        Font::Mono,
+    ];
+
+    fn resource(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+            Font::Italic => "F3",
+            Font::BoldItalic => "F4",
+            Font::Mono => "F5",
+        }
+    }
+
+    fn base_name(self) -> &'static str {
+        match self {
+            Font::Regular => "Helvetica",
+            Font::Bold => "Helvetica-Bold",
+            Font::Italic => "Helvetica-Oblique",
+            Font::BoldItalic => "Helvetica-BoldOblique",
+            Font::Mono => "Courier",
+        }
+    }
+}
+
+// Glyph widths (1/1000 em) for ASCII 32..=126, taken from the Adobe AFM files
+const HELVETICA_WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+
+const HELVETICA_BOLD_WIDTHS: [u16; 95] = [
+    278, 333, 474, 556, 556, 889, 722, 238, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 333, 333, 584, 584, 584, 611, 975, 722, 722, 722, 722, 667,
+    611, 778, 722, 278, 556, 722, 611, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 333, 278, 333, 584, 556, 333, 556, 611, 556, 611, 556, 333, 611, 611, 278, 278, 556,
+    278, 889, 611, 611, 611, 611, 389, 556, 333, 611, 556, 778, 556, 556, 500, 389, 280, 389, 584,
+];
+
+fn char_width(font: Font, c: char, fallbacks: &Fallbacks) -> f32 {
+    if let Some(width) = fallbacks.width(c) {
+        return width;
+    }
+    let table = match font {
+        Font::Mono => return 600.0,
+        Font::Regular | Font::Italic => &HELVETICA_WIDTHS,
+        Font::Bold | Font::BoldItalic => &HELVETICA_BOLD_WIDTHS,
+    };
+    match c as u32 {
+        32..=126 => table[c as usize - 32] as f32,
+        // Bullet and dashes are the only common non-ASCII glyphs with very different widths
+        0x2022 => 350.0,
+        0x2013 => 556.0,
+        0x2014 => 1000.0,
+        _ => 556.0,
+    }
+}
+
+fn text_width(text: &str, font: Font, size: f32, fallbacks: &Fallbacks) -> f32 {
+    text.chars()
+        .map(|c| char_width(font, c, fallbacks))
+        .sum::<f32>()
+        * size
+        / 1000.0
+}
+
+/// Map a character to its WinAnsiEncoding byte, the encoding used for the base-14 fonts
+fn win_ansi(c: char) -> Option<u8> {
+    let code = c as u32;
+    match code {
+        32..=126 | 160..=255 => Some(code as u8),
+        _ => match c {
+            '€' => Some(0x80),
+            '‚' => Some(0x82),
+            'ƒ' => Some(0x83),
+            '„' => Some(0x84),
+            '…' => Some(0x85),
+            '†' => Some(0x86),
+            '‡' => Some(0x87),
+            'ˆ' => Some(0x88),
+            '‰' => Some(0x89),
+            'Š' => Some(0x8A),
+            '‹' => Some(0x8B),
+            'Œ' => Some(0x8C),
+            'Ž' => Some(0x8E),
+            '‘' => Some(0x91),
+            '’' => Some(0x92),
+            '“' => Some(0x93),
+            '”' => Some(0x94),
+            '•' => Some(0x95),
+            '–' => Some(0x96),
+            '—' => Some(0x97),
+            '˜' => Some(0x98),
+            '™' => Some(0x99),
+            'š' => Some(0x9A),
+            '›' => Some(0x9B),
+            'œ' => Some(0x9C),
+            'ž' => Some(0x9E),
+            'Ÿ' => Some(0x9F),
+            _ => None,
+        },
+    }
+}
+
+/// Encode text as a PDF literal string in WinAnsiEncoding
+fn pdf_string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('(');
+    for c in text.chars() {
+        let byte = win_ansi(c).unwrap_or(b'?');
+        match byte {
+            b'(' | b')' | b'\\' => {
+                out.push('\\');
+                out.push(byte as char);
+            }
+            32..=126 => out.push(byte as char),
+            _ => out.push_str(&format!("\\{:03o}", byte)),
+        }
+    }
+    out.push(')');
+    out
+}
+
+/// Encode text for document metadata (UTF-16BE with BOM, as hex)
+fn pdf_text_string(text: &str) -> String {
+    let mut out = String::from("<FEFF");
+    for unit in text.encode_utf16() {
+        out.push_str(&format!("{:04X}", unit));
+    }
+    out.push('>');
+    out
+}
+
+// Installed fonts tried, in order, for characters outside WinAnsiEncoding
+const FALLBACK_FONTS: [&str; 21] = [
+    "dejavusans.ttf",
+    "notosans-regular.ttf",
+    "liberationsans-regular.ttf",
+    "freesans.ttf",
+    "arial.ttf",
+    "segoeui.ttf",
+    "arial unicode.ttf",
+    "arialuni.ttf",
+    "notosanssymbols-regular.ttf",
+    "notosanssymbols2-regular.ttf",
+    "seguisym.ttf",
+    "droidsansfallbackfull.ttf",
+    "droidsansfallback.ttf",
+    "wqy-microhei.ttc",
+    "wqy-zenhei.ttc",
+    "msyh.ttc",
+    "simsun.ttc",
+    "msgothic.ttc",
+    "malgun.ttf",
+    "applegothic.ttf",
+    "unifont.ttf",
+];
+
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+        PathBuf::from("/System/Library/Fonts"),
+        PathBuf::from("/Library/Fonts"),
+    ];
+    if let Some(home) = std::env::var_os("HOME").map(PathBuf::from) {
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+        dirs.push(home.join("Library/Fonts"));
+    }
+    if let Some(windows) = std::env::var_os("WINDIR").map(PathBuf::from) {
+        dirs.push(windows.join("Fonts"));
+    }
+    dirs
+}
+
+/// An installed font embedded in the PDF, with the glyphs drawn from it
+struct EmbeddedFont {
+    name: String,
+    font: truetype::Font,
+    used: BTreeMap<u16, char>,
+}
+
+/// Installed fonts that draw the characters the base-14 fonts can't
+/// encode. Characters none of them has are drawn as `?` and reported.
+#[derive(Default)]
+struct Fallbacks {
+    fonts: Vec<EmbeddedFont>,
+    glyphs: HashMap<char, (usize, u16)>,
+    missing: BTreeSet<char>,
+}
+
+impl Fallbacks {
+    /// Load the fonts needed for the characters of `text`
+    fn for_text(text: &str) -> Self {
+        let mut needed: BTreeSet<char> = text
+            .chars()
+            .filter(|&c| !c.is_control() && win_ansi(c).is_none())
+            .collect();
+        let mut fallbacks = Fallbacks::default();
+        if needed.is_empty() {
+            return fallbacks;
+        }
+
+        let mut found: Vec<(usize, PathBuf)> = Vec::new();
+        for dir in font_dirs() {
+            for entry in walkdir::WalkDir::new(dir).into_iter().flatten() {
+                let name = entry.file_name().to_string_lossy().to_lowercase();
+                if let Some(rank) = FALLBACK_FONTS.iter().position(|f| *f == name) {
+                    found.push((rank, entry.into_path()));
+                }
+            }
+        }
+        found.sort();
+
+        for (_, path) in found {
+            if needed.is_empty() {
+                break;
+            }
+            let font = match truetype::Font::load(&path) {
+                Ok(font) => font,
+                Err(e) => {
+                    log::warn!("Skipping font {}: {}", path.display(), e);
+                    continue;
+                }
+            };
+            let covered: Vec<(char, u16)> = needed
+                .iter()
+                .filter_map(|&c| font.glyph(c).map(|glyph| (c, glyph)))
+                .collect();
+            if covered.is_empty() {
+                continue;
+            }
+            let index = fallbacks.fonts.len();
+            for (c, glyph) in covered {
+                needed.remove(&c);
+                fallbacks.glyphs.insert(c, (index, glyph));
+            }
+            let name = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy())
+                .unwrap_or_default()
+                .chars()
+                .filter(char::is_ascii_alphanumeric)
+                .collect();
+            fallbacks.fonts.push(EmbeddedFont {
+                name,
+                font,
+                used: BTreeMap::new(),
+            });
+        }
+        fallbacks
+    }
+
+    /// Width of `c` in thousandths of an em, if a fallback font draws it
+    fn width(&self, c: char) -> Option<f32> {
+        if win_ansi(c).is_some() {
+            return None;
+        }
+        let (index, glyph) = self.glyphs.get(&c)?;
+        Some(self.fonts[*index].font.advance(*glyph))
+    }
+
+    /// Text operators that show `text` in `font`, switching to a fallback
+    /// font for the characters it can't encode
+    fn show(&mut self, text: &str, font: Font, size: f32) -> String {
+        let mut pieces: Vec<(Option<usize>, String)> = Vec::new();
+        for c in text.chars() {
+            let fallback = match self.glyphs.get(&c) {
+                Some(&(index, glyph)) if win_ansi(c).is_none() => {
+                    self.fonts[index].used.insert(glyph, c);
+                    Some(index)
+                }
+                _ => {
+                    if win_ansi(c).is_none() && !c.is_control() {
+                        self.missing.insert(c);
+                    }
+                    None
+                }
+            };
+            match pieces.last_mut() {
+                Some((last, piece)) if *last == fallback => piece.push(c),
+                _ => pieces.push((fallback, c.to_string())),
+            }
+        }
+
+        let mut ops = String::new();
+        for (fallback, piece) in pieces {
+            let shown = match fallback {
+                None => format!(
+                    "/{} {:.2} Tf {} Tj ",
+                    font.resource(),
+                    size,
+                    pdf_string(&piece)
+                ),
+                Some(index) => {
+                    let font = &self.fonts[index].font;
+                    let glyphs: String = piece
+                        .chars()
+                        .filter_map(|c| font.glyph(c))
+                        .map(|glyph| format!("{:04X}", glyph))
+                        .collect();
+                    format!("/U{} {:.2} Tf <{}> Tj ", index + 1, size, glyphs)
+                }
+            };
+            ops.push_str(&shown);
+        }
+        ops
+    }
+}
+
+/// Maps the glyphs drawn from an embedded font back to their characters,
+/// so text can be searched and copied
+fn to_unicode_cmap(used: &BTreeMap<u16, char>) -> String {
+    let mut cmap = String::from(
+        "/CIDInit /ProcSet findresource begin\n12 dict begin\nbegincmap\n\
+         /CIDSystemInfo << /Registry (Adobe) /Ordering (UCS) /Supplement 0 >> def\n\
+         /CMapName /Adobe-Identity-UCS def\n/CMapType 2 def\n\
+         1 begincodespacerange\n<0000> <FFFF>\nendcodespacerange\n",
+    );
+    let entries: Vec<_> = used.iter().collect();
+    for chunk in entries.chunks(100) {
+        cmap.push_str(&format!("{} beginbfchar\n", chunk.len()));
+        for (glyph, c) in chunk {
+            let utf16: String = c
+                .encode_utf16(&mut [0; 2])
+                .iter()
+                .map(|unit| format!("{:04X}", unit))
+                .collect();
+            cmap.push_str(&format!("<{:04X}> <{}>\n", glyph, utf16));
+        }
+        cmap.push_str("endbfchar\n");
+    }
+    cmap.push_str("endcmap\nCMapName currentdict /CMap defineresource pop\nend\nend\n");
+    cmap
+}
+
+/// Six capital letters naming a font subset, as the PDF format asks for
+fn subset_tag(used: &BTreeMap<u16, char>) -> String {
+    let mut hash: u32 = 2166136261;
+    for glyph in used.keys() {
+        hash = (hash ^ *glyph as u32).wrapping_mul(16777619);
+    }
+    (0..6)
+        .map(|i| (b'A' + ((hash >> (i * 5)) % 26) as u8) as char)
+        .collect()
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Style {
+    bold: bool,
+    italic: bool,
+    mono: bool,
+    strike: bool,
+    size: f32,
+    color: Color,
+    link: Option<String>,
+}
+
+impl Style {
+    fn body() -> Self {
+        Self {
+            bold: false,
+            italic: false,
+            mono: false,
+            strike: false,
+            size: BODY_SIZE,
+            color: BLACK,
+            link: None,
+        }
+    }
+
+    fn font(&self) -> Font {
+        match (self.mono, self.bold, self.italic) {
+            (true, _, _) => Font::Mono,
+            (false, true, true) => Font::BoldItalic,
+            (false, true, false) => Font::Bold,
+            (false, false, true) => Font::Italic,
+            (false, false, false) => Font::Regular,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Run {
+    text: String,
+    style: Style,
+}
+
+#[derive(Debug, Clone)]
+struct Segment {
+    text: String,
+    style: Style,
+    x: f32,
+    width: f32,
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    segments: Vec<Segment>,
+    width: f32,
+    size: f32,
+}
+
+impl Line {
+    fn new(size: f32) -> Self {
+        Self {
+            segments: Vec::new(),
+            width: 0.0,
+            size,
+        }
+    }
+
+    fn height(&self) -> f32 {
+        self.size * LINE_SPACING
+    }
+
+    fn push(&mut self, text: &str, style: &Style, fallbacks: &Fallbacks) {
+        let width = text_width(text, style.font(), style.size, fallbacks);
+        self.size = if self.segments.is_empty() {
+            style.size
+        } else {
+            self.size.max(style.size)
+        };
+        match self.segments.last_mut() {
+            Some(last) if last.style == *style => {
+                last.text.push_str(text);
+                last.width += width;
+            }
+            _ => self.segments.push(Segment {
+                text: text.to_string(),
+                style: style.clone(),
+                x: self.width,
+                width,
+            }),
+        }
+        self.width += width;
+    }
+
+    fn has_content(&self) -> bool {
+        self.segments.iter().any(|s| !s.text.trim().is_empty())
+    }
+
+    fn trim_end(&mut self, fallbacks: &Fallbacks) {
+        while let Some(last) = self.segments.last_mut() {
+            let trimmed = last.text.trim_end().to_string();
+            if trimmed.is_empty() {
+                self.width = last.x;
+                self.segments.pop();
+            } else {
+                last.width = text_width(&trimmed, last.style.font(), last.style.size, fallbacks);
+                last.text = trimmed;
+                self.width = last.x + last.width;
+                break;
+            }
+        }
+    }
+}
+
+/// Break styled runs into lines no wider than `max_width`
+fn wrap_runs(runs: &[Run], max_width: f32, fallbacks: &Fallbacks) -> Vec<Line> {
+    let base_size = runs.first().map(|r| r.style.size).unwrap_or(BODY_SIZE);
+    let mut lines = Vec::new();
+    let mut line = Line::new(base_size);
+
+    for run in runs {
+        let font = run.style.font();
+        for (index, part) in run.text.split('\n').enumerate() {
+            if index > 0 {
+                line.trim_end(fallbacks);
+                lines.push(std::mem::replace(&mut line, Line::new(run.style.size)));
+            }
+
+            for token in split_tokens(part) {
+                if token.chars().all(char::is_whitespace) {
+                    if !line.segments.is_empty() {
+                        line.push(token, &run.style, fallbacks);
+                    }
+                    continue;
+                }
+
+                let width = text_width(token, font, run.style.size, fallbacks);
+                if line.width + width > max_width && line.has_content() {
+                    line.trim_end(fallbacks);
+                    lines.push(std::mem::replace(&mut line, Line::new(run.style.size)));
+                }
+
+                if width <= max_width {
+                    line.push(token, &run.style, fallbacks);
+                    continue;
+                }
+
+                // A single word wider than the line is broken between characters
+                let mut chunk = String::new();
+                for c in token.chars() {
+                    let next =
+                        text_width(&format!("{}{}", chunk, c), font, run.style.size, fallbacks);
+                    if line.width + next > max_width && !chunk.is_empty() {
+                        line.push(&chunk, &run.style, fallbacks);
+                        lines.push(std::mem::replace(&mut line, Line::new(run.style.size)));
+                        chunk.clear();
+                    }
+                    chunk.push(c);
+                }
+                line.push(&chunk, &run.style, fallbacks);
+            }
+        }
+    }
+
+    line.trim_end(fallbacks);
+    if !line.segments.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Split text into alternating word and whitespace tokens
+fn split_tokens(text: &str) -> Vec<&str> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_space = None;
+
+    for (i, c) in text.char_indices() {
+        let is_space = c.is_whitespace();
+        if in_space.is_some_and(|s| s != is_space) {
+            tokens.push(&text[start..i]);
+            start = i;
+        }
+        in_space = Some(is_space);
+    }
+    if start < text.len() {
+        tokens.push(&text[start..]);
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Marker {
+    Bullet,
+    Number(u64),
+    Task(bool),
+}
+
+struct PdfImage {
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+}
+
+impl PdfImage {
+    /// Decode an image file and flatten it onto white as compressed RGB
+    fn load(path: &Path) -> Result<Self, String> {
+        let decoded = image::open(path)
+            .map_err(|e| format!("Failed to load image {}: {}", path.display(), e))?
+            .to_rgba8();
+
+        let len = (decoded.width() as usize)
+            .checked_mul(decoded.height() as usize)
+            .and_then(|pixels| pixels.checked_mul(3))
+            .ok_or_else(|| format!("Image {} is too large", path.display()))?;
+        let mut rgb = Vec::with_capacity(len);
+        for pixel in decoded.pixels() {
+            let [r, g, b, a] = pixel.0;
+            let alpha = a as u32;
+            for channel in [r, g, b] {
+                rgb.push(((channel as u32 * alpha + 255 * (255 - alpha)) / 255) as u8);
+            }
+        }
+
+        Ok(Self {
+            width: decoded.width(),
+            height: decoded.height(),
+            data: deflate(&rgb),
+        })
+    }
+}
+
+struct Page {
+    content: String,
+    links: Vec<([f32; 4], String)>,
+}
+
+/// Places text, rules and images on pages, breaking to a new page as needed
+struct Layout {
+    pages: Vec<Page>,
+    images: Vec<PdfImage>,
+    fallbacks: Fallbacks,
+    y: f32,
+    quote_depth: usize,
+    marker: Option<Marker>,
+}
+
+impl Layout {
+    fn new(fallbacks: Fallbacks) -> Self {
+        let mut layout = Self {
+            pages: Vec::new(),
+            images: Vec::new(),
+            fallbacks,
+            y: 0.0,
+            quote_depth: 0,
+            marker: None,
+        };
+        layout.new_page();
+        layout
+    }
+
+    fn content_width() -> f32 {
+        PAGE_WIDTH - 2.0 * MARGIN
+    }
+
+    fn top() -> f32 {
+        PAGE_HEIGHT - MARGIN
+    }
+
+    fn new_page(&mut self) {
+        self.pages.push(Page {
+            content: String::new(),
+            links: Vec::new(),
+        });
+        self.y = Self::top();
+    }
+
+    fn page(&mut self) -> &mut Page {
+        self.pages.last_mut().expect("layout always has a page")
+    }
+
+    fn at_page_top(&self) -> bool {
+        self.y >= Self::top()
+    }
+
+    /// Start a new page unless `height` still fits on the current one
+    fn ensure(&mut self, height: f32) -> bool {
+        if self.y - height < MARGIN && !self.at_page_top() {
+            self.new_page();
+            return true;
+        }
+        false
+    }
+
+    /// Vertical gap between blocks (suppressed at the top of a page)
+    fn space(&mut self, amount: f32) {
+        if !self.at_page_top() {
+            self.y = (self.y - amount).max(MARGIN);
+        }
+    }
+
+    fn fill_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let op = format!(
+            "q {:.3} {:.3} {:.3} rg {:.2} {:.2} {:.2} {:.2} re f Q\n",
+            color.0, color.1, color.2, x, y, width, height
+        );
+        self.page().content.push_str(&op);
+    }
+
+    fn stroke_rect(&mut self, x: f32, y: f32, width: f32, height: f32, color: Color) {
+        let op = format!(
+            "q 0.5 w {:.3} {:.3} {:.3} RG {:.2} {:.2} {:.2} {:.2} re S Q\n",
+            color.0, color.1, color.2, x, y, width, height
+        );
+        self.page().content.push_str(&op);
+    }
+
+    fn stroke_line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: Color) {
+        let op = format!(
+            "q {:.2} w {:.3} {:.3} {:.3} RG {:.2} {:.2} m {:.2} {:.2} l S Q\n",
+            width, color.0, color.1, color.2, from.0, from.1, to.0, to.1
+        );
+        self.page().content.push_str(&op);
+    }
+
+    fn text(&mut self, text: &str, font: Font, size: f32, color: Color, x: f32, y: f32) {
+        let op = format!(
+            "BT {:.3} {:.3} {:.3} rg {:.2} {:.2} Td {}ET\n",
+            color.0,
+            color.1,
+            color.2,
+            x,
+            y,
+            self.fallbacks.show(text, font, size)
+        );
+        self.page().content.push_str(&op);
+    }
+
+    fn quote_bars(&mut self, height: f32) {
+        for depth in 0..self.quote_depth {
+            let x = MARGIN + depth as f32 * QUOTE_INDENT;
+            let y = self.y - height;
+            self.fill_rect(x, y, 3.0, height, RULE_GREY);
+        }
+    }
+
+    fn draw_marker(&mut self, marker: Marker, x: f32, baseline: f32, size: f32) {
+        match marker {
+            Marker::Bullet => self.text("•", Font::Regular, size, BLACK, x - 10.0, baseline),
+            Marker::Number(n) => {
+                let label = format!("{}.", n);
+                let width = text_width(&label, Font::Regular, size, &self.fallbacks);
+                self.text(
+                    &label,
+                    Font::Regular,
+                    size,
+                    BLACK,
+                    x - width - 4.0,
+                    baseline,
+                );
+            }
+            Marker::Task(checked) => {
+                let side = size * 0.75;
+                let left = x - side - 5.0;
+                self.stroke_rect(left, baseline, side, side, BLACK);
+                if checked {
+                    self.stroke_line(
+                        (left + side * 0.2, baseline + side * 0.5),
+                        (left + side * 0.45, baseline + side * 0.2),
+                        1.2,
+                        BLACK,
+                    );
+                    self.stroke_line(
+                        (left + side * 0.45, baseline + side * 0.2),
+                        (left + side * 0.85, baseline + side * 0.85),
+                        1.2,
+                        BLACK,
+                    );
+                }
+            }
+        }
+    }
+
+    fn draw_segment(&mut self, segment: &Segment, x: f32, baseline: f32) {
+        let style = &segment.style;
+        self.text(
+            &segment.text,
+            style.font(),
+            style.size,
+            style.color,
+            x,
+            baseline,
+        );
+
+        if style.strike {
+            let y = baseline + style.size * 0.3;
+            self.stroke_line((x, y), (x + segment.width, y), 0.7, style.color);
+        }
+
+        if let Some(url) = &style.link {
+            let y = baseline - 1.5;
+            self.stroke_line((x, y), (x + segment.width, y), 0.5, style.color);
+            if is_external_url(url) {
+                let rect = [x, baseline - 3.0, x + segment.width, baseline + style.size];
+                self.page().links.push((rect, url.clone()));
+            }
+        }
+    }
+
+    /// Draw a single wrapped line with its top edge at `top`
+    fn draw_line(&mut self, line: &Line, x: f32, top: f32) {
+        let baseline = top - line.size * 1.1;
+        if let Some(marker) = self.marker.take() {
+            self.draw_marker(marker, x, baseline, line.size);
+        }
+        for segment in &line.segments {
+            self.draw_segment(segment, x + segment.x, baseline);
+        }
+    }
+
+    /// Emit wrapped lines top to bottom starting at the current position
+    fn lines(&mut self, lines: &[Line], x: f32) {
+        for line in lines {
+            let height = line.height();
+            self.ensure(height);
+            self.quote_bars(height);
+            let top = self.y;
+            self.draw_line(line, x, top);
+            self.y -= height;
+        }
+    }
+
+    fn code_block(&mut self, code: &str, x: f32, width: f32) {
+        let char_width = char_width(Font::Mono, ' ', &self.fallbacks) * CODE_SIZE / 1000.0;
+        let max_chars = (((width - 2.0 * CODE_PADDING) / char_width).floor() as usize).max(1);
+        let line_height = CODE_SIZE * 1.35;
+
+        let mut lines: Vec<String> = Vec::new();
+        for source_line in code.trim_end_matches('\n').split('\n') {
+            let expanded: Vec<char> = source_line.replace('\t', "    ").chars().collect();
+            if expanded.is_empty() {
+                lines.push(String::new());
+            }
+            for chunk in expanded.chunks(max_chars) {
+                lines.push(chunk.iter().collect());
+            }
+        }
+
+        let count = lines.len();
+        for (i, text) in lines.iter().enumerate() {
+            let pad_top = if i == 0 { CODE_PADDING } else { 0.0 };
+            let pad_bottom = if i + 1 == count { CODE_PADDING } else { 0.0 };
+            let height = line_height + pad_top + pad_bottom;
+            self.ensure(height);
+            self.quote_bars(height);
+            self.fill_rect(x, self.y - height, width, height, CODE_BACKGROUND);
+            let baseline = self.y - pad_top - CODE_SIZE * 1.05;
+            self.text(
+                text,
+                Font::Mono,
+                CODE_SIZE,
+                BLACK,
+                x + CODE_PADDING,
+                baseline,
+            );
+            self.y -= height;
+        }
+    }
+
+    fn rule(&mut self, x: f32, width: f32) {
+        self.ensure(12.0);
+        self.y -= 6.0;
+        let y = self.y;
+        self.stroke_line((x, y), (x + width, y), 1.0, RULE_GREY);
+        self.y -= 6.0;
+    }
+
+    fn image(&mut self, image: PdfImage, x: f32, max_width: f32) {
+        // Treat pixels as 96 dpi and shrink to fit the text column and page
+        let mut width = image.width as f32 * 0.75;
+        let mut height = image.height as f32 * 0.75;
+        let max_height = Self::top() - MARGIN;
+        let scale = (max_width / width).min(max_height / height).min(1.0);
+        width *= scale;
+        height *= scale;
+
+        self.ensure(height);
+        let index = self.images.len();
+        self.images.push(image);
+        let y = self.y - height;
+        let op = format!(
+            "q {:.2} 0 0 {:.2} {:.2} {:.2} cm /Im{} Do Q\n",
+            width, height, x, y, index
+        );
+        self.page().content.push_str(&op);
+        self.y = y;
+    }
+
+    fn table(&mut self, table: TableState, x: f32, max_width: f32) {
+        let columns = table.rows.iter().map(Vec::len).max().unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+
+        // Natural column widths, scaled down proportionally when too wide
+        let mut widths = vec![30.0_f32; columns];
+        for row in &table.rows {
+            for (i, cell) in row.iter().enumerate() {
+                let natural: f32 = cell
+                    .iter()
+                    .map(|r| text_width(&r.text, r.style.font(), r.style.size, &self.fallbacks))
+                    .sum::<f32>()
+                    + 2.0 * CELL_PADDING;
+                widths[i] = widths[i].max(natural);
+            }
+        }
+        let total: f32 = widths.iter().sum();
+        if total > max_width {
+            for width in widths.iter_mut() {
+                *width *= max_width / total;
+            }
+        }
+
+        let header_rows = table.header_rows.min(table.rows.len());
+        let mut laid_out = Vec::new();
+        for row in &table.rows {
+            let cells: Vec<Vec<Line>> = (0..columns)
+                .map(|i| {
+                    let runs = row.get(i).map(Vec::as_slice).unwrap_or(&[]);
+                    wrap_runs(runs, widths[i] - 2.0 * CELL_PADDING, &self.fallbacks)
+                })
+                .collect();
+            let height = cells
+                .iter()
+                .map(|lines| lines.iter().map(Line::height).sum::<f32>())
+                .fold(BODY_SIZE * LINE_SPACING, f32::max)
+                + 2.0 * CELL_PADDING;
+            laid_out.push((cells, height));
+        }
+
+        for (index, (cells, height)) in laid_out.iter().enumerate() {
+            let is_header = index < header_rows;
+            if self.ensure(*height) && !is_header {
+                // Repeat the header rows at the top of each continuation page
+                for (header_cells, header_height) in &laid_out[..header_rows] {
+                    self.table_row(
+                        header_cells,
+                        *header_height,
+                        &widths,
+                        &table.alignments,
+                        x,
+                        true,
+                    );
+                }
+            }
+            self.table_row(cells, *height, &widths, &table.alignments, x, is_header);
+        }
+    }
+
+    fn table_row(
+        &mut self,
+        cells: &[Vec<Line>],
+        height: f32,
+        widths: &[f32],
+        alignments: &[Alignment],
+        x: f32,
+        is_header: bool,
+    ) {
+        let top = self.y;
+        let mut cell_x = x;
+        for (i, lines) in cells.iter().enumerate() {
+            let width = widths[i];
+            if is_header {
+                self.fill_rect(cell_x, top - height, width, height, HEADER_BACKGROUND);
+            }
+            self.stroke_rect(cell_x, top - height, width, height, RULE_GREY);
+
+            let inner = width - 2.0 * CELL_PADDING;
+            let mut line_top = top - CELL_PADDING;
+            for line in lines {
+                let offset = match alignments.get(i) {
+                    Some(Alignment::Center) => (inner - line.width) / 2.0,
+                    Some(Alignment::Right) => inner - line.width,
+                    _ => 0.0,
+                };
+                self.draw_line(line, cell_x + CELL_PADDING + offset.max(0.0), line_top);
+                line_top -= line.height();
+            }
+            cell_x += width;
+        }
+        self.y -= height;
+    }
+
+    fn footers(&mut self) {
+        let total = self.pages.len();
+        for index in 0..total {
+            let label = format!("Page {} of {}", index + 1, total);
+            let width = text_width(&label, Font::Regular, FOOTER_SIZE, &self.fallbacks);
+            let op = format!(
+                "BT {:.3} {:.3} {:.3} rg /{} {:.2} Tf {:.2} {:.2} Td {} Tj ET\n",
+                GREY.0,
+                GREY.1,
+                GREY.2,
+                Font::Regular.resource(),
+                FOOTER_SIZE,
+                (PAGE_WIDTH - width) / 2.0,
+                MARGIN / 2.0,
+                pdf_string(&label)
+            );
+            self.pages[index].content.push_str(&op);
+        }
+    }
+}
+
+fn is_external_url(url: &str) -> bool {
+    let lower = url.to_lowercase();
+    lower.starts_with("http://") || lower.starts_with("https://") || lower.starts_with("mailto:")
+}
+
+struct TableState {
+    alignments: Vec<Alignment>,
+    rows: Vec<Vec<Vec<Run>>>,
+    header_rows: usize,
+}
+
+/// Walks the Markdown document tree and feeds styled blocks into the layout
+struct Renderer<'a> {
+    layout: Layout,
+    base_dir: Option<&'a Path>,
+    runs: Vec<Run>,
+    bold: usize,
+    italic: usize,
+    strike: usize,
+    link: Option<String>,
+    heading: Option<u8>,
+    list_depth: usize,
+    in_table: bool,
+}
+
+impl<'a> Renderer<'a> {
+    fn new(base_dir: Option<&'a Path>, fallbacks: Fallbacks) -> Self {
+        Self {
+            layout: Layout::new(fallbacks),
+            base_dir,
+            runs: Vec::new(),
+            bold: 0,
+            italic: 0,
+            strike: 0,
+            link: None,
+            heading: None,
+            list_depth: 0,
+            in_table: false,
+        }
+    }
+
+    fn indent(&self) -> f32 {
+        self.list_depth as f32 * LIST_INDENT + self.layout.quote_depth as f32 * QUOTE_INDENT
+    }
+
+    fn style(&self) -> Style {
+        let mut style = Style::body();
+        if let Some(level) = self.heading {
+            style.size = heading_size(level);
+            style.bold = true;
+        }
+        style.bold |= self.bold > 0;
+        style.italic = self.italic > 0;
+        style.strike = self.strike > 0;
+        if self.layout.quote_depth > 0 {
+            style.color = GREY;
+        }
+        if let Some(url) = &self.link {
+            style.color = LINK_BLUE;
+            style.link = Some(url.clone());
+        }
+        style
+    }
+
+    fn push_text(&mut self, text: &str, mono: bool) {
+        let mut style = self.style();
+        style.mono = mono;
+        if mono {
+            style.size = style.size.min(BODY_SIZE) * 0.92;
+        }
+        self.runs.push(Run {
+            text: text.to_string(),
+            style,
+        });
+    }
+
+    /// Lay out the collected inline runs as one block
+    fn flush(&mut self) {
+        if self.runs.is_empty() {
+            return;
+        }
+        let runs = std::mem::take(&mut self.runs);
+        let indent = self.indent();
+        let lines = wrap_runs(
+            &runs,
+            Layout::content_width() - indent,
+            &self.layout.fallbacks,
+        );
+        self.layout.lines(&lines, MARGIN + indent);
+    }
+
+    fn render(mut self, doc: &Document) -> Layout {
+        self.blocks(&doc.blocks, false);
+        self.flush();
+        self.layout
+    }
+
+    fn blocks(&mut self, blocks: &[Block], tight: bool) {
+        for block in blocks {
+            self.block(block, tight);
+        }
+    }
+
+    fn block(&mut self, block: &Block, tight: bool) {
+        let indent = self.indent();
+        let x = MARGIN + indent;
+        let width = Layout::content_width() - indent;
+
+        match block {
+            Block::Heading { level, content, .. } => {
+                self.flush();
+                self.layout.space(heading_size(*level) * 0.6);
+                self.heading = Some(*level);
+                self.inlines(content);
+                self.flush();
+                self.heading = None;
+                if *level <= 2 {
+                    self.layout.y -= 2.0;
+                    let y = self.layout.y;
+                    self.layout
+                        .stroke_line((x, y), (x + width, y), 0.75, RULE_GREY);
+                }
+                self.layout.space(6.0);
+            }
+            Block::Paragraph(content) => {
+                self.inlines(content);
+                self.flush();
+                if !tight {
+                    self.layout.space(6.0);
+                }
+            }
+            Block::Quote(children) => {
+                self.flush();
+                self.layout.quote_depth += 1;
+                self.blocks(children, false);
+                self.flush();
+                self.layout.quote_depth -= 1;
+                self.layout.space(6.0);
+            }
+            Block::Code { code, .. } => {
+                self.flush();
+                self.layout.code_block(code, x, width);
+                self.layout.space(8.0);
+            }
+            Block::List {
+                start,
+                tight,
+                items,
+            } => {
+                self.flush();
+                self.list_depth += 1;
+                for (i, item) in items.iter().enumerate() {
+                    self.layout.marker = Some(match (item.checked, start) {
+                        (Some(checked), _) => Marker::Task(checked),
+                        (None, Some(start)) => Marker::Number(start + i as u64),
+                        (None, None) => Marker::Bullet,
+                    });
+                    self.blocks(&item.blocks, *tight);
+                    self.flush();
+                }
+                self.list_depth -= 1;
+                self.layout.marker = None;
+                if self.list_depth == 0 {
+                    self.layout.space(6.0);
+                }
+            }
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => {
+                self.flush();
+                self.in_table = true;
+                let mut table = TableState {
+                    alignments: alignments.clone(),
+                    rows: Vec::new(),
+                    header_rows: 1,
+                };
+                self.bold += 1;
+                let header_row = header.iter().map(|cell| self.cell_runs(cell)).collect();
+                self.bold -= 1;
+                table.rows.push(header_row);
+                for row in rows {
+                    let cells = row.iter().map(|cell| self.cell_runs(cell)).collect();
+                    table.rows.push(cells);
+                }
+                self.in_table = false;
+                self.layout.table(table, x, width);
+                self.layout.space(8.0);
+            }
+            Block::ThematicBreak => {
+                self.flush();
+                self.layout.rule(x, width);
+            }
+            Block::Html(_) => {}
+            Block::FootnoteDefinition { label, blocks } => {
+                self.flush();
+                self.bold += 1;
+                self.push_text(&format!("[{}] ", label), false);
+                self.bold -= 1;
+                self.blocks(blocks, false);
+            }
+        }
+    }
+
+    fn cell_runs(&mut self, content: &[Inline]) -> Vec<Run> {
+        self.inlines(content);
+        std::mem::take(&mut self.runs)
+    }
+
+    fn inlines(&mut self, inlines: &[Inline]) {
+        for inline in inlines {
+            self.inline(inline);
+        }
+    }
+
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => self.push_text(text, false),
+            Inline::Code(code) => self.push_text(code, true),
+            Inline::Emphasis(children) => {
+                self.italic += 1;
+                self.inlines(children);
+                self.italic -= 1;
+            }
+            Inline::Strong(children) => {
+                self.bold += 1;
+                self.inlines(children);
+                self.bold -= 1;
+            }
+            Inline::Strikethrough(children) => {
+                self.strike += 1;
+                self.inlines(children);
+                self.strike -= 1;
+            }
+            Inline::Link { url, content, .. } => {
+                let outer = self.link.replace(url.clone());
+                self.inlines(content);
+                self.link = outer;
+            }
+            Inline::Image { url, alt, .. } => self.image(url, alt),
+            Inline::FootnoteReference(label) => self.push_text(&format!("[{}]", label), false),
+            Inline::Html(html) if html.trim().to_lowercase().starts_with("<br") => {
+                self.push_text("\n", false)
+            }
+            Inline::Html(_) => {}
+            Inline::SoftBreak => self.push_text(" ", false),
+            Inline::HardBreak => self.push_text("\n", false),
+        }
+    }
+
+    fn image(&mut self, url: &str, alt: &str) {
+        let loaded = if self.in_table {
+            None
+        } else {
+            markdown::resolve_local_path(url, self.base_dir).and_then(|path| {
+                match PdfImage::load(&path) {
+                    Ok(image) => Some(image),
+                    Err(e) => {
+                        log::warn!("{}", e);
+                        None
+                    }
+                }
+            })
+        };
+
+        match loaded {
+            Some(image) => {
+                self.flush();
+                let indent = self.indent();
+                self.layout
+                    .image(image, MARGIN + indent, Layout::content_width() - indent);
+                self.layout.space(4.0);
+            }
+            None => {
+                let label = if alt.is_empty() { url } else { alt };
+                let mut style = self.style();
+                style.italic = true;
+                style.color = GREY;
+                self.runs.push(Run {
+                    text: format!("[image: {}]", label),
+                    style,
+                });
+            }
+        }
+    }
+}
+
+fn heading_size(level: u8) -> f32 {
+    match level {
+        1 => 22.0,
+        2 => 18.0,
+        3 => 15.0,
+        4 => 13.0,
+        5 => 12.0,
+        _ => 11.0,
+    }
+}
+
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder
+        .write_all(data)
+        .and_then(|_| encoder.finish())
+        .unwrap_or_default()
+}
+
+/// Serializes numbered objects and the cross-reference table
+struct PdfWriter {
+    buffer: Vec<u8>,
+    offsets: Vec<usize>,
+}
+
+impl PdfWriter {
+    fn new(object_count: usize) -> Self {
+        let mut buffer = Vec::new();
+        buffer.extend_from_slice(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n");
+        Self {
+            buffer,
+            offsets: vec![0; object_count + 1],
+        }
+    }
+
+    fn object(&mut self, id: usize, dictionary: &str) {
+        self.offsets[id] = self.buffer.len();
+        self.buffer
+            .extend_from_slice(format!("{} 0 obj\n{}\nendobj\n", id, dictionary).as_bytes());
+    }
+
+    fn stream(&mut self, id: usize, dictionary: &str, data: &[u8]) {
+        self.offsets[id] = self.buffer.len();
+        let header = format!(
+            "{} 0 obj\n<< {} /Length {} >>\nstream\n",
+            id,
+            dictionary,
+            data.len()
+        );
+        self.buffer.extend_from_slice(header.as_bytes());
+        self.buffer.extend_from_slice(data);
+        self.buffer.extend_from_slice(b"\nendstream\nendobj\n");
+    }
+
+    fn finish(mut self, root: usize, info: usize) -> Vec<u8> {
+        let xref_offset = self.buffer.len();
+        let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", self.offsets.len());
+        for offset in &self.offsets[1..] {
+            xref.push_str(&format!("{:010} 00000 n \n", offset));
+        }
+        xref.push_str(&format!(
+            "trailer\n<< /Size {} /Root {} 0 R /Info {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+            self.offsets.len(),
+            root,
+            info,
+            xref_offset
+        ));
+        self.buffer.extend_from_slice(xref.as_bytes());
+        self.buffer
+    }
+}
+
+fn write_pdf(layout: Layout, title: &str) -> Vec<u8> {
+    // Object numbering: catalog, page tree, info, fonts, images, then page/content pairs
+    let catalog_id = 1;
+    let pages_id = 2;
+    let info_id = 3;
+    let font_base = 4;
+    // Each embedded font takes five objects: the font, its descendant CID
+    // font, descriptor, font file and ToUnicode map
+    let embedded: Vec<&EmbeddedFont> = layout
+        .fallbacks
+        .fonts
+        .iter()
+        .filter(|f| !f.used.is_empty())
+        .collect();
+    let embedded_base = font_base + Font::ALL.len();
+    let image_base = embedded_base + embedded.len() * 5;
+    let page_base = image_base + layout.images.len();
+    let link_count: usize = layout.pages.iter().map(|p| p.links.len()).sum();
+    let annotation_base = page_base + layout.pages.len() * 2;
+    let object_count = annotation_base + link_count - 1;
+
+    let mut writer = PdfWriter::new(object_count);
+
+    writer.object(
+        catalog_id,
+        &format!("<< /Type /Catalog /Pages {} 0 R >>", pages_id),
+    );
+
+    let kids: Vec<String> = (0..layout.pages.len())
+        .map(|i| format!("{} 0 R", page_base + i * 2))
+        .collect();
+    writer.object(
+        pages_id,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} /MediaBox [0 0 {:.2} {:.2}] >>",
+            kids.join(" "),
+            layout.pages.len(),
+            PAGE_WIDTH,
+            PAGE_HEIGHT
+        ),
+    );
+
+    let created = chrono::Local::now().format("D:%Y%m%d%H%M%S").to_string();
+    writer.object(
+        info_id,
+        &format!(
+            "<< /Title {} /Producer (Docura) /CreationDate ({}) >>",
+            pdf_text_string(title),
+            created
+        ),
+    );
+
+    for (i, font) in Font::ALL.iter().enumerate() {
+        writer.object(
+            font_base + i,
+            &format!(
+                "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+                font.base_name()
+            ),
+        );
+    }
+
+    let mut embedded_fonts = Vec::new();
+    for (i, embedded_font) in embedded.iter().enumerate() {
+        let id = embedded_base + i * 5;
+        let font = &embedded_font.font;
+        let name = format!("{}+{}", subset_tag(&embedded_font.used), embedded_font.name);
+        let glyphs: BTreeSet<u16> = embedded_font.used.keys().copied().collect();
+        let file = match font.subset(&glyphs) {
+            Ok(file) => file,
+            Err(e) => {
+                log::warn!("Failed to subset font {}: {}", embedded_font.name, e);
+                Vec::new()
+            }
+        };
+        let widths: Vec<String> = glyphs
+            .iter()
+            .map(|glyph| format!("{} [{:.0}]", glyph, font.advance(*glyph)))
+            .collect();
+
+        writer.object(
+            id,
+            &format!(
+                "<< /Type /Font /Subtype /Type0 /BaseFont /{} /Encoding /Identity-H /DescendantFonts [{} 0 R] /ToUnicode {} 0 R >>",
+                name,
+                id + 1,
+                id + 4
+            ),
+        );
+        writer.object(
+            id + 1,
+            &format!(
+                "<< /Type /Font /Subtype /CIDFontType2 /BaseFont /{} /CIDSystemInfo << /Registry (Adobe) /Ordering (Identity) /Supplement 0 >> /FontDescriptor {} 0 R /CIDToGIDMap /Identity /DW {:.0} /W [{}] >>",
+                name,
+                id + 2,
+                font.advance(0),
+                widths.join(" ")
+            ),
+        );
+        writer.object(
+            id + 2,
+            &format!(
+                "<< /Type /FontDescriptor /FontName /{} /Flags 32 /FontBBox [{:.0} {:.0} {:.0} {:.0}] /ItalicAngle 0 /Ascent {:.0} /Descent {:.0} /CapHeight {:.0} /StemV 80 /FontFile2 {} 0 R >>",
+                name,
+                font.scale(font.bbox[0]),
+                font.scale(font.bbox[1]),
+                font.scale(font.bbox[2]),
+                font.scale(font.bbox[3]),
+                font.scale(font.ascent),
+                font.scale(font.descent),
+                font.scale(font.ascent),
+                id + 3
+            ),
+        );
+        writer.stream(
+            id + 3,
+            &format!("/Length1 {} /Filter /FlateDecode", file.len()),
+            &deflate(&file),
+        );
+        writer.stream(
+            id + 4,
+            "/Filter /FlateDecode",
+            &deflate(to_unicode_cmap(&embedded_font.used).as_bytes()),
+        );
+        embedded_fonts.push(format!("/U{} {} 0 R", i + 1, id));
+    }
+
+    for (i, image) in layout.images.iter().enumerate() {
+        writer.stream(
+            image_base + i,
+            &format!(
+                "/Type /XObject /Subtype /Image /Width {} /Height {} /ColorSpace /DeviceRGB /BitsPerComponent 8 /Filter /FlateDecode",
+                image.width, image.height
+            ),
+            &image.data,
+        );
+    }
+
+    let fonts: Vec<String> = Font::ALL
+        .iter()
+        .enumerate()
+        .map(|(i, font)| format!("/{} {} 0 R", font.resource(), font_base + i))
+        .chain(embedded_fonts)
+        .collect();
+    let images: Vec<String> = (0..layout.images.len())
+        .map(|i| format!("/Im{} {} 0 R", i, image_base + i))
+        .collect();
+    let resources = format!(
+        "<< /Font << {} >> /XObject << {} >> >>",
+        fonts.join(" "),
+        images.join(" ")
+    );
+
+    let mut annotation_id = annotation_base;
+    for (i, page) in layout.pages.iter().enumerate() {
+        let page_id = page_base + i * 2;
+        let content_id = page_id + 1;
+
+        let mut annotations = Vec::new();
+        for (rect, url) in &page.links {
+            writer.object(
+                annotation_id,
+                &format!(
+                    "<< /Type /Annot /Subtype /Link /Rect [{:.2} {:.2} {:.2} {:.2}] /Border [0 0 0] /A << /S /URI /URI {} >> >>",
+                    rect[0], rect[1], rect[2], rect[3],
+                    pdf_string(url)
+                ),
+            );
+            annotations.push(format!("{} 0 R", annotation_id));
+            annotation_id += 1;
+        }
+
+        writer.object(
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /Resources {} /Contents {} 0 R /Annots [{}] >>",
+                pages_id,
+                resources,
+                content_id,
+                annotations.join(" ")
+            ),
+        );
+        writer.stream(
+            content_id,
+            "/Filter /FlateDecode",
+            &deflate(page.content.as_bytes()),
+        );
+    }
+
+    writer.finish(catalog_id, info_id)
+}
+
+/// A rendered PDF
+pub struct RenderedPdf {
+    pub bytes: Vec<u8>,
+    /// Characters no installed font could draw, shown as `?`
+    pub missing: Vec<char>,
+}
+
+/// Render Markdown to PDF bytes; relative images resolve against `base_dir`
+pub fn markdown_to_pdf(markdown: &str, title: &str, base_dir: Option<&Path>) -> RenderedPdf {
+    let fallbacks = Fallbacks::for_text(markdown);
+    let mut layout = Renderer::new(base_dir, fallbacks).render(&markdown::parse(markdown));
+    layout.footers();
+    let missing = layout.fallbacks.missing.iter().copied().collect();
+    RenderedPdf {
+        bytes: write_pdf(layout, title),
+        missing,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pdf_string_escapes_and_encodes() {
+        assert_eq!(pdf_string("a (b) \\c"), "(a \\(b\\) \\\\c)");
+        assert_eq!(pdf_string("café – •"), "(caf\\351 \\226 \\225)");
+        assert_eq!(pdf_string("日本"), "(??)");
+    }
+
+    #[test]
+    fn test_wrap_runs_respects_width() {
+        let runs = vec![Run {
+            text: "lorem ipsum dolor sit amet ".repeat(20),
+            style: Style::body(),
+        }];
+        let lines = wrap_runs(&runs, 200.0, &Fallbacks::default());
+        assert!(lines.len() > 5);
+        assert!(lines.iter().all(|l| l.width <= 200.0));
+        assert!(lines.iter().all(|l| !l.segments[0].text.starts_with(' ')));
+    }
+
+    #[test]
+    fn test_long_document_paginates() {
+        let markdown = "# Title\n\nSome paragraph text.\n\n".repeat(80);
+        let layout = Renderer::new(None, Fallbacks::default()).render(&markdown::parse(&markdown));
+        assert!(layout.pages.len() > 1);
+    }
+
+    #[test]
+    fn test_markdown_to_pdf_structure() {
+        let markdown = "# Spec\n\n| A | B |\n|---|---|\n| 1 | 2 |\n\n- [x] done\n- [ ] todo\n\n```rust\nfn main() {}\n```\n\n[Docs](https://example.com)\n";
+        let pdf = markdown_to_pdf(markdown, "Spec", None);
+        assert!(pdf.missing.is_empty());
+        let text = String::from_utf8_lossy(&pdf.bytes);
+        assert!(text.starts_with("%PDF-1.4"));
+        assert!(text.contains("/Type /Catalog"));
+        assert!(text.contains("/URI (https://example.com)"));
+        assert!(text.trim_end().ends_with("%%EOF"));
+    }
+
+    #[test]
+    fn test_non_latin_text_uses_installed_fonts() {
+        let markdown = "# Привет\n\nκόσμε, **日本語** and ✓ in `код`\n";
+        let pdf = markdown_to_pdf(markdown, "Мир", None);
+        let text = String::from_utf8_lossy(&pdf.bytes);
+
+        // Every character WinAnsiEncoding lacks is drawn from an embedded
+        // font, or reported; none silently becomes "?"
+        let layout =
+            Renderer::new(None, Fallbacks::for_text(markdown)).render(&markdown::parse(markdown));
+        let drawn: BTreeSet<char> = layout
+            .fallbacks
+            .fonts
+            .iter()
+            .flat_map(|f| f.used.values().copied())
+            .collect();
+        for c in "Приветκόσμε日本語✓код".chars() {
+            assert!(
+                drawn.contains(&c) != pdf.missing.contains(&c),
+                "{} is neither drawn nor reported",
+                c
+            );
+        }
+        if !drawn.is_empty() {
+            assert!(text.contains("/Subtype /CIDFontType2"));
+            assert!(text.contains("/ToUnicode"));
+            assert!(text.contains("/FontFile2"));
+        }
+        assert!(text.trim_end().ends_with("%%EOF"));
+    }
+}
@@ -0,0 +1,586 @@
+// RTF Export Module
+// Converts the Markdown document tree to Rich Text Format with real
+// heading styles, list tables, hyperlink fields and footnotes, so the
+// result opens cleanly in Word, LibreOffice and WordPad.
+
+use crate::markdown::{self, Alignment, Block, Document, Inline};
+use std::collections::HashMap;
+
+// Measurements are in twips (1/20 pt); font sizes in half-points
+const LIST_INDENT: i32 = 720;
+const LIST_HANGING: i32 = 360;
+const QUOTE_INDENT: i32 = 720;
+const TABLE_WIDTH: i32 = 9360;
+const BODY_SIZE: u32 = 24;
+const CODE_SIZE: u32 = 20;
+const HEADING_SIZES: [u32; 6] = [36, 32, 28, 26, 24, 24];
+
+// Color table indices
+const LINK_COLOR: u32 = 1;
+const QUOTE_COLOR: u32 = 2;
+const CODE_BACKGROUND: u32 = 3;
+
+/// Escape text for an RTF body: `\`, `{`, `}` and anything beyond ASCII
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '\t' => out.push_str("\\tab "),
+            '\n' | '\r' => out.push(' '),
+            c if c.is_ascii() => out.push(c),
+            c => {
+                let mut units = [0u16; 2];
+                for unit in c.encode_utf16(&mut units) {
+                    // \uN takes a signed 16-bit value; `?` is the fallback for \uc1 readers
+                    out.push_str(&format!("\\u{}?", *unit as i16));
+                }
+            }
+        }
+    }
+    out
+}
+
+/// Render a document as a complete RTF file
+pub fn render(doc: &Document) -> String {
+    let mut footnotes = HashMap::new();
+    markdown::visit_blocks(&doc.blocks, &mut |block| {
+        if let Block::FootnoteDefinition { label, blocks } = block {
+            footnotes.insert(label.clone(), blocks.clone());
+        }
+    });
+
+    let mut writer = RtfWriter {
+        body: String::new(),
+        lists: Vec::new(),
+        footnotes,
+    };
+    writer.blocks(&doc.blocks, Context::default());
+
+    let mut out = String::new();
+    out.push_str("{\\rtf1\\ansi\\ansicpg1252\\deff0\\uc1\n");
+    out.push_str(
+        "{\\fonttbl{\\f0\\froman\\fcharset0 Times New Roman;}{\\f1\\fmodern\\fcharset0 Courier New;}{\\f2\\fswiss\\fcharset0 Arial;}}\n",
+    );
+    out.push_str(
+        "{\\colortbl;\\red5\\green99\\blue193;\\red102\\green102\\blue102;\\red242\\green242\\blue242;}\n",
+    );
+    out.push_str(&stylesheet());
+    out.push_str(&writer.list_tables());
+    out.push_str(&writer.body);
+    out.push('}');
+    out
+}
+
+fn stylesheet() -> String {
+    let mut styles = format!("{{\\stylesheet{{\\s0\\f0\\fs{} Normal;}}", BODY_SIZE);
+    for (i, size) in HEADING_SIZES.iter().enumerate() {
+        styles.push_str(&format!(
+            "{{\\s{}\\sbasedon0\\snext0\\keepn\\outlinelevel{}\\sb240\\sa120\\f2\\fs{}\\b heading {};}}",
+            i + 1,
+            i,
+            size,
+            i + 1
+        ));
+    }
+    styles.push_str(&format!(
+        "{{\\s7\\sbasedon0\\snext7\\f1\\fs{}\\cbpat{} Code;}}",
+        CODE_SIZE, CODE_BACKGROUND
+    ));
+    styles.push_str(&format!(
+        "{{\\s8\\sbasedon0\\snext8\\li{}\\i\\cf{} Quote;}}",
+        QUOTE_INDENT, QUOTE_COLOR
+    ));
+    styles.push_str("}\n");
+    styles
+}
+
+#[derive(Debug, Clone, Copy)]
+enum ListFormat {
+    Bullet,
+    Decimal(u64),
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+struct Context {
+    left: i32,
+    quote: bool,
+    list_level: usize,
+}
+
+struct RtfWriter {
+    body: String,
+    /// One list definition per Markdown list; `\lsN` refers to index N - 1
+    lists: Vec<ListFormat>,
+    footnotes: HashMap<String, Vec<Block>>,
+}
+
+impl RtfWriter {
+    fn list_tables(&self) -> String {
+        if self.lists.is_empty() {
+            return String::new();
+        }
+
+        let mut table = String::from("{\\*\\listtable");
+        for (i, format) in self.lists.iter().enumerate() {
+            let id = i + 1;
+            table.push_str(&format!("{{\\list\\listtemplateid{}", 1000 + id));
+            for level in 0..9 {
+                let indent = (level + 1) * LIST_INDENT;
+                let (nfc, start, text, numbers) = match format {
+                    ListFormat::Bullet => (23, 1, "\\'01\\u8226 ?".to_string(), String::new()),
+                    ListFormat::Decimal(start) => (
+                        0,
+                        *start,
+                        format!("\\'02\\'0{}.", level),
+                        "\\'01".to_string(),
+                    ),
+                };
+                table.push_str(&format!(
+                    "{{\\listlevel\\levelnfc{0}\\levelnfcn{0}\\leveljc0\\leveljcn0\\levelfollow0\\levelstartat{1}\\levelspace0\\levelindent0{{\\leveltext{2};}}{{\\levelnumbers{3};}}\\fi-{4}\\li{5}\\lin{5}}}",
+                    nfc, start, text, numbers, LIST_HANGING, indent
+                ));
+            }
+            table.push_str(&format!("{{\\listname ;}}\\listid{}}}", id));
+        }
+        table.push_str("}\n{\\*\\listoverridetable");
+        for i in 0..self.lists.len() {
+            table.push_str(&format!(
+                "{{\\listoverride\\listid{0}\\listoverridecount0\\ls{0}}}",
+                i + 1
+            ));
+        }
+        table.push_str("}\n");
+        table
+    }
+
+    /// Open a body paragraph group, applying the indent and quote styling of `ctx`
+    fn paragraph_start(&mut self, ctx: Context, spacing: &str) {
+        self.body.push_str("{\\pard\\plain");
+        if ctx.quote {
+            self.body.push_str("\\s8");
+        } else {
+            self.body.push_str("\\s0");
+        }
+        self.body
+            .push_str(&format!("{}\\li{}\\f0\\fs{}", spacing, ctx.left, BODY_SIZE));
+        if ctx.quote {
+            self.body.push_str(&format!("\\i\\cf{}", QUOTE_COLOR));
+        }
+        self.body.push(' ');
+    }
+
+    fn blocks(&mut self, blocks: &[Block], ctx: Context) {
+        for block in blocks {
+            self.block(block, ctx);
+        }
+    }
+
+    fn block(&mut self, block: &Block, ctx: Context) {
+        match block {
+            Block::Heading { level, content, .. } => {
+                let level = (*level).clamp(1, 6) as usize;
+                self.body.push_str(&format!(
+                    "{{\\pard\\plain\\s{}\\keepn\\outlinelevel{}\\sb240\\sa120\\li{}\\f2\\fs{}\\b ",
+                    level,
+                    level - 1,
+                    ctx.left,
+                    HEADING_SIZES[level - 1]
+                ));
+                self.inlines(content);
+                self.body.push_str("\\par}\n");
+            }
+            Block::Paragraph(content) => {
+                self.paragraph_start(ctx, "\\sa120");
+                self.inlines(content);
+                self.body.push_str("\\par}\n");
+            }
+            Block::Quote(children) => {
+                let inner = Context {
+                    left: ctx.left + QUOTE_INDENT,
+                    quote: true,
+                    ..ctx
+                };
+                self.blocks(children, inner);
+            }
+            Block::Code { code, .. } => {
+                self.body.push_str(&format!(
+                    "{{\\pard\\plain\\s7\\sa120\\li{}\\cbpat{}\\f1\\fs{} ",
+                    ctx.left, CODE_BACKGROUND, CODE_SIZE
+                ));
+                let lines: Vec<String> = code
+                    .trim_end_matches('\n')
+                    .split('\n')
+                    .map(escape)
+                    .collect();
+                self.body.push_str(&lines.join("\\line "));
+                self.body.push_str("\\par}\n");
+            }
+            Block::List { start, items, .. } => self.list(*start, items, ctx),
+            Block::Table {
+                alignments,
+                header,
+                rows,
+            } => self.table(alignments, header, rows),
+            Block::ThematicBreak => self
+                .body
+                .push_str("{\\pard\\plain\\brdrb\\brdrs\\brdrw10\\brsp20\\sa120 \\par}\n"),
+            // Raw HTML has no RTF equivalent; footnote bodies are emitted at their references
+            Block::Html(_) | Block::FootnoteDefinition { .. } => {}
+        }
+    }
+
+    fn list(&mut self, start: Option<u64>, items: &[markdown::ListItem], ctx: Context) {
+        self.lists.push(match start {
+            Some(n) => ListFormat::Decimal(n),
+            None => ListFormat::Bullet,
+        });
+        let list_id = self.lists.len();
+        let left = ctx.left + LIST_INDENT;
+
+        for (i, item) in items.iter().enumerate() {
+            let marker = match start {
+                Some(n) => format!("{}.", n + i as u64),
+                None => "\\u8226?".to_string(),
+            };
+            let (first, rest) = match item.blocks.split_first() {
+                Some((Block::Paragraph(content), rest)) => (Some(content), rest),
+                _ => (None, item.blocks.as_slice()),
+            };
+
+            self.body.push_str(&format!(
+                "{{\\pard\\plain\\s0\\sa60\\fi-{}\\li{}\\ls{}\\ilvl{}\\f0\\fs{}{} {{\\listtext\\pard\\plain\\f0\\fs{} {}\\tab}}",
+                LIST_HANGING,
+                left,
+                list_id,
+                ctx.list_level.min(8),
+                BODY_SIZE,
+                if ctx.quote { "\\i" } else { "" },
+                BODY_SIZE,
+                marker
+            ));
+            match item.checked {
+                Some(true) => self.body.push_str("\\u9746? "),
+                Some(false) => self.body.push_str("\\u9744? "),
+                None => {}
+            }
+            if let Some(content) = first {
+                self.inlines(content);
+            }
+            self.body.push_str("\\par}\n");
+
+            let inner = Context {
+                left,
+                list_level: ctx.list_level + 1,
+                ..ctx
+            };
+            self.blocks(rest, inner);
+        }
+    }
+
+    fn table(
+        &mut self,
+        alignments: &[Alignment],
+        header: &[Vec<Inline>],
+        rows: &[Vec<Vec<Inline>>],
+    ) {
+        let columns = rows
+            .iter()
+            .map(Vec::len)
+            .chain(std::iter::once(header.len()))
+            .max()
+            .unwrap_or(0);
+        if columns == 0 {
+            return;
+        }
+        let width = TABLE_WIDTH / columns as i32;
+
+        let mut row_definition = String::from("\\trowd\\trgaph108\\trleft0");
+        for i in 0..columns {
+            row_definition.push_str(&format!(
+                "\\clbrdrt\\brdrs\\brdrw10\\clbrdrl\\brdrs\\brdrw10\\clbrdrb\\brdrs\\brdrw10\\clbrdrr\\brdrs\\brdrw10\\cellx{}",
+                width * (i as i32 + 1)
+            ));
+        }
+
+        for (index, row) in std::iter::once(header)
+            .chain(rows.iter().map(Vec::as_slice))
+            .enumerate()
+        {
+            let is_header = index == 0;
+            self.body.push('{');
+            self.body.push_str(&row_definition);
+            if is_header {
+                self.body.push_str("\\trhdr");
+            }
+            self.body.push('\n');
+            for i in 0..columns {
+                let align = match alignments.get(i) {
+                    Some(Alignment::Center) => "\\qc",
+                    Some(Alignment::Right) => "\\qr",
+                    _ => "\\ql",
+                };
+                self.body.push_str(&format!(
+                    "{{\\pard\\plain\\intbl{}\\f0\\fs{}{} ",
+                    align,
+                    BODY_SIZE,
+                    if is_header { "\\b" } else { "" }
+                ));
+                if let Some(cell) = row.get(i) {
+                    self.inlines(cell);
+                }
+                self.body.push_str("\\cell}\n");
+            }
+            self.body.push_str("\\row}\n");
+        }
+        self.body.push_str("{\\pard\\plain\\sa120 \\par}\n");
+    }
+
+    fn inlines(&mut self, content: &[Inline]) {
+        for inline in content {
+            self.inline(inline);
+        }
+    }
+
+    fn group(&mut self, control: &str, children: &[Inline]) {
+        self.body.push('{');
+        self.body.push_str(control);
+        self.body.push(' ');
+        self.inlines(children);
+        self.body.push('}');
+    }
+
+    fn inline(&mut self, inline: &Inline) {
+        match inline {
+            Inline::Text(text) => self.body.push_str(&escape(text)),
+            Inline::Code(code) => {
+                self.body
+                    .push_str(&format!("{{\\f1\\fs{} {}}}", CODE_SIZE, escape(code)));
+            }
+            Inline::Emphasis(children) => self.group("\\i", children),
+            Inline::Strong(children) => self.group("\\b", children),
+            Inline::Strikethrough(children) => self.group("\\strike", children),
+            Inline::Link { url, content, .. } => {
+                // Field instructions are quoted strings, so quotes are percent-encoded
+                let target = escape(&url.replace('"', "%22"));
+                self.body.push_str(&format!(
+                    "{{\\field{{\\*\\fldinst{{HYPERLINK \"{}\"}}}}{{\\fldrslt{{\\ul\\cf{} ",
+                    target, LINK_COLOR
+                ));
+                self.inlines(content);
+                self.body.push_str("}}}");
+            }
+            Inline::Image { url, alt, .. } => {
+                let label = if alt.is_empty() { url } else { alt };
+                self.body
+                    .push_str(&format!("{{\\i [image: {}]}}", escape(label)));
+            }
+            Inline::FootnoteReference(label) => self.footnote(label),
+            Inline::Html(_) => {}
+            Inline::SoftBreak => self.body.push(' '),
+            Inline::HardBreak => self.body.push_str("\\line "),
+        }
+    }
+
+    fn footnote(&mut self, label: &str) {
+        let Some(blocks) = self.footnotes.get(label).cloned() else {
+            self.body
+                .push_str(&format!("{{\\super [{}]}}", escape(label)));
+            return;
+        };
+
+        self.body
+            .push_str("{\\super\\chftn}{\\footnote\\pard\\plain\\f0\\fs20 {\\super\\chftn} ");
+        for (i, block) in blocks.iter().enumerate() {
+            if i > 0 {
+                self.body.push_str("\\par ");
+            }
+            match block {
+                Block::Paragraph(content) => self.inlines(content),
+                other => self
+                    .body
+                    .push_str(&escape(&markdown::plain_text(&block_inlines(other)))),
+            }
+        }
+        self.body.push('}');
+    }
+}
+
+/// Flatten a non-paragraph block to inline text (used inside footnotes)
+fn block_inlines(block: &Block) -> Vec<Inline> {
+    match block {
+        Block::Heading { content, .. } | Block::Paragraph(content) => content.clone(),
+        Block::Code { code, .. } => vec![Inline::Code(code.trim_end().to_string())],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minimal RTF reader: checks group balance and recovers the visible text
+    fn rtf_to_text(rtf: &str) -> String {
+        let chars: Vec<char> = rtf.chars().collect();
+        let mut skip_stack = vec![false];
+        let mut text = String::new();
+        let mut units: Vec<u16> = Vec::new();
+        let mut i = 0;
+
+        let flush = |units: &mut Vec<u16>, text: &mut String| {
+            text.push_str(&String::from_utf16_lossy(units));
+            units.clear();
+        };
+
+        while i < chars.len() {
+            let skipping = *skip_stack.last().expect("unbalanced closing brace");
+            match chars[i] {
+                '{' => {
+                    skip_stack.push(skipping);
+                    i += 1;
+                }
+                '}' => {
+                    skip_stack.pop();
+                    assert!(!skip_stack.is_empty(), "unbalanced closing brace");
+                    i += 1;
+                }
+                '\\' => {
+                    let next = chars[i + 1];
+                    if next == '\\' || next == '{' || next == '}' {
+                        if !skipping {
+                            flush(&mut units, &mut text);
+                            text.push(next);
+                        }
+                        i += 2;
+                        continue;
+                    }
+                    if next == '*' {
+                        *skip_stack.last_mut().unwrap() = true;
+                        i += 2;
+                        continue;
+                    }
+                    if next == '\'' {
+                        i += 4;
+                        continue;
+                    }
+                    let mut j = i + 1;
+                    while j < chars.len() && chars[j].is_ascii_alphabetic() {
+                        j += 1;
+                    }
+                    let word: String = chars[i + 1..j].iter().collect();
+                    let param_start = j;
+                    if j < chars.len() && chars[j] == '-' {
+                        j += 1;
+                    }
+                    while j < chars.len() && chars[j].is_ascii_digit() {
+                        j += 1;
+                    }
+                    let param: Option<i32> = chars[param_start..j]
+                        .iter()
+                        .collect::<String>()
+                        .parse()
+                        .ok();
+                    if j < chars.len() && chars[j] == ' ' {
+                        j += 1;
+                    }
+                    i = j;
+
+                    match word.as_str() {
+                        "fonttbl" | "colortbl" | "stylesheet" | "listtext" | "footnote" => {
+                            *skip_stack.last_mut().unwrap() = true;
+                        }
+                        "u" if !skipping => {
+                            units.push(param.unwrap() as i16 as u16);
+                            i += 1; // fallback character
+                        }
+                        "u" => i += 1,
+                        "par" | "line" | "row" if !skipping => {
+                            flush(&mut units, &mut text);
+                            text.push('\n');
+                        }
+                        "tab" | "cell" if !skipping => {
+                            flush(&mut units, &mut text);
+                            text.push('\t');
+                        }
+                        _ => {}
+                    }
+                }
+                '\n' | '\r' => i += 1,
+                c => {
+                    if !skipping {
+                        flush(&mut units, &mut text);
+                        text.push(c);
+                    }
+                    i += 1;
+                }
+            }
+        }
+        flush(&mut units, &mut text);
+        assert_eq!(skip_stack.len(), 1, "unclosed group");
+        text
+    }
+
+    fn export(markdown: &str) -> String {
+        render(&markdown::parse(markdown))
+    }
+
+    #[test]
+    fn test_escapes_special_characters() {
+        let rtf = export(
+            "Braces {x} and back\\\\slash in `code {y}` and [a link](https://example.com/#frag)",
+        );
+        assert!(rtf.contains("Braces \\{x\\} and back\\\\slash"));
+        assert!(rtf.contains("{\\f1\\fs20 code \\{y\\}}"));
+        assert!(rtf.contains("HYPERLINK \"https://example.com/#frag\""));
+        assert_eq!(
+            rtf_to_text(&rtf),
+            "Braces {x} and back\\slash in code {y} and a link\n"
+        );
+    }
+
+    #[test]
+    fn test_unicode_round_trip() {
+        let rtf = export("café — 日本語 😀");
+        assert!(rtf.contains("caf\\u233?"));
+        assert!(rtf.contains("\\u-10179?\\u-8704?"));
+        assert!(rtf.is_ascii());
+        assert_eq!(rtf_to_text(&rtf), "café — 日本語 😀\n");
+    }
+
+    #[test]
+    fn test_emphasis_is_scoped_to_groups() {
+        let rtf = export("**bold** then *italic* then ~~gone~~ plain");
+        assert!(rtf.contains("{\\b bold} then {\\i italic} then {\\strike gone} plain"));
+    }
+
+    #[test]
+    fn test_headings_use_styles_and_ignore_hashes_in_code() {
+        let rtf = export("# Title\n\n### Third\n\n```\n# not a heading\n```\n");
+        assert!(rtf.contains("\\s1\\keepn\\outlinelevel0"));
+        assert!(rtf.contains("\\s3\\keepn\\outlinelevel2"));
+        assert!(rtf.contains("\\s7\\sa120\\li0\\cbpat3\\f1\\fs20 # not a heading\\par"));
+        assert_eq!(rtf_to_text(&rtf), "Title\nThird\n# not a heading\n");
+    }
+
+    #[test]
+    fn test_sample_document_round_trip() {
+        let markdown = "# Weekly Spec\n\nIntro with **bold** and a [link](https://example.com).\n\n1. First\n2. Second\n   - Nested\n\n- [x] Done\n\n> Quoted\n\n| Name | Value |\n|------|------:|\n| a | 1 |\n\nSee note[^1].\n\n[^1]: The footnote.\n";
+        let rtf = export(markdown);
+
+        assert!(rtf.contains("{\\*\\listtable"));
+        assert!(rtf.contains("\\ls1\\ilvl0"));
+        assert!(rtf.contains("\\ls2\\ilvl1"));
+        assert!(rtf
+            .contains("\\levelnfc0\\levelnfcn0\\leveljc0\\leveljcn0\\levelfollow0\\levelstartat1"));
+        assert_eq!(rtf.matches("\\row}").count(), 2);
+        assert!(rtf.contains("{\\footnote"));
+
+        assert_eq!(
+            rtf_to_text(&rtf),
+            "Weekly Spec\nIntro with bold and a link.\nFirst\nSecond\nNested\n\u{2612} Done\nQuoted\nName\tValue\t\na\t1\t\n\nSee note.\n"
+        );
+    }
+}
@@ -0,0 +1,692 @@
+// Full-text Search Module
+// An in-memory inverted index over the Markdown files of an opened folder.
+// Queries combine plain terms, "quoted phrases", prefix* and fuzzy~ terms,
+// and every part must match. Files are only re-read when their size or
+// modification time changes, so repeated searches stay cheap.
+
+use serde::Serialize;
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+/// Longer words (hashes, base64 blobs) are not indexed
+const MAX_TERM_LEN: usize = 64;
+const SNIPPET_CHARS: usize = 160;
+/// Characters of context kept before the first match of a long line
+const SNIPPET_LEAD: usize = 40;
+const MAX_LINES_PER_HIT: usize = 5;
+/// Occurrences in headings count this many times
+const HEADING_BOOST: f64 = 3.0;
+// BM25 parameters
+const K1: f64 = 1.2;
+const B: f64 = 0.75;
+
+/// One index per opened folder, shared between windows
+pub type SearchIndexes = Mutex<HashMap<PathBuf, SearchIndex>>;
+
+/// Size and modification time; a file is re-indexed when either changes
+pub type Stamp = (u64, Option<SystemTime>);
+
+/// A matching line of a hit
+#[derive(Debug, Serialize)]
+pub struct LineMatch {
+    /// 1-based line number
+    pub line: usize,
+    pub heading: bool,
+    /// The line, shortened around the first match when it is long
+    pub snippet: String,
+    /// Matched ranges of `snippet` as UTF-16 offsets, ready for `String.slice`
+    pub highlights: Vec<[usize; 2]>,
+}
+
+/// A matching file, best first in search results
+#[derive(Debug, Serialize)]
+pub struct SearchHit {
+    pub path: String,
+    pub score: f64,
+    /// Number of matched words in the whole file
+    pub matches: usize,
+    pub lines: Vec<LineMatch>,
+}
+
+/// A part of a query
+#[derive(Debug, PartialEq)]
+enum Clause {
+    Term(String),
+    Prefix(String),
+    Fuzzy(String, usize),
+    Phrase(Vec<String>),
+}
+
+struct Token {
+    line: usize,
+    // Byte range within the line
+    start: usize,
+    end: usize,
+}
+
+struct IndexedDoc {
+    path: PathBuf,
+    stamp: Stamp,
+    lines: Vec<String>,
+    headings: Vec<bool>,
+    tokens: Vec<Token>,
+    /// (term id, token position) pairs, sorted
+    occurrences: Vec<(u32, u32)>,
+}
+
+impl IndexedDoc {
+    /// Positions of `term` in this document, in order
+    fn positions(&self, term: u32) -> impl Iterator<Item = u32> + '_ {
+        let start = self.occurrences.partition_point(|&(t, _)| t < term);
+        self.occurrences[start..]
+            .iter()
+            .take_while(move |&&(t, _)| t == term)
+            .map(|&(_, position)| position)
+    }
+
+    fn has(&self, term: u32, position: u32) -> bool {
+        self.occurrences.binary_search(&(term, position)).is_ok()
+    }
+
+    /// Distinct term ids of the document
+    fn terms(&self) -> impl Iterator<Item = u32> + '_ {
+        self.occurrences
+            .chunk_by(|a, b| a.0 == b.0)
+            .map(|group| group[0].0)
+    }
+}
+
+#[derive(Default)]
+pub struct SearchIndex {
+    docs: Vec<Option<IndexedDoc>>,
+    ids: HashMap<PathBuf, usize>,
+    free_ids: Vec<usize>,
+    /// Terms are interned; ids index `vocabulary` and `postings`
+    term_ids: HashMap<String, u32>,
+    vocabulary: Vec<String>,
+    /// term id -> documents containing the term. Positions live in the
+    /// documents, which keeps indexing to one push per distinct term.
+    postings: Vec<Vec<usize>>,
+    total_tokens: usize,
+}
+
+/// Byte ranges of the words of `text`
+fn words(text: &str) -> Vec<(usize, usize)> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut length = 0;
+    for (i, c) in text
+        .char_indices()
+        .chain(std::iter::once((text.len(), ' ')))
+    {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => {
+                start = Some(i);
+                length = 1;
+            }
+            (true, Some(_)) => length += 1,
+            (false, Some(s)) => {
+                if length <= MAX_TERM_LEN {
+                    words.push((s, i));
+                }
+                start = None;
+            }
+            (false, None) => {}
+        }
+    }
+    words
+}
+
+/// Terms are lowercase; most words already are, so avoid the copy
+fn normalize(word: &str) -> Cow<'_, str> {
+    if word.chars().any(char::is_uppercase) {
+        Cow::Owned(word.to_lowercase())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+fn is_heading(line: &str) -> bool {
+    let hashes = line.chars().take_while(|&c| c == '#').count();
+    (1..=6).contains(&hashes) && matches!(line[hashes..].chars().next(), None | Some(' ' | '\t'))
+}
+
+pub fn file_stamp(path: &Path) -> Option<Stamp> {
+    let metadata = fs::metadata(path).ok()?;
+    Some((metadata.len(), metadata.modified().ok()))
+}
+
+/// Edit distance between `a` and `b`, or `None` once it exceeds `max`
+fn edit_distance(a: &str, b: &str, max: usize) -> Option<usize> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut current = vec![i + 1; b.len() + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != cb);
+            current[j + 1] = substitution.min(previous[j + 1] + 1).min(current[j] + 1);
+        }
+        if current.iter().min().is_some_and(|&d| d > max) {
+            return None;
+        }
+        previous = current;
+    }
+    Some(previous[b.len()]).filter(|&d| d <= max)
+}
+
+/// Split a query into clauses: `"exact phrase"`, `prefix*`, `fuzzy~` (or
+/// `fuzzy~2` for a larger edit distance) and plain terms
+fn parse_query(query: &str) -> Vec<Clause> {
+    let mut clauses = Vec::new();
+    let mut rest = query.trim_start();
+    while !rest.is_empty() {
+        let (word, quoted, remainder) = match rest.strip_prefix('"') {
+            Some(inner) => {
+                let end = inner.find('"').unwrap_or(inner.len());
+                (&inner[..end], true, inner.get(end + 1..).unwrap_or(""))
+            }
+            None => {
+                let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+                (&rest[..end], false, &rest[end..])
+            }
+        };
+        rest = remainder.trim_start();
+
+        let (stem, modifier) = if quoted {
+            (word, None)
+        } else if let Some(stem) = word.strip_suffix('*') {
+            (stem, Some(Clause::Prefix(String::new())))
+        } else if let Some((stem, distance)) = word.rsplit_once('~') {
+            let distance = distance.parse().ok();
+            (
+                stem,
+                Some(Clause::Fuzzy(String::new(), distance.unwrap_or(0))),
+            )
+        } else {
+            (word, None)
+        };
+
+        let mut terms: Vec<String> = words(stem)
+            .into_iter()
+            .map(|(start, end)| normalize(&stem[start..end]).into_owned())
+            .collect();
+        let clause = match (terms.len(), modifier) {
+            (0, _) => continue,
+            (1, Some(Clause::Prefix(_))) => Clause::Prefix(terms.remove(0)),
+            (1, Some(Clause::Fuzzy(_, distance))) => {
+                let term = terms.remove(0);
+                let distance = match distance {
+                    0 if term.chars().count() <= 4 => 1,
+                    0 => 2,
+                    distance => distance.min(3),
+                };
+                Clause::Fuzzy(term, distance)
+            }
+            (1, _) => Clause::Term(terms.remove(0)),
+            _ => Clause::Phrase(terms),
+        };
+        clauses.push(clause);
+    }
+    clauses
+}
+
+/// Shorten `line` around its first match and convert the matched byte
+/// ranges to UTF-16 offsets within the snippet
+fn snippet(line: &str, ranges: &[(usize, usize)]) -> (String, Vec<[usize; 2]>) {
+    let trimmed_start = line.len() - line.trim_start().len();
+    let text = line.trim();
+    let ranges: Vec<(usize, usize)> = ranges
+        .iter()
+        .map(|&(start, end)| (start - trimmed_start, end - trimmed_start))
+        .collect();
+
+    let chars: Vec<usize> = text.char_indices().map(|(i, _)| i).collect();
+    let first = ranges.first().map_or(0, |&(start, _)| start);
+    let first_char = chars.partition_point(|&i| i < first);
+    let mut window_start = first_char.saturating_sub(SNIPPET_LEAD);
+    let window_end = (window_start + SNIPPET_CHARS).min(chars.len());
+    window_start = window_start.min(window_end.saturating_sub(SNIPPET_CHARS));
+
+    let byte_start = chars.get(window_start).copied().unwrap_or(text.len());
+    let byte_end = chars.get(window_end).copied().unwrap_or(text.len());
+    let mut snippet = String::new();
+    if window_start > 0 {
+        snippet.push('…');
+    }
+    let offset = snippet.encode_utf16().count();
+    snippet.push_str(&text[byte_start..byte_end]);
+    if window_end < chars.len() {
+        snippet.push('…');
+    }
+
+    let utf16 = |byte: usize| offset + text[byte_start..byte].encode_utf16().count();
+    let highlights = ranges
+        .iter()
+        .map(|&(start, end)| {
+            (
+                start.clamp(byte_start, byte_end),
+                end.clamp(byte_start, byte_end),
+            )
+        })
+        .filter(|(start, end)| start < end)
+        .map(|(start, end)| [utf16(start), utf16(end)])
+        .collect();
+    (snippet, highlights)
+}
+
+/// Files read to bring an index in line with a folder
+pub struct FileReads {
+    listed: Vec<PathBuf>,
+    read: Vec<(PathBuf, Stamp, String)>,
+    /// Listed files that are gone or could not be read
+    failed: Vec<PathBuf>,
+}
+
+/// Read the files of `paths` whose stamp differs from `indexed`, the
+/// [`SearchIndex::stamps`] of the index they are for. Only the disk is
+/// touched, so a search doesn't hold the index while files are read.
+pub fn read_changed(paths: Vec<PathBuf>, indexed: &HashMap<PathBuf, Stamp>) -> FileReads {
+    let mut read = Vec::new();
+    let mut failed = Vec::new();
+    for path in &paths {
+        let Some(stamp) = file_stamp(path) else {
+            failed.push(path.clone());
+            continue;
+        };
+        if indexed.get(path) == Some(&stamp) {
+            continue;
+        }
+        match fs::read(path) {
+            Ok(bytes) => {
+                let content = String::from_utf8_lossy(&bytes).into_owned();
+                read.push((path.clone(), stamp, content));
+            }
+            Err(e) => {
+                log::warn!("Failed to index {}: {}", path.display(), e);
+                failed.push(path.clone());
+            }
+        }
+    }
+    FileReads {
+        listed: paths,
+        read,
+        failed,
+    }
+}
+
+impl SearchIndex {
+    /// Stamps of the indexed files, for [`read_changed`]
+    pub fn stamps(&self) -> HashMap<PathBuf, Stamp> {
+        self.docs
+            .iter()
+            .flatten()
+            .map(|doc| (doc.path.clone(), doc.stamp))
+            .collect()
+    }
+
+    /// Bring the index in line with the files of `reads`: changed files are
+    /// (re)indexed and files no longer listed or readable are dropped.
+    /// Returns how many files were indexed.
+    pub fn apply(&mut self, reads: FileReads) -> usize {
+        let listed: HashSet<&PathBuf> = reads.listed.iter().collect();
+        let gone: Vec<PathBuf> = self
+            .ids
+            .keys()
+            .filter(|path| !listed.contains(path))
+            .chain(&reads.failed)
+            .cloned()
+            .collect();
+        for path in gone {
+            self.remove_file(&path);
+        }
+
+        let count = reads.read.len();
+        for (path, stamp, content) in reads.read {
+            self.insert(path, stamp, &content);
+        }
+        count
+    }
+
+    /// Re-index a single file, e.g. right after it was saved
+    pub fn update_file(&mut self, path: &Path) -> Result<(), String> {
+        let Some(stamp) = file_stamp(path) else {
+            self.remove_file(path);
+            return Ok(());
+        };
+        let bytes =
+            fs::read(path).map_err(|e| format!("Failed to index {}: {}", path.display(), e))?;
+        self.insert(path.to_path_buf(), stamp, &String::from_utf8_lossy(&bytes));
+        Ok(())
+    }
+
+    pub fn remove_file(&mut self, path: &Path) {
+        let Some(id) = self.ids.remove(path) else {
+            return;
+        };
+        if let Some(doc) = self.docs[id].take() {
+            for term in doc.terms() {
+                self.postings[term as usize].retain(|&d| d != id);
+            }
+            self.total_tokens -= doc.tokens.len();
+        }
+        self.free_ids.push(id);
+    }
+
+    fn intern(&mut self, term: &str) -> u32 {
+        if let Some(&id) = self.term_ids.get(term) {
+            return id;
+        }
+        let id = self.vocabulary.len() as u32;
+        self.vocabulary.push(term.to_string());
+        self.term_ids.insert(term.to_string(), id);
+        self.postings.push(Vec::new());
+        id
+    }
+
+    fn insert(&mut self, path: PathBuf, stamp: Stamp, content: &str) {
+        self.remove_file(&path);
+        let id = self.free_ids.pop().unwrap_or_else(|| {
+            self.docs.push(None);
+            self.docs.len() - 1
+        });
+
+        let mut doc = IndexedDoc {
+            path: path.clone(),
+            stamp,
+            lines: Vec::new(),
+            headings: Vec::new(),
+            tokens: Vec::new(),
+            occurrences: Vec::new(),
+        };
+        let mut in_fence = false;
+        for (line_number, line) in content.lines().enumerate() {
+            let trimmed = line.trim_start();
+            if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+                in_fence = !in_fence;
+            }
+            doc.headings.push(!in_fence && is_heading(trimmed));
+            for (start, end) in words(line) {
+                let term = self.intern(&normalize(&line[start..end]));
+                doc.occurrences.push((term, doc.tokens.len() as u32));
+                doc.tokens.push(Token {
+                    line: line_number,
+                    start,
+                    end,
+                });
+            }
+            doc.lines.push(line.to_string());
+        }
+        doc.occurrences.sort_unstable();
+        for term in doc.terms() {
+            self.postings[term as usize].push(id);
+        }
+
+        self.total_tokens += doc.tokens.len();
+        self.ids.insert(path, id);
+        self.docs[id] = Some(doc);
+    }
+
+    /// Id of `term` if any document contains it
+    fn term_id(&self, term: &str) -> Option<u32> {
+        let id = *self.term_ids.get(term)?;
+        (!self.postings[id as usize].is_empty()).then_some(id)
+    }
+
+    /// Indexed term ids matching a clause, with how much a match counts
+    fn expand(&self, clause: &Clause) -> Vec<(u32, f64)> {
+        let weight = |term: &str| -> Option<f64> {
+            match clause {
+                Clause::Prefix(prefix) if term == prefix => Some(1.0),
+                Clause::Prefix(prefix) => term.starts_with(prefix.as_str()).then_some(0.8),
+                Clause::Fuzzy(target, max) => {
+                    let distance = edit_distance(target, term, *max)?;
+                    Some(1.0 / (1.0 + distance as f64))
+                }
+                Clause::Term(_) | Clause::Phrase(_) => None,
+            }
+        };
+        match clause {
+            Clause::Term(term) => self.term_id(term).map(|id| (id, 1.0)).into_iter().collect(),
+            Clause::Phrase(_) => Vec::new(),
+            // Prefix and fuzzy terms are matched against the whole vocabulary
+            _ => self
+                .vocabulary
+                .iter()
+                .enumerate()
+                .filter(|(id, _)| !self.postings[*id].is_empty())
+                .filter_map(|(id, term)| Some((id as u32, weight(term)?)))
+                .collect(),
+        }
+    }
+
+    /// BM25 weight of `positions` in `doc` for a term with document frequency `df`
+    fn score(&self, doc: &IndexedDoc, positions: &[u32], df: usize) -> f64 {
+        let docs = self.ids.len() as f64;
+        let idf = (1.0 + (docs - df as f64 + 0.5) / (df as f64 + 0.5)).ln();
+        let tf: f64 = positions
+            .iter()
+            .map(|&p| {
+                if doc.headings[doc.tokens[p as usize].line] {
+                    HEADING_BOOST
+                } else {
+                    1.0
+                }
+            })
+            .sum();
+        let average = self.total_tokens as f64 / docs.max(1.0);
+        let length = doc.tokens.len() as f64 / average.max(1.0);
+        idf * tf * (K1 + 1.0) / (tf + K1 * (1.0 - B + B * length))
+    }
+
+    /// Documents matching a clause, with their score and matched positions
+    fn evaluate(&self, clause: &Clause) -> HashMap<usize, (f64, Vec<u32>)> {
+        let mut results: HashMap<usize, (f64, Vec<u32>)> = HashMap::new();
+        if let Clause::Phrase(terms) = clause {
+            let Some(ids) = terms
+                .iter()
+                .map(|term| self.term_id(term))
+                .collect::<Option<Vec<u32>>>()
+            else {
+                return results;
+            };
+            let df = ids
+                .iter()
+                .map(|&term| self.postings[term as usize].len())
+                .min()
+                .unwrap_or(0);
+            for &id in &self.postings[ids[0] as usize] {
+                let Some(doc) = self.docs[id].as_ref() else {
+                    continue;
+                };
+                let starts: Vec<u32> = doc
+                    .positions(ids[0])
+                    .filter(|&start| {
+                        ids.iter()
+                            .enumerate()
+                            .skip(1)
+                            .all(|(offset, &term)| doc.has(term, start + offset as u32))
+                    })
+                    .collect();
+                if !starts.is_empty() {
+                    let score = self.score(doc, &starts, df) * terms.len() as f64;
+                    let positions = starts
+                        .iter()
+                        .flat_map(|&start| start..start + terms.len() as u32)
+                        .collect();
+                    results.insert(id, (score, positions));
+                }
+            }
+            return results;
+        }
+
+        for (term, weight) in self.expand(clause) {
+            let docs = &self.postings[term as usize];
+            for &id in docs {
+                let Some(doc) = self.docs[id].as_ref() else {
+                    continue;
+                };
+                let positions: Vec<u32> = doc.positions(term).collect();
+                let entry = results.entry(id).or_default();
+                entry.0 += weight * self.score(doc, &positions, docs.len());
+                entry.1.extend(positions);
+            }
+        }
+        results
+    }
+
+    /// Files matching every part of `query`, best first
+    pub fn search(&self, query: &str, limit: usize) -> Vec<SearchHit> {
+        let mut matched: Option<HashMap<usize, (f64, Vec<u32>)>> = None;
+        for clause in &parse_query(query) {
+            let results = self.evaluate(clause);
+            matched = Some(match matched {
+                None => results,
+                Some(mut previous) => {
+                    previous.retain(|id, _| results.contains_key(id));
+                    for (id, (score, positions)) in results {
+                        if let Some(entry) = previous.get_mut(&id) {
+                            entry.0 += score;
+                            entry.1.extend(positions);
+                        }
+                    }
+                    previous
+                }
+            });
+        }
+
+        let mut ranked: Vec<(&IndexedDoc, f64, Vec<u32>)> = matched
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|(id, (score, positions))| {
+                Some((self.docs[id].as_ref()?, score, positions))
+            })
+            .collect();
+        ranked.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.path.cmp(&b.0.path)));
+        ranked.truncate(limit);
+
+        ranked
+            .into_iter()
+            .map(|(doc, score, mut positions)| {
+                positions.sort_unstable();
+                positions.dedup();
+
+                let mut by_line: BTreeMap<usize, Vec<(usize, usize)>> = BTreeMap::new();
+                for &p in &positions {
+                    let token = &doc.tokens[p as usize];
+                    by_line
+                        .entry(token.line)
+                        .or_default()
+                        .push((token.start, token.end));
+                }
+                let lines = by_line
+                    .into_iter()
+                    .take(MAX_LINES_PER_HIT)
+                    .map(|(line, ranges)| {
+                        let (snippet, highlights) = snippet(&doc.lines[line], &ranges);
+                        LineMatch {
+                            line: line + 1,
+                            heading: doc.headings[line],
+                            snippet,
+                            highlights,
+                        }
+                    })
+                    .collect();
+
+                SearchHit {
+                    path: doc.path.to_string_lossy().to_string(),
+                    score,
+                    matches: positions.len(),
+                    lines,
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_query() {
+        assert_eq!(
+            parse_query(r#"Rust "static  site" expo* colour~ recieve~1 a-b"#),
+            vec![
+                Clause::Term("rust".to_string()),
+                Clause::Phrase(vec!["static".to_string(), "site".to_string()]),
+                Clause::Prefix("expo".to_string()),
+                Clause::Fuzzy("colour".to_string(), 2),
+                Clause::Fuzzy("recieve".to_string(), 1),
+                Clause::Phrase(vec!["a".to_string(), "b".to_string()]),
+            ]
+        );
+        assert_eq!(edit_distance("recieve", "receive", 2), Some(2));
+        assert_eq!(edit_distance("colour", "color", 1), Some(1));
+        assert_eq!(edit_distance("kitten", "sitting", 2), None);
+    }
+
+    #[test]
+    fn test_search_and_incremental_update() {
+        let dir = std::env::temp_dir().join(format!("docura-search-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let guide = dir.join("guide.md");
+        let notes = dir.join("notes.md");
+        fs::write(
+            &guide,
+            "# Exporting\n\nExport the folder as a static site.\n",
+        )
+        .unwrap();
+        fs::write(
+            &notes,
+            "Some notes about the site.\n\n```\n# not a heading\n```\n",
+        )
+        .unwrap();
+        let paths = vec![guide.clone(), notes.clone()];
+
+        let mut index = SearchIndex::default();
+        assert_eq!(index.apply(read_changed(paths.clone(), &index.stamps())), 2);
+        assert_eq!(index.apply(read_changed(paths, &index.stamps())), 0);
+
+        let hits = index.search("\"static site\"", 10);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].lines[0].line, 3);
+        assert_eq!(
+            hits[0].lines[0].snippet,
+            "Export the folder as a static site."
+        );
+        assert_eq!(hits[0].lines[0].highlights, vec![[23, 29], [30, 34]]);
+
+        // The heading match ranks the guide first
+        let hits = index.search("expor*", 10);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].lines[0].heading && hits[0].matches == 2);
+        let hits = index.search("sitte~ folder", 10);
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].path.ends_with("guide.md"));
+        let hits = index.search("heading", 10);
+        assert!(!hits[0].lines[0].heading);
+
+        fs::write(&notes, "Only the export remains.\n").unwrap();
+        index.update_file(&notes).unwrap();
+        let hits = index.search("export*", 10);
+        assert_eq!(hits.len(), 2);
+        assert!(hits[0].path.ends_with("guide.md"));
+        assert!(index.search("heading", 10).is_empty());
+
+        fs::remove_file(&guide).unwrap();
+        index.apply(read_changed(vec![notes.clone()], &index.stamps()));
+        let hits = index.search("export", 10);
+        fs::remove_dir_all(&dir).ok();
+        assert_eq!(hits.len(), 1);
+        assert!(hits[0].path.ends_with("notes.md"));
+        assert!(index.term_id("static").is_none());
+    }
+}
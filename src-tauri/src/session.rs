@@ -0,0 +1,501 @@
+// Session Module
+// A journal of the buffer open in every window, so that unsaved work
+// survives a crash: the file it edits (none for an untitled one), its
+// window, cursor and scroll position, whether it has unsaved changes and,
+// while it does, its text. Buffers left behind by an earlier run of the
+// app are offered for recovery when it starts again. Several instances of
+// the app can run at once: each holds a lock file for as long as it runs,
+// so their open buffers are never mistaken for a crashed run's.
+
+use crate::document_save::{self, DiskVersion};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File, TryLockError};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard, OnceLock};
+
+const JOURNAL_FILE: &str = "journal.json";
+const JOURNAL_LOCK: &str = "journal.lock";
+
+/// Every window updates the same journal
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Tells this run's buffers from those of earlier ones
+static SESSION: OnceLock<String> = OnceLock::new();
+
+/// The session lock this run holds in each journal directory, for as long
+/// as it runs: other instances of the app know its buffers are still open
+static HELD: Mutex<Vec<(PathBuf, File)>> = Mutex::new(Vec::new());
+
+fn current_session() -> &'static str {
+    SESSION.get_or_init(|| {
+        format!(
+            "{}-{}",
+            std::process::id(),
+            chrono::Utc::now().timestamp_millis()
+        )
+    })
+}
+
+fn session_lock_file(root: &Path, session: &str) -> PathBuf {
+    root.join(format!("{}.lock", session))
+}
+
+/// Mark this run's session as alive in `root`
+fn hold_session(root: &Path) -> Result<(), String> {
+    let mut held = HELD.lock().unwrap_or_else(|e| e.into_inner());
+    if held.iter().any(|(dir, _)| dir == root) {
+        return Ok(());
+    }
+    let file = File::create(session_lock_file(root, current_session()))
+        .map_err(|e| format!("Failed to create session lock: {}", e))?;
+    file.lock()
+        .map_err(|e| format!("Failed to lock session: {}", e))?;
+    held.push((root.to_path_buf(), file));
+    Ok(())
+}
+
+/// Whether the run that wrote `session` is still going: this one, or
+/// another instance of the app that holds its session lock
+fn session_alive(root: &Path, session: &str) -> bool {
+    if session == current_session() {
+        return true;
+    }
+    let Ok(file) = File::open(session_lock_file(root, session)) else {
+        return false;
+    };
+    matches!(file.try_lock(), Err(TryLockError::WouldBlock))
+}
+
+/// Exclusive access to the journal, for this process's windows and any
+/// other instance of the app
+struct JournalLock {
+    _file: File,
+    _guard: MutexGuard<'static, ()>,
+}
+
+fn lock(root: &Path) -> Result<JournalLock, String> {
+    let guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    fs::create_dir_all(root).map_err(|e| format!("Failed to create session directory: {}", e))?;
+    let file = File::create(root.join(JOURNAL_LOCK))
+        .map_err(|e| format!("Failed to create journal lock: {}", e))?;
+    file.lock()
+        .map_err(|e| format!("Failed to lock session journal: {}", e))?;
+    // Under the journal lock, so no one takes the new session lock for stale
+    hold_session(root)?;
+    Ok(JournalLock {
+        _file: file,
+        _guard: guard,
+    })
+}
+
+/// What a window reports about its buffer
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Buffer {
+    /// Assigned by the journal on the first update
+    #[serde(default)]
+    pub id: String,
+    /// The file being edited, `None` for an untitled buffer
+    pub path: Option<String>,
+    #[serde(rename = "windowLabel")]
+    pub window_label: String,
+    pub line: u32,
+    pub column: u32,
+    #[serde(rename = "scrollTop")]
+    pub scroll_top: f64,
+    /// Whether the buffer has changes that aren't saved
+    pub dirty: bool,
+    /// The version of the file the unsaved changes were made to
+    pub base: Option<DiskVersion>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry {
+    #[serde(flatten)]
+    buffer: Buffer,
+    session: String,
+    /// When the buffer was last recorded, in milliseconds since the epoch
+    updated: i64,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Journal {
+    buffers: Vec<Entry>,
+}
+
+/// An unsaved buffer from an earlier run
+#[derive(Debug, Clone, Serialize)]
+pub struct RecoveredBuffer {
+    #[serde(flatten)]
+    pub buffer: Buffer,
+    pub content: String,
+    pub updated: i64,
+    /// The file was changed or deleted after the unsaved changes were made
+    #[serde(rename = "fileChanged")]
+    pub file_changed: bool,
+}
+
+/// Where the text of buffer `id` is kept. Ids come back from the frontend,
+/// so anything that could name another file is refused.
+fn content_file(root: &Path, id: &str) -> Result<PathBuf, String> {
+    if id.is_empty() || !id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return Err(format!("Invalid buffer id: {}", id));
+    }
+    Ok(root.join(format!("{}.txt", id)))
+}
+
+/// The journal in `root`. One that can't be parsed is moved aside and a
+/// new one started, so that a damaged file doesn't stop all journaling.
+fn load_journal(root: &Path) -> Result<Journal, String> {
+    let path = root.join(JOURNAL_FILE);
+    let json = match fs::read_to_string(&path) {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Journal::default()),
+        Err(e) => return Err(format!("Failed to read session journal: {}", e)),
+    };
+    match serde_json::from_str(&json) {
+        Ok(journal) => Ok(journal),
+        Err(e) => {
+            let aside = root.join(format!(
+                "{}.corrupt-{}",
+                JOURNAL_FILE,
+                chrono::Utc::now().timestamp_millis()
+            ));
+            log::warn!(
+                "Session journal is damaged ({}), moving it to {}",
+                e,
+                aside.display()
+            );
+            fs::rename(&path, &aside)
+                .map_err(|e| format!("Failed to move damaged session journal: {}", e))?;
+            Ok(Journal::default())
+        }
+    }
+}
+
+fn save_journal(root: &Path, journal: &Journal) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(journal)
+        .map_err(|e| format!("Failed to serialize session journal: {}", e))?;
+    document_save::write_atomic(&root.join(JOURNAL_FILE), json.as_bytes())
+}
+
+fn remove_content(root: &Path, id: &str) {
+    if let Ok(path) = content_file(root, id) {
+        if let Err(e) = fs::remove_file(&path) {
+            if e.kind() != std::io::ErrorKind::NotFound {
+                log::warn!("Failed to remove {}: {}", path.display(), e);
+            }
+        }
+    }
+}
+
+fn new_id(journal: &Journal) -> String {
+    let mut id = chrono::Utc::now().timestamp_millis();
+    while journal
+        .buffers
+        .iter()
+        .any(|e| e.buffer.id == id.to_string())
+    {
+        id += 1;
+    }
+    id.to_string()
+}
+
+/// Record the state of a buffer, with its text while it is `dirty`; a
+/// clean buffer's text is dropped. Returns the buffer's id.
+pub fn update(root: &Path, mut buffer: Buffer, content: Option<&str>) -> Result<String, String> {
+    let _lock = lock(root)?;
+    let mut journal = load_journal(root)?;
+    if buffer.id.is_empty() {
+        buffer.id = new_id(&journal);
+    }
+
+    if !buffer.dirty {
+        remove_content(root, &buffer.id);
+    } else if let Some(content) = content {
+        document_save::write_atomic(&content_file(root, &buffer.id)?, content.as_bytes())?;
+    }
+
+    let id = buffer.id.clone();
+    let entry = Entry {
+        buffer,
+        session: current_session().to_string(),
+        updated: chrono::Utc::now().timestamp_millis(),
+    };
+    match journal.buffers.iter_mut().find(|e| e.buffer.id == id) {
+        Some(existing) => *existing = entry,
+        None => journal.buffers.push(entry),
+    }
+    save_journal(root, &journal)?;
+    Ok(id)
+}
+
+/// Forget a buffer: its window closed it, saved or not as the user chose
+pub fn close(root: &Path, id: &str) -> Result<(), String> {
+    let _lock = lock(root)?;
+    let mut journal = load_journal(root)?;
+    content_file(root, id)?;
+    remove_content(root, id);
+    journal.buffers.retain(|e| e.buffer.id != id);
+    save_journal(root, &journal)
+}
+
+fn recovered(root: &Path, entry: &Entry) -> Option<RecoveredBuffer> {
+    if !entry.buffer.dirty {
+        return None;
+    }
+    let content = fs::read_to_string(content_file(root, &entry.buffer.id).ok()?).ok()?;
+    // An emptied untitled buffer holds nothing worth keeping
+    if entry.buffer.path.is_none() && content.is_empty() {
+        return None;
+    }
+    let file_changed = match &entry.buffer.path {
+        Some(path) => match DiskVersion::of(Path::new(path)) {
+            Ok(Some(disk)) => entry
+                .buffer
+                .base
+                .as_ref()
+                .is_some_and(|base| base.hash != disk.hash),
+            Ok(None) => true,
+            Err(_) => false,
+        },
+        None => false,
+    };
+    Some(RecoveredBuffer {
+        buffer: entry.buffer.clone(),
+        content,
+        updated: entry.updated,
+        file_changed,
+    })
+}
+
+/// Unsaved buffers left behind by earlier runs, newest first. Their other
+/// buffers had nothing to lose and are dropped from the journal. Buffers
+/// of other instances of the app that are still running are left alone.
+pub fn recoverable(root: &Path) -> Result<Vec<RecoveredBuffer>, String> {
+    let _lock = lock(root)?;
+    let mut journal = load_journal(root)?;
+    let mut buffers = Vec::new();
+    let before = journal.buffers.len();
+    let mut alive = HashMap::new();
+    journal.buffers.retain(|entry| {
+        if *alive
+            .entry(entry.session.clone())
+            .or_insert_with(|| session_alive(root, &entry.session))
+        {
+            return true;
+        }
+        match recovered(root, entry) {
+            Some(buffer) => {
+                buffers.push(buffer);
+                true
+            }
+            None => {
+                remove_content(root, &entry.buffer.id);
+                false
+            }
+        }
+    });
+    if journal.buffers.len() != before {
+        save_journal(root, &journal)?;
+    }
+    remove_stale_session_locks(root, &journal);
+    buffers.sort_by_key(|b| std::cmp::Reverse(b.updated));
+    Ok(buffers)
+}
+
+/// Delete the session locks of runs that ended and have no buffers left
+fn remove_stale_session_locks(root: &Path, journal: &Journal) {
+    let Ok(entries) = fs::read_dir(root) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let name = entry.file_name();
+        let Some(session) = name.to_str().and_then(|n| n.strip_suffix(".lock")) else {
+            continue;
+        };
+        if name == JOURNAL_LOCK
+            || journal.buffers.iter().any(|e| e.session == session)
+            || session_alive(root, session)
+        {
+            continue;
+        }
+        fs::remove_file(entry.path()).ok();
+    }
+}
+
+/// Take over a buffer from an earlier run in `window_label`, so that no
+/// other window or instance of the app restores it too
+pub fn claim(root: &Path, id: &str, window_label: &str) -> Result<RecoveredBuffer, String> {
+    let _lock = lock(root)?;
+    let mut journal = load_journal(root)?;
+    let entry = journal
+        .buffers
+        .iter_mut()
+        .find(|e| e.buffer.id == id && !session_alive(root, &e.session))
+        .ok_or_else(|| format!("No unsaved buffer {} to restore", id))?;
+    let buffer =
+        recovered(root, entry).ok_or_else(|| format!("Buffer {} has no unsaved text", id))?;
+    entry.session = current_session().to_string();
+    entry.buffer.window_label = window_label.to_string();
+    save_journal(root, &journal)?;
+    Ok(buffer)
+}
+
+/// Move the untitled buffers of the temp files older versions kept into
+/// the journal, as unsaved buffers of an earlier run
+pub fn adopt_temp_files(root: &Path, temp_dir: &Path) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(temp_dir) else {
+        return Ok(());
+    };
+    let _lock = lock(root)?;
+    let mut journal = load_journal(root)?;
+    let mut adopted = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        let Some(id) = name
+            .strip_prefix("temp-")
+            .and_then(|n| n.strip_suffix(".md"))
+        else {
+            continue;
+        };
+        if content_file(root, id).is_err() || journal.buffers.iter().any(|e| e.buffer.id == id) {
+            continue;
+        }
+        let updated = fs::metadata(&path)
+            .ok()
+            .and_then(|m| m.modified().ok())
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+        fs::copy(&path, content_file(root, id)?)
+            .map_err(|e| format!("Failed to adopt {}: {}", path.display(), e))?;
+        journal.buffers.push(Entry {
+            buffer: Buffer {
+                id: id.to_string(),
+                path: None,
+                window_label: "main".to_string(),
+                line: 1,
+                column: 1,
+                scroll_top: 0.0,
+                dirty: true,
+                base: None,
+            },
+            session: "temp-files".to_string(),
+            updated,
+        });
+        adopted.push(path);
+    }
+    if adopted.is_empty() {
+        return Ok(());
+    }
+    save_journal(root, &journal)?;
+    for path in adopted {
+        fs::remove_file(&path).ok();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_journal_and_recovery() {
+        let root = std::env::temp_dir().join(format!("docura-session-test-{}", std::process::id()));
+        let note = root.join("note.md");
+        fs::create_dir_all(&root).unwrap();
+        fs::write(&note, "# Note\n").unwrap();
+        let buffer = Buffer {
+            id: String::new(),
+            path: Some(note.to_string_lossy().to_string()),
+            window_label: "main".to_string(),
+            line: 3,
+            column: 7,
+            scroll_top: 120.0,
+            dirty: true,
+            base: DiskVersion::of(&note).unwrap(),
+        };
+
+        let id = update(&root, buffer.clone(), Some("# Note\n\nDraft\n")).unwrap();
+        let clean = update(
+            &root,
+            Buffer {
+                path: None,
+                dirty: false,
+                ..buffer.clone()
+            },
+            None,
+        )
+        .unwrap();
+        assert_ne!(id, clean);
+        // This run's buffers are still open in their windows
+        assert!(recoverable(&root).unwrap().is_empty());
+
+        // As if the app had crashed: the journal is from an earlier run
+        let journal = fs::read_to_string(root.join(JOURNAL_FILE)).unwrap();
+        fs::write(
+            root.join(JOURNAL_FILE),
+            journal.replace(current_session(), "crashed"),
+        )
+        .unwrap();
+        fs::write(&note, "# Note, edited elsewhere\n").unwrap();
+        let buffers = recoverable(&root).unwrap();
+        assert_eq!(buffers.len(), 1);
+        assert_eq!(
+            buffers[0].buffer,
+            Buffer {
+                id: id.clone(),
+                ..buffer.clone()
+            }
+        );
+        assert_eq!(buffers[0].content, "# Note\n\nDraft\n");
+        assert!(buffers[0].file_changed);
+
+        // Another instance of the app, still running, has it open
+        let journal = fs::read_to_string(root.join(JOURNAL_FILE)).unwrap();
+        fs::write(
+            root.join(JOURNAL_FILE),
+            journal.replace("crashed", "4242-1"),
+        )
+        .unwrap();
+        let other = File::create(session_lock_file(&root, "4242-1")).unwrap();
+        other.lock().unwrap();
+        let live = recoverable(&root).unwrap();
+        let stolen = claim(&root, &id, "docura-2");
+        drop(other);
+
+        // ...until it exits without closing it
+        let claimed = claim(&root, &id, "docura-2").unwrap();
+        let again = claim(&root, &id, "docura-3");
+        let left = recoverable(&root).unwrap();
+        close(&root, &id).unwrap();
+
+        // A damaged journal is put aside and journaling goes on
+        fs::write(root.join(JOURNAL_FILE), "{ not json").unwrap();
+        let fresh = update(&root, buffer.clone(), Some("Again\n")).unwrap();
+        close(&root, &fresh).unwrap();
+
+        let mut files: Vec<_> = fs::read_dir(&root)
+            .unwrap()
+            .flatten()
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        let invalid = close(&root, "../note");
+        fs::remove_dir_all(&root).ok();
+        assert!(live.is_empty());
+        assert!(stolen.is_err());
+        assert_eq!(claimed.content, "# Note\n\nDraft\n");
+        assert!(again.is_err());
+        assert!(left.is_empty());
+        // The note, the journal, the damaged one and the locks of this run
+        assert_eq!(files.len(), 5, "{:?}", files);
+        assert!(files[0].starts_with(current_session()));
+        assert_eq!(files[1], "journal.json");
+        assert!(files[2].starts_with("journal.json.corrupt-"));
+        assert_eq!(files[3..], ["journal.lock", "note.md"]);
+        assert!(invalid.is_err());
+    }
+}
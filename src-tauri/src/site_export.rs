@@ -0,0 +1,514 @@
+// Static Site Export Module
+// Renders a folder tree (as listed by `get_folder_files`) to a static
+// website: one page per Markdown file with links between documents
+// rewritten to the generated pages, a sidebar mirroring the folder tree,
+// the folder's other files copied alongside, and an index page.
+
+use crate::html_export::{self, escape, RenderOptions};
+use crate::markdown;
+use crate::text_files;
+use crate::themes::Theme;
+use crate::FileItem;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const LAYOUT_STYLESHEET: &str = "body { max-width: none; margin: 0; padding: 0; display: flex; min-height: 100vh; }
+.site-sidebar { flex: 0 0 260px; padding: 24px 16px; border-right: 1px solid var(--border-color, #ddd); overflow-y: auto; position: sticky; top: 0; max-height: 100vh; box-sizing: border-box; }
+.site-sidebar .site-title { display: block; font-weight: 600; font-size: 1.1em; margin-bottom: 16px; text-decoration: none; }
+.site-sidebar ul { list-style: none; margin: 0; padding-left: 14px; }
+.site-sidebar > ul { padding-left: 0; }
+.site-sidebar li { margin: 4px 0; }
+.site-sidebar .folder { font-weight: 600; opacity: 0.8; }
+.site-sidebar a { text-decoration: none; }
+.site-sidebar a[aria-current] { font-weight: 600; text-decoration: underline; }
+.site-content { flex: 1; max-width: 860px; margin: 0 auto; padding: 40px 32px; min-width: 0; }
+@media (max-width: 720px) {
+    body { display: block; }
+    .site-sidebar { position: static; max-height: none; border-right: 0; border-bottom: 1px solid var(--border-color, #ddd); }
+}
+";
+
+/// Options for a generated site
+pub struct SiteOptions {
+    pub title: String,
+    /// Editor theme to style the pages with; the default stylesheet otherwise
+    pub theme: Option<&'static Theme>,
+}
+
+/// Files the site itself writes at its root
+const RESERVED_OUTPUTS: [&str; 2] = ["index.html", "style.css"];
+
+/// What was written, for reporting back to the user
+#[derive(Debug, Default)]
+pub struct SiteReport {
+    pub pages: usize,
+    pub assets: usize,
+    /// Files left out because a page or the stylesheet goes where they would
+    pub skipped: Vec<PathBuf>,
+}
+
+/// A page of the site: its source file and output path relative to the site root
+struct Page {
+    source: PathBuf,
+    output: PathBuf,
+    title: String,
+}
+
+/// The sidebar tree, pruned to folders that contain pages
+enum NavNode {
+    Page(usize),
+    Folder(String, Vec<NavNode>),
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn is_external(url: &str) -> bool {
+    url.contains("://") || url.starts_with("mailto:")
+}
+
+/// Site-relative path as a URL path (always `/`-separated)
+fn url_path(path: &Path) -> String {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => {
+                Some(urlencoding::encode(&part.to_string_lossy()).into_owned())
+            }
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// Prefix that leads from a page back to the site root, e.g. `../../`
+fn root_prefix(output: &Path) -> String {
+    "../".repeat(output.components().count().saturating_sub(1))
+}
+
+/// Render `items` (the tree of `source`) to a static site in `output`
+pub fn build_site(
+    source: &Path,
+    items: &[FileItem],
+    output: &Path,
+    options: &SiteOptions,
+) -> Result<SiteReport, String> {
+    fs::create_dir_all(output)
+        .map_err(|e| format!("Failed to create {}: {}", output.display(), e))?;
+    let source = canonical(source);
+    let output_dir = canonical(output);
+
+    let mut pages = Vec::new();
+    let mut assets = Vec::new();
+    let nav = collect(items, &source, &output_dir, &mut pages, &mut assets);
+    if pages.is_empty() {
+        return Err("No Markdown files to export".to_string());
+    }
+    let mut report = SiteReport {
+        skipped: resolve_clashes(&pages, &mut assets)?,
+        ..SiteReport::default()
+    };
+
+    // A root README doubles as the index page unless there is an index.md
+    let has_index = pages
+        .iter()
+        .any(|page| page.output == Path::new("index.html"));
+    if !has_index {
+        if let Some(readme) = pages.iter_mut().find(|page| {
+            page.output.parent() == Some(Path::new(""))
+                && page
+                    .output
+                    .file_stem()
+                    .is_some_and(|stem| stem.eq_ignore_ascii_case("readme"))
+        }) {
+            readme.output = PathBuf::from("index.html");
+        }
+    }
+
+    let outputs: HashMap<PathBuf, PathBuf> = pages
+        .iter()
+        .map(|page| (canonical(&page.source), page.output.clone()))
+        .collect();
+
+    for (index, page) in pages.iter().enumerate() {
+        let markdown = text_files::read(&page.source)?.content;
+        let doc = markdown::parse(&markdown);
+        let prefix = root_prefix(&page.output);
+        let base_dir = page.source.parent();
+
+        // Links to other documents of the folder point at their pages
+        let rewrite_link = |url: &str| -> Option<String> {
+            if is_external(url) || url.starts_with('#') {
+                return None;
+            }
+            let (path, fragment) = match url.split_once('#') {
+                Some((path, fragment)) => (path, format!("#{}", fragment)),
+                None => (url, String::new()),
+            };
+            let decoded = urlencoding::decode(path).ok()?;
+            let target = canonical(&base_dir?.join(decoded.as_ref()));
+            outputs
+                .get(&target)
+                .map(|output| format!("{}{}{}", prefix, url_path(output), fragment))
+        };
+        let render_options = RenderOptions {
+            rewrite_link: Some(&rewrite_link),
+            highlight: options.theme.is_some(),
+            ..RenderOptions::default()
+        };
+        let body = html_export::render_body_with(&doc, &render_options);
+
+        let sidebar = sidebar(&nav, &pages, Some(index), &prefix, &options.title);
+        write_page(
+            output,
+            &page.output,
+            &page.title,
+            &options.title,
+            &sidebar,
+            &body,
+        )?;
+        report.pages += 1;
+    }
+
+    if !pages
+        .iter()
+        .any(|page| page.output == Path::new("index.html"))
+    {
+        let mut body = format!("<h1>{}</h1>\n", escape(&options.title));
+        body.push_str(&nav_list(&nav, &pages, None, ""));
+        let sidebar = sidebar(&nav, &pages, None, "", &options.title);
+        write_page(
+            output,
+            Path::new("index.html"),
+            &options.title,
+            &options.title,
+            &sidebar,
+            &body,
+        )?;
+    }
+
+    for (source_path, relative) in &assets {
+        let target = output.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        fs::copy(source_path, &target)
+            .map_err(|e| format!("Failed to copy {}: {}", source_path.display(), e))?;
+        report.assets += 1;
+    }
+
+    let stylesheet = match options.theme {
+        Some(theme) => html_export::theme_stylesheet(theme),
+        None => format!(
+            "{}{}",
+            html_export::STYLESHEET,
+            html_export::STANDALONE_STYLESHEET
+        ),
+    };
+    fs::write(output.join("style.css"), stylesheet + LAYOUT_STYLESHEET)
+        .map_err(|e| format!("Failed to write stylesheet: {}", e))?;
+
+    Ok(report)
+}
+
+/// Make sure every file of the site has its own output path. Two documents
+/// that would become the same page (`notes.md` and `notes.markdown`) are an
+/// error; a file that a page or the stylesheet would overwrite is left out
+/// and returned.
+fn resolve_clashes(
+    pages: &[Page],
+    assets: &mut Vec<(PathBuf, PathBuf)>,
+) -> Result<Vec<PathBuf>, String> {
+    let mut page_sources: HashMap<&Path, &Path> = HashMap::new();
+    for page in pages {
+        if let Some(other) = page_sources.insert(&page.output, &page.source) {
+            return Err(format!(
+                "{} and {} would both become {}; rename one of them",
+                other.display(),
+                page.source.display(),
+                page.output.display()
+            ));
+        }
+    }
+
+    let reserved: HashSet<&Path> = RESERVED_OUTPUTS.iter().map(Path::new).collect();
+    let mut skipped = Vec::new();
+    assets.retain(|(source, relative)| {
+        let clashes =
+            page_sources.contains_key(relative.as_path()) || reserved.contains(relative.as_path());
+        if clashes {
+            log::warn!(
+                "Not copying {}: the site writes {} itself",
+                source.display(),
+                relative.display()
+            );
+            skipped.push(source.clone());
+        }
+        !clashes
+    });
+    Ok(skipped)
+}
+
+/// Sort the tree into pages, assets and the sidebar structure. The output
+/// folder is skipped when it lies inside the source folder.
+fn collect(
+    items: &[FileItem],
+    source: &Path,
+    output_dir: &Path,
+    pages: &mut Vec<Page>,
+    assets: &mut Vec<(PathBuf, PathBuf)>,
+) -> Vec<NavNode> {
+    let mut nodes = Vec::new();
+    for item in items {
+        let path = PathBuf::from(&item.path);
+        if canonical(&path) == output_dir {
+            continue;
+        }
+        let Ok(relative) = canonical(&path).strip_prefix(source).map(Path::to_path_buf) else {
+            continue;
+        };
+
+        match &item.children {
+            Some(children) => {
+                let children = collect(children, source, output_dir, pages, assets);
+                if !children.is_empty() {
+                    nodes.push(NavNode::Folder(item.name.clone(), children));
+                }
+            }
+            None if markdown::is_markdown_path(&path) => {
+                let title = text_files::read(&path)
+                    .ok()
+                    .and_then(|document| markdown::parse(&document.content).title())
+                    .unwrap_or_else(|| {
+                        path.file_stem()
+                            .and_then(|s| s.to_str())
+                            .unwrap_or("Untitled")
+                            .to_string()
+                    });
+                nodes.push(NavNode::Page(pages.len()));
+                pages.push(Page {
+                    source: path,
+                    output: relative.with_extension("html"),
+                    title,
+                });
+            }
+            None => assets.push((path, relative)),
+        }
+    }
+    nodes
+}
+
+fn nav_list(nodes: &[NavNode], pages: &[Page], current: Option<usize>, prefix: &str) -> String {
+    let mut html = String::from("<ul>\n");
+    for node in nodes {
+        match node {
+            NavNode::Page(index) => {
+                let page = &pages[*index];
+                let current_attr = if current == Some(*index) {
+                    " aria-current=\"page\""
+                } else {
+                    ""
+                };
+                html.push_str(&format!(
+                    "<li><a href=\"{}{}\"{}>{}</a></li>\n",
+                    prefix,
+                    escape(&url_path(&page.output)),
+                    current_attr,
+                    escape(&page.title)
+                ));
+            }
+            NavNode::Folder(name, children) => {
+                html.push_str(&format!(
+                    "<li><span class=\"folder\">{}</span>\n{}</li>\n",
+                    escape(name),
+                    nav_list(children, pages, current, prefix)
+                ));
+            }
+        }
+    }
+    html.push_str("</ul>\n");
+    html
+}
+
+fn sidebar(
+    nodes: &[NavNode],
+    pages: &[Page],
+    current: Option<usize>,
+    prefix: &str,
+    site_title: &str,
+) -> String {
+    format!(
+        "<nav class=\"site-sidebar\">\n<a class=\"site-title\" href=\"{}index.html\">{}</a>\n{}</nav>",
+        prefix,
+        escape(site_title),
+        nav_list(nodes, pages, current, prefix)
+    )
+}
+
+fn write_page(
+    output: &Path,
+    relative: &Path,
+    title: &str,
+    site_title: &str,
+    sidebar: &str,
+    body: &str,
+) -> Result<(), String> {
+    let html = format!(
+        "<!DOCTYPE html>
+<html lang=\"en\">
+<head>
+    <meta charset=\"UTF-8\">
+    <meta name=\"viewport\" content=\"width=device-width, initial-scale=1.0\">
+    <title>{} - {}</title>
+    <link rel=\"stylesheet\" href=\"{}style.css\">
+</head>
+<body>
+{}
+<main class=\"site-content\">
+{}
+</main>
+</body>
+</html>
+",
+        escape(title),
+        escape(site_title),
+        root_prefix(relative),
+        sidebar,
+        body
+    );
+
+    let path = output.join(relative);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    fs::write(&path, html).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(path: &Path, children: Option<Vec<FileItem>>) -> FileItem {
+        FileItem {
+            name: path.file_name().unwrap().to_string_lossy().to_string(),
+            path: path.to_string_lossy().to_string(),
+            file_type: if children.is_some() { "folder" } else { "file" }.to_string(),
+            children,
+            metadata: None,
+        }
+    }
+
+    #[test]
+    fn test_build_site() {
+        let root = std::env::temp_dir().join(format!("docura-site-test-{}", std::process::id()));
+        let source = root.join("docs");
+        let output = source.join("site");
+        fs::create_dir_all(source.join("guide/img")).unwrap();
+        fs::write(
+            source.join("README.md"),
+            "# Welcome\n\nRead the [setup guide](guide/Setup%20Guide.md#install).\n",
+        )
+        .unwrap();
+        fs::write(
+            source.join("guide/Setup Guide.md"),
+            "# Setup\n\n## Install\n\n![Logo](img/logo.png) Back [home](../README.md).\n",
+        )
+        .unwrap();
+        fs::write(source.join("guide/img/logo.png"), b"png").unwrap();
+        fs::write(source.join("guide/Café.md"), b"# Caf\xe9 cr\xe8me\r\n").unwrap();
+        fs::create_dir_all(&output).unwrap();
+
+        let items = vec![
+            item(
+                &source.join("guide"),
+                Some(vec![
+                    item(
+                        &source.join("guide/img"),
+                        Some(vec![item(&source.join("guide/img/logo.png"), None)]),
+                    ),
+                    item(&source.join("guide/Café.md"), None),
+                    item(&source.join("guide/Setup Guide.md"), None),
+                ]),
+            ),
+            item(&output, Some(Vec::new())),
+            item(&source.join("README.md"), None),
+        ];
+        let options = SiteOptions {
+            title: "Docs".to_string(),
+            theme: None,
+        };
+        let report = build_site(&source, &items, &output, &options).unwrap();
+
+        let index = fs::read_to_string(output.join("index.html")).unwrap();
+        let guide = fs::read_to_string(output.join("guide/Setup Guide.html")).unwrap();
+        let latin = fs::read_to_string(output.join("guide/Café.html")).unwrap();
+        let copied = output.join("guide/img/logo.png").exists();
+        let stylesheet = output.join("style.css").exists();
+        fs::remove_dir_all(&root).ok();
+
+        assert_eq!((report.pages, report.assets), (3, 1));
+        assert!(latin.contains("<title>Café crème - Docs</title>"));
+        assert!(copied && stylesheet);
+        assert!(index.contains("<title>Welcome - Docs</title>"));
+        assert!(index.contains("<a href=\"guide/Setup%20Guide.html#install\">setup guide</a>"));
+        assert!(index.contains("<li><span class=\"folder\">guide</span>"));
+        assert!(!index.contains("site/"));
+        assert!(guide.contains("<link rel=\"stylesheet\" href=\"../style.css\">"));
+        assert!(guide.contains("<a href=\"../index.html\">home</a>"));
+        assert!(guide.contains("<img src=\"img/logo.png\" alt=\"Logo\">"));
+        assert!(guide
+            .contains("<a href=\"../guide/Setup%20Guide.html\" aria-current=\"page\">Setup</a>"));
+    }
+
+    #[test]
+    fn test_output_clashes() {
+        let root =
+            std::env::temp_dir().join(format!("docura-site-clash-test-{}", std::process::id()));
+        let source = root.join("docs");
+        let output = root.join("site");
+        fs::create_dir_all(&source).unwrap();
+        for (name, content) in [
+            ("notes.md", "# Notes"),
+            ("notes.html", "<p>old export</p>"),
+            ("style.css", "body {}"),
+            ("logo.png", "png"),
+            ("notes.markdown", "# Other notes"),
+        ] {
+            fs::write(source.join(name), content).unwrap();
+        }
+        let options = SiteOptions {
+            title: "Docs".to_string(),
+            theme: None,
+        };
+        let files = |names: &[&str]| -> Vec<FileItem> {
+            names.iter().map(|n| item(&source.join(n), None)).collect()
+        };
+
+        let report = build_site(
+            &source,
+            &files(&["logo.png", "notes.html", "notes.md", "style.css"]),
+            &output,
+            &options,
+        )
+        .unwrap();
+        let notes = fs::read_to_string(output.join("notes.html")).unwrap();
+        assert_eq!((report.pages, report.assets), (1, 1));
+        assert_eq!(
+            report.skipped,
+            vec![source.join("notes.html"), source.join("style.css")]
+        );
+        assert!(notes.contains("<h1"));
+
+        let clash = build_site(
+            &source,
+            &files(&["notes.markdown", "notes.md"]),
+            &output,
+            &options,
+        );
+        fs::remove_dir_all(&root).ok();
+        assert!(clash.unwrap_err().contains("would both become notes.html"));
+    }
+}
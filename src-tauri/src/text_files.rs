@@ -0,0 +1,348 @@
+// Text Files Module
+// Decides which files open as text and decodes them: UTF-8 with or without
+// a byte order mark, UTF-16 in either byte order, and Latin-1 for anything
+// else. Latin-1 is only a guess, so it is marked as one and the user is
+// asked before edits are saved in it. Binary files are recognised by their
+// content, not their extension.
+// The encoding and line endings found are handed back with the text, so a
+// saved file keeps them.
+
+use crate::markdown;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Extensions opened as text besides the Markdown ones; the settings can
+/// add more
+pub const TEXT_EXTENSIONS: [&str; 9] = [
+    "txt", "text", "mdx", "rst", "org", "qmd", "rmd", "adoc", "asciidoc",
+];
+
+/// Bytes looked at to tell text from binary
+const SNIFF_LEN: usize = 8000;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    Utf16Le,
+    Utf16Be,
+    /// ISO-8859-1, read as its Windows-1252 superset so that curly quotes
+    /// and dashes come out right. Every byte maps to a character and back.
+    Latin1,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    Crlf,
+    Cr,
+}
+
+/// How a text file is stored on disk
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct TextFormat {
+    #[serde(default)]
+    pub encoding: Encoding,
+    /// Starts with a byte order mark
+    #[serde(default)]
+    pub bom: bool,
+    #[serde(rename = "lineEnding", default)]
+    pub line_ending: LineEnding,
+    /// The bytes were neither UTF-8 nor UTF-16, so the encoding may well
+    /// be another one, e.g. Shift-JIS or KOI8-R
+    #[serde(rename = "encodingGuessed", default)]
+    pub encoding_guessed: bool,
+}
+
+/// A decoded file; `content` always uses `\n` line endings
+#[derive(Debug, Serialize)]
+pub struct TextDocument {
+    pub content: String,
+    #[serde(flatten)]
+    pub format: TextFormat,
+}
+
+/// Whether `path` should open as text: a known extension, one of `extra`,
+/// or none at all
+pub fn is_text_path(path: &Path, extra: &[String]) -> bool {
+    let Some(extension) = path.extension() else {
+        return true;
+    };
+    let extension = extension.to_string_lossy().to_lowercase();
+    markdown::MARKDOWN_EXTENSIONS.contains(&extension.as_str())
+        || TEXT_EXTENSIONS.contains(&extension.as_str())
+        || extra
+            .iter()
+            .any(|e| e.trim_start_matches('.').eq_ignore_ascii_case(&extension))
+}
+
+/// Byte order of BOM-less UTF-16, guessed from where the zero bytes of
+/// ASCII characters fall
+fn guess_utf16(sample: &[u8]) -> Option<Encoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 2 {
+        return None;
+    }
+    let zeros_at = |parity: usize| {
+        sample
+            .iter()
+            .skip(parity)
+            .step_by(2)
+            .take(pairs)
+            .filter(|&&b| b == 0)
+            .count()
+    };
+    let (even, odd) = (zeros_at(0), zeros_at(1));
+    // Mostly ASCII: every other byte is zero, the rest hardly ever
+    if odd * 10 >= pairs * 4 && even * 20 < pairs {
+        Some(Encoding::Utf16Le)
+    } else if even * 10 >= pairs * 4 && odd * 20 < pairs {
+        Some(Encoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+/// Binary content: NUL bytes that aren't UTF-16, or lots of control
+/// characters
+pub fn looks_binary(bytes: &[u8]) -> bool {
+    let sample = &bytes[..bytes.len().min(SNIFF_LEN)];
+    if sample.starts_with(&[0xFF, 0xFE]) || sample.starts_with(&[0xFE, 0xFF]) {
+        return false;
+    }
+    if sample.contains(&0) {
+        return guess_utf16(sample).is_none();
+    }
+    let controls = sample
+        .iter()
+        .filter(|&&b| b < 0x20 && !matches!(b, b'\n' | b'\r' | b'\t' | 0x0C | 0x1B))
+        .count();
+    controls * 10 > sample.len()
+}
+
+/// Windows-1252 characters for bytes 0x80-0x9F; the five it leaves
+/// undefined keep their Latin-1 control characters
+const WINDOWS_1252: [char; 32] = [
+    '€', '\u{81}', '‚', 'ƒ', '„', '…', '†', '‡', 'ˆ', '‰', 'Š', '‹', 'Œ', '\u{8D}', 'Ž', '\u{8F}',
+    '\u{90}', '‘', '’', '“', '”', '•', '–', '—', '˜', '™', 'š', '›', 'œ', '\u{9D}', 'ž', 'Ÿ',
+];
+
+fn decode_latin1(bytes: &[u8]) -> String {
+    bytes
+        .iter()
+        .map(|&b| match b {
+            0x80..=0x9F => WINDOWS_1252[(b - 0x80) as usize],
+            _ => b as char,
+        })
+        .collect()
+}
+
+fn encode_latin1(text: &str) -> Result<Vec<u8>, String> {
+    text.chars()
+        .map(|c| match c as u32 {
+            code @ (0..=0x7F | 0xA0..=0xFF) => Ok(code as u8),
+            _ => WINDOWS_1252
+                .iter()
+                .position(|&w| w == c)
+                .map(|i| 0x80 + i as u8)
+                .ok_or_else(|| format!("'{}' can't be saved in Latin-1", c)),
+        })
+        .collect()
+}
+
+fn decode_utf16(bytes: &[u8], encoding: Encoding) -> Result<String, String> {
+    let pairs = bytes.chunks_exact(2);
+    if !pairs.remainder().is_empty() {
+        return Err("Failed to decode UTF-16: odd number of bytes".to_string());
+    }
+    let units = pairs.map(|pair| match encoding {
+        Encoding::Utf16Be => u16::from_be_bytes([pair[0], pair[1]]),
+        _ => u16::from_le_bytes([pair[0], pair[1]]),
+    });
+    char::decode_utf16(units)
+        .collect::<Result<String, _>>()
+        .map_err(|e| format!("Failed to decode UTF-16: {}", e))
+}
+
+/// The most common line ending of `text`, and `text` with `\n` throughout
+fn normalize_line_endings(text: &str) -> (String, LineEnding) {
+    let crlf = text.matches("\r\n").count();
+    let cr = text.matches('\r').count() - crlf;
+    let lf = text.matches('\n').count() - crlf;
+
+    let line_ending = if crlf > lf && crlf >= cr {
+        LineEnding::Crlf
+    } else if cr > lf && cr > crlf {
+        LineEnding::Cr
+    } else {
+        LineEnding::Lf
+    };
+    if crlf + cr == 0 {
+        return (text.to_string(), line_ending);
+    }
+    (text.replace("\r\n", "\n").replace('\r', "\n"), line_ending)
+}
+
+/// Decode the contents of a text file
+pub fn decode(bytes: &[u8]) -> Result<TextDocument, String> {
+    if looks_binary(bytes) {
+        return Err("File looks like a binary file".to_string());
+    }
+
+    let (text, encoding, bom) = if let Some(rest) = bytes.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+        let text = std::str::from_utf8(rest)
+            .map_err(|e| format!("Failed to decode UTF-8: {}", e))?
+            .to_string();
+        (text, Encoding::Utf8, true)
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFF, 0xFE]) {
+        (
+            decode_utf16(rest, Encoding::Utf16Le)?,
+            Encoding::Utf16Le,
+            true,
+        )
+    } else if let Some(rest) = bytes.strip_prefix(&[0xFE, 0xFF]) {
+        (
+            decode_utf16(rest, Encoding::Utf16Be)?,
+            Encoding::Utf16Be,
+            true,
+        )
+    } else if let Some(encoding) = bytes
+        .contains(&0)
+        .then(|| guess_utf16(&bytes[..bytes.len().min(SNIFF_LEN)]))
+        .flatten()
+    {
+        (decode_utf16(bytes, encoding)?, encoding, false)
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => (text.to_string(), Encoding::Utf8, false),
+            Err(_) => (decode_latin1(bytes), Encoding::Latin1, false),
+        }
+    };
+
+    let (content, line_ending) = normalize_line_endings(&text);
+    Ok(TextDocument {
+        content,
+        format: TextFormat {
+            encoding,
+            bom,
+            line_ending,
+            encoding_guessed: encoding == Encoding::Latin1,
+        },
+    })
+}
+
+/// Read and decode the text file at `path`
+pub fn read(path: &Path) -> Result<TextDocument, String> {
+    let bytes =
+        std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    decode(&bytes).map_err(|e| format!("Failed to read {}: {}", path.display(), e))
+}
+
+/// Encode `content` for saving in `format`
+pub fn encode(content: &str, format: TextFormat) -> Result<Vec<u8>, String> {
+    let (content, _) = normalize_line_endings(content);
+    let content = match format.line_ending {
+        LineEnding::Lf => content,
+        LineEnding::Crlf => content.replace('\n', "\r\n"),
+        LineEnding::Cr => content.replace('\n', "\r"),
+    };
+
+    let mut bytes = Vec::with_capacity(content.len() + 3);
+    match format.encoding {
+        Encoding::Utf8 => {
+            if format.bom {
+                bytes.extend_from_slice(&[0xEF, 0xBB, 0xBF]);
+            }
+            bytes.extend_from_slice(content.as_bytes());
+        }
+        Encoding::Utf16Le | Encoding::Utf16Be => {
+            let big_endian = format.encoding == Encoding::Utf16Be;
+            let units = format
+                .bom
+                .then_some(0xFEFF)
+                .into_iter()
+                .chain(content.encode_utf16());
+            for unit in units {
+                bytes.extend_from_slice(&if big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+        }
+        Encoding::Latin1 => bytes = encode_latin1(&content)?,
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_and_encode_round_trip() {
+        let samples: Vec<(Vec<u8>, Encoding, bool, LineEnding)> = vec![
+            (
+                b"# Title\nText\n".to_vec(),
+                Encoding::Utf8,
+                false,
+                LineEnding::Lf,
+            ),
+            (
+                b"\xEF\xBB\xBF# Caf\xC3\xA9\r\n\r\n".to_vec(),
+                Encoding::Utf8,
+                true,
+                LineEnding::Crlf,
+            ),
+            (
+                b"\xFF\xFE#\0 \0\xE9\0\r\0\n\0".to_vec(),
+                Encoding::Utf16Le,
+                true,
+                LineEnding::Crlf,
+            ),
+            (
+                b"\0#\0 \0T\0i\0t\0l\0e\0\n".to_vec(),
+                Encoding::Utf16Be,
+                false,
+                LineEnding::Lf,
+            ),
+            (
+                b"Caf\xE9 \x93quoted\x94\rnext\r".to_vec(),
+                Encoding::Latin1,
+                false,
+                LineEnding::Cr,
+            ),
+        ];
+        for (bytes, encoding, bom, line_ending) in samples {
+            let document = decode(&bytes).unwrap();
+            assert_eq!(
+                document.format,
+                TextFormat {
+                    encoding,
+                    bom,
+                    line_ending,
+                    encoding_guessed: encoding == Encoding::Latin1,
+                }
+            );
+            assert!(!document.content.contains('\r'));
+            assert_eq!(encode(&document.content, document.format).unwrap(), bytes);
+        }
+        assert_eq!(decode(b"Caf\xE9 \x93q\x94").unwrap().content, "Café “q”");
+
+        assert!(looks_binary(b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR\0\0\x01\0"));
+        assert!(decode(b"\x7fELF\x02\x01\x01\0\0\0\0\0\0\0\0\0\x03\0>\0").is_err());
+        let latin1 = TextFormat {
+            encoding: Encoding::Latin1,
+            ..Default::default()
+        };
+        assert!(encode("smile 🙂", latin1).is_err());
+
+        assert!(is_text_path(Path::new("notes/Analysis.Rmd"), &[]));
+        assert!(is_text_path(Path::new("Makefile"), &[]));
+        assert!(is_text_path(Path::new("doc.tex"), &[".tex".to_string()]));
+        assert!(!is_text_path(Path::new("photo.png"), &[]));
+    }
+}
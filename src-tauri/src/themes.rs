@@ -0,0 +1,521 @@
+// Editor Themes Module
+// Colour palettes of the editor themes (mirroring `src/styles/global.css`)
+// for exports that should look like the editor, plus the syntax colours
+// used for highlighted code.
+
+/// Token colours for highlighted code blocks
+pub struct Syntax {
+    pub keyword: &'static str,
+    pub string: &'static str,
+    pub comment: &'static str,
+    pub number: &'static str,
+    pub function: &'static str,
+    pub type_name: &'static str,
+}
+
+/// An editor theme, identified by the id stored in `AppConfig.theme`
+pub struct Theme {
+    pub id: &'static str,
+    pub dark: bool,
+    pub bg_primary: &'static str,
+    pub bg_secondary: &'static str,
+    pub bg_tertiary: &'static str,
+    pub text_primary: &'static str,
+    pub text_secondary: &'static str,
+    pub border_color: &'static str,
+    pub accent_color: &'static str,
+    pub accent_hover: &'static str,
+    pub heading_color: &'static str,
+    pub code_bg: &'static str,
+    pub code_color: &'static str,
+    pub blockquote_border: &'static str,
+    pub link_color: &'static str,
+    pub link_hover: &'static str,
+    pub text_strong: &'static str,
+    pub text_emphasis: &'static str,
+    pub syntax: Syntax,
+}
+
+pub const THEMES: [Theme; 17] = [
+    Theme {
+        id: "dracula-dark",
+        dark: true,
+        bg_primary: "#282a36",
+        bg_secondary: "#44475a",
+        bg_tertiary: "#6272a4",
+        text_primary: "#f8f8f2",
+        text_secondary: "#8be9fd",
+        border_color: "#6272a4",
+        accent_color: "#bd93f9",
+        accent_hover: "#ff79c6",
+        heading_color: "#bd93f9",
+        code_bg: "#44475a",
+        code_color: "#50fa7b",
+        blockquote_border: "#ff79c6",
+        link_color: "#8be9fd",
+        link_hover: "#50fa7b",
+        text_strong: "#ff79c6",
+        text_emphasis: "#f1fa8c",
+        syntax: Syntax {
+            keyword: "#ff79c6",
+            string: "#f1fa8c",
+            comment: "#6272a4",
+            number: "#bd93f9",
+            function: "#50fa7b",
+            type_name: "#8be9fd",
+        },
+    },
+    Theme {
+        id: "dracula-light",
+        dark: false,
+        bg_primary: "#f8f8f2",
+        bg_secondary: "#e6e6e6",
+        bg_tertiary: "#d4d4d4",
+        text_primary: "#282a36",
+        text_secondary: "#6272a4",
+        border_color: "#bd93f9",
+        accent_color: "#6272a4",
+        accent_hover: "#44475a",
+        heading_color: "#6272a4",
+        code_bg: "#e6e6e6",
+        code_color: "#50fa7b",
+        blockquote_border: "#ff79c6",
+        link_color: "#8be9fd",
+        link_hover: "#50fa7b",
+        text_strong: "#ff79c6",
+        text_emphasis: "#f1fa8c",
+        syntax: Syntax {
+            keyword: "#a3144d",
+            string: "#846e15",
+            comment: "#635d97",
+            number: "#644ac9",
+            function: "#14710a",
+            type_name: "#036a96",
+        },
+    },
+    Theme {
+        id: "cappuccino-dark",
+        dark: true,
+        bg_primary: "#2d2a2e",
+        bg_secondary: "#403e41",
+        bg_tertiary: "#5b595c",
+        text_primary: "#e2e2e3",
+        text_secondary: "#fcfcfa",
+        border_color: "#5b595c",
+        accent_color: "#ab9df2",
+        accent_hover: "#78dce8",
+        heading_color: "#ab9df2",
+        code_bg: "#403e41",
+        code_color: "#a9dc76",
+        blockquote_border: "#ff6188",
+        link_color: "#78dce8",
+        link_hover: "#a9dc76",
+        text_strong: "#ff6188",
+        text_emphasis: "#ffd866",
+        syntax: Syntax {
+            keyword: "#ff6188",
+            string: "#ffd866",
+            comment: "#727072",
+            number: "#ab9df2",
+            function: "#a9dc76",
+            type_name: "#78dce8",
+        },
+    },
+    Theme {
+        id: "cappuccino-light",
+        dark: false,
+        bg_primary: "#faf4ed",
+        bg_secondary: "#f2e9e1",
+        bg_tertiary: "#e9ddd2",
+        text_primary: "#575279",
+        text_secondary: "#6e6a86",
+        border_color: "#dfdad9",
+        accent_color: "#907aa9",
+        accent_hover: "#56949f",
+        heading_color: "#907aa9",
+        code_bg: "#f2e9e1",
+        code_color: "#56949f",
+        blockquote_border: "#b4637a",
+        link_color: "#286983",
+        link_hover: "#56949f",
+        text_strong: "#b4637a",
+        text_emphasis: "#ea9d34",
+        syntax: Syntax {
+            keyword: "#286983",
+            string: "#ea9d34",
+            comment: "#9893a5",
+            number: "#907aa9",
+            function: "#d7827e",
+            type_name: "#56949f",
+        },
+    },
+    Theme {
+        id: "nord-dark",
+        dark: true,
+        bg_primary: "#2e3440",
+        bg_secondary: "#3b4252",
+        bg_tertiary: "#434c5e",
+        text_primary: "#eceff4",
+        text_secondary: "#d8dee9",
+        border_color: "#4c566a",
+        accent_color: "#81a1c1",
+        accent_hover: "#88c0d0",
+        heading_color: "#81a1c1",
+        code_bg: "#3b4252",
+        code_color: "#a3be8c",
+        blockquote_border: "#5e81ac",
+        link_color: "#88c0d0",
+        link_hover: "#8fbcbb",
+        text_strong: "#bf616a",
+        text_emphasis: "#d08770",
+        syntax: Syntax {
+            keyword: "#81a1c1",
+            string: "#a3be8c",
+            comment: "#616e88",
+            number: "#b48ead",
+            function: "#88c0d0",
+            type_name: "#8fbcbb",
+        },
+    },
+    Theme {
+        id: "nord-light",
+        dark: false,
+        bg_primary: "#eceff4",
+        bg_secondary: "#e5e9f0",
+        bg_tertiary: "#d8dee9",
+        text_primary: "#2e3440",
+        text_secondary: "#3b4252",
+        border_color: "#d8dee9",
+        accent_color: "#5e81ac",
+        accent_hover: "#81a1c1",
+        heading_color: "#5e81ac",
+        code_bg: "#e5e9f0",
+        code_color: "#a3be8c",
+        blockquote_border: "#5e81ac",
+        link_color: "#81a1c1",
+        link_hover: "#88c0d0",
+        text_strong: "#bf616a",
+        text_emphasis: "#d08770",
+        syntax: Syntax {
+            keyword: "#5e81ac",
+            string: "#5f7e4a",
+            comment: "#7b88a1",
+            number: "#9a6f96",
+            function: "#3b7d8f",
+            type_name: "#4c8c8a",
+        },
+    },
+    Theme {
+        id: "solarized-dark",
+        dark: true,
+        bg_primary: "#002b36",
+        bg_secondary: "#073642",
+        bg_tertiary: "#586e75",
+        text_primary: "#839496",
+        text_secondary: "#93a1a1",
+        border_color: "#586e75",
+        accent_color: "#268bd2",
+        accent_hover: "#2aa198",
+        heading_color: "#268bd2",
+        code_bg: "#073642",
+        code_color: "#859900",
+        blockquote_border: "#cb4b16",
+        link_color: "#2aa198",
+        link_hover: "#859900",
+        text_strong: "#dc322f",
+        text_emphasis: "#cb4b16",
+        syntax: Syntax {
+            keyword: "#859900",
+            string: "#2aa198",
+            comment: "#586e75",
+            number: "#d33682",
+            function: "#268bd2",
+            type_name: "#b58900",
+        },
+    },
+    Theme {
+        id: "solarized-light",
+        dark: false,
+        bg_primary: "#fdf6e3",
+        bg_secondary: "#eee8d5",
+        bg_tertiary: "#93a1a1",
+        text_primary: "#657b83",
+        text_secondary: "#586e75",
+        border_color: "#93a1a1",
+        accent_color: "#268bd2",
+        accent_hover: "#2aa198",
+        heading_color: "#268bd2",
+        code_bg: "#eee8d5",
+        code_color: "#859900",
+        blockquote_border: "#cb4b16",
+        link_color: "#2aa198",
+        link_hover: "#859900",
+        text_strong: "#dc322f",
+        text_emphasis: "#cb4b16",
+        syntax: Syntax {
+            keyword: "#859900",
+            string: "#2aa198",
+            comment: "#93a1a1",
+            number: "#d33682",
+            function: "#268bd2",
+            type_name: "#b58900",
+        },
+    },
+    Theme {
+        id: "monokai-dark",
+        dark: true,
+        bg_primary: "#272822",
+        bg_secondary: "#3e3d32",
+        bg_tertiary: "#49483e",
+        text_primary: "#f8f8f2",
+        text_secondary: "#75715e",
+        border_color: "#49483e",
+        accent_color: "#f92672",
+        accent_hover: "#a6e22e",
+        heading_color: "#f92672",
+        code_bg: "#3e3d32",
+        code_color: "#a6e22e",
+        blockquote_border: "#fd971f",
+        link_color: "#66d9ef",
+        link_hover: "#a6e22e",
+        text_strong: "#f92672",
+        text_emphasis: "#fd971f",
+        syntax: Syntax {
+            keyword: "#f92672",
+            string: "#e6db74",
+            comment: "#75715e",
+            number: "#ae81ff",
+            function: "#a6e22e",
+            type_name: "#66d9ef",
+        },
+    },
+    Theme {
+        id: "monokai-light",
+        dark: false,
+        bg_primary: "#fafafa",
+        bg_secondary: "#f0f0f0",
+        bg_tertiary: "#e6e6e6",
+        text_primary: "#272822",
+        text_secondary: "#75715e",
+        border_color: "#e6e6e6",
+        accent_color: "#f92672",
+        accent_hover: "#a6e22e",
+        heading_color: "#f92672",
+        code_bg: "#f0f0f0",
+        code_color: "#a6e22e",
+        blockquote_border: "#fd971f",
+        link_color: "#66d9ef",
+        link_hover: "#a6e22e",
+        text_strong: "#f92672",
+        text_emphasis: "#fd971f",
+        syntax: Syntax {
+            keyword: "#f92672",
+            string: "#998f2f",
+            comment: "#75715e",
+            number: "#ae81ff",
+            function: "#5f8d00",
+            type_name: "#0089b3",
+        },
+    },
+    Theme {
+        id: "github-dark",
+        dark: true,
+        bg_primary: "#0d1117",
+        bg_secondary: "#161b22",
+        bg_tertiary: "#21262d",
+        text_primary: "#f0f6fc",
+        text_secondary: "#8b949e",
+        border_color: "#30363d",
+        accent_color: "#58a6ff",
+        accent_hover: "#7c3aed",
+        heading_color: "#58a6ff",
+        code_bg: "#161b22",
+        code_color: "#79c0ff",
+        blockquote_border: "#d2a8ff",
+        link_color: "#58a6ff",
+        link_hover: "#79c0ff",
+        text_strong: "#d73a49",
+        text_emphasis: "#e36209",
+        syntax: Syntax {
+            keyword: "#ff7b72",
+            string: "#a5d6ff",
+            comment: "#8b949e",
+            number: "#79c0ff",
+            function: "#d2a8ff",
+            type_name: "#ffa657",
+        },
+    },
+    Theme {
+        id: "github-light",
+        dark: false,
+        bg_primary: "#ffffff",
+        bg_secondary: "#f6f8fa",
+        bg_tertiary: "#f1f3f4",
+        text_primary: "#24292f",
+        text_secondary: "#656d76",
+        border_color: "#d0d7de",
+        accent_color: "#0969da",
+        accent_hover: "#0550ae",
+        heading_color: "#0969da",
+        code_bg: "#f6f8fa",
+        code_color: "#0969da",
+        blockquote_border: "#d1a7ff",
+        link_color: "#0969da",
+        link_hover: "#0550ae",
+        text_strong: "#d73a49",
+        text_emphasis: "#e36209",
+        syntax: Syntax {
+            keyword: "#cf222e",
+            string: "#0a3069",
+            comment: "#6e7781",
+            number: "#0550ae",
+            function: "#8250df",
+            type_name: "#953800",
+        },
+    },
+    Theme {
+        id: "tokyo-night",
+        dark: true,
+        bg_primary: "#1a1b26",
+        bg_secondary: "#24283b",
+        bg_tertiary: "#364a82",
+        text_primary: "#c0caf5",
+        text_secondary: "#9aa5ce",
+        border_color: "#292e42",
+        accent_color: "#7aa2f7",
+        accent_hover: "#7dcfff",
+        heading_color: "#7aa2f7",
+        code_bg: "#24283b",
+        code_color: "#bb9af7",
+        blockquote_border: "#7aa2f7",
+        link_color: "#7aa2f7",
+        link_hover: "#7dcfff",
+        text_strong: "#bb9af7",
+        text_emphasis: "#ff9e64",
+        syntax: Syntax {
+            keyword: "#bb9af7",
+            string: "#9ece6a",
+            comment: "#565f89",
+            number: "#ff9e64",
+            function: "#7aa2f7",
+            type_name: "#2ac3de",
+        },
+    },
+    Theme {
+        id: "everforest-dark",
+        dark: true,
+        bg_primary: "#2d353b",
+        bg_secondary: "#3d484d",
+        bg_tertiary: "#4a555b",
+        text_primary: "#d3c6aa",
+        text_secondary: "#a7c080",
+        border_color: "#475258",
+        accent_color: "#a7c080",
+        accent_hover: "#83c092",
+        heading_color: "#a7c080",
+        code_bg: "#3d484d",
+        code_color: "#7fbbb3",
+        blockquote_border: "#a7c080",
+        link_color: "#a7c080",
+        link_hover: "#83c092",
+        text_strong: "#e67e80",
+        text_emphasis: "#d699b6",
+        syntax: Syntax {
+            keyword: "#e67e80",
+            string: "#a7c080",
+            comment: "#859289",
+            number: "#d699b6",
+            function: "#83c092",
+            type_name: "#dbbc7f",
+        },
+    },
+    Theme {
+        id: "gruvbox-dark",
+        dark: true,
+        bg_primary: "#282828",
+        bg_secondary: "#3c3836",
+        bg_tertiary: "#504945",
+        text_primary: "#ebdbb2",
+        text_secondary: "#d5c4a1",
+        border_color: "#504945",
+        accent_color: "#fe8019",
+        accent_hover: "#fabd2f",
+        heading_color: "#fabd2f",
+        code_bg: "#3c3836",
+        code_color: "#8ec07c",
+        blockquote_border: "#fe8019",
+        link_color: "#83a598",
+        link_hover: "#8ec07c",
+        text_strong: "#fb4934",
+        text_emphasis: "#fe8019",
+        syntax: Syntax {
+            keyword: "#fb4934",
+            string: "#b8bb26",
+            comment: "#928374",
+            number: "#d3869b",
+            function: "#fabd2f",
+            type_name: "#8ec07c",
+        },
+    },
+    Theme {
+        id: "rose-pine",
+        dark: true,
+        bg_primary: "#191724",
+        bg_secondary: "#26233a",
+        bg_tertiary: "#403d52",
+        text_primary: "#e0def4",
+        text_secondary: "#908caa",
+        border_color: "#403d52",
+        accent_color: "#ebbcba",
+        accent_hover: "#f6c177",
+        heading_color: "#ebbcba",
+        code_bg: "#26233a",
+        code_color: "#c4a7e7",
+        blockquote_border: "#ebbcba",
+        link_color: "#9ccfd8",
+        link_hover: "#ebbcba",
+        text_strong: "#eb6f92",
+        text_emphasis: "#f6c177",
+        syntax: Syntax {
+            keyword: "#31748f",
+            string: "#f6c177",
+            comment: "#6e6a86",
+            number: "#c4a7e7",
+            function: "#ebbcba",
+            type_name: "#9ccfd8",
+        },
+    },
+    Theme {
+        id: "kanagawa",
+        dark: true,
+        bg_primary: "#1f1f28",
+        bg_secondary: "#363646",
+        bg_tertiary: "#54546d",
+        text_primary: "#dcd7ba",
+        text_secondary: "#c8c093",
+        border_color: "#54546d",
+        accent_color: "#957fb8",
+        accent_hover: "#7e9cd8",
+        heading_color: "#957fb8",
+        code_bg: "#363646",
+        code_color: "#7e9cd8",
+        blockquote_border: "#957fb8",
+        link_color: "#7fb4ca",
+        link_hover: "#7e9cd8",
+        text_strong: "#d27e99",
+        text_emphasis: "#ffa066",
+        syntax: Syntax {
+            keyword: "#957fb8",
+            string: "#98bb6c",
+            comment: "#727169",
+            number: "#d27e99",
+            function: "#7e9cd8",
+            type_name: "#7aa89f",
+        },
+    },
+];
+
+/// Look up a theme by id
+pub fn find(id: &str) -> Option<&'static Theme> {
+    THEMES.iter().find(|theme| theme.id == id)
+}
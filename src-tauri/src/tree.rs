@@ -0,0 +1,411 @@
+// Folder Tree Module
+// Lists one directory level at a time for the sidebar, in pages, so huge
+// folders show up immediately and subfolders are only read when expanded.
+// Symlinked folders that point back at one of their own ancestors are
+// flagged instead of being offered for endless expansion. A folder is read
+// and sorted once per pass through its pages.
+
+use crate::file_filter::FileFilter;
+use crate::file_meta::{self, FileMetadata, SortKey, SortMode};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime};
+
+/// Listings kept for folders whose later pages haven't been asked for yet
+const MAX_LISTINGS: usize = 16;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TreeEntry {
+    name: String,
+    path: String,
+    #[serde(rename = "type")]
+    file_type: String,
+    /// A folder with at least one visible entry, i.e. worth an expand arrow
+    #[serde(rename = "hasChildren")]
+    has_children: bool,
+    #[serde(rename = "isSymlink")]
+    is_symlink: bool,
+    /// A symlinked folder leading back to one of its ancestors
+    #[serde(rename = "isCycle")]
+    is_cycle: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    metadata: Option<FileMetadata>,
+}
+
+/// One page of a directory listing
+#[derive(Debug, Serialize)]
+pub struct DirectoryPage {
+    entries: Vec<TreeEntry>,
+    /// Visible entries in the whole directory
+    total: usize,
+    /// Offset of the next page, if there is one
+    #[serde(rename = "nextOffset")]
+    next_offset: Option<usize>,
+}
+
+/// Canonical paths of `dir` and every folder above it. A symlink whose
+/// target is among them would lead back into itself.
+pub fn canonical_ancestors(dir: &Path) -> Vec<PathBuf> {
+    dir.ancestors()
+        .filter_map(|ancestor| ancestor.canonicalize().ok())
+        .collect()
+}
+
+fn has_visible_entries(dir: &Path, filter: &mut FileFilter) -> bool {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) => {
+            log::warn!("Failed to read {}: {}", dir.display(), e);
+            return false;
+        }
+    };
+
+    filter.enter(dir);
+    let visible = entries.flatten().any(|entry| {
+        let is_dir = entry.file_type().is_ok_and(|t| t.is_dir());
+        filter.is_visible(&entry.path(), is_dir)
+    });
+    filter.leave();
+    visible
+}
+
+struct Listed {
+    name: String,
+    path: PathBuf,
+    is_dir: bool,
+    is_symlink: bool,
+    metadata: Option<FileMetadata>,
+}
+
+impl Listed {
+    fn sort_key(&self) -> SortKey<'_> {
+        SortKey {
+            name: &self.name,
+            is_dir: self.is_dir,
+            metadata: self.metadata.as_ref(),
+        }
+    }
+}
+
+/// A folder listed in one order, with the ignore settings of the folder
+/// opened in the sidebar
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ListingKey {
+    dir: PathBuf,
+    sort: SortMode,
+    root: PathBuf,
+    patterns: Vec<String>,
+    show_ignored: bool,
+}
+
+impl ListingKey {
+    pub fn new(
+        dir: &Path,
+        sort: SortMode,
+        root: &Path,
+        patterns: &[String],
+        show_ignored: bool,
+    ) -> Self {
+        ListingKey {
+            dir: dir.to_path_buf(),
+            sort,
+            root: root.to_path_buf(),
+            patterns: patterns.to_vec(),
+            show_ignored,
+        }
+    }
+}
+
+/// A sorted listing and the folder's modification time when it was read
+pub struct CachedListing {
+    modified: Option<SystemTime>,
+    stored: Instant,
+    entries: Arc<Vec<Listed>>,
+}
+
+/// Listings whose first pages were returned, for slicing the rest from
+pub type DirectoryListings = Mutex<HashMap<ListingKey, CachedListing>>;
+
+fn modified_time(dir: &Path) -> Option<SystemTime> {
+    fs::metadata(dir).and_then(|m| m.modified()).ok()
+}
+
+/// The entries of `dir` that `filter` lets through, folders first and then
+/// files, in `sort` order
+fn read_listing(dir: &Path, filter: &FileFilter, sort: SortMode) -> Result<Vec<Listed>, String> {
+    // Names and types come from the directory itself; only symlinks need a stat
+    let mut listing: Vec<Listed> = Vec::new();
+    let entries = fs::read_dir(dir).map_err(|e| format!("Error reading directory: {}", e))?;
+    for entry in entries {
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                log::warn!("Error reading directory entry: {}", e);
+                continue;
+            }
+        };
+        let name = entry.file_name().to_string_lossy().to_string();
+        let path = entry.path();
+        let file_type = entry
+            .file_type()
+            .map_err(|e| format!("Error reading directory: {}", e))?;
+        let is_symlink = file_type.is_symlink();
+        let is_dir = if is_symlink {
+            path.is_dir()
+        } else {
+            file_type.is_dir()
+        };
+        if !filter.is_visible(&path, is_dir) {
+            continue;
+        }
+        let metadata = if sort.needs_metadata() {
+            file_meta::read_metadata(&path, sort == SortMode::Title)
+        } else {
+            None
+        };
+        listing.push(Listed {
+            name,
+            path,
+            is_dir,
+            is_symlink,
+            metadata,
+        });
+    }
+
+    listing.sort_by(|a, b| file_meta::compare(&a.sort_key(), &b.sort_key(), sort));
+    Ok(listing)
+}
+
+/// List `offset..offset + limit` of the folder and order in `key`, of the
+/// entries `filter` lets through. Metadata is read for the whole folder
+/// only when the order depends on it, and otherwise just for the returned
+/// page if `with_metadata` is set.
+///
+/// The first page reads the folder afresh, since edits inside it don't
+/// change its modification time. Later pages are sliced from that reading
+/// in `listings` while the folder's entries stay the same.
+pub fn list_directory(
+    key: &ListingKey,
+    offset: usize,
+    limit: usize,
+    filter: &mut FileFilter,
+    with_metadata: bool,
+    listings: &DirectoryListings,
+) -> Result<DirectoryPage, String> {
+    let (dir, sort) = (key.dir.as_path(), key.sort);
+    if !dir.is_dir() {
+        return Err("Path is not a directory".to_string());
+    }
+
+    let modified = modified_time(dir);
+    let lock = || {
+        listings
+            .lock()
+            .map_err(|e| format!("Failed to lock directory listings: {}", e))
+    };
+    let cached = if offset > 0 && modified.is_some() {
+        lock()?
+            .get(key)
+            .filter(|cached| cached.modified == modified)
+            .map(|cached| Arc::clone(&cached.entries))
+    } else {
+        None
+    };
+    let fresh = cached.is_none();
+    let listing = match cached {
+        Some(listing) => listing,
+        None => Arc::new(read_listing(dir, filter, sort)?),
+    };
+
+    let total = listing.len();
+    let end = offset.saturating_add(limit).min(total);
+    {
+        let mut listings = lock()?;
+        if end == total {
+            listings.remove(key);
+        } else if fresh && modified.is_some() {
+            // Passes that were given up on shouldn't pile up
+            if listings.len() >= MAX_LISTINGS && !listings.contains_key(key) {
+                let oldest = listings
+                    .iter()
+                    .min_by_key(|(_, cached)| cached.stored)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    listings.remove(&oldest);
+                }
+            }
+            listings.insert(
+                key.clone(),
+                CachedListing {
+                    modified,
+                    stored: Instant::now(),
+                    entries: Arc::clone(&listing),
+                },
+            );
+        }
+    }
+
+    let mut ancestors = None;
+    let entries = listing[offset.min(end)..end]
+        .iter()
+        .map(|entry| {
+            let Listed {
+                name,
+                path,
+                is_dir,
+                is_symlink,
+                metadata,
+            } = entry;
+            let (is_dir, is_symlink) = (*is_dir, *is_symlink);
+            // Sorting by title already read everything there is to show
+            let metadata = if with_metadata && !(metadata.is_some() && sort == SortMode::Title) {
+                file_meta::read_metadata(path, true)
+            } else {
+                metadata.clone()
+            };
+            let is_cycle = is_dir
+                && is_symlink
+                && path.canonicalize().is_ok_and(|target| {
+                    ancestors
+                        .get_or_insert_with(|| canonical_ancestors(dir))
+                        .contains(&target)
+                });
+            TreeEntry {
+                name: name.clone(),
+                path: path.to_string_lossy().to_string(),
+                file_type: if is_dir { "folder" } else { "file" }.to_string(),
+                has_children: is_dir && !is_cycle && has_visible_entries(path, filter),
+                is_symlink,
+                is_cycle,
+                metadata,
+            }
+        })
+        .collect();
+
+    Ok(DirectoryPage {
+        entries,
+        total,
+        next_offset: (end < total).then_some(end),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_list_directory_pages() {
+        let root = std::env::temp_dir().join(format!("docura-tree-test-{}", std::process::id()));
+        fs::create_dir_all(root.join("Notes/deep")).unwrap();
+        fs::create_dir_all(root.join("empty")).unwrap();
+        fs::create_dir_all(root.join("only-hidden/.git")).unwrap();
+        fs::write(root.join("b.md"), "").unwrap();
+        fs::write(root.join("A.md"), "").unwrap();
+        fs::write(root.join(".hidden.md"), "").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&root, root.join("Notes/deep/loop")).unwrap();
+
+        let listings = DirectoryListings::default();
+        let key = ListingKey::new(&root, SortMode::Name, &root, &[], false);
+        let mut filter = FileFilter::new(&root, &[], false);
+        let first = list_directory(&key, 0, 3, &mut filter, false, &listings).unwrap();
+        assert!(listings.lock().unwrap().contains_key(&key));
+        let second = list_directory(&key, 3, 3, &mut filter, true, &listings).unwrap();
+        assert!(listings.lock().unwrap().is_empty());
+        #[cfg(unix)]
+        {
+            let deep = root.join("Notes/deep");
+            let key = ListingKey::new(&deep, SortMode::Name, &root, &[], false);
+            let mut filter = FileFilter::for_directory(&root, &deep, &[], false);
+            let nested = list_directory(&key, 0, 10, &mut filter, false, &listings).unwrap();
+            let link = &nested.entries[0];
+            assert!(link.is_symlink && link.is_cycle && !link.has_children);
+            assert_eq!(link.file_type, "folder");
+        }
+        fs::remove_dir_all(&root).ok();
+
+        let names: Vec<(&str, bool)> = first
+            .entries
+            .iter()
+            .chain(&second.entries)
+            .map(|e| (e.name.as_str(), e.has_children))
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                ("empty", false),
+                ("Notes", true),
+                ("only-hidden", false),
+                ("A.md", false),
+                ("b.md", false)
+            ]
+        );
+        assert_eq!((first.total, first.next_offset), (5, Some(3)));
+        assert_eq!(second.next_offset, None);
+        assert!(first.entries.iter().all(|e| e.metadata.is_none()));
+        assert!(second.entries.iter().all(|e| e.metadata.is_some()));
+    }
+
+    #[test]
+    fn test_later_pages_use_the_first_reading() {
+        let root =
+            std::env::temp_dir().join(format!("docura-tree-cache-test-{}", std::process::id()));
+        fs::create_dir_all(&root).unwrap();
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(root.join(name), "").unwrap();
+        }
+        let past = SystemTime::now() - std::time::Duration::from_secs(60);
+        fs::File::open(&root).unwrap().set_modified(past).unwrap();
+
+        let listings = DirectoryListings::default();
+        let key = ListingKey::new(&root, SortMode::Name, &root, &[], false);
+        let names = |page: &DirectoryPage| {
+            page.entries
+                .iter()
+                .map(|e| e.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let first = list_directory(
+            &key,
+            0,
+            2,
+            &mut FileFilter::new(&root, &[], false),
+            false,
+            &listings,
+        )
+        .unwrap();
+        assert_eq!(names(&first), vec!["a.md", "b.md"]);
+
+        // The folder isn't read again, so a filter that hides everything
+        // doesn't apply to the rest of this pass
+        let mut hide_all = FileFilter::new(&root, &["*".to_string()], false);
+        let second = list_directory(&key, 2, 2, &mut hide_all, false, &listings).unwrap();
+        assert_eq!(
+            (names(&second), second.total),
+            (vec!["c.md".to_string()], 3)
+        );
+        assert!(listings.lock().unwrap().is_empty());
+
+        // Once the folder's entries change, later pages read it again
+        list_directory(
+            &key,
+            0,
+            1,
+            &mut FileFilter::new(&root, &[], false),
+            false,
+            &listings,
+        )
+        .unwrap();
+        fs::write(root.join("d.md"), "").unwrap();
+        fs::File::open(&root)
+            .unwrap()
+            .set_modified(SystemTime::now())
+            .unwrap();
+        let second = list_directory(&key, 1, 2, &mut hide_all, false, &listings).unwrap();
+        fs::remove_dir_all(&root).ok();
+        assert_eq!((second.entries.len(), second.total), (0, 0));
+    }
+}
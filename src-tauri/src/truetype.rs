@@ -0,0 +1,462 @@
+// TrueType Module
+// Reads what embedding a TrueType font in a PDF needs: which glyph draws a
+// character, glyph widths and the font's overall metrics. A subset keeps
+// the glyph numbering of the full font but drops the outlines of every
+// glyph a document doesn't use, so only a small part of a large font ends
+// up in the file. Fonts with CFF outlines (most .otf files) aren't read.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::Path;
+
+/// Tables a PDF reader needs to draw the glyphs of an embedded font
+const SUBSET_TABLES: [&[u8; 4]; 9] = [
+    b"cvt ", b"fpgm", b"glyf", b"head", b"hhea", b"hmtx", b"loca", b"maxp", b"prep",
+];
+
+// Flags of the components of a composite glyph
+const ARG_1_AND_2_ARE_WORDS: u16 = 0x0001;
+const WE_HAVE_A_SCALE: u16 = 0x0008;
+const MORE_COMPONENTS: u16 = 0x0020;
+const WE_HAVE_AN_X_AND_Y_SCALE: u16 = 0x0040;
+const WE_HAVE_A_TWO_BY_TWO: u16 = 0x0080;
+
+pub struct Font {
+    data: Vec<u8>,
+    tables: HashMap<[u8; 4], (usize, usize)>,
+    units_per_em: u16,
+    long_loca: bool,
+    glyph_count: u16,
+    advances: Vec<u16>,
+    cmap: HashMap<u32, u16>,
+    /// Bounding box of all glyphs, in font units
+    pub bbox: [i16; 4],
+    pub ascent: i16,
+    pub descent: i16,
+}
+
+fn u16_at(data: &[u8], offset: usize) -> Result<u16, String> {
+    data.get(offset..offset + 2)
+        .map(|b| u16::from_be_bytes([b[0], b[1]]))
+        .ok_or_else(|| "Truncated font file".to_string())
+}
+
+fn i16_at(data: &[u8], offset: usize) -> Result<i16, String> {
+    u16_at(data, offset).map(|v| v as i16)
+}
+
+fn u32_at(data: &[u8], offset: usize) -> Result<u32, String> {
+    data.get(offset..offset + 4)
+        .map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| "Truncated font file".to_string())
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    data.chunks(4).fold(0u32, |sum, chunk| {
+        let mut word = [0u8; 4];
+        word[..chunk.len()].copy_from_slice(chunk);
+        sum.wrapping_add(u32::from_be_bytes(word))
+    })
+}
+
+impl Font {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let data = std::fs::read(path)
+            .map_err(|e| format!("Failed to read font {}: {}", path.display(), e))?;
+        Self::parse(data)
+    }
+
+    /// Parse a TrueType font, or the first font of a collection (.ttc)
+    pub fn parse(data: Vec<u8>) -> Result<Self, String> {
+        let face = if data.starts_with(b"ttcf") {
+            u32_at(&data, 12)? as usize
+        } else {
+            0
+        };
+        let table_count = u16_at(&data, face + 4)? as usize;
+        let mut tables = HashMap::new();
+        for i in 0..table_count {
+            let record = face + 12 + i * 16;
+            let tag: [u8; 4] = data
+                .get(record..record + 4)
+                .and_then(|t| t.try_into().ok())
+                .ok_or("Truncated font file")?;
+            let offset = u32_at(&data, record + 8)? as usize;
+            let length = u32_at(&data, record + 12)? as usize;
+            if offset
+                .checked_add(length)
+                .is_none_or(|end| end > data.len())
+            {
+                return Err("Truncated font file".to_string());
+            }
+            tables.insert(tag, (offset, length));
+        }
+        let table = |tag: &[u8; 4]| {
+            tables
+                .get(tag)
+                .map(|&(offset, _)| offset)
+                .ok_or_else(|| format!("Font has no {} table", String::from_utf8_lossy(tag)))
+        };
+
+        let head = table(b"head")?;
+        let hhea = table(b"hhea")?;
+        let maxp = table(b"maxp")?;
+        let hmtx = table(b"hmtx")?;
+        let cmap = table(b"cmap")?;
+        // CFF fonts have no glyf table and can't be embedded this way
+        table(b"glyf")?;
+        table(b"loca")?;
+
+        let glyph_count = u16_at(&data, maxp + 4)?;
+        let metric_count = u16_at(&data, hhea + 34)?.max(1);
+        let mut advances = Vec::with_capacity(glyph_count as usize);
+        for glyph in 0..glyph_count {
+            let index = glyph.min(metric_count - 1) as usize;
+            advances.push(u16_at(&data, hmtx + index * 4)?);
+        }
+
+        let mut font = Font {
+            units_per_em: u16_at(&data, head + 18)?.max(1),
+            bbox: [
+                i16_at(&data, head + 36)?,
+                i16_at(&data, head + 38)?,
+                i16_at(&data, head + 40)?,
+                i16_at(&data, head + 42)?,
+            ],
+            long_loca: i16_at(&data, head + 50)? == 1,
+            ascent: i16_at(&data, hhea + 4)?,
+            descent: i16_at(&data, hhea + 6)?,
+            glyph_count,
+            advances,
+            cmap: HashMap::new(),
+            tables,
+            data,
+        };
+        font.cmap = font.read_cmap(cmap)?;
+        Ok(font)
+    }
+
+    /// The Unicode mapping of the font, from its best subtable
+    fn read_cmap(&self, cmap: usize) -> Result<HashMap<u32, u16>, String> {
+        let data = &self.data;
+        let mut best = None;
+        for i in 0..u16_at(data, cmap + 2)? as usize {
+            let record = cmap + 4 + i * 8;
+            let platform = u16_at(data, record)?;
+            let encoding = u16_at(data, record + 2)?;
+            let subtable = cmap + u32_at(data, record + 4)? as usize;
+            let format = u16_at(data, subtable)?;
+            let rank = match (platform, encoding, format) {
+                (3, 10, 12) | (0, _, 12) => 3,
+                (3, 1, 4) => 2,
+                (0, _, 4) => 1,
+                _ => continue,
+            };
+            if best.is_none_or(|(r, _)| rank > r) {
+                best = Some((rank, subtable));
+            }
+        }
+        let Some((_, subtable)) = best else {
+            return Err("Font has no Unicode character map".to_string());
+        };
+
+        let mut map = HashMap::new();
+        if u16_at(data, subtable)? == 12 {
+            for i in 0..u32_at(data, subtable + 12)? as usize {
+                let group = subtable + 16 + i * 12;
+                let start = u32_at(data, group)?;
+                let end = u32_at(data, group + 4)?.min(0x10FFFF);
+                let glyph = u32_at(data, group + 8)?;
+                for code in start..=end {
+                    map.insert(code, glyph.wrapping_add(code - start) as u16);
+                }
+            }
+        } else {
+            let segments = u16_at(data, subtable + 6)? as usize / 2;
+            let ends = subtable + 14;
+            let starts = ends + segments * 2 + 2;
+            let deltas = starts + segments * 2;
+            let range_offsets = deltas + segments * 2;
+            for i in 0..segments {
+                let end = u16_at(data, ends + i * 2)?;
+                let start = u16_at(data, starts + i * 2)?;
+                let delta = u16_at(data, deltas + i * 2)?;
+                let range_offset = u16_at(data, range_offsets + i * 2)?;
+                for code in start..=end {
+                    if code == 0xFFFF {
+                        break;
+                    }
+                    let glyph = if range_offset == 0 {
+                        code.wrapping_add(delta)
+                    } else {
+                        let at = range_offsets
+                            + i * 2
+                            + range_offset as usize
+                            + (code - start) as usize * 2;
+                        match u16_at(data, at)? {
+                            0 => 0,
+                            glyph => glyph.wrapping_add(delta),
+                        }
+                    };
+                    map.insert(code as u32, glyph);
+                }
+            }
+        }
+        map.retain(|_, glyph| *glyph != 0 && *glyph < self.glyph_count);
+        Ok(map)
+    }
+
+    /// The glyph that draws `c`, if the font has one
+    pub fn glyph(&self, c: char) -> Option<u16> {
+        self.cmap.get(&(c as u32)).copied()
+    }
+
+    /// Convert font units to thousandths of an em, as PDF measures glyphs
+    pub fn scale(&self, units: i16) -> f32 {
+        units as f32 * 1000.0 / self.units_per_em as f32
+    }
+
+    /// Advance width of `glyph` in thousandths of an em
+    pub fn advance(&self, glyph: u16) -> f32 {
+        let units = self.advances.get(glyph as usize).copied().unwrap_or(0);
+        units as f32 * 1000.0 / self.units_per_em as f32
+    }
+
+    fn table(&self, tag: &[u8; 4]) -> &[u8] {
+        self.tables
+            .get(tag)
+            .map(|&(offset, length)| &self.data[offset..offset + length])
+            .unwrap_or(&[])
+    }
+
+    /// Byte range of `glyph`'s outline within the glyf table
+    fn glyph_range(&self, glyph: u16) -> Result<(usize, usize), String> {
+        let loca = self.table(b"loca");
+        let index = glyph as usize;
+        let (start, end) = if self.long_loca {
+            (
+                u32_at(loca, index * 4)? as usize,
+                u32_at(loca, index * 4 + 4)? as usize,
+            )
+        } else {
+            (
+                u16_at(loca, index * 2)? as usize * 2,
+                u16_at(loca, index * 2 + 2)? as usize * 2,
+            )
+        };
+        if start > end || end > self.table(b"glyf").len() {
+            return Err(format!("Glyph {} is out of range", glyph));
+        }
+        Ok((start, end))
+    }
+
+    /// Glyphs that composite glyph data refers to
+    fn components(outline: &[u8]) -> Result<Vec<u16>, String> {
+        let mut components = Vec::new();
+        if outline.len() < 10 || i16_at(outline, 0)? >= 0 {
+            return Ok(components);
+        }
+        let mut offset = 10;
+        loop {
+            let flags = u16_at(outline, offset)?;
+            components.push(u16_at(outline, offset + 2)?);
+            offset += 4;
+            offset += if flags & ARG_1_AND_2_ARE_WORDS != 0 {
+                4
+            } else {
+                2
+            };
+            if flags & WE_HAVE_A_SCALE != 0 {
+                offset += 2;
+            } else if flags & WE_HAVE_AN_X_AND_Y_SCALE != 0 {
+                offset += 4;
+            } else if flags & WE_HAVE_A_TWO_BY_TWO != 0 {
+                offset += 8;
+            }
+            if flags & MORE_COMPONENTS == 0 {
+                return Ok(components);
+            }
+        }
+    }
+
+    /// A copy of the font with only the outlines of `glyphs` (plus the
+    /// missing glyph and the parts of composite glyphs). Glyph numbers stay
+    /// the same, so text can refer to glyphs by their number in the font.
+    pub fn subset(&self, glyphs: &BTreeSet<u16>) -> Result<Vec<u8>, String> {
+        let glyf = self.table(b"glyf");
+        let mut keep = BTreeSet::new();
+        let mut pending = vec![0];
+        pending.extend(glyphs);
+        while let Some(glyph) = pending.pop() {
+            if glyph >= self.glyph_count || !keep.insert(glyph) {
+                continue;
+            }
+            let (start, end) = self.glyph_range(glyph)?;
+            pending.extend(Self::components(&glyf[start..end])?);
+        }
+
+        let mut new_glyf = Vec::new();
+        let mut new_loca = Vec::with_capacity((self.glyph_count as usize + 1) * 4);
+        for glyph in 0..self.glyph_count {
+            new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+            if keep.contains(&glyph) {
+                let (start, end) = self.glyph_range(glyph)?;
+                new_glyf.extend_from_slice(&glyf[start..end]);
+                new_glyf.resize(new_glyf.len().div_ceil(4) * 4, 0);
+            }
+        }
+        new_loca.extend_from_slice(&(new_glyf.len() as u32).to_be_bytes());
+
+        let mut head = self.table(b"head").to_vec();
+        if head.len() < 54 {
+            return Err("Truncated font file".to_string());
+        }
+        head[8..12].fill(0);
+        // The new loca table always holds 32-bit offsets
+        head[50..52].copy_from_slice(&1i16.to_be_bytes());
+
+        let tables: Vec<(&[u8; 4], Vec<u8>)> = SUBSET_TABLES
+            .iter()
+            .filter_map(|&tag| match tag {
+                b"glyf" => Some((tag, new_glyf.clone())),
+                b"loca" => Some((tag, new_loca.clone())),
+                b"head" => Some((tag, head.clone())),
+                _ if self.tables.contains_key(tag) => Some((tag, self.table(tag).to_vec())),
+                _ => None,
+            })
+            .collect();
+
+        // Table directory, then the tables, each 4-byte aligned
+        let count = tables.len() as u16;
+        let selector = 15 - count.leading_zeros() as u16;
+        let search_range = (1u16 << selector) * 16;
+        let mut out = Vec::new();
+        out.extend_from_slice(&0x0001_0000u32.to_be_bytes());
+        out.extend_from_slice(&count.to_be_bytes());
+        out.extend_from_slice(&search_range.to_be_bytes());
+        out.extend_from_slice(&selector.to_be_bytes());
+        out.extend_from_slice(&(count * 16 - search_range).to_be_bytes());
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, bytes) in &tables {
+            out.extend_from_slice(*tag);
+            out.extend_from_slice(&checksum(bytes).to_be_bytes());
+            out.extend_from_slice(&(offset as u32).to_be_bytes());
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            offset += bytes.len().div_ceil(4) * 4;
+        }
+        let mut head_offset = 0;
+        for (tag, bytes) in &tables {
+            if *tag == b"head" {
+                head_offset = out.len();
+            }
+            out.extend_from_slice(bytes);
+            out.resize(out.len().div_ceil(4) * 4, 0);
+        }
+        let adjustment = 0xB1B0_AFBAu32.wrapping_sub(checksum(&out));
+        out[head_offset + 8..head_offset + 12].copy_from_slice(&adjustment.to_be_bytes());
+        Ok(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Assemble a font file from its tables
+    fn build_font(tables: &[(&[u8; 4], Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![0, 1, 0, 0];
+        out.extend_from_slice(&(tables.len() as u16).to_be_bytes());
+        out.extend_from_slice(&[0; 6]);
+        let mut offset = 12 + tables.len() * 16;
+        for (tag, bytes) in tables {
+            out.extend_from_slice(*tag);
+            out.extend_from_slice(&[0; 4]);
+            out.extend_from_slice(&(offset as u32).to_be_bytes());
+            out.extend_from_slice(&(bytes.len() as u32).to_be_bytes());
+            offset += bytes.len();
+        }
+        for (_, bytes) in tables {
+            out.extend_from_slice(bytes);
+        }
+        out
+    }
+
+    fn words(values: &[u16]) -> Vec<u8> {
+        values.iter().flat_map(|v| v.to_be_bytes()).collect()
+    }
+
+    #[test]
+    fn test_cmap_metrics_and_subset() {
+        // Glyphs: 0 missing, 1 "Ж" (simple), 2 "З" (composite of 1 and 3),
+        // 3 without a character (simple), 4 "A" (simple, left out)
+        let simple = |marker: u16| words(&[1, 0, 0, 100, 100, marker, 0, 0]);
+        let composite = [
+            words(&[0xFFFF, 0, 0, 100, 100]),
+            words(&[ARG_1_AND_2_ARE_WORDS | MORE_COMPONENTS, 1, 0, 0]),
+            words(&[0, 3, 0]),
+        ]
+        .concat();
+        let outlines = [vec![], simple(1), composite, simple(3), simple(4)];
+        let mut glyf = Vec::new();
+        let mut loca = vec![0u16];
+        for outline in &outlines {
+            glyf.extend_from_slice(outline);
+            loca.push((glyf.len() / 2) as u16);
+        }
+
+        let mut head = vec![0u8; 54];
+        head[18..20].copy_from_slice(&2048u16.to_be_bytes());
+        head[40..42].copy_from_slice(&1900i16.to_be_bytes());
+        let mut hhea = vec![0u8; 36];
+        hhea[4..6].copy_from_slice(&1600i16.to_be_bytes());
+        hhea[34..36].copy_from_slice(&2u16.to_be_bytes());
+        let maxp = words(&[0, 0x5000, 5]);
+        let hmtx = words(&[1024, 0, 1536, 0, 0, 0, 0]);
+        // Format 4: A alone, Ж З in one segment, then the closing segment
+        let cmap = [
+            words(&[0, 1, 3, 1, 0, 12]),
+            words(&[4, 40, 0, 6, 4, 1, 2]),
+            words(&[0x0041, 0x0417, 0xFFFF, 0]),
+            words(&[0x0041, 0x0416, 0xFFFF]),
+            words(&[4u16.wrapping_sub(0x41), 1u16.wrapping_sub(0x416), 1]),
+            words(&[0, 0, 0]),
+        ]
+        .concat();
+
+        let data = build_font(&[
+            (b"cmap", cmap),
+            (b"glyf", glyf),
+            (b"head", head),
+            (b"hhea", hhea),
+            (b"hmtx", hmtx),
+            (b"loca", words(&loca)),
+            (b"maxp", maxp),
+        ]);
+        let font = Font::parse(data).unwrap();
+        assert_eq!(font.glyph('Ж'), Some(1));
+        assert_eq!(font.glyph('З'), Some(2));
+        assert_eq!(font.glyph('A'), Some(4));
+        assert_eq!(font.glyph('Б'), None);
+        assert_eq!(font.advance(1), 750.0);
+        // Glyphs past the last metric share its advance
+        assert_eq!(font.advance(4), 750.0);
+        assert_eq!(font.scale(font.ascent), 781.25);
+
+        let bytes = font.subset(&BTreeSet::from([2])).unwrap();
+        assert_eq!(checksum(&bytes), 0xB1B0_AFBA);
+        let mut tables = HashMap::new();
+        for i in 0..u16_at(&bytes, 4).unwrap() as usize {
+            let record = 12 + i * 16;
+            let tag: [u8; 4] = bytes[record..record + 4].try_into().unwrap();
+            let offset = u32_at(&bytes, record + 8).unwrap() as usize;
+            let length = u32_at(&bytes, record + 12).unwrap() as usize;
+            tables.insert(tag, bytes[offset..offset + length].to_vec());
+        }
+        assert_eq!(i16_at(&tables[b"head"], 50).unwrap(), 1);
+        let loca: Vec<u32> = (0..6)
+            .map(|i| u32_at(&tables[b"loca"], i * 4).unwrap())
+            .collect();
+        let sizes: Vec<u32> = loca.windows(2).map(|w| w[1] - w[0]).collect();
+        // The composite glyph keeps its parts; the unused "A" is dropped
+        assert_eq!(sizes, [0, 16, 24, 16, 0]);
+    }
+}
@@ -0,0 +1,360 @@
+// Folder Watcher Module
+// Watches the folder open in each window and emits debounced
+// `folder-changed` events to that window, so the sidebar and the open
+// document follow changes made by other programs (git pull, sync clients).
+// Changes to entries the tree doesn't show are left out, by the same rules.
+
+use crate::file_filter::{self, FileFilter};
+use notify_debouncer_full::notify::event::{CreateKind, ModifyKind, RemoveKind, RenameMode};
+use notify_debouncer_full::notify::{Event, EventKind, RecommendedWatcher, RecursiveMode};
+use notify_debouncer_full::{new_debouncer, DebounceEventResult, Debouncer, RecommendedCache};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter};
+
+/// Quiet time before a burst of events (e.g. a checkout) is reported
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+pub type FolderWatcher = Debouncer<RecommendedWatcher, RecommendedCache>;
+
+/// Watchers by window label; dropping one stops it
+pub type FolderWatchers = Mutex<HashMap<String, FolderWatcher>>;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Created,
+    Removed,
+    Renamed,
+    Modified,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FsChange {
+    kind: ChangeKind,
+    path: String,
+    /// Previous path of a renamed entry
+    #[serde(rename = "oldPath")]
+    old_path: Option<String>,
+    #[serde(rename = "isDir")]
+    is_dir: bool,
+}
+
+/// Payload of the `folder-changed` event
+#[derive(Debug, Clone, Serialize)]
+pub struct FolderChanges {
+    folder: String,
+    changes: Vec<FsChange>,
+}
+
+/// Decides which changes the tree cares about, following the ignore rules
+/// of each folder from the opened one down
+pub struct WatchFilter {
+    root: PathBuf,
+    patterns: Vec<String>,
+    show_ignored: bool,
+    /// Filters for the entries of each folder that had changes
+    filters: HashMap<PathBuf, FileFilter>,
+}
+
+impl WatchFilter {
+    pub fn new(root: &Path, patterns: Vec<String>, show_ignored: bool) -> Self {
+        WatchFilter {
+            root: root.to_path_buf(),
+            patterns,
+            show_ignored,
+            filters: HashMap::new(),
+        }
+    }
+
+    /// Filter for the entries of `dir`, a folder below the root
+    fn filter(&mut self, dir: &Path) -> &FileFilter {
+        if !self.filters.contains_key(dir) {
+            let filter = match dir.parent() {
+                Some(parent) if dir != self.root => {
+                    let mut filter = self.filter(parent).clone();
+                    filter.enter(dir);
+                    filter
+                }
+                _ => FileFilter::new(&self.root, &self.patterns, self.show_ignored),
+            };
+            self.filters.insert(dir.to_path_buf(), filter);
+        }
+        &self.filters[dir]
+    }
+
+    /// Whether the tree shows `path`, and every folder on the way to it
+    fn is_visible(&mut self, path: &Path, is_dir: bool) -> bool {
+        let Ok(relative) = path.strip_prefix(&self.root) else {
+            return true;
+        };
+        let mut dir = self.root.clone();
+        for component in relative.components() {
+            let entry = dir.join(component);
+            let entry_is_dir = is_dir || entry != path;
+            if !self.filter(&dir).is_visible(&entry, entry_is_dir) {
+                return false;
+            }
+            dir = entry;
+        }
+        true
+    }
+
+    /// Whether a change to `path` is reported: visible entries, and the
+    /// ignore files of visible folders, which change what else is visible
+    fn reports(&mut self, path: &Path, is_dir: bool) -> bool {
+        if file_filter::is_ignore_file(path) {
+            return path.parent().is_some_and(|dir| self.is_visible(dir, true));
+        }
+        self.is_visible(path, is_dir)
+    }
+}
+
+/// Turn raw events into one change per path: a file created and then
+/// written is reported as created, one created and removed again not at all
+fn collect_changes(events: &[Event], filter: &mut WatchFilter) -> Vec<FsChange> {
+    // Edited ignore files are read again
+    if events
+        .iter()
+        .flat_map(|e| &e.paths)
+        .any(|p| file_filter::is_ignore_file(p))
+    {
+        filter.filters.clear();
+    }
+
+    let mut changes: Vec<Option<FsChange>> = Vec::new();
+    let mut by_path: HashMap<String, usize> = HashMap::new();
+
+    let mut record = |change: FsChange| {
+        let previous = by_path
+            .get(&change.path)
+            .and_then(|&i| changes[i].as_ref().map(|c| (i, c.kind)));
+        match (previous, change.kind) {
+            (Some((i, ChangeKind::Created)), ChangeKind::Removed) => changes[i] = None,
+            (Some(_), ChangeKind::Modified) => {}
+            (Some((i, _)), _) => changes[i] = Some(change),
+            (None, _) => {
+                by_path.insert(change.path.clone(), changes.len());
+                changes.push(Some(change));
+            }
+        }
+    };
+
+    for event in events {
+        let Some(path) = event.paths.first() else {
+            continue;
+        };
+        let is_dir = match event.kind {
+            EventKind::Create(CreateKind::Folder) | EventKind::Remove(RemoveKind::Folder) => true,
+            _ => path.is_dir(),
+        };
+        let change = |kind, path: &Path, is_dir: Option<bool>| FsChange {
+            kind,
+            path: path.to_string_lossy().to_string(),
+            old_path: None,
+            is_dir: is_dir.unwrap_or_else(|| path.is_dir()),
+        };
+
+        match event.kind {
+            EventKind::Modify(ModifyKind::Name(RenameMode::Both)) if event.paths.len() == 2 => {
+                let (from, to) = (&event.paths[0], &event.paths[1]);
+                let is_dir = to.is_dir();
+                match (filter.reports(from, is_dir), filter.reports(to, is_dir)) {
+                    (false, false) => {}
+                    // Renames into or out of hidden entries look like creation or removal
+                    (false, true) => record(change(ChangeKind::Created, to, None)),
+                    (true, false) => record(change(ChangeKind::Removed, from, Some(false))),
+                    (true, true) => record(FsChange {
+                        old_path: Some(from.to_string_lossy().to_string()),
+                        ..change(ChangeKind::Renamed, to, None)
+                    }),
+                }
+            }
+            _ if !filter.reports(path, is_dir) => {}
+            EventKind::Create(kind) => record(change(
+                ChangeKind::Created,
+                path,
+                (kind == CreateKind::Folder).then_some(true),
+            )),
+            EventKind::Remove(kind) => record(change(
+                ChangeKind::Removed,
+                path,
+                Some(kind == RemoveKind::Folder),
+            )),
+            EventKind::Modify(ModifyKind::Name(RenameMode::From)) => {
+                record(change(ChangeKind::Removed, path, Some(false)))
+            }
+            EventKind::Modify(ModifyKind::Name(_)) if path.exists() => {
+                record(change(ChangeKind::Created, path, None))
+            }
+            EventKind::Modify(ModifyKind::Name(_)) => {
+                record(change(ChangeKind::Removed, path, Some(false)))
+            }
+            // Permission and timestamp changes don't affect the tree or the contents
+            EventKind::Modify(ModifyKind::Metadata(_)) | EventKind::Access(_) => {}
+            EventKind::Modify(_) | EventKind::Any | EventKind::Other if path.is_file() => {
+                record(change(ChangeKind::Modified, path, Some(false)))
+            }
+            _ => {}
+        }
+    }
+
+    changes.into_iter().flatten().collect()
+}
+
+/// Start watching `folder` for the window `label`. Events stop when the
+/// returned watcher is dropped.
+pub fn watch(
+    app: AppHandle,
+    label: String,
+    folder: &Path,
+    mut filter: WatchFilter,
+) -> Result<FolderWatcher, String> {
+    let folder_name = folder.to_string_lossy().to_string();
+
+    let mut debouncer =
+        new_debouncer(
+            DEBOUNCE,
+            None,
+            move |result: DebounceEventResult| match result {
+                Ok(events) => {
+                    let events: Vec<Event> = events.into_iter().map(|e| e.event).collect();
+                    let changes = collect_changes(&events, &mut filter);
+                    if changes.is_empty() {
+                        return;
+                    }
+                    let payload = FolderChanges {
+                        folder: folder_name.clone(),
+                        changes,
+                    };
+                    if let Err(e) = app.emit_to(&label, "folder-changed", payload) {
+                        log::error!("Failed to emit folder changes to {}: {}", label, e);
+                    }
+                }
+                Err(errors) => {
+                    for error in errors {
+                        log::warn!("Folder watcher error: {}", error);
+                    }
+                }
+            },
+        )
+        .map_err(|e| format!("Failed to create folder watcher: {}", e))?;
+
+    debouncer
+        .watch(folder, RecursiveMode::Recursive)
+        .map_err(|e| format!("Failed to watch {}: {}", folder.display(), e))?;
+    Ok(debouncer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collect_changes() {
+        let root = std::env::temp_dir().join(format!("docura-watch-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join("notes/new.md"), "# New").unwrap();
+        std::fs::write(root.join("edited.md"), "edited").unwrap();
+
+        let event = |kind, paths: &[&str]| {
+            paths.iter().fold(Event::new(kind), |event, path| {
+                event.add_path(root.join(path))
+            })
+        };
+        let events = vec![
+            event(EventKind::Create(CreateKind::File), &["notes/new.md"]),
+            event(EventKind::Modify(ModifyKind::Any), &["notes/new.md"]),
+            event(EventKind::Create(CreateKind::File), &["scratch.md"]),
+            event(EventKind::Remove(RemoveKind::File), &["scratch.md"]),
+            event(EventKind::Modify(ModifyKind::Any), &["edited.md"]),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &["old.md", "notes"],
+            ),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &[".git/index.lock", ".git/index"],
+            ),
+            event(
+                EventKind::Modify(ModifyKind::Name(RenameMode::Both)),
+                &[".draft.md", "edited.md"],
+            ),
+            event(EventKind::Remove(RemoveKind::Folder), &["gone"]),
+        ];
+        let changes = collect_changes(&events, &mut WatchFilter::new(&root, Vec::new(), false));
+        std::fs::remove_dir_all(&root).ok();
+
+        let path = |p: &str| root.join(p).to_string_lossy().to_string();
+        let summary: Vec<(ChangeKind, String, bool)> = changes
+            .iter()
+            .map(|c| (c.kind, c.path.clone(), c.is_dir))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (ChangeKind::Created, path("notes/new.md"), false),
+                (ChangeKind::Created, path("edited.md"), false),
+                (ChangeKind::Renamed, path("notes"), true),
+                (ChangeKind::Removed, path("gone"), true),
+            ]
+        );
+        assert_eq!(changes[2].old_path, Some(path("old.md")));
+    }
+
+    #[test]
+    fn test_ignored_changes() {
+        let root =
+            std::env::temp_dir().join(format!("docura-watch-ignore-test-{}", std::process::id()));
+        std::fs::create_dir_all(root.join("build")).unwrap();
+        std::fs::create_dir_all(root.join("notes")).unwrap();
+        std::fs::write(root.join(".gitignore"), "build/\n*.tmp\n").unwrap();
+        std::fs::write(root.join("notes/.docuraignore"), "private.md\n").unwrap();
+
+        let event = |kind, path: &str| Event::new(kind).add_path(root.join(path));
+        let created = |path| event(EventKind::Create(CreateKind::File), path);
+        let path = |p: &str| root.join(p).to_string_lossy().to_string();
+        let paths =
+            |changes: Vec<FsChange>| changes.into_iter().map(|c| c.path).collect::<Vec<_>>();
+
+        let mut filter = WatchFilter::new(&root, vec!["drafts".to_string()], false);
+        let changes = collect_changes(
+            &[
+                created("build/index.html"),
+                created("notes/scratch.tmp"),
+                created("notes/private.md"),
+                created("drafts/idea.md"),
+                created("notes/.hidden.md"),
+                created("notes/public.md"),
+            ],
+            &mut filter,
+        );
+        assert_eq!(paths(changes), vec![path("notes/public.md")]);
+
+        // An edited ignore file is reported and applies to the next changes
+        std::fs::write(root.join(".gitignore"), "*.tmp\n").unwrap();
+        let changes = collect_changes(
+            &[
+                event(EventKind::Modify(ModifyKind::Any), ".gitignore"),
+                created("build/index.html"),
+            ],
+            &mut filter,
+        );
+        assert_eq!(
+            paths(changes),
+            vec![path(".gitignore"), path("build/index.html")]
+        );
+
+        // Nothing is left out when hidden and ignored entries are shown
+        let mut filter = WatchFilter::new(&root, vec!["drafts".to_string()], true);
+        let changes = collect_changes(
+            &[created("drafts/idea.md"), created(".git/index")],
+            &mut filter,
+        );
+        std::fs::remove_dir_all(&root).ok();
+        assert_eq!(paths(changes), vec![path("drafts/idea.md")]);
+    }
+}
@@ -2,8 +2,8 @@ import React, { useState, useEffect, useRef } from "react";
 import { invoke } from "@tauri-apps/api/core";
 import { getCurrentWindow } from "@tauri-apps/api/window";
 import { listen } from "@tauri-apps/api/event";
-import { open, save } from "@tauri-apps/plugin-dialog";
-import { writeTextFile, readTextFile } from "@tauri-apps/plugin-fs";
+import { ask, open, save } from "@tauri-apps/plugin-dialog";
+import { writeTextFile } from "@tauri-apps/plugin-fs";
 import toast, { Toaster } from "react-hot-toast";
 import Toolbar from "./components/Toolbar";
 import Sidebar from "./components/Sidebar";
@@ -15,12 +15,16 @@ import FolderSwitchDialog from "./components/FolderSwitchDialog";
 import QuickOpenDialog from "./components/QuickOpenDialog";
 import ContextMenu from "./components/ContextMenu";
 import UnsavedChangesDialog from "./components/UnsavedChangesDialog";
+import LinkUpdatesDialog from "./components/LinkUpdatesDialog";
 import RecoveryDialog from "./components/RecoveryDialog";
 import StatusBar from "./components/StatusBar";
 import OAuthDialog from "./components/OAuthDialog";
 import DropboxFilesDialog from "./components/DropboxFilesDialog";
 import AboutDialog from "./components/AboutDialog";
-import { exportToPDF, generatePDFBlob } from "./utils/pdfExport";
+import HistoryDialog from "./components/HistoryDialog";
+import GitHistoryDialog from "./components/GitHistoryDialog";
+import SaveConflictDialog from "./components/SaveConflictDialog";
+import { generatePDFBlob } from "./utils/pdfExport";
 import { convertMarkdownImagePaths } from "./utils/imagePathConverter";
 import { isOmakaseEnvironment, syncWithOmakase } from "./utils/omakaseSync";
 import { isPlasmaEnvironment, syncWithPlasma } from "./utils/plasmaSync";
@@ -49,6 +53,14 @@ function App() {
   const [currentFile, setCurrentFile] = useState(null);
   const [currentFolder, setCurrentFolder] = useState(null);
   const [files, setFiles] = useState([]);
+  const [folderChanges, setFolderChanges] = useState([]);
+  const [showIgnoredFiles, setShowIgnoredFiles] = useState(false);
+  const [ignorePatterns, setIgnorePatterns] = useState([]);
+  // Extensions opened as text besides the built-in ones
+  const [textExtensions, setTextExtensions] = useState([]);
+  const [historyRetention, setHistoryRetention] = useState(null);
+  const [treeSort, setTreeSort] = useState("name");
+  const folderFilesRequestRef = useRef(0);
   const [fileContent, setFileContent] = useState("");
   const [displayContent, setDisplayContent] = useState(""); // Content with converted image paths for display
   const [originalContent, setOriginalContent] = useState(""); // Track original content for unsaved changes
@@ -62,6 +74,8 @@ function App() {
   const [pdfBlob, setPdfBlob] = useState(null);
   const [isSettingsOpen, setIsSettingsOpen] = useState(false);
   const [isAboutOpen, setIsAboutOpen] = useState(false);
+  const [isHistoryOpen, setIsHistoryOpen] = useState(false);
+  const [isGitHistoryOpen, setIsGitHistoryOpen] = useState(false);
   const [omakaseAvailable, setOmakaseAvailable] = useState(false);
   const [omakaseSyncEnabled, setOmakaseSyncEnabled] = useState(false);
   const [plasmaAvailable, setPlasmaAvailable] = useState(false);
@@ -89,12 +103,14 @@ function App() {
     onDontSave: null,
     onCancel: null,
   });
-  const [recoveryDialog, setRecoveryDialog] = useState({
-    visible: false,
-    tempFile: null,
-  });
-  const [currentTempId, setCurrentTempId] = useState(null); // Track temp file ID for unsaved files
+  // Links to rewrite before a rename or move: { plan, resolve }
+  const [linkUpdatesDialog, setLinkUpdatesDialog] = useState(null);
+  const [saveConflictDialog, setSaveConflictDialog] = useState(null);
+  // Unsaved buffers of a crashed session, offered for recovery
+  const [recoverableBuffers, setRecoverableBuffers] = useState([]);
   const [recoveryChecked, setRecoveryChecked] = useState(false); // Track if we've already checked for recovery
+  // Cursor and scroll position to put the editor at, after a recovery
+  const [restoreView, setRestoreView] = useState(null);
   const [editorSettings, setEditorSettings] = useState({
     indentation: "2",
     lineEnding: "LF",
@@ -113,12 +129,23 @@ function App() {
   const previewRef = useRef(null);
   const syncIntervalRef = useRef(null);
   const autoSaveTimeoutRef = useRef(null);
-  const tempSaveTimeoutRef = useRef(null);
+  const journalTimeoutRef = useRef(null);
   const isQuittingRef = useRef(false); // Use ref instead of state for immediate access
   const hasUnsavedChangesRef = useRef(hasUnsavedChanges);
   const fileContentRef = useRef(fileContent);
   const isEditingRef = useRef(isEditing);
-  const currentTempIdRef = useRef(currentTempId);
+  // This window's buffer in the session journal, assigned on first update;
+  // the generation changes whenever the buffer is closed
+  const sessionBufferIdRef = useRef(null);
+  const sessionGenerationRef = useRef(0);
+  const editorScrollRef = useRef(0);
+  const currentFileRef = useRef(currentFile);
+  // Encoding and line endings of the open file, written back on save;
+  // null for new files, which are saved as UTF-8 with \n line endings
+  const fileFormatRef = useRef(null);
+  // Version of the open file on disk when it was read or last saved; a save
+  // is refused if the file no longer matches it
+  const fileVersionRef = useRef(null);
   const showUnsavedChangesDialogRef = useRef(null);
 
   // Available themes for random cycling
@@ -168,9 +195,10 @@ function App() {
     loadDropboxStatus();
     loadSyncFolders();
 
-    // Check for temp files (crash recovery) - ONLY ONCE on mount
-    if (!recoveryChecked) {
-      loadTempFilesOnStartup();
+    // Check for unsaved buffers of a crashed session - ONLY ONCE on mount,
+    // and only in the first window: later ones open while it runs
+    if (!recoveryChecked && getCurrentWindow().label === "main") {
+      loadRecoverableBuffers();
       setRecoveryChecked(true);
     }
 
@@ -203,21 +231,18 @@ function App() {
             // Grant file scope first
             await invoke("grant_file_scope", { filePath: path });
 
-            // Try to get folder files - if this succeeds, it's a directory
+            // Try to list it as a folder - if this succeeds, it's a directory
             try {
-              const folderFiles = await invoke("get_folder_files", {
-                folderPath: path,
-              });
+              await invoke("list_directory", { dirPath: path, limit: 1 });
               console.log("📁 CLI: Opening folder:", path);
-              console.log(`📂 Got ${folderFiles.length} files`);
 
               setCurrentFolder(path);
-              setFiles(folderFiles);
+              loadFolderFiles(path);
               toast.success(`Opened folder: ${path.split("/").pop()}`);
             } catch (folderError) {
               // Not a folder, try as file
               console.log("📄 CLI: Opening file:", path);
-              const content = await readTextFile(path);
+              const content = await readDocument(path);
               setCurrentFile(path);
               setFileContent(content);
               setOriginalContent(content);
@@ -288,17 +313,8 @@ function App() {
           const choice = await showUnsavedChangesDialogRef.current();
 
           if (choice === "saved" || choice === "dont-save") {
-            // User chose to save or discard - clean up temp file if exists
-            if (currentTempIdRef.current) {
-              try {
-                await invoke("delete_temp_file", {
-                  tempId: currentTempIdRef.current,
-                });
-                console.log("🗑️ Deleted temp file on app close");
-              } catch (error) {
-                console.error("Failed to delete temp file:", error);
-              }
-            }
+            // User chose to save or discard - nothing left to recover
+            await closeSessionBuffer();
 
             // Set flag to allow close, then use our custom quit command
             isQuittingRef.current = true;
@@ -394,10 +410,11 @@ function App() {
       autoSaveTimeoutRef.current = setTimeout(async () => {
         try {
           setIsAutoSaving(true);
-          await writeTextFile(currentFile, fileContent);
+          await writeDocument(currentFile, fileContent);
           setOriginalContent(fileContent);
           setHasUnsavedChanges(false);
           console.log("✅ Auto-saved:", currentFile);
+          updateFolderIndexes(currentFile);
 
           // Show a subtle toast
           toast.success("Auto-saved", {
@@ -418,7 +435,15 @@ function App() {
           }
         } catch (error) {
           console.error("Auto-save failed:", error);
-          toast.error("Auto-save failed");
+          if (error?.kind === "changedOnDisk") {
+            // Leave the choice to an explicit save
+            toast.error(
+              `${currentFile.split("/").pop()} changed on disk; save to merge or overwrite`,
+              { id: "save-conflict" }
+            );
+          } else {
+            toast.error("Auto-save failed");
+          }
         } finally {
           setIsAutoSaving(false);
         }
@@ -441,39 +466,66 @@ function App() {
   ]);
 
   useEffect(() => {
-    // Temp file auto-save for untitled files (crash recovery)
-    if (!currentFile && fileContent !== "" && isEditing) {
-      // Clear any pending temp save
-      if (tempSaveTimeoutRef.current) {
-        clearTimeout(tempSaveTimeoutRef.current);
-      }
-
-      // Save to temp after 2 seconds of inactivity
-      tempSaveTimeoutRef.current = setTimeout(async () => {
-        try {
-          const tempId = await invoke("save_temp_file", {
-            content: fileContent,
-            tempId: currentTempId,
-          });
-
-          if (!currentTempId) {
-            setCurrentTempId(tempId);
-            console.log("💾 Created temp file:", tempId);
-          } else {
-            console.log("💾 Updated temp file:", tempId);
-          }
-        } catch (error) {
-          console.error("Failed to save temp file:", error);
+    // Keep this window's buffer in the session journal (crash recovery):
+    // its file, cursor, scroll position and, while unsaved, its text
+    const hasBuffer = currentFile || (fileContent !== "" && isEditing);
+    if (!hasBuffer) return;
+
+    if (journalTimeoutRef.current) {
+      clearTimeout(journalTimeoutRef.current);
+    }
+    journalTimeoutRef.current = setTimeout(async () => {
+      // An untitled buffer is unsaved whatever it holds
+      const dirty = hasUnsavedChanges || !currentFile;
+      const generation = sessionGenerationRef.current;
+      try {
+        const bufferId = await invoke("update_session_buffer", {
+          buffer: {
+            id: sessionBufferIdRef.current ?? "",
+            path: currentFile,
+            windowLabel: getCurrentWindow().label,
+            line: cursorPosition.line,
+            column: cursorPosition.column,
+            scrollTop: editorScrollRef.current,
+            dirty,
+            base: currentFile ? fileVersionRef.current : null,
+          },
+          content: dirty ? fileContent : null,
+        });
+        if (generation === sessionGenerationRef.current) {
+          sessionBufferIdRef.current = bufferId;
+        } else {
+          // Closed while this update was on its way
+          await invoke("close_session_buffer", { bufferId });
         }
-      }, 2000);
-    }
+      } catch (error) {
+        console.error("Failed to update session journal:", error);
+      }
+    }, 1000);
 
     return () => {
-      if (tempSaveTimeoutRef.current) {
-        clearTimeout(tempSaveTimeoutRef.current);
+      if (journalTimeoutRef.current) {
+        clearTimeout(journalTimeoutRef.current);
       }
     };
-  }, [fileContent, currentFile, isEditing, currentTempId]);
+  }, [fileContent, currentFile, isEditing, hasUnsavedChanges, cursorPosition]);
+
+  // Drop this window's buffer from the session journal, when it was saved
+  // or discarded and another one takes its place
+  const closeSessionBuffer = async () => {
+    if (journalTimeoutRef.current) {
+      clearTimeout(journalTimeoutRef.current);
+    }
+    sessionGenerationRef.current += 1;
+    const bufferId = sessionBufferIdRef.current;
+    sessionBufferIdRef.current = null;
+    if (!bufferId) return;
+    try {
+      await invoke("close_session_buffer", { bufferId });
+    } catch (error) {
+      console.error("Failed to close session buffer:", error);
+    }
+  };
 
   useEffect(() => {
     // Convert image paths when content or file changes
@@ -692,8 +744,43 @@ function App() {
   }, [isEditing]);
 
   useEffect(() => {
-    currentTempIdRef.current = currentTempId;
-  }, [currentTempId]);
+    currentFileRef.current = currentFile;
+  }, [currentFile]);
+
+  // Windows opened to recover a buffer are told which one once loaded
+  useEffect(() => {
+    const unlistenPromise = getCurrentWindow().listen(
+      "recover-buffer",
+      (event) => restoreBuffer(event.payload),
+    );
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  // Watch the open folder so the sidebar and the open file follow changes
+  // made by other programs (git pull, sync clients, ...). It leaves out what
+  // the tree hides, so it starts over when the ignore settings change
+  useEffect(() => {
+    if (!currentFolder) {
+      invoke("unwatch_folder").catch((error) =>
+        console.error("Failed to stop watching folder:", error),
+      );
+      return;
+    }
+
+    const unlistenPromise = getCurrentWindow().listen(
+      "folder-changed",
+      (event) => handleFolderChanges(event.payload),
+    );
+    invoke("watch_folder", { folderPath: currentFolder }).catch((error) =>
+      console.error("Failed to watch folder:", error),
+    );
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [currentFolder, showIgnoredFiles, ignorePatterns]);
 
   const checkThemeProviders = async () => {
     // Check Omakase/Omarchy
@@ -766,6 +853,21 @@ function App() {
       if (config && config.dropbox_sync_enabled !== undefined) {
         setDropboxSyncEnabled(config.dropbox_sync_enabled);
       }
+      if (config && config.ignore_patterns) {
+        setIgnorePatterns(config.ignore_patterns);
+      }
+      if (config && config.show_ignored_files !== undefined) {
+        setShowIgnoredFiles(config.show_ignored_files);
+      }
+      if (config && config.tree_sort) {
+        setTreeSort(config.tree_sort);
+      }
+      if (config && config.text_extensions) {
+        setTextExtensions(config.text_extensions);
+      }
+      if (config && config.history_retention) {
+        setHistoryRetention(config.history_retention);
+      }
     } catch (error) {
       console.error("Error loading config:", error);
     }
@@ -804,6 +906,11 @@ function App() {
           plasma_sync: plasmaSync,
           auto_save: autoSave,
           live_editor_type: liveEditor,
+          ignore_patterns: ignorePatterns,
+          show_ignored_files: showIgnoredFiles,
+          tree_sort: treeSort,
+          text_extensions: textExtensions,
+          history_retention: historyRetention || undefined,
           recent_items: [],
         },
       });
@@ -841,106 +948,112 @@ function App() {
     }
   };
 
-  const loadTempFilesOnStartup = async () => {
+  const loadRecoverableBuffers = async () => {
     try {
-      const tempFiles = await invoke("load_temp_files");
-
-      if (tempFiles && tempFiles.length > 0) {
+      const buffers = await invoke("list_recoverable_buffers");
+      if (buffers.length > 0) {
         console.log(
-          `🔄 Found ${tempFiles.length} unsaved file(s) from previous session`,
+          `🔄 Found ${buffers.length} unsaved buffer(s) from a previous session`,
         );
-
-        // Get the most recent temp file
-        const mostRecent = tempFiles[0];
-
-        // Show recovery dialog instead of auto-recovering
-        setRecoveryDialog({
-          visible: true,
-          tempFile: mostRecent,
-          allTempFiles: tempFiles, // Keep reference to all temp files for cleanup
-        });
       }
+      setRecoverableBuffers(buffers);
     } catch (error) {
-      console.error("Error loading temp files:", error);
+      console.error("Error loading recoverable buffers:", error);
     }
   };
 
-  const handleRecoverWork = async () => {
-    try {
-      const { tempFile, allTempFiles } = recoveryDialog;
-
-      // Load the recovered content
-      setCurrentTempId(tempFile.id);
-      setFileContent(tempFile.content);
-      setOriginalContent(tempFile.content);
-      setIsEditing(true);
-      extractHeaders(tempFile.content);
-
-      // Add to sidebar
-      setFiles([
-        {
-          name: getSmartFileName(tempFile.content),
-          path: null,
-          type: "file",
-          isUntitled: true,
-        },
-      ]);
-
-      // Delete the temp file (it's now being edited, will create new temp if needed)
+  // Put a recovered buffer in this window, or in a new one when this
+  // window already holds a document
+  const restoreBuffer = async (bufferId) => {
+    const windowIsFree =
+      !currentFileRef.current &&
+      !hasUnsavedChangesRef.current &&
+      fileContentRef.current === "";
+    if (!windowIsFree) {
       try {
-        await invoke("delete_temp_file", { tempId: tempFile.id });
-        console.log("🗑️ Deleted temp file after recovery");
+        await invoke("open_new_window", { recoverBuffer: bufferId });
+        setRecoverableBuffers((buffers) =>
+          buffers.filter((buffer) => buffer.id !== bufferId),
+        );
       } catch (error) {
-        console.error("Failed to delete temp file:", error);
+        console.error("Failed to open window for recovery:", error);
+        toast.error("Failed to open a window for the recovered buffer");
       }
+      return;
+    }
 
-      // Clean up other old temp files
-      if (allTempFiles && allTempFiles.length > 1) {
-        for (let i = 1; i < allTempFiles.length; i++) {
-          try {
-            await invoke("delete_temp_file", { tempId: allTempFiles[i].id });
-          } catch (error) {
-            console.error("Failed to delete old temp file:", error);
-          }
+    try {
+      const buffer = await invoke("restore_session_buffer", { bufferId });
+      setRecoverableBuffers((buffers) =>
+        buffers.filter((other) => other.id !== bufferId),
+      );
+
+      // The text on disk stays the original, so the recovered changes show
+      // as unsaved; saving over a file changed since asks first
+      let original = "";
+      if (buffer.path) {
+        try {
+          original = await readDocument(buffer.path);
+        } catch (error) {
+          console.warn("Recovered file is no longer readable:", error);
+          fileFormatRef.current = null;
         }
+        fileVersionRef.current = buffer.base;
+      } else {
+        fileFormatRef.current = null;
+        fileVersionRef.current = null;
       }
 
-      // Close dialog
-      setRecoveryDialog({ visible: false, tempFile: null });
+      sessionGenerationRef.current += 1;
+      sessionBufferIdRef.current = buffer.id;
+      setCurrentFile(buffer.path);
+      setFileContent(buffer.content);
+      setOriginalContent(original);
+      setIsEditing(true);
+      extractHeaders(buffer.content);
+      setActiveTab("code");
+      setRestoreView({
+        line: buffer.line,
+        column: buffer.column,
+        scrollTop: buffer.scrollTop,
+      });
+      if (!buffer.path) {
+        setFiles([
+          {
+            name: getSmartFileName(buffer.content),
+            path: null,
+            type: "file",
+            isUntitled: true,
+          },
+        ]);
+      }
 
-      // Show success toast
       toast.success("✨ Work recovered successfully!", {
         duration: 3000,
         icon: "📂",
       });
+      if (buffer.fileChanged) {
+        toast(
+          "The file changed on disk since; saving will ask before overwriting it",
+          { icon: "⚠️" },
+        );
+      }
     } catch (error) {
-      console.error("Error recovering work:", error);
+      console.error("Error recovering buffer:", error);
       toast.error("Failed to recover work");
     }
   };
 
-  const handleDiscardRecovery = async () => {
+  const discardBuffer = async (bufferId) => {
     try {
-      const { allTempFiles } = recoveryDialog;
-
-      // Delete all temp files
-      if (allTempFiles && allTempFiles.length > 0) {
-        for (const tempFile of allTempFiles) {
-          try {
-            await invoke("delete_temp_file", { tempId: tempFile.id });
-            console.log("🗑️ Deleted temp file:", tempFile.id);
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-        }
-      }
-
-      // Close dialog
-      setRecoveryDialog({ visible: false, tempFile: null });
-
-      console.log("❌ User chose to discard recovered work");
+      await invoke("close_session_buffer", { bufferId });
+      setRecoverableBuffers((buffers) =>
+        buffers.filter((buffer) => buffer.id !== bufferId),
+      );
+      console.log("❌ User chose to discard recovered buffer", bufferId);
     } catch (error) {
-      console.error("Error discarding recovery:", error);
+      console.error("Error discarding recovered buffer:", error);
+      toast.error("Failed to discard recovered work");
     }
   };
 
@@ -1009,8 +1122,8 @@ function App() {
             await saveFileAs();
             resolve(currentFile ? "saved" : "cancelled");
           } else {
-            await saveFile();
-            resolve("saved");
+            // Cancelled if the file changed on disk and the user kept it
+            resolve((await saveFile()) ? "saved" : "cancelled");
           }
         },
         onDontSave: () => {
@@ -1049,18 +1162,13 @@ function App() {
         }
       }
 
-      // Delete old temp file if exists
-      if (currentTempId) {
-        try {
-          await invoke("delete_temp_file", { tempId: currentTempId });
-        } catch (error) {
-          console.error("Failed to delete temp file:", error);
-        }
-      }
+      // The old buffer was saved or discarded
+      await closeSessionBuffer();
 
       // Clear editor and state
       setCurrentFile(null);
-      setCurrentTempId(null);
+      fileFormatRef.current = null;
+      fileVersionRef.current = null;
       setFileContent("");
       setOriginalContent("");
       setDisplayContent("");
@@ -1096,19 +1204,11 @@ function App() {
         }
       }
 
-      // Delete temp file if exists
-      if (currentTempId) {
-        try {
-          await invoke("delete_temp_file", { tempId: currentTempId });
-          console.log("🗑️ Deleted temp file on close");
-        } catch (error) {
-          console.error("Failed to delete temp file:", error);
-        }
-      }
+      // The buffer was saved or discarded
+      await closeSessionBuffer();
 
       // Clear editor and state (back to welcome screen)
       setCurrentFile(null);
-      setCurrentTempId(null);
       setFileContent("");
       setOriginalContent("");
       setDisplayContent("");
@@ -1142,29 +1242,13 @@ function App() {
         }
 
         console.log("🔧 User chose:", choice);
-
-        // If user chose to save or discard, clean up temp file
-        if (currentTempId) {
-          try {
-            await invoke("delete_temp_file", { tempId: currentTempId });
-            console.log("🗑️ Deleted temp file on quit");
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-        }
       } else {
         console.log("🔧 No unsaved changes, proceeding with quit");
-        // No unsaved changes, just clean up temp file if exists
-        if (currentTempId) {
-          try {
-            await invoke("delete_temp_file", { tempId: currentTempId });
-            console.log("🗑️ Deleted temp file on quit");
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-        }
       }
 
+      // Saved, discarded or never changed: nothing left to recover
+      await closeSessionBuffer();
+
       // Set flag to bypass window close handler (using ref for immediate access)
       isQuittingRef.current = true;
       console.log("🚪 Quitting app... (flag set to true)");
@@ -1225,14 +1309,11 @@ function App() {
         console.warn("⚠️ Failed to grant file scope:", scopeError);
       }
 
-      console.log("📂 Getting folder files...");
-      const folderFiles = await invoke("get_folder_files", {
-        folderPath,
-      });
-      console.log(`📄 Found ${folderFiles.length} files`);
-
+      // The sidebar lists the folder itself, one level at a time
+      await invoke("list_directory", { dirPath: folderPath, limit: 1 });
       setCurrentFolder(folderPath);
-      setFiles(folderFiles);
+      setFiles([]);
+      loadFolderFiles(folderPath);
 
       // Add to recent items
       await addRecentItem(folderPath, "folder");
@@ -1245,6 +1326,21 @@ function App() {
     }
   };
 
+  // The complete file list (for quick open) can take a while in big
+  // folders, so it is read in the background after the folder is shown
+  const loadFolderFiles = (folderPath) => {
+    console.log("📂 Getting folder files...");
+    const request = ++folderFilesRequestRef.current;
+    invoke("get_folder_files", { folderPath })
+      .then((folderFiles) => {
+        // Another folder was opened while this one was being read
+        if (request !== folderFilesRequestRef.current) return;
+        console.log(`📄 Found ${folderFiles.length} files`);
+        setFiles(folderFiles);
+      })
+      .catch((error) => console.error("Failed to get folder files:", error));
+  };
+
   const handleFolderSwitchChoice = async (choice) => {
     if (choice === "new-window") {
       // Open in new window
@@ -1281,6 +1377,21 @@ function App() {
             name: "Markdown",
             extensions: ["md", "markdown", "mdown", "mkdn", "mdx"],
           },
+          {
+            name: "Text",
+            extensions: [
+              "txt",
+              "text",
+              "rst",
+              "org",
+              "qmd",
+              "rmd",
+              "Rmd",
+              "adoc",
+              "asciidoc",
+              ...textExtensions,
+            ],
+          },
         ],
       });
 
@@ -1292,18 +1403,10 @@ function App() {
           console.warn("Failed to grant file scope:", scopeError);
         }
 
-        const content = await readTextFile(selected);
+        const content = await readDocument(selected);
 
-        // Clean up temp file if switching from untitled
-        if (currentTempId) {
-          try {
-            await invoke("delete_temp_file", { tempId: currentTempId });
-            console.log("🗑️ Deleted temp file when opening new file");
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-          setCurrentTempId(null);
-        }
+        // The buffer being replaced was saved or discarded
+        await closeSessionBuffer();
 
         setCurrentFile(selected);
         setFileContent(content);
@@ -1338,6 +1441,250 @@ function App() {
     }
   };
 
+  // Follow the open file when it (or a folder above it) is renamed or moved
+  const followMovedPath = (oldPath, newPath) => {
+    const openFile = currentFileRef.current;
+    if (!openFile) return;
+    if (oldPath === openFile) {
+      setCurrentFile(newPath);
+    } else if (openFile.startsWith(`${oldPath}/`)) {
+      setCurrentFile(newPath + openFile.slice(oldPath.length));
+    }
+  };
+
+  // Ask whether to rewrite the links a rename or move would break.
+  // Resolves to "update", "skip" or "cancel".
+  const confirmLinkUpdates = (plan) => {
+    return new Promise((resolve) => {
+      setLinkUpdatesDialog({
+        plan,
+        resolve: (choice) => {
+          setLinkUpdatesDialog(null);
+          resolve(choice);
+        },
+      });
+    });
+  };
+
+  // Results of the sidebar's file commands; the watcher reports them too,
+  // but acting right away keeps the editor in step with the tree
+  const handleFileOperation = ({ kind, item, oldPath }) => {
+    if (kind === "create" && item.type === "file") {
+      selectFile(item.path);
+    } else if (kind === "rename" || kind === "move") {
+      followMovedPath(oldPath, item.path);
+    } else if (kind === "duplicate") {
+      toast.success(`Created ${item.name}`);
+    } else if (kind === "trash") {
+      toast.success(`Moved ${oldPath.split("/").pop()} to the trash`);
+      const openFile = currentFileRef.current;
+      const trashedOpenFile =
+        openFile && (openFile === oldPath || openFile.startsWith(`${oldPath}/`));
+      if (trashedOpenFile && !hasUnsavedChangesRef.current) {
+        currentFileRef.current = null;
+        setCurrentFile(null);
+        setFileContent("");
+        setOriginalContent("");
+        setOutlineHeaders([]);
+      }
+    }
+    if (currentFolder) {
+      loadFolderFiles(currentFolder);
+    }
+  };
+
+  const handleFolderChanges = async ({ folder, changes }) => {
+    setFolderChanges(changes);
+    loadFolderFiles(folder);
+
+    const openFile = currentFileRef.current;
+    if (!openFile) return;
+    const fileName = openFile.split("/").pop();
+
+    for (const change of changes) {
+      if (change.kind === "renamed") {
+        followMovedPath(change.oldPath, change.path);
+        continue;
+      }
+
+      if (change.path !== openFile) continue;
+
+      if (change.kind === "removed") {
+        // Keep the buffer so saving writes the file back
+        setHasUnsavedChanges(true);
+        toast.error(`${fileName} was deleted on disk`);
+      } else {
+        try {
+          const document = await invoke("read_document", { filePath: openFile });
+          // Our own saves come back as changes with the same content
+          if (document.content === fileContentRef.current) continue;
+
+          if (hasUnsavedChangesRef.current) {
+            toast(`${fileName} changed on disk; saving will ask before overwriting it`, {
+              icon: "⚠️",
+            });
+          } else {
+            const content = adoptDocument(document);
+            setFileContent(content);
+            setOriginalContent(content);
+            extractHeaders(content);
+            toast(`Reloaded ${fileName}`, { icon: "🔄", duration: 1500 });
+          }
+        } catch (error) {
+          console.error("Failed to reload changed file:", error);
+        }
+      }
+    }
+  };
+
+  // Make a document read by the backend the open one: remember its
+  // encoding, line endings and version for saving it back
+  const adoptDocument = ({ content, version, ...format }) => {
+    fileFormatRef.current = format;
+    fileVersionRef.current = version;
+    return content;
+  };
+
+  const readDocument = async (filePath) => {
+    const document = await invoke("read_document", { filePath });
+    if (document.encodingGuessed) {
+      toast(`${filePath.split("/").pop()} isn't UTF-8; read as Windows-1252, which may be wrong`, {
+        id: `encoding-${filePath}`,
+        icon: "⚠️",
+        duration: 5000,
+      });
+    }
+    return adoptDocument(document);
+  };
+
+  // Show the open file as it is on disk, e.g. after restoring a version
+  const reloadCurrentFile = async () => {
+    const openFile = currentFileRef.current;
+    if (!openFile) return;
+    try {
+      const content = await readDocument(openFile);
+      setFileContent(content);
+      setOriginalContent(content);
+      setHasUnsavedChanges(false);
+      extractHeaders(content);
+    } catch (error) {
+      console.error("Failed to reload file:", error);
+      toast.error("Failed to reload file");
+    }
+  };
+
+  // Save through the backend, which writes atomically and refuses with a
+  // "changedOnDisk" error if another program changed the open file since
+  // it was read. `overwrite` saves regardless.
+  const writeDocument = async (filePath, content, { overwrite = false } = {}) => {
+    const checkVersion = !overwrite && filePath === currentFileRef.current;
+    fileVersionRef.current = await invoke("save_document", {
+      filePath,
+      content,
+      format: fileFormatRef.current,
+      expected: checkVersion ? fileVersionRef.current : null,
+    });
+  };
+
+  // Ask what to do about a file another program changed. Resolves to
+  // "merge", "overwrite" or "cancel".
+  const confirmSaveConflict = (filePath, content) => {
+    return new Promise((resolve) => {
+      setSaveConflictDialog({
+        filePath,
+        content,
+        resolve: (choice) => {
+          setSaveConflictDialog(null);
+          resolve(choice);
+        },
+      });
+    });
+  };
+
+  // Put the changes made on disk into the editor alongside the user's own,
+  // with the version on disk as the one saving is checked against
+  const mergeWithDisk = async (filePath, content) => {
+    const disk = await invoke("read_document", { filePath });
+    const merged = await invoke("merge_texts", {
+      base: originalContent,
+      ours: content,
+      theirs: disk.content,
+      ourLabel: "Your changes",
+      theirLabel: "Changes on disk",
+    });
+    adoptDocument(disk);
+    setFileContent(merged.content);
+    setOriginalContent(disk.content);
+    extractHeaders(merged.content);
+    if (merged.conflicts > 0) {
+      toast(
+        `${merged.conflicts} conflict${merged.conflicts === 1 ? "" : "s"} marked in the text; resolve and save`,
+        { icon: "⚠️", duration: 5000 }
+      );
+    } else {
+      toast.success("Merged with the changes on disk; save to keep the result");
+    }
+  };
+
+  // A file that was neither UTF-8 nor UTF-16 was read as Windows-1252,
+  // which may be wrong; edits are only saved in it once the user agrees
+  const confirmGuessedEncoding = async (filePath) => {
+    const keep = await ask(
+      `${filePath.split("/").pop()} isn't UTF-8, so Docura read it as Windows-1252 (Latin-1). ` +
+        "If the text looked garbled, it uses another encoding and saving would damage it.",
+      {
+        title: "Unknown Encoding",
+        kind: "warning",
+        okLabel: "Save as Latin-1",
+        cancelLabel: "Cancel",
+      }
+    );
+    if (keep) {
+      fileFormatRef.current = { ...fileFormatRef.current, encodingGuessed: false };
+    }
+    return keep;
+  };
+
+  // Save, asking before overwriting a file another program changed.
+  // Resolves to false if it wasn't saved.
+  const saveDocument = async (filePath, content) => {
+    try {
+      await writeDocument(filePath, content);
+      return true;
+    } catch (error) {
+      if (error?.kind === "encodingGuessed") {
+        if (!(await confirmGuessedEncoding(filePath))) {
+          toast(`${filePath.split("/").pop()} was not saved`, { icon: "⚠️" });
+          return false;
+        }
+        return saveDocument(filePath, content);
+      }
+      if (error?.kind !== "changedOnDisk") throw error;
+    }
+    const choice = await confirmSaveConflict(filePath, content);
+    if (choice === "overwrite") {
+      await writeDocument(filePath, content, { overwrite: true });
+      return true;
+    }
+    if (choice === "merge") {
+      await mergeWithDisk(filePath, content);
+    } else {
+      toast(`${filePath.split("/").pop()} was not saved`, { icon: "⚠️" });
+    }
+    return false;
+  };
+
+  // Keep the folder search and link indexes current without waiting for
+  // the next query
+  const updateFolderIndexes = (filePath) => {
+    invoke("search_update_file", { filePath }).catch((error) =>
+      console.error("Failed to update search index:", error)
+    );
+    invoke("links_update_file", { filePath }).catch((error) =>
+      console.error("Failed to update link index:", error)
+    );
+  };
+
   const saveFile = async () => {
     // If no file is open, use Save As instead
     if (!currentFile) {
@@ -1351,9 +1698,10 @@ function App() {
 
     if (fileContent !== undefined) {
       try {
-        await writeTextFile(currentFile, fileContent);
+        if (!(await saveDocument(currentFile, fileContent))) return false;
         setOriginalContent(fileContent); // Update original content after save
         setHasUnsavedChanges(false);
+        updateFolderIndexes(currentFile);
         const fileName = currentFile.split("/").pop();
         toast.success(`Saved: ${fileName}`);
 
@@ -1369,11 +1717,13 @@ function App() {
             }
           }
         }
+        return true;
       } catch (error) {
         console.error("Error saving file:", error);
         toast.error("Failed to save file");
       }
     }
+    return false;
   };
 
   const saveFileAs = async () => {
@@ -1410,22 +1760,15 @@ function App() {
       });
 
       if (selected) {
-        await writeTextFile(selected, fileContent);
+        if (!(await saveDocument(selected, fileContent))) return;
 
-        // Delete temp file if exists (now that we've saved permanently)
-        if (currentTempId) {
-          try {
-            await invoke("delete_temp_file", { tempId: currentTempId });
-            console.log("🗑️ Deleted temp file after permanent save");
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-          setCurrentTempId(null);
-        }
+        // Saved under a name: the untitled buffer is gone
+        await closeSessionBuffer();
 
         setCurrentFile(selected);
         setOriginalContent(fileContent);
         setHasUnsavedChanges(false);
+        updateFolderIndexes(selected);
         const fileName = selected.split("/").pop();
         toast.success(`Saved as: ${fileName}`);
 
@@ -1448,48 +1791,34 @@ function App() {
 
   const handleExportToPdf = async () => {
     try {
-      console.log("Export PDF clicked, current tab:", activeTab);
-
-      // Switch to preview mode if not already there
-      if (activeTab === "code") {
-        alert("Please switch to Preview or Live mode to export PDF");
-        return;
-      }
-
-      // Wait a moment for DOM to be ready
-      await new Promise((resolve) => setTimeout(resolve, 100));
-
-      // Get the preview element
-      let previewElement = document.querySelector(".wmde-markdown");
-
-      console.log("Preview element found:", previewElement);
-
-      if (!previewElement) {
-        console.error("No preview element found!");
-        alert(
-          "Could not find preview. Please make sure you are in Preview or Live mode.",
-        );
+      if (!currentFile || !fileContent) {
+        alert("Please open a file first");
         return;
       }
 
-      // Get filename from current file or use default
-      const filename = currentFile
-        ? currentFile
-            .split("/")
-            .pop()
-            .replace(/\.(md|markdown)$/i, ".pdf")
-        : "document.pdf";
-
-      console.log("Exporting to:", filename);
-
-      // Export to PDF (will show save dialog)
-      const result = await exportToPDF(previewElement, filename);
+      const filename = await save({
+        defaultPath: currentFile.replace(/\.(md|markdown)$/i, ".pdf"),
+        filters: [
+          {
+            name: "PDF",
+            extensions: ["pdf"],
+          },
+        ],
+      });
 
-      if (result) {
-        console.log("PDF exported successfully!");
-        toast.success("PDF exported successfully!");
-      } else {
-        console.log("Export cancelled");
+      if (filename) {
+        // Rendered natively from the Markdown source, so no preview is needed
+        const result = await invoke("export_to_pdf", {
+          content: fileContent,
+          filename: filename,
+          documentPath: currentFile,
+        });
+        if (result.includes("no installed font")) {
+          // Characters no font on this system can draw
+          toast(result, { icon: "⚠️", duration: 6000 });
+        } else {
+          toast.success("PDF exported successfully!");
+        }
       }
     } catch (error) {
       console.error("Error exporting to PDF:", error);
@@ -1542,14 +1871,6 @@ function App() {
         return;
       }
 
-      // Get the rendered HTML content
-      let previewElement = document.querySelector(".wmde-markdown");
-      if (!previewElement) {
-        alert("Please switch to Preview or Live mode first");
-        return;
-      }
-
-      const htmlContent = previewElement.innerHTML;
       const filename = await save({
         defaultPath: currentFile.replace(/\.(md|markdown)$/i, ".html"),
         filters: [
@@ -1562,8 +1883,9 @@ function App() {
 
       if (filename) {
         const result = await invoke("export_to_html", {
-          content: htmlContent,
+          content: fileContent,
           filename: filename,
+          theme: currentTheme,
         });
         toast.success("HTML exported successfully!");
       }
@@ -1573,20 +1895,48 @@ function App() {
     }
   };
 
-  const handleExportHtmlPlain = async () => {
+  const handleExportHtmlStandalone = async () => {
     try {
       if (!currentFile || !fileContent) {
         alert("Please open a file first");
         return;
       }
 
-      let previewElement = document.querySelector(".wmde-markdown");
-      if (!previewElement) {
-        alert("Please switch to Preview or Live mode first");
+      const filename = await save({
+        defaultPath: currentFile.replace(/\.(md|markdown)$/i, ".html"),
+        filters: [
+          {
+            name: "HTML",
+            extensions: ["html"],
+          },
+        ],
+      });
+
+      if (filename) {
+        // Embed the editor font when one is set, so the file renders the same elsewhere
+        const result = await invoke("export_to_html_standalone", {
+          content: fileContent,
+          filename: filename,
+          documentPath: currentFile,
+          fontFamily: omakaseFont,
+          embedFonts: !!omakaseFont,
+          theme: currentTheme,
+        });
+        toast.success("Self-contained HTML exported successfully!");
+      }
+    } catch (error) {
+      console.error("Error exporting self-contained HTML:", error);
+      toast.error(`Failed to export self-contained HTML: ${error.message}`);
+    }
+  };
+
+  const handleExportHtmlPlain = async () => {
+    try {
+      if (!currentFile || !fileContent) {
+        alert("Please open a file first");
         return;
       }
 
-      const htmlContent = previewElement.innerHTML;
       const filename = await save({
         defaultPath: currentFile.replace(/\.(md|markdown)$/i, "_plain.html"),
         filters: [
@@ -1599,7 +1949,7 @@ function App() {
 
       if (filename) {
         const result = await invoke("export_to_html_plain", {
-          content: htmlContent,
+          content: fileContent,
           filename: filename,
         });
         toast.success("Plain HTML exported successfully!");
@@ -1617,13 +1967,6 @@ function App() {
         return;
       }
 
-      let previewElement = document.querySelector(".wmde-markdown");
-      if (!previewElement) {
-        alert("Please switch to Preview or Live mode first");
-        return;
-      }
-
-      const htmlContent = previewElement.innerHTML;
       const filename = await save({
         defaultPath: currentFile.replace(/\.(md|markdown)$/i, "_tailwind.html"),
         filters: [
@@ -1636,7 +1979,7 @@ function App() {
 
       if (filename) {
         const result = await invoke("export_to_html_tailwind", {
-          content: htmlContent,
+          content: fileContent,
           filename: filename,
         });
         toast.success("HTML with Tailwind CSS exported successfully!");
@@ -1654,13 +1997,6 @@ function App() {
         return;
       }
 
-      let previewElement = document.querySelector(".wmde-markdown");
-      if (!previewElement) {
-        alert("Please switch to Preview or Live mode first");
-        return;
-      }
-
-      const htmlContent = previewElement.innerHTML;
       const filename = await save({
         defaultPath: currentFile.replace(
           /\.(md|markdown)$/i,
@@ -1676,7 +2012,7 @@ function App() {
 
       if (filename) {
         const result = await invoke("export_to_html_bootstrap", {
-          content: htmlContent,
+          content: fileContent,
           filename: filename,
         });
         toast.success("HTML with Bootstrap exported successfully!");
@@ -1777,6 +2113,135 @@ function App() {
     }
   };
 
+  const handleExportDocx = async () => {
+    try {
+      if (!currentFile || !fileContent) {
+        alert("Please open a file first");
+        return;
+      }
+
+      const filename = await save({
+        defaultPath: currentFile.replace(/\.(md|markdown)$/i, ".docx"),
+        filters: [
+          {
+            name: "Word Document",
+            extensions: ["docx"],
+          },
+        ],
+      });
+
+      if (filename) {
+        const result = await invoke("export_to_docx", {
+          content: fileContent,
+          filename: filename,
+          documentPath: currentFile,
+        });
+        toast.success("DOCX exported successfully!");
+      }
+    } catch (error) {
+      console.error("Error exporting DOCX:", error);
+      toast.error(`Failed to export DOCX: ${error.message}`);
+    }
+  };
+
+  const handleExportOdt = async () => {
+    try {
+      if (!currentFile || !fileContent) {
+        alert("Please open a file first");
+        return;
+      }
+
+      const filename = await save({
+        defaultPath: currentFile.replace(/\.(md|markdown)$/i, ".odt"),
+        filters: [
+          {
+            name: "OpenDocument Text",
+            extensions: ["odt"],
+          },
+        ],
+      });
+
+      if (filename) {
+        const result = await invoke("export_to_odt", {
+          content: fileContent,
+          filename: filename,
+          documentPath: currentFile,
+        });
+        toast.success("ODT exported successfully!");
+      }
+    } catch (error) {
+      console.error("Error exporting ODT:", error);
+      toast.error(`Failed to export ODT: ${error.message}`);
+    }
+  };
+
+  const handleExportFolderEpub = async () => {
+    try {
+      if (!currentFolder) {
+        alert("Please open a folder first");
+        return;
+      }
+
+      const folderName = currentFolder.split("/").pop();
+      const filename = await save({
+        defaultPath: `${currentFolder}/${folderName}.epub`,
+        filters: [
+          {
+            name: "EPUB",
+            extensions: ["epub"],
+          },
+        ],
+      });
+
+      if (filename) {
+        // Every Markdown file in the folder becomes a chapter, in sidebar order
+        const result = await invoke("export_folder_to_epub", {
+          folderPath: currentFolder,
+          filename: filename,
+          title: folderName,
+        });
+        toast.success("EPUB exported successfully!");
+      }
+    } catch (error) {
+      console.error("Error exporting EPUB:", error);
+      toast.error(`Failed to export EPUB: ${error.message || error}`);
+    }
+  };
+
+  const handleExportFolderSite = async () => {
+    try {
+      if (!currentFolder) {
+        alert("Please open a folder first");
+        return;
+      }
+
+      const outputPath = await open({
+        directory: true,
+        multiple: false,
+        defaultPath: currentFolder,
+        title: "Choose a folder for the website",
+      });
+
+      if (outputPath) {
+        // One page per Markdown file, styled with the current editor theme
+        const result = await invoke("export_folder_to_site", {
+          folderPath: currentFolder,
+          outputPath: outputPath,
+          title: currentFolder.split("/").pop(),
+          theme: currentTheme,
+        });
+        if (result.includes("weren't copied")) {
+          toast(result, { icon: "⚠️", duration: 6000 });
+        } else {
+          toast.success(result);
+        }
+      }
+    } catch (error) {
+      console.error("Error exporting website:", error);
+      toast.error(`Failed to export website: ${error.message || error}`);
+    }
+  };
+
   const extractHeaders = (content) => {
     const headerRegex = /^(#{1,6})\s+(.*)$/gm;
     const headers = [];
@@ -1829,18 +2294,10 @@ function App() {
         console.warn("Failed to grant file scope:", scopeError);
       }
 
-      const content = await readTextFile(filePath);
+      const content = await readDocument(filePath);
 
-      // Clean up temp file if switching from untitled
-      if (currentTempId) {
-        try {
-          await invoke("delete_temp_file", { tempId: currentTempId });
-          console.log("🗑️ Deleted temp file when switching files");
-        } catch (error) {
-          console.error("Failed to delete temp file:", error);
-        }
-        setCurrentTempId(null);
-      }
+      // The buffer being replaced was saved or discarded
+      await closeSessionBuffer();
 
       setCurrentFile(filePath);
       setFileContent(content);
@@ -1884,18 +2341,10 @@ function App() {
       } else {
         // Open file
         await invoke("grant_file_scope", { filePath: item.path });
-        const content = await readTextFile(item.path);
+        const content = await readDocument(item.path);
 
-        // Clean up temp file if switching from untitled
-        if (currentTempId) {
-          try {
-            await invoke("delete_temp_file", { tempId: currentTempId });
-            console.log("🗑️ Deleted temp file when opening recent item");
-          } catch (error) {
-            console.error("Failed to delete temp file:", error);
-          }
-          setCurrentTempId(null);
-        }
+        // The buffer being replaced was saved or discarded
+        await closeSessionBuffer();
 
         setCurrentFile(item.path);
         setFileContent(content);
@@ -1942,18 +2391,10 @@ function App() {
       await invoke("grant_file_scope", { filePath });
 
       // Read file content
-      const content = await readTextFile(filePath);
+      const content = await readDocument(filePath);
 
-      // Clean up temp file if switching from untitled
-      if (currentTempId) {
-        try {
-          await invoke("delete_temp_file", { tempId: currentTempId });
-          console.log("🗑️ Deleted temp file when opening from quick search");
-        } catch (error) {
-          console.error("Failed to delete temp file:", error);
-        }
-        setCurrentTempId(null);
-      }
+      // The buffer being replaced was saved or discarded
+      await closeSessionBuffer();
 
       setCurrentFile(filePath);
       setFileContent(content);
@@ -2303,16 +2744,8 @@ function App() {
         }
       }
 
-      // Delete temp file if switching from untitled
-      if (currentTempId) {
-        try {
-          await invoke("delete_temp_file", { tempId: currentTempId });
-          console.log("🗑️ Deleted temp file when opening from Dropbox");
-        } catch (error) {
-          console.error("Failed to delete temp file:", error);
-        }
-        setCurrentTempId(null);
-      }
+      // The buffer being replaced was saved or discarded
+      await closeSessionBuffer();
 
       // Set the content and file info
       setFileContent(content);
@@ -2397,6 +2830,62 @@ function App() {
     }
   };
 
+  const handleShowIgnoredFilesToggle = async () => {
+    const show = !showIgnoredFiles;
+    try {
+      await invoke("set_show_ignored_files", { show });
+      setShowIgnoredFiles(show);
+      if (currentFolder) {
+        loadFolderFiles(currentFolder);
+      }
+    } catch (error) {
+      console.error("Error saving hidden files setting:", error);
+      toast.error("Failed to change hidden files setting");
+    }
+  };
+
+  const handleTreeSortChange = async (sort) => {
+    setTreeSort(sort);
+    try {
+      await invoke("set_tree_sort", { sort });
+    } catch (error) {
+      console.error("Error saving sort order:", error);
+    }
+  };
+
+  const handleIgnorePatternsChange = async (patterns) => {
+    try {
+      await invoke("set_ignore_patterns", { patterns });
+      setIgnorePatterns(patterns);
+      if (currentFolder) {
+        loadFolderFiles(currentFolder);
+      }
+    } catch (error) {
+      console.error("Error saving ignore patterns:", error);
+      toast.error("Failed to save ignore patterns");
+    }
+  };
+
+  const handleHistoryRetentionChange = async (retention) => {
+    try {
+      await invoke("set_history_retention", { retention });
+      setHistoryRetention(retention);
+    } catch (error) {
+      console.error("Error saving history retention:", error);
+      toast.error("Failed to save version history settings");
+    }
+  };
+
+  const handleTextExtensionsChange = async (extensions) => {
+    try {
+      await invoke("set_text_extensions", { extensions });
+      setTextExtensions(extensions);
+    } catch (error) {
+      console.error("Error saving text extensions:", error);
+      toast.error("Failed to save text file extensions");
+    }
+  };
+
   const handleEditorSettingsChange = (newSettings) => {
     setEditorSettings(newSettings);
     // TODO: Persist editor settings to config
@@ -2506,16 +2995,22 @@ function App() {
           onSaveAs={saveFileAs}
           onExportPdf={handleExportToPdf}
           onExportHtml={handleExportHtml}
+          onExportHtmlStandalone={handleExportHtmlStandalone}
           onExportHtmlPlain={handleExportHtmlPlain}
           onExportHtmlTailwind={handleExportHtmlTailwind}
           onExportHtmlBootstrap={handleExportHtmlBootstrap}
           onExportJson={handleExportJson}
           onExportRtf={handleExportRtf}
           onExportMediaWiki={handleExportMediaWiki}
+          onExportDocx={handleExportDocx}
+          onExportOdt={handleExportOdt}
+          onExportFolderEpub={handleExportFolderEpub}
+          onExportFolderSite={handleExportFolderSite}
           onPrint={handlePrint}
           onOpenThemeSelector={() => setIsThemeSelectorOpen(true)}
           onOpenSettings={() => setIsSettingsOpen(true)}
           hasFile={!!currentFile}
+          hasFolder={!!currentFolder}
           onToggleFullscreen={toggleFullscreen}
           isFullscreen={isFullscreen}
           onToggleSidebar={toggleSidebar}
@@ -2538,6 +3033,7 @@ function App() {
           onOpenFromDropbox={handleOpenFromDropbox}
           dropboxConnected={dropboxStatus?.connected || false}
           onOpenAbout={() => setIsAboutOpen(true)}
+          onOpenHistory={() => setIsHistoryOpen(true)}
         />
       )}
 
@@ -2550,11 +3046,19 @@ function App() {
             onSelectFile={selectFile}
             onRefreshFiles={() => {
               if (currentFolder) {
-                invoke("get_folder_files", { folderPath: currentFolder }).then(
-                  setFiles,
-                );
+                loadFolderFiles(currentFolder);
               }
             }}
+            folderChanges={folderChanges}
+            showIgnoredFiles={showIgnoredFiles}
+            onToggleIgnoredFiles={handleShowIgnoredFilesToggle}
+            ignorePatterns={ignorePatterns}
+            textExtensions={textExtensions}
+            treeSort={treeSort}
+            onTreeSortChange={handleTreeSortChange}
+            onFileOperation={handleFileOperation}
+            onConfirmLinkUpdates={confirmLinkUpdates}
+            onOpenGitHistory={() => setIsGitHistoryOpen(true)}
             onHeaderClick={handleHeaderClick}
             currentFile={currentFile}
             hasUnsavedChanges={hasUnsavedChanges}
@@ -2581,6 +3085,11 @@ function App() {
           onNewFile={newFile}
           onOpenRecentItem={openRecentItem}
           onCursorPositionChange={setCursorPosition}
+          onScrollChange={(scrollTop) => {
+            editorScrollRef.current = scrollTop;
+          }}
+          restoreView={restoreView}
+          onViewRestored={() => setRestoreView(null)}
           onOpenThemeSelector={() => setIsThemeSelectorOpen(true)}
           liveEditorType={liveEditorType}
           focusMode={focusMode}
@@ -2636,6 +3145,12 @@ function App() {
         syncProvider={syncProvider}
         autoSaveEnabled={autoSaveEnabled}
         onAutoSaveToggle={handleAutoSaveToggle}
+        ignorePatterns={ignorePatterns}
+        onIgnorePatternsChange={handleIgnorePatternsChange}
+        textExtensions={textExtensions}
+        onTextExtensionsChange={handleTextExtensionsChange}
+        historyRetention={historyRetention}
+        onHistoryRetentionChange={handleHistoryRetentionChange}
         editorSettings={editorSettings}
         onEditorSettingsChange={handleEditorSettingsChange}
         liveEditorType={liveEditorType}
@@ -2693,20 +3208,30 @@ function App() {
         fileName={currentFile ? currentFile.split("/").pop() : "Untitled"}
       />
 
+      <LinkUpdatesDialog
+        isOpen={linkUpdatesDialog !== null}
+        plan={linkUpdatesDialog?.plan}
+        folderPath={currentFolder}
+        unsavedFile={hasUnsavedChanges ? currentFile : null}
+        onUpdate={() => linkUpdatesDialog.resolve("update")}
+        onSkip={() => linkUpdatesDialog.resolve("skip")}
+        onCancel={() => linkUpdatesDialog.resolve("cancel")}
+      />
+
+      <SaveConflictDialog
+        isOpen={saveConflictDialog !== null}
+        filePath={saveConflictDialog?.filePath}
+        content={saveConflictDialog?.content}
+        onMerge={() => saveConflictDialog.resolve("merge")}
+        onOverwrite={() => saveConflictDialog.resolve("overwrite")}
+        onCancel={() => saveConflictDialog.resolve("cancel")}
+      />
+
       <RecoveryDialog
-        isOpen={recoveryDialog.visible}
-        onRecover={handleRecoverWork}
-        onDiscard={handleDiscardRecovery}
-        fileName={
-          recoveryDialog.tempFile
-            ? getSmartFileName(recoveryDialog.tempFile.content)
-            : "Untitled"
-        }
-        preview={
-          recoveryDialog.tempFile
-            ? recoveryDialog.tempFile.content.substring(0, 200)
-            : ""
-        }
+        buffers={recoverableBuffers}
+        onRestore={restoreBuffer}
+        onDiscard={discardBuffer}
+        onClose={() => setRecoverableBuffers([])}
       />
 
       <OAuthDialog
@@ -2725,6 +3250,23 @@ function App() {
 
       <AboutDialog isOpen={isAboutOpen} onClose={() => setIsAboutOpen(false)} />
 
+      <HistoryDialog
+        isOpen={isHistoryOpen}
+        filePath={currentFile}
+        hasUnsavedChanges={hasUnsavedChanges}
+        onClose={() => setIsHistoryOpen(false)}
+        onRestored={reloadCurrentFile}
+      />
+
+      <GitHistoryDialog
+        isOpen={isGitHistoryOpen}
+        filePath={currentFile}
+        content={fileContent}
+        hasUnsavedChanges={hasUnsavedChanges}
+        onClose={() => setIsGitHistoryOpen(false)}
+        onCheckedOut={reloadCurrentFile}
+      />
+
       <Toaster
         position="bottom-right"
         toastOptions={{
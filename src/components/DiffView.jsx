@@ -0,0 +1,42 @@
+import React from 'react'
+
+const LINE_PREFIX = { added: '+', removed: '-', context: ' ' }
+
+// Hunks of a line diff, as returned by the backend's diff commands. Within
+// a changed line the words that changed are highlighted.
+const DiffView = ({ diff, emptyMessage = 'No differences' }) => {
+  if (!diff) return null
+  if (diff.hunks.length === 0) {
+    return <div className="diff-empty">{emptyMessage}</div>
+  }
+
+  return (
+    <div className="diff-view">
+      {diff.hunks.map((hunk) => (
+        <div key={`${hunk.oldStart}:${hunk.newStart}`} className="diff-hunk">
+          <div className="diff-hunk-header">
+            @@ -{hunk.oldStart},{hunk.oldLines} +{hunk.newStart},{hunk.newLines} @@
+          </div>
+          {hunk.lines.map((line, index) => (
+            <div key={index} className={`diff-line ${line.kind}`}>
+              <span className="diff-line-number">{line.oldLine ?? ''}</span>
+              <span className="diff-line-number">{line.newLine ?? ''}</span>
+              <span className="diff-line-text">
+                {LINE_PREFIX[line.kind]}{' '}
+                {line.words
+                  ? line.words.map((word, wordIndex) => (
+                      <span key={wordIndex} className={word.changed ? 'diff-word-changed' : undefined}>
+                        {word.text}
+                      </span>
+                    ))
+                  : line.text}
+              </span>
+            </div>
+          ))}
+        </div>
+      ))}
+    </div>
+  )
+}
+
+export default DiffView
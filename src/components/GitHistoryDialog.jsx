@@ -0,0 +1,132 @@
+import React, { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { ask } from '@tauri-apps/plugin-dialog'
+import { GitCommit, RotateCcw } from 'lucide-react'
+import toast from 'react-hot-toast'
+import DiffView from './DiffView'
+
+// The commits that changed the open file: what the editor's text changes
+// compared to each of them, and checking one out. The newest is the
+// version in HEAD.
+const GitHistoryDialog = ({ isOpen, filePath, content, hasUnsavedChanges, onClose, onCheckedOut }) => {
+  const [commits, setCommits] = useState([])
+  const [selected, setSelected] = useState(null)
+  const [diff, setDiff] = useState(null)
+  const [loading, setLoading] = useState(false)
+
+  useEffect(() => {
+    if (!isOpen || !filePath) return
+    setLoading(true)
+    setDiff(null)
+    invoke('git_file_log', { filePath })
+      .then((log) => {
+        setCommits(log)
+        setSelected(log.length > 0 ? log[0].id : null)
+      })
+      .catch((error) => {
+        console.error('Failed to load git log:', error)
+        toast.error('Failed to load git history')
+        setCommits([])
+      })
+      .finally(() => setLoading(false))
+  }, [isOpen, filePath])
+
+  useEffect(() => {
+    if (!isOpen || selected === null) return
+    invoke('git_diff_file', { filePath, content, commit: selected })
+      .then(setDiff)
+      .catch((error) => {
+        console.error('Failed to compare with commit:', error)
+        setDiff(null)
+      })
+  }, [isOpen, filePath, content, selected])
+
+  if (!isOpen) return null
+
+  const checkout = async () => {
+    if (hasUnsavedChanges) {
+      const discard = await ask('Checking out this version discards your unsaved changes.', {
+        title: 'Check Out Version',
+        kind: 'warning',
+        okLabel: 'Check Out',
+        cancelLabel: 'Cancel'
+      })
+      if (!discard) return
+    }
+    try {
+      await invoke('git_checkout_file', { filePath, commit: selected })
+      toast.success('Version checked out')
+      onCheckedOut()
+      onClose()
+    } catch (error) {
+      console.error('Failed to check out version:', error)
+      toast.error('Failed to check out version')
+    }
+  }
+
+  return (
+    <div className="unsaved-changes-overlay" onClick={onClose}>
+      <div className="unsaved-changes-dialog history-dialog" onClick={(e) => e.stopPropagation()}>
+        <div className="unsaved-changes-header">
+          <GitCommit size={24} className="text-accent" />
+          <h2>Git History</h2>
+        </div>
+
+        <div className="unsaved-changes-content history-content">
+          {loading ? (
+            <p className="unsaved-changes-message">Loading…</p>
+          ) : commits.length === 0 ? (
+            <p className="unsaved-changes-message">
+              This file hasn't been committed yet.
+            </p>
+          ) : (
+            <>
+              <ul className="history-list">
+                {commits.map((commit, index) => (
+                  <li
+                    key={commit.id}
+                    className={`history-item git-commit-item ${commit.id === selected ? 'active' : ''}`}
+                    onClick={() => setSelected(commit.id)}
+                    title={`${commit.id}\n${commit.author}`}
+                  >
+                    <span className="git-commit-summary">
+                      {commit.summary}
+                      {index === 0 && <span className="git-commit-head">HEAD</span>}
+                    </span>
+                    <span className="history-item-size">
+                      {commit.shortId} · {commit.author} · {new Date(commit.time).toLocaleString()}
+                    </span>
+                  </li>
+                ))}
+              </ul>
+              <div className="history-diff">
+                {diff && (
+                  <div className="history-diff-summary">
+                    Your changes since this commit: +{diff.added} −{diff.removed} lines
+                  </div>
+                )}
+                <DiffView diff={diff} emptyMessage="Same as in this commit" />
+              </div>
+            </>
+          )}
+        </div>
+
+        <div className="unsaved-changes-actions">
+          <button
+            className="unsaved-button unsaved-button-save"
+            onClick={checkout}
+            disabled={selected === null || (diff && diff.hunks.length === 0)}
+          >
+            <RotateCcw size={16} />
+            Check Out This Version
+          </button>
+          <button className="unsaved-button unsaved-button-cancel" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default GitHistoryDialog
@@ -0,0 +1,127 @@
+import React, { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { ask } from '@tauri-apps/plugin-dialog'
+import { History, RotateCcw } from 'lucide-react'
+import toast from 'react-hot-toast'
+import DiffView from './DiffView'
+
+const formatSize = (bytes) =>
+  bytes < 1024 ? `${bytes} B` : `${(bytes / 1024).toFixed(1)} KB`
+
+// Earlier saved versions of the open file: what each changed compared to
+// the file on disk, and restoring one
+const HistoryDialog = ({ isOpen, filePath, hasUnsavedChanges, onClose, onRestored }) => {
+  const [revisions, setRevisions] = useState([])
+  const [selected, setSelected] = useState(null)
+  const [diff, setDiff] = useState(null)
+  const [loading, setLoading] = useState(false)
+
+  useEffect(() => {
+    if (!isOpen || !filePath) return
+    setLoading(true)
+    setDiff(null)
+    invoke('list_revisions', { filePath })
+      .then((list) => {
+        setRevisions(list)
+        setSelected(list.length > 0 ? list[0].id : null)
+      })
+      .catch((error) => {
+        console.error('Failed to load history:', error)
+        toast.error('Failed to load version history')
+      })
+      .finally(() => setLoading(false))
+  }, [isOpen, filePath])
+
+  useEffect(() => {
+    if (!isOpen || selected === null) return
+    invoke('diff_revisions', { filePath, fromRevision: selected, toRevision: null })
+      .then(setDiff)
+      .catch((error) => {
+        console.error('Failed to compare versions:', error)
+        setDiff(null)
+      })
+  }, [isOpen, filePath, selected])
+
+  if (!isOpen) return null
+
+  const restore = async () => {
+    if (hasUnsavedChanges) {
+      const discard = await ask('Restoring this version discards your unsaved changes.', {
+        title: 'Restore Version',
+        kind: 'warning',
+        okLabel: 'Restore',
+        cancelLabel: 'Cancel'
+      })
+      if (!discard) return
+    }
+    try {
+      await invoke('restore_revision', { filePath, revisionId: selected })
+      toast.success('Version restored')
+      onRestored()
+      onClose()
+    } catch (error) {
+      console.error('Failed to restore version:', error)
+      toast.error('Failed to restore version')
+    }
+  }
+
+  return (
+    <div className="unsaved-changes-overlay" onClick={onClose}>
+      <div className="unsaved-changes-dialog history-dialog" onClick={(e) => e.stopPropagation()}>
+        <div className="unsaved-changes-header">
+          <History size={24} className="text-accent" />
+          <h2>Version History</h2>
+        </div>
+
+        <div className="unsaved-changes-content history-content">
+          {loading ? (
+            <p className="unsaved-changes-message">Loading…</p>
+          ) : revisions.length === 0 ? (
+            <p className="unsaved-changes-message">
+              No earlier versions yet. A version is kept every time the file is saved.
+            </p>
+          ) : (
+            <>
+              <ul className="history-list">
+                {revisions.map((revision) => (
+                  <li
+                    key={revision.id}
+                    className={`history-item ${revision.id === selected ? 'active' : ''}`}
+                    onClick={() => setSelected(revision.id)}
+                  >
+                    <span>{new Date(revision.id).toLocaleString()}</span>
+                    <span className="history-item-size">{formatSize(revision.size)}</span>
+                  </li>
+                ))}
+              </ul>
+              <div className="history-diff">
+                {diff && (
+                  <div className="history-diff-summary">
+                    Compared to the saved file: +{diff.added} −{diff.removed} lines
+                  </div>
+                )}
+                <DiffView diff={diff} emptyMessage="Same as the saved file" />
+              </div>
+            </>
+          )}
+        </div>
+
+        <div className="unsaved-changes-actions">
+          <button
+            className="unsaved-button unsaved-button-save"
+            onClick={restore}
+            disabled={selected === null || (diff && diff.hunks.length === 0)}
+          >
+            <RotateCcw size={16} />
+            Restore This Version
+          </button>
+          <button className="unsaved-button unsaved-button-cancel" onClick={onClose}>
+            Close
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default HistoryDialog
@@ -0,0 +1,94 @@
+import React from 'react'
+import { Link2, X } from 'lucide-react'
+
+const LinkUpdatesDialog = ({
+  isOpen,
+  plan,
+  folderPath,
+  unsavedFile,
+  onUpdate,
+  onSkip,
+  onCancel
+}) => {
+  if (!isOpen || !plan) return null
+
+  const displayPath = (path) =>
+    folderPath && path.startsWith(`${folderPath}/`) ? path.slice(folderPath.length + 1) : path
+  const linkCount = plan.files.reduce((count, file) => count + file.changes.length, 0)
+
+  return (
+    <div className="unsaved-changes-overlay" onClick={onCancel}>
+      <div className="unsaved-changes-dialog link-updates-dialog" onClick={(e) => e.stopPropagation()}>
+        <div className="unsaved-changes-header">
+          <Link2 size={24} className="text-accent" />
+          <h2>Update Links?</h2>
+        </div>
+
+        <div className="unsaved-changes-content">
+          <p className="unsaved-changes-message">
+            <strong>{displayPath(plan.from)}</strong> is linked {linkCount} time{linkCount === 1 ? '' : 's'} in{' '}
+            {plan.files.length} file{plan.files.length === 1 ? '' : 's'}.
+          </p>
+          <ul className="link-updates-list">
+            {plan.files.map((file) => (
+              <li key={file.path}>
+                <div className="link-updates-file">{displayPath(file.path)}</div>
+                {file.changes.map((change) => (
+                  <div key={`${change.line}:${change.column}`} className="link-updates-change">
+                    <span className="link-updates-position">{change.line}:{change.column}</span>
+                    <code>{change.old}</code> → <code>{change.new}</code>
+                  </div>
+                ))}
+              </li>
+            ))}
+          </ul>
+          {plan.skipped.length > 0 && (
+            <>
+              <p className="unsaved-changes-hint">
+                Links in {plan.skipped.length === 1 ? 'this file' : 'these files'} can't be updated:
+              </p>
+              <ul className="link-updates-list">
+                {plan.skipped.map((file) => (
+                  <li key={file.path}>
+                    <div className="link-updates-file">{displayPath(file.path)}</div>
+                    <div className="link-updates-change">{file.reason}</div>
+                  </li>
+                ))}
+              </ul>
+            </>
+          )}
+          {unsavedFile && plan.files.some((file) => file.path === unsavedFile) && (
+            <p className="unsaved-changes-hint">
+              The open file has unsaved changes; saving it will undo the updated links.
+            </p>
+          )}
+        </div>
+
+        <div className="unsaved-changes-actions">
+          <button
+            className="unsaved-button unsaved-button-save"
+            onClick={onUpdate}
+          >
+            <Link2 size={16} />
+            Update Links
+          </button>
+          <button
+            className="unsaved-button unsaved-button-dont-save"
+            onClick={onSkip}
+          >
+            <X size={16} />
+            Don't Update
+          </button>
+          <button
+            className="unsaved-button unsaved-button-cancel"
+            onClick={onCancel}
+          >
+            Cancel
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default LinkUpdatesDialog
@@ -339,6 +339,9 @@ const MainEditor = ({
   onNewFile,
   onOpenRecentItem,
   onCursorPositionChange,
+  onScrollChange,
+  restoreView,
+  onViewRestored,
   onOpenThemeSelector,
   liveEditorType = 'modern',
   focusMode = false,
@@ -373,6 +376,21 @@ const MainEditor = ({
     }
   }, [markdownTheme])
 
+  // Put the cursor and scroll position back where a recovered buffer had them
+  const applyRestoreView = () => {
+    const editor = editorRef.current
+    if (!editor || !restoreView) return
+    const position = { lineNumber: restoreView.line, column: restoreView.column }
+    editor.setPosition(position)
+    editor.revealPositionInCenterIfOutsideViewport(position)
+    editor.setScrollTop(restoreView.scrollTop)
+    editor.focus()
+    if (onViewRestored) onViewRestored()
+  }
+
+  useEffect(() => {
+    applyRestoreView()
+  }, [restoreView])
 
   const handleEditorMount = (editor, monaco) => {
     monacoRef.current = monaco
@@ -395,6 +413,12 @@ const MainEditor = ({
         })
       })
     }
+
+    if (onScrollChange) {
+      editor.onDidScrollChange((e) => onScrollChange(e.scrollTop))
+    }
+
+    applyRestoreView()
   }
 
   const handleEditorChange = (value) => {
@@ -26,6 +26,10 @@ import {
   Newspaper,
   Cloud,
   Info,
+  BookOpen,
+  Package,
+  Library,
+  History,
 } from "lucide-react";
 
 const Menu = ({
@@ -36,18 +40,24 @@ const Menu = ({
   onSaveAs,
   onExportPdf,
   onExportHtml,
+  onExportHtmlStandalone,
   onExportHtmlPlain,
   onExportHtmlTailwind,
   onExportHtmlBootstrap,
   onExportJson,
   onExportRtf,
   onExportMediaWiki,
+  onExportDocx,
+  onExportOdt,
+  onExportFolderEpub,
+  onExportFolderSite,
   onPrint,
   onOpenThemeSelector,
   onOpenSettings,
   onToggleFullscreen,
   onToggleSidebar,
   hasFile,
+  hasFolder,
   isFullscreen,
   isSidebarVisible,
   recentItems,
@@ -56,6 +66,7 @@ const Menu = ({
   onOpenFromDropbox,
   dropboxConnected,
   onOpenAbout,
+  onOpenHistory,
 }) => {
   const [isOpen, setIsOpen] = useState(false);
   const [isRecentSubmenuOpen, setIsRecentSubmenuOpen] = useState(false);
@@ -161,6 +172,14 @@ const Menu = ({
               <span>Save As</span>
               <span className="menu-shortcut">Ctrl+Shift+S</span>
             </button>
+            <button
+              className="menu-item"
+              onClick={() => handleMenuClick(onOpenHistory)}
+              disabled={!hasFile}
+            >
+              <History size={16} />
+              <span>Version History</span>
+            </button>
           </div>
 
           <div className="menu-divider"></div>
@@ -247,6 +266,23 @@ const Menu = ({
                       <span className="menu-shortcut">Ctrl+E</span>
                     </button>
                     <div className="menu-divider"></div>
+                    <button
+                      className="menu-item"
+                      onClick={() => handleMenuClick(onExportDocx)}
+                      disabled={!hasFile}
+                    >
+                      <FileText size={14} />
+                      <span>Word (DOCX)</span>
+                    </button>
+                    <button
+                      className="menu-item"
+                      onClick={() => handleMenuClick(onExportOdt)}
+                      disabled={!hasFile}
+                    >
+                      <FileText size={14} />
+                      <span>OpenDocument (ODT)</span>
+                    </button>
+                    <div className="menu-divider"></div>
                     <button
                       className="menu-item"
                       onClick={() => handleMenuClick(onExportHtml)}
@@ -255,6 +291,14 @@ const Menu = ({
                       <Globe size={14} />
                       <span>HTML</span>
                     </button>
+                    <button
+                      className="menu-item"
+                      onClick={() => handleMenuClick(onExportHtmlStandalone)}
+                      disabled={!hasFile}
+                    >
+                      <Package size={14} />
+                      <span>HTML (Self-contained)</span>
+                    </button>
                     <button
                       className="menu-item"
                       onClick={() => handleMenuClick(onExportHtmlPlain)}
@@ -304,6 +348,23 @@ const Menu = ({
                       <Newspaper size={14} />
                       <span>MediaWiki</span>
                     </button>
+                    <div className="menu-divider"></div>
+                    <button
+                      className="menu-item"
+                      onClick={() => handleMenuClick(onExportFolderEpub)}
+                      disabled={!hasFolder}
+                    >
+                      <BookOpen size={14} />
+                      <span>Folder as EPUB</span>
+                    </button>
+                    <button
+                      className="menu-item"
+                      onClick={() => handleMenuClick(onExportFolderSite)}
+                      disabled={!hasFolder}
+                    >
+                      <Library size={14} />
+                      <span>Folder as Website</span>
+                    </button>
                   </div>
                 </div>
               )}
@@ -15,6 +15,7 @@ const QuickOpenDialog = ({
   const [filteredResults, setFilteredResults] = useState([])
   const inputRef = useRef(null)
   const resultsRef = useRef(null)
+  const searchRequestRef = useRef(0)
 
   // Focus input when dialog opens
   useEffect(() => {
@@ -82,19 +83,44 @@ const QuickOpenDialog = ({
     if (searchQuery.trim() === '') {
       setFilteredResults(items.slice(0, 15)) // Show first 15 when no query
     } else {
+      // Ignore results of searches overtaken by further typing
+      const request = ++searchRequestRef.current
       searchFilesWithContent(items, searchQuery).then(filtered => {
-        setFilteredResults(filtered.slice(0, 15)) // Limit to 15 results
+        if (request === searchRequestRef.current) {
+          setFilteredResults(filtered.slice(0, 15)) // Limit to 15 results
+        }
       })
     }
 
     setSelectedIndex(0)
   }, [searchQuery, files, recentItems, isOpen])
 
+  // Treat the word being typed as a prefix so results appear while typing
+  const toIndexQuery = (query) => {
+    const quotes = (query.match(/"/g) || []).length
+    return quotes % 2 === 0 && /[\p{L}\p{N}]$/u.test(query) ? `${query}*` : query
+  }
+
   // Enhanced search function that searches in file content
   const searchFilesWithContent = async (items, query) => {
     const queryLower = query.toLowerCase()
     const results = []
 
+    // One call to the backend index instead of reading every file
+    const contentHits = new Map()
+    if (currentFolder) {
+      try {
+        const hits = await invoke('search_folder', {
+          folderPath: currentFolder,
+          query: toIndexQuery(query),
+          limit: 50
+        })
+        hits.forEach(hit => contentHits.set(hit.path, hit))
+      } catch (error) {
+        console.warn('Content search failed:', error)
+      }
+    }
+
     for (const item of items) {
       let relevanceScore = 0
       let matchType = 'none'
@@ -116,37 +142,35 @@ const QuickOpenDialog = ({
         matchType = matchType === 'none' ? 'path' : matchType
       }
 
-      // Search in file content (only for markdown files)
-      if (isMarkdownFile(item.name)) {
-        try {
-          // Use Tauri's file reading API instead of fetch
-          const content = await invoke('read_file_content', { filePath: item.path })
-          if (content) {
-            const contentMatches = searchInContent(content, queryLower)
-
-            if (contentMatches.headings.length > 0 || contentMatches.body.length > 0) {
-              // Headings matches (high priority - after filename)
-              if (contentMatches.headings.length > 0) {
-                relevanceScore += 75 // Higher than body content
-                matchType = matchType === 'filename' ? 'filename' : 'headings'
-              }
-
-              // Body content matches (medium priority)
-              if (contentMatches.body.length > 0) {
-                relevanceScore += contentMatches.body.length * 5 // More matches = higher score
-                if (matchType === 'none') {
-                  matchType = 'content'
-                }
-              }
-
-              // Store content matches for preview
-              item.contentMatches = contentMatches
-            }
+      const hit = contentHits.get(item.path)
+      if (hit) {
+        const toMatch = (match) => ({
+          line: match.line,
+          text: match.snippet,
+          preview: match.snippet,
+          highlights: match.highlights
+        })
+        const contentMatches = {
+          headings: hit.lines.filter(match => match.heading).map(toMatch),
+          body: hit.lines.filter(match => !match.heading).map(toMatch)
+        }
+
+        // Headings matches (high priority - after filename)
+        if (contentMatches.headings.length > 0) {
+          relevanceScore += 75 // Higher than body content
+          matchType = matchType === 'filename' ? 'filename' : 'headings'
+        }
+
+        // Body content matches (medium priority), ranked by the index
+        if (contentMatches.body.length > 0) {
+          relevanceScore += hit.score * 10
+          if (matchType === 'none') {
+            matchType = 'content'
           }
-        } catch (error) {
-          // If Tauri command doesn't exist, fallback to basic search
-          console.warn(`Failed to read file ${item.path}:`, error)
         }
+
+        // Store content matches for preview
+        item.contentMatches = contentMatches
       }
 
       if (relevanceScore > 0) {
@@ -196,54 +220,17 @@ const QuickOpenDialog = ({
     return results
   }
 
-  // Search for query in markdown content
-  const searchInContent = (content, queryLower) => {
-    const lines = content.split('\n')
-    const headings = []
-    const body = []
-
-    lines.forEach((line, index) => {
-      const lineLower = line.toLowerCase()
-
-      // Check for headings (lines starting with #)
-      if (line.trim().match(/^#+\s/)) {
-        if (lineLower.includes(queryLower)) {
-          // Count how many times the query appears in this heading
-          const matchCount = (lineLower.match(new RegExp(queryLower.replace(/[.*+?^${}()|[\]\\]/g, '\\$&'), 'g')) || []).length
-          headings.push({
-            line: index + 1,
-            text: line.trim(),
-            preview: line.trim().substring(0, 100) + (line.trim().length > 100 ? '...' : ''),
-            matchCount
-          })
-        }
-      } else if (line.trim().length > 0) {
-        // Check for matches in body content (skip empty lines)
-        if (lineLower.includes(queryLower)) {
-          // Count how many times the query appears in this line
-          const matchCount = (lineLower.match(new RegExp(queryLower.replace(/[.*+?^${}()|[\]\\]/g, '\\$&'), 'g')) || []).length
-          
-          // Create context around the match
-          const matchIndex = lineLower.indexOf(queryLower)
-          const contextStart = Math.max(0, matchIndex - 30)
-          const contextEnd = Math.min(line.length, matchIndex + queryLower.length + 30)
-          let preview = line.substring(contextStart, contextEnd)
-          
-          // Add ellipsis if we truncated
-          if (contextStart > 0) preview = '...' + preview
-          if (contextEnd < line.length) preview = preview + '...'
-          
-          body.push({
-            line: index + 1,
-            text: line.trim(),
-            preview: preview,
-            matchCount
-          })
-        }
-      }
+  // Preview text with the matched words marked
+  const renderPreview = (match) => {
+    const parts = []
+    let last = 0
+    ;(match.highlights || []).forEach(([start, end], index) => {
+      parts.push(match.preview.slice(last, start))
+      parts.push(<mark key={index}>{match.preview.slice(start, end)}</mark>)
+      last = end
     })
-
-    return { headings, body }
+    parts.push(match.preview.slice(last))
+    return parts
   }
 
   // Handle keyboard navigation
@@ -345,12 +332,12 @@ const QuickOpenDialog = ({
                     <div className="content-preview">
                       {item.contentMatches.headings.length > 0 && (
                         <div className="heading-match">
-                          📝 {item.contentMatches.headings[0].preview}
+                          📝 {renderPreview(item.contentMatches.headings[0])}
                         </div>
                       )}
                       {item.contentMatches.body.length > 0 && !item.contentMatches.headings.length && (
                         <div className="content-match">
-                          💬 {item.contentMatches.body[0].preview}
+                          💬 {renderPreview(item.contentMatches.body[0])}
                         </div>
                       )}
                     </div>
@@ -1,44 +1,109 @@
-import React from 'react'
-import { RotateCcw, Trash2, X } from 'lucide-react'
+import React, { useState } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { AlertTriangle, FileDiff, RotateCcw, Trash2 } from 'lucide-react'
+import DiffView from './DiffView'
 
-const RecoveryDialog = ({ isOpen, onRecover, onDiscard, fileName, preview }) => {
-  if (!isOpen) return null
+const bufferName = (buffer) => {
+  if (buffer.path) return buffer.path.split('/').pop()
+  const firstLine = buffer.content.split('\n').find((line) => line.trim() !== '')
+  return firstLine ? firstLine.replace(/^#+\s*/, '').slice(0, 60) : 'Untitled'
+}
+
+// Unsaved buffers left behind by a session that didn't close cleanly. Each
+// can be restored, compared with its file on disk, or discarded; the ones
+// left undecided are offered again next time.
+const RecoveryDialog = ({ buffers, onRestore, onDiscard, onClose }) => {
+  const [comparing, setComparing] = useState(null)
+  const [diff, setDiff] = useState(null)
+
+  if (buffers.length === 0) return null
+
+  const compare = async (buffer) => {
+    if (comparing === buffer.id) {
+      setComparing(null)
+      setDiff(null)
+      return
+    }
+    try {
+      setDiff(await invoke('diff_with_file', { filePath: buffer.path, content: buffer.content }))
+      setComparing(buffer.id)
+    } catch (error) {
+      console.error('Failed to compare with file:', error)
+      setDiff(null)
+      setComparing(null)
+    }
+  }
 
   return (
-    <div className="recovery-overlay" onClick={onDiscard}>
-      <div className="recovery-dialog" onClick={(e) => e.stopPropagation()}>
+    <div className="recovery-overlay" onClick={onClose}>
+      <div className="recovery-dialog recovery-dialog-list" onClick={(e) => e.stopPropagation()}>
         <div className="recovery-header">
           <RotateCcw size={20} color="var(--accent-color)" />
           <h2>Unsaved Work Found</h2>
         </div>
-        
+
         <div className="recovery-content">
           <p className="recovery-message">
             Docura found unsaved work from your previous session.
           </p>
-          
-          {preview && (
-            <div className="recovery-preview">
-              <div className="recovery-preview-label">Preview:</div>
-              <div className="recovery-preview-content">
-                {preview}
-              </div>
-            </div>
-          )}
-          
-          <p className="recovery-question">
-            Would you like to recover this work?
-          </p>
+
+          <ul className="recovery-list">
+            {buffers.map((buffer) => (
+              <li key={buffer.id} className="recovery-item">
+                <div className="recovery-item-info">
+                  <span className="recovery-item-name" title={buffer.path || undefined}>
+                    {bufferName(buffer)}
+                  </span>
+                  <span className="recovery-item-meta">
+                    {buffer.path || 'Untitled'} · {buffer.windowLabel} ·{' '}
+                    {new Date(buffer.updated).toLocaleString()}
+                  </span>
+                  {buffer.fileChanged && (
+                    <span className="recovery-item-warning">
+                      <AlertTriangle size={12} />
+                      The file changed on disk after these edits
+                    </span>
+                  )}
+                </div>
+                <div className="recovery-item-actions">
+                  {buffer.path && (
+                    <button
+                      className="recovery-button recovery-button-discard recovery-button-compare"
+                      onClick={() => compare(buffer)}
+                      title="Compare with the file on disk"
+                    >
+                      <FileDiff size={14} />
+                      Compare
+                    </button>
+                  )}
+                  <button
+                    className="recovery-button recovery-button-discard"
+                    onClick={() => onDiscard(buffer.id)}
+                  >
+                    <Trash2 size={14} />
+                    Discard
+                  </button>
+                  <button
+                    className="recovery-button recovery-button-recover"
+                    onClick={() => onRestore(buffer.id)}
+                  >
+                    <RotateCcw size={14} />
+                    Restore
+                  </button>
+                </div>
+                {comparing === buffer.id && (
+                  <div className="recovery-diff">
+                    <DiffView diff={diff} emptyMessage="Same as the file on disk" />
+                  </div>
+                )}
+              </li>
+            ))}
+          </ul>
         </div>
-        
+
         <div className="recovery-actions">
-          <button className="recovery-button recovery-button-discard" onClick={onDiscard}>
-            <Trash2 size={16} />
-            No, Delete It
-          </button>
-          <button className="recovery-button recovery-button-recover" onClick={onRecover}>
-            <RotateCcw size={16} />
-            Yes, Recover
+          <button className="recovery-button recovery-button-discard" onClick={onClose}>
+            Decide Later
           </button>
         </div>
       </div>
@@ -47,4 +112,3 @@ const RecoveryDialog = ({ isOpen, onRecover, onDiscard, fileName, preview }) =>
 }
 
 export default RecoveryDialog
-
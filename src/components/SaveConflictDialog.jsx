@@ -0,0 +1,61 @@
+import React, { useState, useEffect } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import { AlertTriangle, GitMerge, Save } from 'lucide-react'
+import DiffView from './DiffView'
+
+// Shown when saving would overwrite changes another program made to the
+// file: what saving would change on disk, and whether to merge the two
+// versions, overwrite, or not save
+const SaveConflictDialog = ({ isOpen, filePath, content, onMerge, onOverwrite, onCancel }) => {
+  const [diff, setDiff] = useState(null)
+
+  useEffect(() => {
+    if (!isOpen) return
+    setDiff(null)
+    invoke('diff_with_file', { filePath, content })
+      .then(setDiff)
+      .catch((error) => console.error('Failed to compare with the file on disk:', error))
+  }, [isOpen, filePath, content])
+
+  if (!isOpen) return null
+
+  return (
+    <div className="unsaved-changes-overlay" onClick={onCancel}>
+      <div className="unsaved-changes-dialog save-conflict-dialog" onClick={(e) => e.stopPropagation()}>
+        <div className="unsaved-changes-header">
+          <AlertTriangle size={24} className="text-accent" />
+          <h2>File Changed on Disk</h2>
+        </div>
+
+        <div className="unsaved-changes-content">
+          <p className="unsaved-changes-message">
+            <strong>{filePath.split('/').pop()}</strong> was changed by another program since you opened it.
+            Saving would make these changes to it:
+          </p>
+          <div className="save-conflict-diff">
+            <DiffView diff={diff} emptyMessage="Your version is the same as the one on disk" />
+          </div>
+          <p className="unsaved-changes-hint">
+            Merging keeps both sets of changes and marks the lines you both changed.
+          </p>
+        </div>
+
+        <div className="unsaved-changes-actions">
+          <button className="unsaved-button unsaved-button-save" onClick={onMerge}>
+            <GitMerge size={16} />
+            Merge
+          </button>
+          <button className="unsaved-button unsaved-button-dont-save" onClick={onOverwrite}>
+            <Save size={16} />
+            Overwrite
+          </button>
+          <button className="unsaved-button unsaved-button-cancel" onClick={onCancel}>
+            Cancel
+          </button>
+        </div>
+      </div>
+    </div>
+  )
+}
+
+export default SaveConflictDialog
@@ -3,6 +3,9 @@ import { X, Check, RefreshCw } from 'lucide-react'
 import { getOmakaseStatus } from '../utils/omakaseSync'
 import { getPlasmaStatus } from '../utils/plasmaSync'
 
+// Same as the backend's default history retention
+const DEFAULT_RETENTION = { keep_last: 20, keep_hourly: 24, keep_daily: 30 }
+
 const SettingsDialog = ({ 
   isOpen, 
   onClose, 
@@ -13,6 +16,12 @@ const SettingsDialog = ({
   onSyncNow,
   autoSaveEnabled,
   onAutoSaveToggle,
+  ignorePatterns,
+  onIgnorePatternsChange,
+  textExtensions,
+  onTextExtensionsChange,
+  historyRetention,
+  onHistoryRetentionChange,
   editorSettings,
   onEditorSettingsChange,
   liveEditorType,
@@ -33,6 +42,9 @@ const SettingsDialog = ({
   const [loading, setLoading] = useState(true)
   const [activeTab, setActiveTab] = useState('general')
   const [syncingFolders, setSyncingFolders] = useState({})
+  const [ignorePatternsText, setIgnorePatternsText] = useState('')
+  const [textExtensionsText, setTextExtensionsText] = useState('')
+  const [retentionDraft, setRetentionDraft] = useState(DEFAULT_RETENTION)
 
   useEffect(() => {
     if (isOpen) {
@@ -40,6 +52,60 @@ const SettingsDialog = ({
     }
   }, [isOpen])
 
+  useEffect(() => {
+    setIgnorePatternsText((ignorePatterns || []).join('\n'))
+  }, [ignorePatterns, isOpen])
+
+  useEffect(() => {
+    setTextExtensionsText((textExtensions || []).join(', '))
+  }, [textExtensions, isOpen])
+
+  useEffect(() => {
+    setRetentionDraft(historyRetention || DEFAULT_RETENTION)
+  }, [historyRetention, isOpen])
+
+  const saveRetention = () => {
+    const retention = Object.fromEntries(
+      Object.entries(retentionDraft).map(([key, value]) => [key, Math.max(0, parseInt(value, 10) || 0)])
+    )
+    setRetentionDraft(retention)
+    const current = historyRetention || DEFAULT_RETENTION
+    if (Object.keys(retention).some(key => retention[key] !== current[key])) {
+      onHistoryRetentionChange(retention)
+    }
+  }
+
+  const retentionInput = (key) => (
+    <input
+      type="number"
+      min="0"
+      className="setting-textarea setting-input"
+      value={retentionDraft[key]}
+      onChange={(e) => setRetentionDraft({ ...retentionDraft, [key]: e.target.value })}
+      onBlur={saveRetention}
+    />
+  )
+
+  const saveTextExtensions = () => {
+    const extensions = textExtensionsText
+      .split(/[\s,]+/)
+      .map(extension => extension.replace(/^\./, '').toLowerCase())
+      .filter(Boolean)
+    if (extensions.join(',') !== (textExtensions || []).join(',')) {
+      onTextExtensionsChange(extensions)
+    }
+  }
+
+  const saveIgnorePatterns = () => {
+    const patterns = ignorePatternsText
+      .split('\n')
+      .map(pattern => pattern.trim())
+      .filter(Boolean)
+    if (patterns.join('\n') !== (ignorePatterns || []).join('\n')) {
+      onIgnorePatternsChange(patterns)
+    }
+  }
+
   const loadThemeProviderStatus = async () => {
     setLoading(true)
     
@@ -138,6 +204,54 @@ const SettingsDialog = ({
                       Automatically save your file 2 seconds after you stop typing. Only works with saved files (not "Untitled" documents).
                     </p>
                   </div>
+
+                  <div className="settings-option">
+                    <label className="setting-label">
+                      Version History
+                    </label>
+                    <div className="history-retention">
+                      Keep the last {retentionInput('keep_last')} saves, one per hour for{' '}
+                      {retentionInput('keep_hourly')} hours and one per day for {retentionInput('keep_daily')} days
+                    </div>
+                    <p className="option-description">
+                      A copy of the file is kept every time it is saved, auto-saves included. Open Menu → Version History to compare or restore one.
+                    </p>
+                  </div>
+
+                  <div className="settings-option">
+                    <label className="setting-label">
+                      Hidden from Folder Tree
+                    </label>
+                    <textarea
+                      className="setting-textarea"
+                      value={ignorePatternsText}
+                      onChange={(e) => setIgnorePatternsText(e.target.value)}
+                      onBlur={saveIgnorePatterns}
+                      placeholder={'node_modules/\n*.pdf'}
+                      spellCheck={false}
+                    />
+                    <p className="option-description">
+                      One pattern per line, in .gitignore syntax. Entries listed in .gitignore, .ignore and .docuraignore files are hidden as well.
+                    </p>
+                  </div>
+
+                  <div className="settings-option">
+                    <label className="setting-label">
+                      More Text File Types
+                    </label>
+                    <input
+                      type="text"
+                      className="setting-textarea setting-input"
+                      value={textExtensionsText}
+                      onChange={(e) => setTextExtensionsText(e.target.value)}
+                      onBlur={saveTextExtensions}
+                      placeholder="tex, wiki, log"
+                      spellCheck={false}
+                    />
+                    <p className="option-description">
+                      Extensions to open as text, besides Markdown, .txt, .rst, .org, .qmd, .Rmd and .adoc. Files without an extension open too, unless they turn out to be binary.
+                    </p>
+                  </div>
                 </div>
               </>
             )}
@@ -1,4 +1,6 @@
-import React, { useState } from 'react'
+import React, { useState, useMemo } from 'react'
+import { invoke } from '@tauri-apps/api/core'
+import toast from 'react-hot-toast'
 import { 
   FolderIcon, 
   FileIcon, 
@@ -10,10 +12,105 @@ import {
   Cloud,
   CloudOff,
   FolderPlus,
-  Check
+  Check,
+  Link2,
+  Eye,
+  EyeOff,
+  FilePlus,
+  Pencil,
+  Copy,
+  Trash2,
+  Unlink,
+  Globe,
+  AlertTriangle,
+  GitBranch,
+  GitCommit,
+  Plus,
+  Minus
 } from 'lucide-react'
 import ScrollableContainer from './ScrollableContainer'
 
+// Extensions shown in the tree and opened as text; keep in sync with
+// MARKDOWN_EXTENSIONS and TEXT_EXTENSIONS in the backend
+const TEXT_EXTENSIONS = [
+  'md', 'markdown', 'mdown', 'mkdn', 'mdx',
+  'txt', 'text', 'rst', 'org', 'qmd', 'rmd', 'adoc', 'asciidoc'
+]
+
+// Entries fetched per request; large folders fill in page by page
+const DIRECTORY_PAGE_SIZE = 200
+
+// Files whose rules decide which entries of their folder are shown
+const IGNORE_FILES = ['.gitignore', '.ignore', '.docuraignore']
+
+const parentPath = (path) => path.substring(0, path.lastIndexOf('/'))
+
+const baseName = (path) => path.substring(path.lastIndexOf('/') + 1)
+
+const SORT_MODES = [
+  { value: 'name', label: 'Name' },
+  { value: 'natural', label: 'Name (1, 2, 10)' },
+  { value: 'title', label: 'Title' },
+  { value: 'modified', label: 'Last modified' },
+  { value: 'created', label: 'Created' }
+]
+
+const formatSize = (bytes) => {
+  if (bytes < 1024) return `${bytes} B`
+  if (bytes < 1024 * 1024) return `${(bytes / 1024).toFixed(1)} KB`
+  return `${(bytes / (1024 * 1024)).toFixed(1)} MB`
+}
+
+// Tooltip with the title, size, word count and dates of a tree entry
+const describeEntry = (item) => {
+  const metadata = item.metadata
+  if (!metadata) return undefined
+
+  const lines = [metadata.title || item.name]
+  const details = []
+  if (metadata.size != null) details.push(formatSize(metadata.size))
+  if (metadata.wordCount != null) {
+    details.push(`${metadata.wordCount} ${metadata.wordCount === 1 ? 'word' : 'words'}`)
+  }
+  if (details.length > 0) lines.push(details.join(' · '))
+  if (metadata.modified) {
+    lines.push(`Modified ${new Date(metadata.modified * 1000).toLocaleString()}`)
+  }
+  if (metadata.created) {
+    lines.push(`Created ${new Date(metadata.created * 1000).toLocaleString()}`)
+  }
+  return lines.join('\n')
+}
+
+// What the Links tab shows
+const LINK_VIEWS = [
+  { value: 'note', label: 'This note' },
+  { value: 'broken', label: 'Broken links' },
+  { value: 'orphans', label: 'Orphan notes' },
+  { value: 'problems', label: 'All problems' }
+]
+
+// Letters shown next to changed files, as in `git status --short`
+const GIT_CHANGE_LETTERS = {
+  added: 'A',
+  modified: 'M',
+  deleted: 'D',
+  renamed: 'R',
+  typeChanged: 'T',
+  untracked: 'U',
+  conflicted: '!'
+}
+
+const GIT_CHANGE_LABELS = {
+  added: 'Added',
+  modified: 'Modified',
+  deleted: 'Deleted',
+  renamed: 'Renamed',
+  typeChanged: 'Type changed',
+  untracked: 'Untracked',
+  conflicted: 'Conflicted'
+}
+
 const Sidebar = ({
   currentFolder,
   files,
@@ -28,14 +125,175 @@ const Sidebar = ({
   syncFolders,
   onSyncCurrentFolder,
   onAddCurrentFolderToSync,
-  currentFolderSyncStatus
+  currentFolderSyncStatus,
+  // Changes reported by the folder watcher
+  folderChanges,
+  showIgnoredFiles,
+  onToggleIgnoredFiles,
+  ignorePatterns,
+  // Extensions opened as text besides the built-in ones
+  textExtensions = [],
+  treeSort = 'name',
+  onTreeSortChange,
+  // Called after a file was created, renamed, moved, duplicated or trashed
+  onFileOperation,
+  // Asked before a rename or move that would break links, with the plan
+  // from preview_link_updates; resolves to 'update', 'skip' or 'cancel'
+  onConfirmLinkUpdates,
+  // Shows the commits that changed the open file
+  onOpenGitHistory
 }) => {
   const [activeTab, setActiveTab] = useState('files')
   const [expandedFolders, setExpandedFolders] = useState(new Set())
+  // Folder listings loaded so far, by path: { entries, nextOffset, loading }
+  const [directories, setDirectories] = useState({})
+  const directoriesRef = React.useRef({})
+  const treeGenerationRef = React.useRef(0)
   const [contextMenu, setContextMenu] = useState(null)
   const [syncingFolder, setSyncingFolder] = useState(false)
+  // Inline name input: { kind: 'rename' | 'file' | 'folder', path, parent }
+  const [editing, setEditing] = useState(null)
+  const editingRef = React.useRef(null)
+  const [dropTarget, setDropTarget] = useState(null)
+  const draggedPathRef = React.useRef(null)
   const fileTreeScrollRef = React.useRef(null)
   const outlineScrollRef = React.useRef(null)
+  const [linksView, setLinksView] = useState('note')
+  // Result of the last link query: { view, data }
+  const [linkResults, setLinkResults] = useState(null)
+  // Whether "All problems" also requests external URLs
+  const [checkExternalLinks, setCheckExternalLinks] = useState(false)
+  // git_status of the open folder, null when it isn't in a repository
+  const [gitStatus, setGitStatus] = useState(null)
+  const [commitMessage, setCommitMessage] = useState('')
+  const [committing, setCommitting] = useState(false)
+
+  const updateDirectory = (dirPath, update) => {
+    directoriesRef.current = {
+      ...directoriesRef.current,
+      [dirPath]: { ...directoriesRef.current[dirPath], ...update }
+    }
+    setDirectories(directoriesRef.current)
+  }
+
+  // Read one folder level page by page, showing each page as it arrives
+  const loadDirectory = async (dirPath) => {
+    const generation = treeGenerationRef.current
+    updateDirectory(dirPath, { loading: true })
+
+    let offset = 0
+    let entries = []
+    try {
+      while (offset !== null) {
+        const page = await invoke('list_directory', {
+          dirPath,
+          rootPath: currentFolder,
+          sort: treeSort,
+          withMetadata: true,
+          offset,
+          limit: DIRECTORY_PAGE_SIZE
+        })
+        // A different folder was opened in the meantime
+        if (generation !== treeGenerationRef.current) return
+
+        entries = entries.concat(page.entries)
+        offset = page.nextOffset ?? null
+        updateDirectory(dirPath, { entries, loading: offset !== null })
+      }
+    } catch (error) {
+      console.error('Failed to list folder:', dirPath, error)
+      if (generation === treeGenerationRef.current) {
+        updateDirectory(dirPath, { entries, loading: false })
+      }
+    }
+  }
+
+  const reloadLoadedDirectories = (dirPaths = Object.keys(directoriesRef.current)) => {
+    dirPaths
+      .filter(dirPath => directoriesRef.current[dirPath])
+      .forEach(dirPath => loadDirectory(dirPath))
+  }
+
+  // Start over with just the top level whenever another folder is opened
+  React.useEffect(() => {
+    treeGenerationRef.current += 1
+    directoriesRef.current = {}
+    setDirectories({})
+    setExpandedFolders(new Set())
+    if (currentFolder) {
+      loadDirectory(currentFolder)
+    }
+  }, [currentFolder])
+
+  // Ignore settings change which entries every loaded folder shows, and
+  // the sort order how they are listed
+  const filterSettingsMountedRef = React.useRef(false)
+  React.useEffect(() => {
+    if (!filterSettingsMountedRef.current) {
+      filterSettingsMountedRef.current = true
+      return
+    }
+    reloadLoadedDirectories()
+  }, [showIgnoredFiles, ignorePatterns, treeSort])
+
+  // Re-read the folders whose contents changed on disk
+  React.useEffect(() => {
+    if (!folderChanges || folderChanges.length === 0) return
+
+    const parents = new Set()
+    folderChanges.forEach(change => {
+      parents.add(parentPath(change.path))
+      if (change.oldPath) {
+        parents.add(parentPath(change.oldPath))
+      }
+    })
+    // An edited ignore file changes what its folder and everything below shows
+    const ruleFolders = folderChanges
+      .filter(change => IGNORE_FILES.includes(baseName(change.path)))
+      .map(change => parentPath(change.path))
+    Object.keys(directoriesRef.current)
+      .filter(dirPath => ruleFolders.some(folder => dirPath === folder || dirPath.startsWith(folder + '/')))
+      .forEach(dirPath => parents.add(dirPath))
+    reloadLoadedDirectories([...parents])
+  }, [folderChanges])
+
+  const loadGitStatus = async () => {
+    if (!currentFolder) {
+      setGitStatus(null)
+      return
+    }
+    const generation = treeGenerationRef.current
+    try {
+      const status = await invoke('git_status', { folderPath: currentFolder })
+      // A different folder was opened in the meantime
+      if (generation === treeGenerationRef.current) setGitStatus(status)
+    } catch (error) {
+      console.error('Failed to read git status:', error)
+      setGitStatus(null)
+    }
+  }
+
+  // The watcher reports saves and other edits; changes made by git itself
+  // (in .git) only show up on refresh
+  React.useEffect(() => {
+    loadGitStatus()
+  }, [currentFolder, folderChanges])
+
+  // Changed files by path, and the folders that contain any
+  const gitFiles = useMemo(() => {
+    const byPath = new Map()
+    gitStatus?.files.forEach(file => byPath.set(file.path, file))
+    return byPath
+  }, [gitStatus])
+  const gitFolders = useMemo(() => {
+    const folders = new Set()
+    gitStatus?.files.forEach(file => {
+      for (let dir = parentPath(file.path); dir.length > 0 && !folders.has(dir); dir = parentPath(dir)) {
+        folders.add(dir)
+      }
+    })
+    return folders
+  }, [gitStatus])
 
   const toggleFolder = (folderPath) => {
     const newExpanded = new Set(expandedFolders)
@@ -43,6 +301,10 @@ const Sidebar = ({
       newExpanded.delete(folderPath)
     } else {
       newExpanded.add(folderPath)
+      // Subfolders are only read the first time they are opened
+      if (currentFolder && !directoriesRef.current[folderPath]) {
+        loadDirectory(folderPath)
+      }
     }
     setExpandedFolders(newExpanded)
   }
@@ -54,6 +316,15 @@ const Sidebar = ({
     )
   }
 
+  // Files the editor opens: Markdown and other text formats
+  const isTextFile = (filename) => {
+    const name = filename.toLowerCase()
+    const dot = name.lastIndexOf('.')
+    if (dot <= 0) return true
+    const extension = name.slice(dot + 1)
+    return TEXT_EXTENSIONS.includes(extension) || textExtensions.includes(extension)
+  }
+
   // Check if current folder is in sync list
   const isCurrentFolderSynced = () => {
     if (!currentFolder || !syncFolders) return false
@@ -90,6 +361,142 @@ const Sidebar = ({
     })
   }
 
+  const startEditing = (edit) => {
+    editingRef.current = edit
+    setEditing(edit)
+  }
+
+  const startCreating = (kind, parent) => {
+    if (parent !== currentFolder && !expandedFolders.has(parent)) {
+      toggleFolder(parent)
+    }
+    startEditing({ kind, parent })
+  }
+
+  // Run a file operation command and re-read the folders it changed
+  const runFileOperation = async (kind, command, args, oldPath) => {
+    try {
+      const item = await invoke(command, args)
+      const changed = [parentPath(item.path)]
+      if (oldPath) changed.push(parentPath(oldPath))
+      reloadLoadedDirectories(changed)
+      if (onFileOperation) onFileOperation({ kind, item, oldPath })
+    } catch (error) {
+      console.error(`Failed to ${kind}:`, error)
+      toast.error(`${error}`)
+    }
+  }
+
+  // Renames and moves offer to rewrite the links that point at the entry
+  const relocateEntry = async (kind, path, newPath, command, args) => {
+    let plan = null
+    try {
+      plan = await invoke('preview_link_updates', { folderPath: currentFolder, path, newPath })
+    } catch (error) {
+      console.error('Failed to find links to update:', error)
+    }
+
+    if (plan && (plan.files.length > 0 || plan.skipped.length > 0) && onConfirmLinkUpdates) {
+      const choice = await onConfirmLinkUpdates(plan)
+      if (choice === 'cancel') return
+      if (choice === 'update') {
+        runFileOperation(kind, 'rename_with_links', { folderPath: currentFolder, path, newPath }, path)
+        return
+      }
+    }
+    runFileOperation(kind, command, args, path)
+  }
+
+  const finishEditing = (value) => {
+    const edit = editingRef.current
+    // Enter and the blur that follows both end up here
+    if (!edit) return
+    editingRef.current = null
+    setEditing(null)
+
+    const name = value.trim()
+    if (!name) return
+    if (edit.kind === 'rename') {
+      if (name !== baseName(edit.path)) {
+        relocateEntry('rename', edit.path, `${parentPath(edit.path)}/${name}`, 'rename_path', {
+          path: edit.path,
+          newName: name
+        })
+      }
+    } else if (edit.kind === 'file') {
+      const fileName = isMarkdownFile(name) ? name : `${name}.md`
+      runFileOperation('create', 'create_file', { parentPath: edit.parent, name: fileName })
+    } else {
+      runFileOperation('create', 'create_folder', { parentPath: edit.parent, name })
+    }
+  }
+
+  const cancelEditing = () => {
+    editingRef.current = null
+    setEditing(null)
+  }
+
+  const moveEntry = (path, targetFolder) => {
+    if (parentPath(path) === targetFolder) return
+    relocateEntry('move', path, `${targetFolder}/${baseName(path)}`, 'move_path', { path, targetFolder })
+  }
+
+  // Drag handlers for a folder (or the tree background) accepting drops
+  const dropHandlers = (folderPath) => ({
+    onDragOver: (e) => {
+      const dragged = draggedPathRef.current
+      if (!dragged || dragged === folderPath || folderPath.startsWith(`${dragged}/`)) return
+      e.preventDefault()
+      e.stopPropagation()
+      e.dataTransfer.dropEffect = 'move'
+      setDropTarget(folderPath)
+    },
+    onDragLeave: () => setDropTarget(null),
+    onDrop: (e) => {
+      e.preventDefault()
+      e.stopPropagation()
+      setDropTarget(null)
+      const dragged = draggedPathRef.current
+      draggedPathRef.current = null
+      if (dragged) moveEntry(dragged, folderPath)
+    }
+  })
+
+  const renderNameInput = (defaultValue) => (
+    <input
+      className="file-tree-input"
+      autoFocus
+      defaultValue={defaultValue}
+      onClick={(e) => e.stopPropagation()}
+      onFocus={(e) => {
+        // Select the name without its extension, like file managers do
+        const dot = defaultValue.lastIndexOf('.')
+        e.target.setSelectionRange(0, dot > 0 ? dot : defaultValue.length)
+      }}
+      onKeyDown={(e) => {
+        if (e.key === 'Enter') finishEditing(e.target.value)
+        if (e.key === 'Escape') cancelEditing()
+      }}
+      onBlur={(e) => finishEditing(e.target.value)}
+    />
+  )
+
+  // Placeholder row with a name input for a new file or folder in `parent`
+  const renderNewEntry = (parent, depth) => {
+    if (!editing || editing.kind === 'rename' || editing.parent !== parent) return null
+    return (
+      <div className="file-tree-node" style={{ paddingLeft: depth * 20 + 8 }}>
+        <span className="folder-icon">
+          <span className="folder-icon-spacer" />
+        </span>
+        <span className="file-icon">
+          {editing.kind === 'folder' ? <FolderIcon size={14} /> : <FileIcon size={14} />}
+        </span>
+        {renderNameInput('')}
+      </div>
+    )
+  }
+
   // Close context menu on click outside
   React.useEffect(() => {
     const handleClick = () => setContextMenu(null)
@@ -105,11 +512,17 @@ const Sidebar = ({
     return items.map((item, index) => {
       const isFolder = item.type === 'folder'
       const isExpanded = expandedFolders.has(item.path)
+      // The folder tree is loaded lazily; a single opened file has no folder
+      const directory = currentFolder ? directories[item.path] : null
+      const children = currentFolder ? directory?.entries : item.children
+      const canExpand = isFolder && (currentFolder ? item.hasChildren : true)
       const isCurrentFile = !isFolder && currentFile === item.path
+      const isRenaming = editing?.kind === 'rename' && editing.path === item.path
+      const isCreatingHere = isFolder && editing && editing.kind !== 'rename' && editing.parent === item.path
       const paddingLeft = depth * 20 + 8
 
-      // Only render markdown files and folders
-      if (!isFolder && !isMarkdownFile(item.name)) {
+      // Only render text files and folders
+      if (!isFolder && !isTextFile(item.name)) {
         return null
       }
 
@@ -118,20 +531,39 @@ const Sidebar = ({
           <div
             className={`file-tree-node ${isFolder ? 'folder' : 'file'} ${
               !isFolder && isMarkdownFile(item.name) ? 'markdown' : ''
-            } ${isCurrentFile ? 'active' : ''}`}
+            } ${isCurrentFile ? 'active' : ''} ${dropTarget === item.path ? 'drop-target' : ''}`}
             style={{ paddingLeft }}
+            draggable={!!currentFolder && !isRenaming}
+            onDragStart={(e) => {
+              draggedPathRef.current = item.path
+              e.dataTransfer.effectAllowed = 'move'
+              e.dataTransfer.setData('text/plain', item.path)
+            }}
+            onDragEnd={() => {
+              draggedPathRef.current = null
+              setDropTarget(null)
+            }}
+            {...(isFolder && currentFolder && !item.isCycle ? dropHandlers(item.path) : {})}
+            title={item.isCycle ? 'Links back to a folder above it' : describeEntry(item)}
             onClick={() => {
+              if (isRenaming) return
               if (isFolder) {
-                toggleFolder(item.path)
+                if (canExpand) toggleFolder(item.path)
               } else {
                 onSelectFile(item.path)
               }
             }}
-            onContextMenu={(e) => !isFolder && handleContextMenu(e, item)}
+            onContextMenu={(e) => (currentFolder || !isFolder) && handleContextMenu(e, item)}
           >
             {isFolder && (
               <span className="folder-icon">
-                {isExpanded ? <ChevronDown size={12} /> : <ChevronRight size={12} />}
+                {!canExpand ? (
+                  <span className="folder-icon-spacer" />
+                ) : isExpanded ? (
+                  <ChevronDown size={12} />
+                ) : (
+                  <ChevronRight size={12} />
+                )}
               </span>
             )}
             {!isFolder && isCurrentFile && hasUnsavedChanges && (
@@ -142,7 +574,24 @@ const Sidebar = ({
             <span className="file-icon">
               {isFolder ? <FolderIcon size={14} /> : <FileIcon size={14} />}
             </span>
-            <span className="file-name">{item.name}</span>
+            {isRenaming ? (
+              renderNameInput(item.name)
+            ) : (
+              <span className="file-name">
+                {treeSort === 'title' && item.metadata?.title ? item.metadata.title : item.name}
+              </span>
+            )}
+            {item.isSymlink && (
+              <span className="symlink-indicator" title="Symbolic link">
+                <Link2 size={12} />
+              </span>
+            )}
+            {isFolder && gitFolders.has(item.path) && (
+              <span className="git-folder-indicator" title="Contains changes">
+                <Circle size={6} fill="currentColor" />
+              </span>
+            )}
+            {!isFolder && gitFiles.has(item.path) && renderGitBadge(gitFiles.get(item.path))}
             {!isFolder && dropboxSyncEnabled && isFileSynced(item.path) && (
               <span className="sync-indicator" title="Synced to Dropbox">
                 <Cloud size={12} />
@@ -150,9 +599,11 @@ const Sidebar = ({
             )}
           </div>
 
-          {isFolder && isExpanded && item.children && (
+          {isFolder && ((isExpanded && children) || isCreatingHere) && (
             <div className="folder-contents">
-              {renderFileTree(item.children, depth + 1)}
+              {renderNewEntry(item.path, depth + 1)}
+              {renderFileTree(children, depth + 1)}
+              {directory?.loading && renderLoading(depth + 1)}
             </div>
           )}
         </div>
@@ -160,6 +611,255 @@ const Sidebar = ({
     }).filter(Boolean) // Remove null items from filtered files
   }
 
+  const renderLoading = (depth) => (
+    <div className="file-tree-loading" style={{ paddingLeft: depth * 20 + 8 }}>
+      Loading…
+    </div>
+  )
+
+  // Re-read whenever the tab is shown, the open file changes or the watcher
+  // reports changes, which includes our own saves
+  React.useEffect(() => {
+    if (activeTab !== 'links' || !currentFolder) return
+    let cancelled = false
+    const load = async () => {
+      try {
+        let data = null
+        if (linksView === 'broken') {
+          data = await invoke('get_broken_links', { folderPath: currentFolder })
+        } else if (linksView === 'orphans') {
+          data = await invoke('get_orphan_notes', { folderPath: currentFolder })
+        } else if (linksView === 'problems') {
+          data = await invoke('lint_folder', {
+            folderPath: currentFolder,
+            checkExternal: checkExternalLinks
+          })
+        } else if (currentFile) {
+          data = await invoke('get_file_links', { folderPath: currentFolder, filePath: currentFile })
+        }
+        if (!cancelled) setLinkResults({ view: linksView, data })
+      } catch (error) {
+        console.error('Failed to load links:', error)
+      }
+    }
+    load()
+    return () => {
+      cancelled = true
+    }
+  }, [activeTab, linksView, currentFolder, currentFile, folderChanges, checkExternalLinks])
+
+  const relativePath = (path) =>
+    currentFolder && path.startsWith(`${currentFolder}/`) ? path.slice(currentFolder.length + 1) : path
+
+  const renderLinkItem = (link, label, { broken = false, path = link.target } = {}) => (
+    <div
+      key={`${link.source}:${link.line}:${link.column}`}
+      className={`link-item ${broken ? 'broken' : ''}`}
+      title={`${relativePath(link.source)}:${link.line}:${link.column}\n${link.context}`}
+      onClick={() => path && isTextFile(baseName(path)) && onSelectFile(path)}
+    >
+      <div className="link-item-label">
+        {broken ? <Unlink size={12} /> : <Link2 size={12} />}
+        <span className="outline-text">{label}</span>
+      </div>
+      <div className="link-item-context">{link.context}</div>
+    </div>
+  )
+
+  const renderLinks = () => {
+    if (!currentFolder) {
+      return <div className="empty-state">Open a folder to see links</div>
+    }
+    if (!linkResults || linkResults.view !== linksView) {
+      return renderLoading(0)
+    }
+    const { data } = linkResults
+
+    if (linksView === 'broken') {
+      if (data.length === 0) return <div className="empty-state">No broken links</div>
+      return data.map((link) =>
+        renderLinkItem(
+          link,
+          `${relativePath(link.source)} → ${link.dest}${link.reason === 'missingAnchor' ? ' (no such heading)' : ''}`,
+          { broken: true, path: link.source }
+        )
+      )
+    }
+
+    if (linksView === 'orphans') {
+      if (data.length === 0) return <div className="empty-state">Every note is linked from another one</div>
+      return data.map((path) => (
+        <div key={path} className="outline-item" onClick={() => onSelectFile(path)}>
+          <FileIcon size={12} />
+          <span className="outline-text">{relativePath(path)}</span>
+        </div>
+      ))
+    }
+
+    if (linksView === 'problems') {
+      if (data.length === 0) return <div className="empty-state">No problems found</div>
+      return data.map((problem) => (
+        <div
+          key={`${problem.file}:${problem.line}:${problem.column}:${problem.rule}`}
+          className={`link-item ${problem.severity === 'error' ? 'broken' : 'warning'}`}
+          title={`${relativePath(problem.file)}:${problem.line}:${problem.column}`}
+          onClick={() => onSelectFile(problem.file)}
+        >
+          <div className="link-item-label">
+            {problem.severity === 'error' ? <Unlink size={12} /> : <AlertTriangle size={12} />}
+            <span className="outline-text">
+              {relativePath(problem.file)}:{problem.line}:{problem.column}
+            </span>
+          </div>
+          <div className="link-item-context">{problem.message}</div>
+        </div>
+      ))
+    }
+
+    if (!data) return <div className="empty-state">Open a note to see its links</div>
+    return (
+      <>
+        <div className="links-section-title">Backlinks ({data.backlinks.length})</div>
+        {data.backlinks.length === 0 && <div className="links-section-empty">No other note links here</div>}
+        {data.backlinks.map((link) => renderLinkItem(link, relativePath(link.source), { path: link.source }))}
+        <div className="links-section-title">Outgoing links ({data.outgoing.length})</div>
+        {data.outgoing.length === 0 && <div className="links-section-empty">This note has no links</div>}
+        {data.outgoing.map((link) => renderLinkItem(link, link.dest))}
+      </>
+    )
+  }
+
+  // Unstaged changes win: they are what the file on disk shows
+  const renderGitBadge = (file) => {
+    const change = file.unstaged || file.staged
+    const title = [
+      file.staged && `${GIT_CHANGE_LABELS[file.staged]} (staged)`,
+      file.unstaged && GIT_CHANGE_LABELS[file.unstaged]
+    ].filter(Boolean).join(', ')
+    return (
+      <span className={`git-badge ${change} ${file.unstaged ? '' : 'staged'}`} title={title}>
+        {GIT_CHANGE_LETTERS[change]}
+      </span>
+    )
+  }
+
+  const runGitCommand = async (command, args, failure) => {
+    try {
+      return await invoke(command, args)
+    } catch (error) {
+      console.error(`${failure}:`, error)
+      toast.error(`${failure}: ${error}`)
+      return null
+    } finally {
+      loadGitStatus()
+    }
+  }
+
+  const stageFiles = (filePaths) => {
+    if (hasUnsavedChanges && filePaths.includes(currentFile)) {
+      toast('Staged the saved version; save to include your latest edits')
+    }
+    return runGitCommand('git_stage', { filePaths }, 'Failed to stage')
+  }
+
+  const unstageFiles = (filePaths) =>
+    runGitCommand('git_unstage', { filePaths }, 'Failed to unstage')
+
+  const commit = async () => {
+    setCommitting(true)
+    const id = await runGitCommand(
+      'git_commit',
+      { folderPath: currentFolder, message: commitMessage },
+      'Failed to commit'
+    )
+    setCommitting(false)
+    if (id) {
+      toast.success(`Committed ${id}`)
+      setCommitMessage('')
+    }
+  }
+
+  const gitPath = (path) =>
+    gitStatus && path.startsWith(`${gitStatus.root}/`) ? path.slice(gitStatus.root.length + 1) : path
+
+  const renderGitFile = (file, change, staged) => (
+    <div
+      key={`${staged ? 'staged' : 'unstaged'}:${file.path}`}
+      className="git-file-item"
+      title={`${gitPath(file.path)}\n${GIT_CHANGE_LABELS[change]}`}
+      onClick={() => change !== 'deleted' && isTextFile(baseName(file.path)) && onSelectFile(file.path)}
+    >
+      <span className={`git-badge ${change} ${staged ? 'staged' : ''}`}>{GIT_CHANGE_LETTERS[change]}</span>
+      <span className="outline-text">{gitPath(file.path)}</span>
+      <button
+        className="panel-action-button"
+        onClick={(e) => {
+          e.stopPropagation()
+          if (staged) {
+            unstageFiles([file.path])
+          } else {
+            stageFiles([file.path])
+          }
+        }}
+        title={staged ? 'Unstage' : 'Stage'}
+      >
+        {staged ? <Minus size={12} /> : <Plus size={12} />}
+      </button>
+    </div>
+  )
+
+  const renderGit = () => {
+    if (!gitStatus) {
+      return <div className="empty-state">This folder isn't in a git repository</div>
+    }
+    const staged = gitStatus.files.filter(file => file.staged)
+    const unstaged = gitStatus.files.filter(file => file.unstaged)
+    return (
+      <>
+        <div className="git-commit-box">
+          <textarea
+            className="git-commit-message"
+            value={commitMessage}
+            onChange={(e) => setCommitMessage(e.target.value)}
+            onKeyDown={(e) => {
+              if (e.key === 'Enter' && (e.ctrlKey || e.metaKey) && staged.length > 0 && commitMessage.trim()) {
+                commit()
+              }
+            }}
+            placeholder="Commit message"
+            rows={3}
+          />
+          <button
+            className="git-commit-button"
+            onClick={commit}
+            disabled={committing || staged.length === 0 || !commitMessage.trim()}
+            title="Commit the staged changes (Ctrl+Enter)"
+          >
+            <GitCommit size={14} />
+            Commit
+          </button>
+        </div>
+        <div className="links-section-title">Staged Changes ({staged.length})</div>
+        {staged.length === 0 && <div className="links-section-empty">Nothing staged</div>}
+        {staged.map(file => renderGitFile(file, file.staged, true))}
+        <div className="links-section-title git-section-title">
+          <span>Changes ({unstaged.length})</span>
+          {unstaged.length > 0 && (
+            <button
+              className="panel-action-button"
+              onClick={() => stageFiles(unstaged.map(file => file.path))}
+              title="Stage all changes"
+            >
+              <Plus size={12} />
+            </button>
+          )}
+        </div>
+        {unstaged.length === 0 && <div className="links-section-empty">No changes</div>}
+        {unstaged.map(file => renderGitFile(file, file.unstaged, false))}
+      </>
+    )
+  }
+
   const renderOutline = () => {
     return outlineHeaders.map((header, index) => (
       <div
@@ -178,6 +878,8 @@ const Sidebar = ({
     ))
   }
 
+  const rootDirectory = currentFolder ? directories[currentFolder] : null
+
   return (
     <div className="sidebar">
       <div className="sidebar-tabs">
@@ -193,6 +895,20 @@ const Sidebar = ({
         >
           Outline
         </button>
+        <button
+          className={`tab ${activeTab === 'links' ? 'active' : ''}`}
+          onClick={() => setActiveTab('links')}
+        >
+          Links
+        </button>
+        {gitStatus && (
+          <button
+            className={`tab ${activeTab === 'git' ? 'active' : ''}`}
+            onClick={() => setActiveTab('git')}
+          >
+            Git
+          </button>
+        )}
       </div>
 
       <div className="sidebar-content">
@@ -209,6 +925,23 @@ const Sidebar = ({
               </span>
               {currentFolder && (
                 <div className="panel-actions">
+                  <button
+                    className="panel-action-button"
+                    onClick={() => startCreating('file', currentFolder)}
+                    title="New file"
+                  >
+                    <FilePlus size={14} />
+                  </button>
+                  <select
+                    className="tree-sort-select"
+                    value={treeSort}
+                    onChange={(e) => onTreeSortChange(e.target.value)}
+                    title="Sort files by"
+                  >
+                    {SORT_MODES.map(mode => (
+                      <option key={mode.value} value={mode.value}>{mode.label}</option>
+                    ))}
+                  </select>
                   {dropboxSyncEnabled && isCurrentFolderSynced() && (
                     <button
                       className="panel-action-button"
@@ -228,9 +961,20 @@ const Sidebar = ({
                       <FolderPlus size={14} />
                     </button>
                   )}
+                  <button
+                    className={`panel-action-button ${showIgnoredFiles ? 'active' : ''}`}
+                    onClick={onToggleIgnoredFiles}
+                    title={showIgnoredFiles ? 'Hide hidden and ignored files' : 'Show hidden and ignored files'}
+                  >
+                    {showIgnoredFiles ? <Eye size={14} /> : <EyeOff size={14} />}
+                  </button>
                   <button
                     className="panel-action-button"
-                    onClick={onRefreshFiles}
+                    onClick={() => {
+                      reloadLoadedDirectories()
+                      loadGitStatus()
+                      onRefreshFiles()
+                    }}
                     title="Refresh"
                   >
                     <RefreshCw size={14} />
@@ -249,8 +993,29 @@ const Sidebar = ({
                 autoHide={true}
                 smoothScroll={true}
               >
-                <div className="file-tree">
-                  {files.length > 0 ? (
+                <div
+                  className={`file-tree ${dropTarget === currentFolder ? 'drop-target' : ''}`}
+                  {...(currentFolder ? dropHandlers(currentFolder) : {})}
+                  onContextMenu={(e) =>
+                    currentFolder &&
+                    handleContextMenu(e, { type: 'folder', path: currentFolder, isRoot: true })
+                  }
+                >
+                  {currentFolder ? (
+                    rootDirectory?.entries?.length > 0 ? (
+                      <>
+                        {renderNewEntry(currentFolder, 0)}
+                        {renderFileTree(rootDirectory.entries)}
+                        {rootDirectory.loading && renderLoading(0)}
+                      </>
+                    ) : rootDirectory?.loading ? (
+                      renderLoading(0)
+                    ) : editing ? (
+                      renderNewEntry(currentFolder, 0)
+                    ) : (
+                      <div className="empty-state">No files found</div>
+                    )
+                  ) : files.length > 0 ? (
                     renderFileTree(files)
                   ) : (
                     <div className="empty-state">No files found</div>
@@ -292,6 +1057,86 @@ const Sidebar = ({
             )}
           </div>
         )}
+
+        {activeTab === 'links' && (
+          <div className="outline-panel">
+            <div className="panel-header">
+              <span className="panel-title">Links</span>
+              <div className="panel-actions">
+                <select
+                  className="tree-sort-select"
+                  value={linksView}
+                  onChange={(e) => setLinksView(e.target.value)}
+                  title="Show"
+                >
+                  {LINK_VIEWS.map(view => (
+                    <option key={view.value} value={view.value}>{view.label}</option>
+                  ))}
+                </select>
+                {linksView === 'problems' && (
+                  <button
+                    className={`panel-action-button ${checkExternalLinks ? 'active' : ''}`}
+                    onClick={() => setCheckExternalLinks(!checkExternalLinks)}
+                    title={checkExternalLinks ? 'Stop checking web links' : 'Also check web links'}
+                  >
+                    <Globe size={14} />
+                  </button>
+                )}
+              </div>
+            </div>
+
+            <ScrollableContainer
+              className="outline-tree-container"
+              thumbColor="var(--border-color)"
+              thumbHoverColor="var(--accent-color)"
+              scrollbarWidth={6}
+              autoHide={true}
+              smoothScroll={true}
+            >
+              <div className="outline-tree">
+                {renderLinks()}
+              </div>
+            </ScrollableContainer>
+          </div>
+        )}
+
+        {activeTab === 'git' && (
+          <div className="outline-panel">
+            <div className="panel-header">
+              <span className="panel-title git-branch" title="Current branch">
+                <GitBranch size={14} />
+                {gitStatus?.branch ?? 'Detached HEAD'}
+              </span>
+              <div className="panel-actions">
+                {currentFile && onOpenGitHistory && (
+                  <button
+                    className="panel-action-button"
+                    onClick={onOpenGitHistory}
+                    title="Changes and commits of the open file"
+                  >
+                    <GitCommit size={14} />
+                  </button>
+                )}
+                <button className="panel-action-button" onClick={loadGitStatus} title="Refresh">
+                  <RefreshCw size={14} />
+                </button>
+              </div>
+            </div>
+
+            <ScrollableContainer
+              className="outline-tree-container"
+              thumbColor="var(--border-color)"
+              thumbHoverColor="var(--accent-color)"
+              scrollbarWidth={6}
+              autoHide={true}
+              smoothScroll={true}
+            >
+              <div className="outline-tree">
+                {renderGit()}
+              </div>
+            </ScrollableContainer>
+          </div>
+        )}
       </div>
 
       {/* Context Menu */}
@@ -305,7 +1150,50 @@ const Sidebar = ({
             zIndex: 10000
           }}
         >
-          {dropboxSyncEnabled && (
+          {currentFolder && contextMenu.item.type === 'folder' && (
+            <>
+              <div
+                className="context-menu-item"
+                onClick={() => startCreating('file', contextMenu.item.path)}
+              >
+                <FilePlus size={14} />
+                <span>New File</span>
+              </div>
+              <div
+                className="context-menu-item"
+                onClick={() => startCreating('folder', contextMenu.item.path)}
+              >
+                <FolderPlus size={14} />
+                <span>New Folder</span>
+              </div>
+            </>
+          )}
+          {currentFolder && !contextMenu.item.isRoot && (
+            <>
+              <div
+                className="context-menu-item"
+                onClick={() => startEditing({ kind: 'rename', path: contextMenu.item.path })}
+              >
+                <Pencil size={14} />
+                <span>Rename</span>
+              </div>
+              <div
+                className="context-menu-item"
+                onClick={() => runFileOperation('duplicate', 'duplicate_path', { path: contextMenu.item.path })}
+              >
+                <Copy size={14} />
+                <span>Duplicate</span>
+              </div>
+              <div
+                className="context-menu-item"
+                onClick={() => runFileOperation('trash', 'trash_path', { path: contextMenu.item.path }, contextMenu.item.path)}
+              >
+                <Trash2 size={14} />
+                <span>Move to Trash</span>
+              </div>
+            </>
+          )}
+          {contextMenu.item.type === 'file' && dropboxSyncEnabled && (
             isFileSynced(contextMenu.item.path) ? (
               <div className="context-menu-item disabled">
                 <Cloud size={14} />
@@ -333,7 +1221,7 @@ const Sidebar = ({
               </div>
             )
           )}
-          {!dropboxSyncEnabled && (
+          {contextMenu.item.type === 'file' && !dropboxSyncEnabled && (
             <div className="context-menu-item disabled">
               <CloudOff size={14} />
               <span>Dropbox sync disabled</span>
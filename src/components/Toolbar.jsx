@@ -54,6 +54,7 @@ const Toolbar = ({
   onOpenFromDropbox,
   dropboxConnected,
   onOpenAbout,
+  onOpenHistory,
 }) => {
   return (
     <div className="toolbar">
@@ -86,6 +87,7 @@ const Toolbar = ({
           onOpenFromDropbox={onOpenFromDropbox}
           dropboxConnected={dropboxConnected}
           onOpenAbout={onOpenAbout}
+          onOpenHistory={onOpenHistory}
         />
       </div>
 